@@ -0,0 +1,115 @@
+//! Argument completion for `completion/complete`.
+//!
+//! Suggests values that exist right now: document keys, branch names, spaces,
+//! and local model names, matched by prefix. Besides the standard
+//! `ref/prompt` and `ref/resource` references, a `ref/tool` reference
+//! (`{ type, name }`) completes tool arguments: `key` for strata_recall,
+//! strata_forget, and strata_history, `name` for strata_branch, and `model`
+//! for strata_generate.
+
+use serde_json::Value as JsonValue;
+use stratadb::{Command, Output};
+
+use crate::convert::output_to_json;
+use crate::error::{McpError, Result};
+use crate::session::{McpSession, SYSTEM_BRANCH};
+use crate::tools::scan;
+
+/// Most values returned in one completion, per the MCP spec.
+const MAX_VALUES: usize = 100;
+
+/// What kind of value an argument takes.
+enum Candidates {
+    Keys,
+    Branches,
+    Spaces,
+    Models,
+}
+
+/// Handle `completion/complete`: `{ completion: { values, total, hasMore } }`.
+///
+/// Arguments with nothing to suggest get an empty list rather than an error.
+/// `total` is left out when there are more keys than one scan returns.
+pub fn complete(session: &mut McpSession, params: Option<&JsonValue>) -> Result<JsonValue> {
+    let invalid = |name: &str, reason: &str| McpError::InvalidArg {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    };
+    let reference = params
+        .and_then(|p| p.get("ref"))
+        .ok_or_else(|| invalid("ref", "expected { type, name } or { type, uri }"))?;
+    let argument = params
+        .and_then(|p| p.get("argument"))
+        .ok_or_else(|| invalid("argument", "expected { name, value }"))?;
+    let arg_name = argument["name"].as_str().unwrap_or_default();
+    let prefix = argument["value"].as_str().unwrap_or_default();
+
+    let candidates = match (reference["type"].as_str(), arg_name) {
+        (Some("ref/tool"), arg) => match (reference["name"].as_str().unwrap_or_default(), arg) {
+            ("strata_recall" | "strata_forget" | "strata_history", "key") => Some(Candidates::Keys),
+            ("strata_branch", "name" | "source" | "compare") => Some(Candidates::Branches),
+            ("strata_generate", "model") => Some(Candidates::Models),
+            _ => None,
+        },
+        (Some("ref/prompt"), arg) => match (reference["name"].as_str().unwrap_or_default(), arg) {
+            ("recover_deleted_data", "key") => Some(Candidates::Keys),
+            _ => None,
+        },
+        (Some("ref/resource"), "branch") => Some(Candidates::Branches),
+        (Some("ref/resource"), "space") => Some(Candidates::Spaces),
+        (Some("ref/resource"), "key") => Some(Candidates::Keys),
+        (Some("ref/tool" | "ref/prompt" | "ref/resource"), _) => None,
+        _ => return Err(invalid("ref", "type must be ref/tool, ref/prompt, or ref/resource")),
+    };
+
+    let mut values = match candidates {
+        None => Vec::new(),
+        Some(Candidates::Keys) => {
+            // One page past the cap is enough to know there are more
+            let limit = MAX_VALUES as u64 + 1;
+            scan::list_page(session, Some(prefix.to_string()), None, limit)?.keys
+        }
+        Some(Candidates::Branches) => {
+            let cmd = Command::BranchList {
+                state: None,
+                limit: None,
+                offset: None,
+            };
+            match session.execute(cmd)? {
+                Output::BranchInfoList(branches) => branches
+                    .into_iter()
+                    .map(|bi| bi.info.id.as_str().to_string())
+                    .filter(|name| name != SYSTEM_BRANCH)
+                    .collect(),
+                _ => Vec::new(),
+            }
+        }
+        Some(Candidates::Spaces) => {
+            let spaces = output_to_json(session.execute(Command::SpaceList {
+                branch: session.branch_id(),
+            })?);
+            spaces
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|s| s.as_str().map(String::from))
+                .collect()
+        }
+        Some(Candidates::Models) => match session.execute(Command::ModelsLocal)? {
+            Output::ModelsList(models) => models.into_iter().map(|m| m.name).collect(),
+            _ => Vec::new(),
+        },
+    };
+    values.retain(|v| v.starts_with(prefix));
+    values.sort();
+    values.dedup();
+
+    let total = values.len();
+    let has_more = total > MAX_VALUES;
+    values.truncate(MAX_VALUES);
+    let mut completion = serde_json::json!({ "values": values, "hasMore": has_more });
+    if !(has_more && matches!(candidates, Some(Candidates::Keys))) {
+        completion["total"] = total.into();
+    }
+    Ok(serde_json::json!({ "completion": completion }))
+}
@@ -8,39 +8,131 @@ use std::collections::HashMap;
 use stratadb::{Output, Value, VersionedValue};
 
 use crate::error::{McpError, Result};
+use crate::jsonpath::{self, Segment};
 
 /// Convert a JSON value to a stratadb Value.
+///
+/// Integers above `i64::MAX` (typically large IDs) do not fit `Value::Int`
+/// and would lose precision as floats, so they are stored as their decimal
+/// string instead. Conversion errors name the offending path within the value.
 pub fn json_to_value(json: JsonValue) -> Result<Value> {
+    json_to_value_at(json, &mut Vec::new())
+}
+
+fn json_to_value_at(json: JsonValue, path: &mut Vec<Segment>) -> Result<Value> {
     match json {
         JsonValue::Null => Ok(Value::Null),
         JsonValue::Bool(b) => Ok(Value::Bool(b)),
         JsonValue::Number(n) => {
             if let Some(i) = n.as_i64() {
                 Ok(Value::Int(i))
+            } else if n.is_u64() {
+                Ok(Value::String(n.to_string()))
             } else if let Some(f) = n.as_f64() {
                 Ok(Value::Float(f))
             } else {
                 Err(McpError::InvalidArg {
                     name: "value".to_string(),
-                    reason: "Number out of range".to_string(),
+                    reason: format!("Number out of range at {}", jsonpath::format(path)),
                 })
             }
         }
         JsonValue::String(s) => Ok(Value::String(s)),
         JsonValue::Array(arr) => {
-            let values: Result<Vec<Value>> = arr.into_iter().map(json_to_value).collect();
-            Ok(Value::Array(values?))
+            let mut values = Vec::with_capacity(arr.len());
+            for (i, v) in arr.into_iter().enumerate() {
+                path.push(Segment::Index(i));
+                values.push(json_to_value_at(v, path)?);
+                path.pop();
+            }
+            Ok(Value::Array(values))
         }
         JsonValue::Object(map) => {
             let mut obj = HashMap::new();
             for (k, v) in map {
-                obj.insert(k, json_to_value(v)?);
+                path.push(Segment::Field(k.clone()));
+                obj.insert(k, json_to_value_at(v, path)?);
+                path.pop();
             }
             Ok(Value::Object(obj))
         }
     }
 }
 
+/// Value type tags accepted by [`apply_value_type`].
+pub const VALUE_TYPES: &[&str] = &["auto", "int", "float", "string"];
+
+/// Apply an explicit value type tag to every number in a JSON value.
+///
+/// JSON doesn't distinguish `3` from `3.0`, so by default whole numbers
+/// become `Int` and the rest `Float`. With `"float"` every number is stored
+/// as `Float`, with `"int"` every number must be whole and is stored as
+/// `Int`, and with `"string"` every number is stored as its decimal text.
+/// `"auto"` leaves the value as is. Tags are case-insensitive.
+pub fn apply_value_type(json: JsonValue, value_type: &str) -> Result<JsonValue> {
+    let tag = value_type.to_ascii_lowercase();
+    if !VALUE_TYPES.contains(&tag.as_str()) {
+        return Err(McpError::InvalidArg {
+            name: "value_type".to_string(),
+            reason: format!(
+                "Unknown value type '{}'. Use one of: {}.",
+                value_type,
+                VALUE_TYPES.join(", ")
+            ),
+        });
+    }
+    apply_value_type_at(json, &tag, &mut Vec::new())
+}
+
+fn apply_value_type_at(json: JsonValue, tag: &str, path: &mut Vec<Segment>) -> Result<JsonValue> {
+    match json {
+        JsonValue::Number(n) => match tag {
+            "float" => Ok(n
+                .as_f64()
+                .and_then(serde_json::Number::from_f64)
+                .map_or(JsonValue::Number(n), JsonValue::Number)),
+            "int" => match n.as_i64() {
+                Some(i) => Ok(JsonValue::from(i)),
+                None => match n.as_f64() {
+                    Some(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+                        Ok(JsonValue::from(f as i64))
+                    }
+                    _ => Err(McpError::InvalidArg {
+                        name: "value".to_string(),
+                        reason: format!(
+                            "{} is not a whole number in range at {}",
+                            n,
+                            jsonpath::format(path)
+                        ),
+                    }),
+                },
+            },
+            "string" => Ok(JsonValue::String(n.to_string())),
+            _ => Ok(JsonValue::Number(n)),
+        },
+        JsonValue::Array(arr) => {
+            let mut values = Vec::with_capacity(arr.len());
+            for (i, v) in arr.into_iter().enumerate() {
+                path.push(Segment::Index(i));
+                values.push(apply_value_type_at(v, tag, path)?);
+                path.pop();
+            }
+            Ok(JsonValue::Array(values))
+        }
+        JsonValue::Object(map) => {
+            let mut obj = Map::new();
+            for (k, v) in map {
+                path.push(Segment::Field(k.clone()));
+                let v = apply_value_type_at(v, tag, path)?;
+                path.pop();
+                obj.insert(k, v);
+            }
+            Ok(JsonValue::Object(obj))
+        }
+        other => Ok(other),
+    }
+}
+
 /// Convert a stratadb Value to a JSON value.
 /// Uses stratadb's built-in conversion which handles base64 encoding for bytes.
 pub fn value_to_json(value: Value) -> JsonValue {
@@ -48,6 +140,87 @@ pub fn value_to_json(value: Value) -> JsonValue {
     value.into()
 }
 
+/// Describe the stratadb `Value` variants that make up a value.
+///
+/// Scalars map to their variant name (`"Int"`, `"Float"`, `"String"`, ...);
+/// arrays and objects mirror the value's structure with each element replaced
+/// by its type. Used to diagnose how JSON input was typed on the way in.
+pub fn value_type_tree(value: &Value) -> JsonValue {
+    match value {
+        Value::Null => JsonValue::String("Null".to_string()),
+        Value::Bool(_) => JsonValue::String("Bool".to_string()),
+        Value::Int(_) => JsonValue::String("Int".to_string()),
+        Value::Float(_) => JsonValue::String("Float".to_string()),
+        Value::String(_) => JsonValue::String("String".to_string()),
+        Value::Bytes(_) => JsonValue::String("Bytes".to_string()),
+        Value::Array(arr) => JsonValue::Array(arr.iter().map(value_type_tree).collect()),
+        Value::Object(map) => JsonValue::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), value_type_tree(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Serialize JSON canonically: object keys sorted, no insignificant whitespace.
+///
+/// Logically equal documents produce identical text regardless of the order
+/// their fields were written in, so the output is suitable for hashing.
+pub fn canonical_json(value: &JsonValue) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+/// Normalize a value for canonical storage: floats with no fractional part
+/// (`2.0`, `-0.0`) stored as integers. Object keys need no sorting, since
+/// `serde_json::Map` keeps them in order.
+pub fn canonicalize(value: JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Array(items) => JsonValue::Array(items.into_iter().map(canonicalize).collect()),
+        JsonValue::Object(map) => {
+            JsonValue::Object(map.into_iter().map(|(k, v)| (k, canonicalize(v))).collect())
+        }
+        JsonValue::Number(n) if n.is_f64() => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+                JsonValue::from(f as i64)
+            }
+            _ => JsonValue::Number(n),
+        },
+        other => other,
+    }
+}
+
+fn write_canonical(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        JsonValue::Object(map) => {
+            let mut fields: Vec<(&String, &JsonValue)> = map.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&JsonValue::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(item, out);
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
 /// Convert a VersionedValue to JSON.
 pub fn versioned_to_json(vv: VersionedValue) -> JsonValue {
     serde_json::json!({
@@ -426,7 +599,32 @@ pub fn get_value_arg(args: &Map<String, JsonValue>, name: &str) -> Result<Value>
         .get(name)
         .cloned()
         .ok_or_else(|| McpError::MissingArg(name.to_string()))?;
-    json_to_value(json)
+    json_to_value(json).map_err(|e| match e {
+        McpError::InvalidArg { reason, .. } => McpError::InvalidArg {
+            name: name.to_string(),
+            reason,
+        },
+        other => other,
+    })
+}
+
+/// Helper to get a required value argument, applying an optional value type
+/// tag (see [`apply_value_type`]) before conversion.
+pub fn get_typed_value_arg(
+    args: &Map<String, JsonValue>,
+    name: &str,
+    value_type: Option<&str>,
+) -> Result<Value> {
+    let Some(value_type) = value_type else {
+        return get_value_arg(args, name);
+    };
+    let json = args
+        .get(name)
+        .cloned()
+        .ok_or_else(|| McpError::MissingArg(name.to_string()))?;
+    let mut typed = Map::new();
+    typed.insert(name.to_string(), apply_value_type(json, value_type)?);
+    get_value_arg(&typed, name)
 }
 
 /// Helper to get a required f32 vector argument.
@@ -38,6 +38,30 @@ pub enum McpError {
     #[error("branch not found: {0}")]
     BranchNotFound(String),
 
+    /// Transaction command issued with no transaction open.
+    #[error("no active transaction: {0} requires strata_txn_begin first")]
+    NoActiveTransaction(String),
+
+    /// Transaction begun while another is still open.
+    #[error(
+        "transaction already active: transactions do not nest — \
+         call strata_txn_commit or strata_txn_rollback first"
+    )]
+    TransactionAlreadyActive,
+
+    /// The client cancelled the request with `notifications/cancelled`.
+    #[error("request cancelled")]
+    Cancelled,
+
+    /// A tool call ran past its time limit.
+    #[error("{tool} timed out after {timeout_ms} ms")]
+    Timeout {
+        /// The tool that was called
+        tool: String,
+        /// The limit it ran past, in milliseconds
+        timeout_ms: u64,
+    },
+
     /// JSON-RPC protocol error.
     #[error("protocol error: {0}")]
     Protocol(String),
@@ -116,6 +140,12 @@ pub mod rpc_codes {
     pub const INVALID_PARAMS: i32 = -32602;
     /// Internal error - Internal JSON-RPC error.
     pub const INTERNAL_ERROR: i32 = -32603;
+    /// Resource not found - `resources/read` named no existing resource (MCP).
+    pub const RESOURCE_NOT_FOUND: i32 = -32002;
+    /// Request cancelled - the client cancelled the request before it finished.
+    pub const REQUEST_CANCELLED: i32 = -32800;
+    /// Request timed out - a tool call ran past its time limit (as in the MCP SDKs).
+    pub const REQUEST_TIMEOUT: i32 = -32001;
 }
 
 impl McpError {
@@ -123,8 +153,12 @@ impl McpError {
     pub fn rpc_code(&self) -> i32 {
         match self {
             McpError::UnknownTool(_) => rpc_codes::METHOD_NOT_FOUND,
+            McpError::Cancelled => rpc_codes::REQUEST_CANCELLED,
+            McpError::Timeout { .. } => rpc_codes::REQUEST_TIMEOUT,
             McpError::MissingArg(_) | McpError::InvalidArg { .. } => rpc_codes::INVALID_PARAMS,
-            McpError::Protocol(_) => rpc_codes::INVALID_REQUEST,
+            McpError::Protocol(_)
+            | McpError::NoActiveTransaction(_)
+            | McpError::TransactionAlreadyActive => rpc_codes::INVALID_REQUEST,
             McpError::Strata { code, .. } => {
                 // Map strata errors to appropriate RPC codes
                 match code.as_str() {
@@ -141,6 +175,17 @@ impl McpError {
             _ => rpc_codes::INTERNAL_ERROR,
         }
     }
+
+    /// Structured details for the JSON-RPC error's `data`, if any.
+    pub fn rpc_data(&self) -> Option<serde_json::Value> {
+        match self {
+            McpError::Timeout { tool, timeout_ms } => Some(serde_json::json!({
+                "tool": tool,
+                "timeout_ms": timeout_ms,
+            })),
+            _ => None,
+        }
+    }
 }
 
 /// Result type for MCP operations.
@@ -0,0 +1,864 @@
+//! MCP Streamable HTTP transport.
+//!
+//! Serves one endpoint, `/mcp`, as the MCP Streamable HTTP spec describes:
+//! clients POST JSON-RPC messages (a single message or a batch) and get the
+//! responses back as JSON, or as an SSE stream when they only accept
+//! `text/event-stream`. A GET opens an SSE stream for server-initiated
+//! messages, and a DELETE ends the client's session.
+//!
+//! Each connection gets its own thread. `initialize` builds a server for the
+//! client and issues an `Mcp-Session-Id` that later requests must send back;
+//! requests with that id run against that server, one at a time, so each
+//! client has its own branch, space, and transaction, while clients of
+//! different sessions run side by side. A session left unused for
+//! [`SESSION_IDLE_TIMEOUT`] is ended as if the client had deleted it, and no
+//! more than [`MAX_SESSIONS`] are open at once, or [`MAX_CONNECTIONS`]
+//! connections served. Requests from web pages on other origins are
+//! rejected, so a browser can't be used to reach a local server. Session
+//! ids are 128 random bits from the operating system's secure generator.
+//!
+//! There is no authentication, so by default the server only binds a
+//! loopback address. Requests without an `Origin` come from non-browser
+//! clients, since browsers send one on every request that could reach this
+//! endpoint; they are accepted from this machine only. Serving other
+//! machines is opted into with `allow_remote`, and then needs a proxy in
+//! front that authenticates clients.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::Value as JsonValue;
+
+use crate::error::{rpc_codes, McpError, Result};
+use crate::server::{JsonRpcResponse, McpServer};
+
+/// Path of the MCP endpoint.
+pub const ENDPOINT: &str = "/mcp";
+
+/// Header carrying the session id issued by `initialize`.
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Largest request body accepted.
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Longest request line or header line accepted.
+const MAX_HEADER_LINE_BYTES: usize = 8 * 1024;
+
+/// Most header lines accepted in one request.
+const MAX_HEADERS: usize = 100;
+
+/// Longest a client may take to send a request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between keepalive comments on an idle SSE stream.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often an open SSE stream checks its session for queued messages.
+const OUTBOX_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Most messages queued for a session's SSE stream; older ones are dropped.
+const MAX_OUTBOX: usize = 64;
+
+/// How long a session may go unused before it is ended.
+pub const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Most client sessions open at once.
+pub const MAX_SESSIONS: usize = 256;
+
+/// Most connections served at once, each on its own thread.
+pub const MAX_CONNECTIONS: usize = 512;
+
+/// Bounds on what clients may hold open, and where they may connect from.
+#[derive(Clone, Copy)]
+struct Limits {
+    session_idle_timeout: Duration,
+    max_sessions: usize,
+    max_connections: usize,
+    /// Serve non-loopback addresses and peers
+    allow_remote: bool,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            session_idle_timeout: SESSION_IDLE_TIMEOUT,
+            max_sessions: MAX_SESSIONS,
+            max_connections: MAX_CONNECTIONS,
+            allow_remote: false,
+        }
+    }
+}
+
+/// State shared by every connection.
+struct Shared<'a, F> {
+    new_server: &'a F,
+    limits: Limits,
+    /// Each client session, by session id
+    sessions: Mutex<HashMap<String, ClientSession>>,
+    /// Connections being served now
+    connections: AtomicUsize,
+}
+
+/// A connection's place under [`MAX_CONNECTIONS`], given up when dropped.
+pub(crate) struct ConnectionSlot<'a>(pub(crate) &'a AtomicUsize);
+
+impl Drop for ConnectionSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A client session's server, when the client last used it, and the
+/// notifications waiting for its SSE stream.
+struct ClientSession {
+    server: Arc<Mutex<McpServer>>,
+    last_used: Instant,
+    outbox: Vec<String>,
+}
+
+impl ClientSession {
+    /// Queue messages for the session's GET stream, keeping the newest.
+    fn queue(&mut self, messages: Vec<String>) {
+        self.outbox.extend(messages);
+        let excess = self.outbox.len().saturating_sub(MAX_OUTBOX);
+        self.outbox.drain(..excess);
+    }
+}
+
+/// A parsed HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    /// Header names are lowercased
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// An HTTP response with a fixed-length body.
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    fn json(status: u16, body: String) -> Self {
+        Self::new(status).header("Content-Type", "application/json").body(body)
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn body(mut self, body: String) -> Self {
+        self.body = body;
+        self
+    }
+
+    fn write_to(&self, stream: &mut TcpStream) -> std::io::Result<()> {
+        write!(stream, "HTTP/1.1 {} {}\r\n", self.status, reason(self.status))?;
+        for (name, value) in &self.headers {
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "Content-Length: {}\r\nConnection: close\r\n\r\n", self.body.len())?;
+        stream.write_all(self.body.as_bytes())?;
+        stream.flush()
+    }
+}
+
+/// Serve MCP over HTTP on `listener` until accepting a connection fails.
+///
+/// `new_server` builds the server for each client session at `initialize`.
+/// When it fails, or [`MAX_SESSIONS`] are already open, the client gets a
+/// JSON-RPC error and no session. When a client deletes its session, or
+/// leaves it unused for [`SESSION_IDLE_TIMEOUT`], its buffered write is
+/// flushed and its session branch closed.
+///
+/// Unless `allow_remote` is set, a listener bound to anything but a
+/// loopback address is refused, and requests without an `Origin` are only
+/// served from loopback peers.
+pub fn serve_http<F>(listener: TcpListener, new_server: F, allow_remote: bool) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String> + Sync,
+{
+    let limits = Limits {
+        allow_remote,
+        ..Limits::default()
+    };
+    serve_with_limits(listener, new_server, limits)
+}
+
+fn serve_with_limits<F>(listener: TcpListener, new_server: F, limits: Limits) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String> + Sync,
+{
+    let addr = listener.local_addr()?;
+    if !limits.allow_remote && !addr.ip().is_loopback() {
+        return Err(McpError::InvalidArg {
+            name: "http".to_string(),
+            reason: format!(
+                "{} is not a loopback address; the HTTP transport has no authentication, so \
+                 serving other machines needs --allow-remote",
+                addr
+            ),
+        });
+    }
+    let shared = Shared {
+        new_server: &new_server,
+        limits,
+        sessions: Mutex::new(HashMap::new()),
+        connections: AtomicUsize::new(0),
+    };
+    std::thread::scope(|scope| -> Result<()> {
+        for stream in listener.incoming() {
+            let mut stream = stream?;
+            let shared = &shared;
+            if shared.connections.fetch_add(1, Ordering::SeqCst) >= shared.limits.max_connections {
+                shared.connections.fetch_sub(1, Ordering::SeqCst);
+                // Answer without a thread, and without waiting on a slow client
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let _ = Response::new(503).write_to(&mut stream);
+                continue;
+            }
+            let slot = ConnectionSlot(&shared.connections);
+            scope.spawn(move || {
+                let _slot = slot;
+                if let Err(e) = handle_connection(shared, stream) {
+                    tracing::debug!("HTTP connection ended: {}", e);
+                }
+            });
+        }
+        Ok(())
+    })
+}
+
+fn handle_connection<F>(shared: &Shared<'_, F>, mut stream: TcpStream) -> std::io::Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String>,
+{
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let request = match read_request(&mut stream)? {
+        Ok(request) => request,
+        Err(response) => return response.write_to(&mut stream),
+    };
+
+    if request.path.split('?').next() != Some(ENDPOINT) {
+        return Response::new(404).write_to(&mut stream);
+    }
+    let allowed = match request.header("origin") {
+        Some(origin) => is_local_origin(origin),
+        // Not a browser: trusted from this machine, or when serving others
+        None => shared.limits.allow_remote || stream.peer_addr()?.ip().is_loopback(),
+    };
+    if !allowed {
+        return Response::new(403).write_to(&mut stream);
+    }
+
+    match request.method.as_str() {
+        "POST" => handle_post(shared, &request, &mut stream),
+        "GET" => handle_get(shared, &request, &mut stream),
+        "DELETE" => {
+            let response = match check_session(shared, &request) {
+                Ok((id, server)) => {
+                    lock(&shared.sessions).remove(&id);
+                    lock(&server).end_client();
+                    Response::new(200)
+                }
+                Err(response) => response,
+            };
+            response.write_to(&mut stream)
+        }
+        _ => Response::new(405).header("Allow", "GET, POST, DELETE").write_to(&mut stream),
+    }
+}
+
+/// Run the JSON-RPC messages in a POST body and send back the responses.
+///
+/// The body is handled as over stdio, except that every request is answered
+/// in the response: with no stream open for late answers, a summary isn't
+/// deferred to the client's model.
+fn handle_post<F>(
+    shared: &Shared<'_, F>,
+    request: &Request,
+    stream: &mut TcpStream,
+) -> std::io::Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String>,
+{
+    let body: JsonValue = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(e) => {
+            let error = JsonRpcResponse::error(
+                None,
+                rpc_codes::PARSE_ERROR,
+                format!("Parse error: {}", e),
+            );
+            return Response::json(400, to_json(&error)).write_to(stream);
+        }
+    };
+    let batch = body.is_array();
+    let initializing = match &body {
+        JsonValue::Array(messages) => messages.iter().any(|m| m["method"] == "initialize"),
+        message => message["method"] == "initialize",
+    };
+    let (session_id, server) = if initializing {
+        end_idle_sessions(shared);
+        if lock(&shared.sessions).len() >= shared.limits.max_sessions {
+            let error = JsonRpcResponse::error(
+                None,
+                rpc_codes::INTERNAL_ERROR,
+                format!(
+                    "Too many open sessions ({}); delete unused ones first",
+                    shared.limits.max_sessions
+                ),
+            );
+            return Response::json(503, to_json(&error)).write_to(stream);
+        }
+        let id = match new_session_id() {
+            Ok(id) => id,
+            Err(reason) => {
+                let error = JsonRpcResponse::error(None, rpc_codes::INTERNAL_ERROR, reason);
+                return Response::json(500, to_json(&error)).write_to(stream);
+            }
+        };
+        let server = match (shared.new_server)() {
+            Ok(mut server) => {
+                server.set_session_id(id.clone());
+                Arc::new(Mutex::new(server))
+            }
+            Err(reason) => {
+                let error = JsonRpcResponse::error(None, rpc_codes::INTERNAL_ERROR, reason);
+                return Response::json(500, to_json(&error)).write_to(stream);
+            }
+        };
+        let session = ClientSession {
+            server: server.clone(),
+            last_used: Instant::now(),
+            outbox: Vec::new(),
+        };
+        lock(&shared.sessions).insert(id.clone(), session);
+        (id, server)
+    } else {
+        match check_session(shared, request) {
+            Ok(session) => session,
+            Err(response) => return response.write_to(stream),
+        }
+    };
+
+    // The body parsed as JSON, so it is UTF-8
+    let text = std::str::from_utf8(&request.body).unwrap_or_default().trim();
+    let (response, notifications) = {
+        let mut server = lock(&server);
+        let response = server.handle_text_now(text);
+        (response, server.take_notifications())
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            let error = JsonRpcResponse::error(None, rpc_codes::INTERNAL_ERROR, e.to_string());
+            return Response::json(500, to_json(&error)).write_to(stream);
+        }
+    };
+
+    let accept = request.header("accept").unwrap_or("");
+    let streamed = accept.contains("text/event-stream") && !accept.contains("application/json");
+    if (!streamed || response.is_none()) && !notifications.is_empty() {
+        // A JSON body has no room for notifications, so the GET stream carries them
+        if let Some(session) = lock(&shared.sessions).get_mut(&session_id) {
+            session.queue(notifications.clone());
+        }
+    }
+
+    let Some(response) = response else {
+        return Response::new(202).header("Mcp-Session-Id", session_id).write_to(stream);
+    };
+
+    if streamed {
+        // Each response in a batch is its own event
+        let responses = match serde_json::from_str::<JsonValue>(&response) {
+            Ok(JsonValue::Array(responses)) if batch => responses.iter().map(to_json).collect(),
+            _ => vec![response],
+        };
+        let events: String = responses
+            .into_iter()
+            .chain(notifications)
+            .map(|message| format!("event: message\ndata: {}\n\n", message))
+            .collect();
+        return Response::new(200)
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .header("Mcp-Session-Id", session_id)
+            .body(events)
+            .write_to(stream);
+    }
+    Response::json(200, response).header("Mcp-Session-Id", session_id).write_to(stream)
+}
+
+/// Hold an SSE stream open for server-initiated messages.
+///
+/// Carries the notifications raised by calls answered with a JSON body, such
+/// as `notifications/tools/list_changed`, and keepalive comments while there
+/// are none. It ends when the client disconnects or its session is deleted.
+fn handle_get<F>(
+    shared: &Shared<'_, F>,
+    request: &Request,
+    stream: &mut TcpStream,
+) -> std::io::Result<()> {
+    if !request.header("accept").unwrap_or("").contains("text/event-stream") {
+        return Response::new(405).header("Allow", "POST, DELETE").write_to(stream);
+    }
+    let session_id = match check_session(shared, request) {
+        Ok((id, _)) => id,
+        Err(response) => return response.write_to(stream),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+         Mcp-Session-Id: {}\r\nConnection: close\r\n\r\n",
+        session_id
+    )?;
+    stream.flush()?;
+    let mut last_write = Instant::now();
+    loop {
+        // An open stream keeps its session from going idle
+        let messages = match lock(&shared.sessions).get_mut(&session_id) {
+            Some(session) => {
+                session.last_used = Instant::now();
+                std::mem::take(&mut session.outbox)
+            }
+            None => return Ok(()),
+        };
+        if !messages.is_empty() {
+            for message in messages {
+                write!(stream, "event: message\ndata: {}\n\n", message)?;
+            }
+            stream.flush()?;
+            last_write = Instant::now();
+        } else if last_write.elapsed() >= KEEPALIVE_INTERVAL {
+            stream.write_all(b": keepalive\n\n")?;
+            stream.flush()?;
+            last_write = Instant::now();
+        }
+        std::thread::sleep(OUTBOX_POLL_INTERVAL);
+    }
+}
+
+/// The request's session id and server, or the error response when the id
+/// is missing or unknown.
+fn check_session<F>(
+    shared: &Shared<'_, F>,
+    request: &Request,
+) -> std::result::Result<(String, Arc<Mutex<McpServer>>), Response> {
+    let Some(id) = request.header(SESSION_HEADER) else {
+        return Err(Response::json(
+            400,
+            to_json(&JsonRpcResponse::error(
+                None,
+                rpc_codes::INVALID_REQUEST,
+                "Missing Mcp-Session-Id header; send initialize first".to_string(),
+            )),
+        ));
+    };
+    match lock(&shared.sessions).get_mut(id) {
+        Some(session) => {
+            session.last_used = Instant::now();
+            Ok((id.to_string(), session.server.clone()))
+        }
+        // 404 tells the client to start a new session with initialize
+        None => Err(Response::new(404)),
+    }
+}
+
+/// End the sessions left unused past the idle timeout, as a DELETE would.
+fn end_idle_sessions<F>(shared: &Shared<'_, F>) {
+    let idle: Vec<ClientSession> = {
+        let mut sessions = lock(&shared.sessions);
+        let ids: Vec<String> = sessions
+            .iter()
+            .filter(|(_, s)| s.last_used.elapsed() >= shared.limits.session_idle_timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.iter().filter_map(|id| sessions.remove(id)).collect()
+    };
+    for session in idle {
+        tracing::debug!("Ending an HTTP session idle for {:?}", session.last_used.elapsed());
+        lock(&session.server).end_client();
+    }
+}
+
+/// Read one request. The inner error is a response to send instead when
+/// the request is malformed or too large.
+fn read_request(
+    stream: &mut TcpStream,
+) -> std::io::Result<std::result::Result<Request, Response>> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    if !read_header_line(&mut reader, &mut line)? {
+        return Ok(Err(Response::new(414)));
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(Err(Response::new(400)));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = Vec::new();
+    for count in 0.. {
+        line.clear();
+        if !read_header_line(&mut reader, &mut line)? {
+            return Ok(Err(Response::new(431)));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Ok(Err(Response::new(431)));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    if request.header("transfer-encoding").is_some() {
+        // Chunked bodies aren't supported; clients must send Content-Length
+        return Ok(Err(Response::new(411)));
+    }
+    let length = match request.header("content-length").map(str::parse::<usize>) {
+        None => 0,
+        Some(Ok(length)) if length <= MAX_BODY_BYTES => length,
+        Some(Ok(_)) => return Ok(Err(Response::new(413))),
+        Some(Err(_)) => return Ok(Err(Response::new(400))),
+    };
+    request.body.resize(length, 0);
+    reader.read_exact(&mut request.body)?;
+    Ok(Ok(request))
+}
+
+/// Read one line of the request head into `line`, returning `false` when it
+/// runs past [`MAX_HEADER_LINE_BYTES`].
+fn read_header_line(
+    reader: &mut BufReader<&mut TcpStream>,
+    line: &mut String,
+) -> std::io::Result<bool> {
+    let limit = MAX_HEADER_LINE_BYTES as u64 + 1;
+    let read = reader.by_ref().take(limit).read_line(line)?;
+    Ok(read <= MAX_HEADER_LINE_BYTES)
+}
+
+/// A new session id: 128 bits from the operating system's secure random
+/// generator, as hex, so one session's id says nothing about another's.
+fn new_session_id() -> std::result::Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| format!("Failed to generate a session id: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Whether an `Origin` header names this machine.
+pub(crate) fn is_local_origin(origin: &str) -> bool {
+    let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => host.split([':', '/']).next().unwrap_or(""),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::McpSession;
+
+    /// Start a server on a free local port and return its address.
+    fn start_server() -> String {
+        start_server_with(Limits::default())
+    }
+
+    fn start_server_with(limits: Limits) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let addr = listener.local_addr().expect("No local address").to_string();
+        std::thread::spawn(move || {
+            let db = stratadb::Strata::cache().expect("Failed to create cache database");
+            let new_server = || Ok(McpServer::new(McpSession::new(db.clone())));
+            let _ = serve_with_limits(listener, new_server, limits);
+        });
+        addr
+    }
+
+    /// Send one request and return the status, headers, and body.
+    fn send(
+        addr: &str,
+        method: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> (u16, String, String) {
+        let mut stream = TcpStream::connect(addr).expect("Failed to connect");
+        write!(stream, "{} {} HTTP/1.1\r\nHost: {}\r\n", method, ENDPOINT, addr).unwrap();
+        for (name, value) in headers {
+            write!(stream, "{}: {}\r\n", name, value).unwrap();
+        }
+        write!(stream, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+
+        let mut response = Vec::new();
+        // A server refusing a request may reset the connection after answering
+        let _ = stream.read_to_end(&mut response);
+        let response = String::from_utf8_lossy(&response).into_owned();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status = head.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+        (status, head.to_string(), body.to_string())
+    }
+
+    fn session_id(head: &str) -> String {
+        head.lines()
+            .find_map(|l| l.strip_prefix("Mcp-Session-Id: "))
+            .expect("Expected a session id")
+            .to_string()
+    }
+
+    #[test]
+    fn test_http_session_lifecycle() {
+        let addr = start_server();
+        let json = [("Accept", "application/json, text/event-stream")];
+
+        let init = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let (status, head, body) = send(&addr, "POST", &json, init);
+        assert_eq!(status, 200);
+        let response: JsonValue = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["result"]["serverInfo"]["name"], "strata-mcp");
+        let id = session_id(&head);
+
+        // Requests without the session id are rejected
+        let ping = r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#;
+        assert_eq!(send(&addr, "POST", &json, ping).0, 400);
+
+        let with_id = [json[0], ("Mcp-Session-Id", id.as_str())];
+        let batch = r#"[{"jsonrpc":"2.0","method":"initialized"},
+                        {"jsonrpc":"2.0","id":3,"method":"ping"}]"#;
+        let (status, _, body) = send(&addr, "POST", &with_id, batch);
+        assert_eq!(status, 200);
+        let responses: JsonValue = serde_json::from_str(&body).unwrap();
+        assert_eq!(responses.as_array().unwrap().len(), 1);
+        assert_eq!(responses[0]["id"], 3);
+
+        let notification = r#"{"jsonrpc":"2.0","method":"initialized"}"#;
+        assert_eq!(send(&addr, "POST", &with_id, notification).0, 202);
+
+        let sse = [("Accept", "text/event-stream"), ("Mcp-Session-Id", id.as_str())];
+        let (status, head, body) = send(&addr, "POST", &sse, ping);
+        assert_eq!(status, 200);
+        assert!(head.contains("Content-Type: text/event-stream"));
+        assert!(body.starts_with("event: message\ndata: {"));
+
+        assert_eq!(send(&addr, "DELETE", &with_id, "").0, 200);
+        assert_eq!(send(&addr, "POST", &with_id, ping).0, 404);
+    }
+
+    #[test]
+    fn test_http_sessions_are_isolated() {
+        let addr = start_server();
+        let json = ("Accept", "application/json, text/event-stream");
+        let init = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let first = session_id(&send(&addr, "POST", &[json], init).1);
+        let second = session_id(&send(&addr, "POST", &[json], init).1);
+        assert_ne!(first, second);
+
+        let call = |id: &str, name: &str, arguments: JsonValue| {
+            let body = serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": {"name": name, "arguments": arguments}});
+            let headers = [json, ("Mcp-Session-Id", id)];
+            let (_, _, body) = send(&addr, "POST", &headers, &body.to_string());
+            let response: JsonValue = serde_json::from_str(&body).unwrap();
+            let text = response["result"]["content"][0]["text"].as_str().unwrap().to_string();
+            serde_json::from_str::<JsonValue>(&text).unwrap()
+        };
+        let branch = serde_json::json!({"action": "create", "name": "experiment"});
+        call(&first, "strata_branch", branch);
+        let branch = serde_json::json!({"action": "switch", "name": "experiment"});
+        call(&first, "strata_branch", branch);
+
+        // The first client's switch leaves the second on its own branch
+        let status = serde_json::json!({});
+        assert_eq!(call(&first, "strata_status", status.clone())["branch"], "experiment");
+        assert_eq!(call(&second, "strata_status", status)["branch"], "default");
+    }
+
+    #[test]
+    fn test_http_sessions_are_capped_and_expire() {
+        let init = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let ping = r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#;
+        let json = ("Accept", "application/json");
+
+        let limits = Limits {
+            max_sessions: 1,
+            ..Limits::default()
+        };
+        let addr = start_server_with(limits);
+        assert_eq!(send(&addr, "POST", &[json], init).0, 200);
+        assert_eq!(send(&addr, "POST", &[json], init).0, 503);
+
+        let limits = Limits {
+            session_idle_timeout: Duration::ZERO,
+            max_sessions: 1,
+            ..Limits::default()
+        };
+        let addr = start_server_with(limits);
+        let first = session_id(&send(&addr, "POST", &[json], init).1);
+        // The idle first session is ended to make room for the second
+        assert_eq!(send(&addr, "POST", &[json], init).0, 200);
+        assert_eq!(send(&addr, "POST", &[json, ("Mcp-Session-Id", first.as_str())], ping).0, 404);
+    }
+
+    #[test]
+    fn test_http_get_stream_carries_notifications() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let addr = listener.local_addr().expect("No local address").to_string();
+        std::thread::spawn(move || {
+            let db = stratadb::Strata::cache().expect("Failed to create cache database");
+            let new_server = || {
+                let registry = crate::tools::ToolRegistry::new().with_developer_access();
+                Ok(McpServer::with_registry(McpSession::new(db.clone()), registry))
+            };
+            let _ = serve_with_limits(listener, new_server, Limits::default());
+        });
+
+        let json = ("Accept", "application/json");
+        let init = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let id = session_id(&send(&addr, "POST", &[json], init).1);
+        let enable = r#"{"jsonrpc":"2.0","id":2,"method":"tools/call",
+            "params":{"name":"strata_developer","arguments":{"action":"enable"}}}"#;
+        let headers = [json, ("Mcp-Session-Id", id.as_str())];
+        assert_eq!(send(&addr, "POST", &headers, enable).0, 200);
+
+        // The JSON response had no room for list_changed; the GET stream sends it
+        let mut stream = TcpStream::connect(&addr).expect("Failed to connect");
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: text/event-stream\r\n\
+             Mcp-Session-Id: {}\r\n\r\n",
+            ENDPOINT, addr, id
+        )
+        .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line).expect("Expected a notification");
+            assert!(read > 0, "The stream ended without a notification");
+            if let Some(data) = line.strip_prefix("data: ") {
+                let message: JsonValue = serde_json::from_str(data).unwrap();
+                assert_eq!(message["method"], "notifications/tools/list_changed");
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_bounds_request_heads_and_connections() {
+        let addr = start_server();
+        let ping = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        let long = "x".repeat(MAX_HEADER_LINE_BYTES);
+        assert_eq!(send(&addr, "POST", &[("X-Long", long.as_str())], ping).0, 431);
+        let many: Vec<(&str, &str)> = vec![("X-Many", "1"); MAX_HEADERS + 1];
+        assert_eq!(send(&addr, "POST", &many, ping).0, 431);
+
+        let limits = Limits {
+            max_connections: 1,
+            ..Limits::default()
+        };
+        let addr = start_server_with(limits);
+        // A client that connects and sends nothing holds the only connection
+        let idle = TcpStream::connect(&addr).expect("Failed to connect");
+        let mut refused = TcpStream::connect(&addr).expect("Failed to connect");
+        let mut response = String::new();
+        refused.read_to_string(&mut response).expect("Failed to read response");
+        assert!(response.starts_with("HTTP/1.1 503"));
+        drop(idle);
+    }
+
+    #[test]
+    fn test_http_rejects_foreign_origins() {
+        let addr = start_server();
+        let init = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let foreign = [("Origin", "https://example.com")];
+        assert_eq!(send(&addr, "POST", &foreign, init).0, 403);
+        let local = [("Origin", "http://localhost:3000")];
+        assert_eq!(send(&addr, "POST", &local, init).0, 200);
+        // No Origin: a non-browser client on this machine
+        assert_eq!(send(&addr, "POST", &[], init).0, 200);
+    }
+
+    #[test]
+    fn test_http_refuses_remote_addresses_unless_allowed() {
+        let listener = TcpListener::bind("0.0.0.0:0").expect("Failed to bind");
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let new_server = || Ok(McpServer::new(McpSession::new(db.clone())));
+        let err = serve_http(listener, new_server, false).unwrap_err();
+        assert!(err.to_string().contains("--allow-remote"), "{}", err);
+    }
+
+    #[test]
+    fn test_http_session_ids_are_random() {
+        let a = new_session_id().unwrap();
+        let b = new_session_id().unwrap();
+        assert_eq!(a.len(), 32);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,320 @@
+//! Minimal JSONPath handling for document paths.
+//!
+//! Supports the subset agents use to address nested fields: the root `$`,
+//! dotted fields (`$.a.b`), bracketed fields (`$['a b']`, with `\` escaping a
+//! quote or backslash), and array indices (`$.items[0]`). Used to inspect
+//! documents locally before or after a write.
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::error::{McpError, Result};
+
+/// Most nulls [`set`] pads an array with to reach an index.
+pub const MAX_PADDING: usize = 1024;
+
+/// A single step in a parsed path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Object field name.
+    Field(String),
+    /// Array index.
+    Index(usize),
+}
+
+/// Parse a path into segments. The root path `$` parses to no segments.
+pub fn parse(path: &str) -> Result<Vec<Segment>> {
+    let invalid = |reason: &str| McpError::InvalidArg {
+        name: "path".to_string(),
+        reason: format!("{} in '{}'", reason, path),
+    };
+
+    let rest = path
+        .trim()
+        .strip_prefix('$')
+        .ok_or_else(|| invalid("path must start with '$'"))?;
+    let chars: Vec<char> = rest.chars().collect();
+
+    let mut segments = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                let start = i + 1;
+                let mut end = start;
+                while end < chars.len() && chars[end] != '.' && chars[end] != '[' {
+                    end += 1;
+                }
+                if end == start {
+                    return Err(invalid("empty field name"));
+                }
+                segments.push(Segment::Field(chars[start..end].iter().collect()));
+                i = end;
+            }
+            '[' => {
+                let mut j = i + 1;
+                while j < chars.len() && chars[j] == ' ' {
+                    j += 1;
+                }
+                match chars.get(j) {
+                    // Quoted field, with `\` escaping the quote or a backslash
+                    Some(&quote @ ('\'' | '"')) => {
+                        let mut field = String::new();
+                        j += 1;
+                        loop {
+                            match chars.get(j) {
+                                None => return Err(invalid("unclosed quote")),
+                                Some(&'\\') => {
+                                    let escaped = chars
+                                        .get(j + 1)
+                                        .ok_or_else(|| invalid("unclosed quote"))?;
+                                    field.push(*escaped);
+                                    j += 2;
+                                }
+                                Some(&c) if c == quote => break,
+                                Some(&c) => {
+                                    field.push(c);
+                                    j += 1;
+                                }
+                            }
+                        }
+                        j += 1;
+                        while j < chars.len() && chars[j] == ' ' {
+                            j += 1;
+                        }
+                        if chars.get(j) != Some(&']') {
+                            return Err(invalid("unclosed '['"));
+                        }
+                        segments.push(Segment::Field(field));
+                        i = j + 1;
+                    }
+                    _ => {
+                        let close = chars[i..]
+                            .iter()
+                            .position(|&c| c == ']')
+                            .map(|p| i + p)
+                            .ok_or_else(|| invalid("unclosed '['"))?;
+                        let inner: String = chars[i + 1..close].iter().collect();
+                        let index = inner
+                            .trim()
+                            .parse::<usize>()
+                            .map_err(|_| invalid("expected array index or quoted field"))?;
+                        segments.push(Segment::Index(index));
+                        i = close + 1;
+                    }
+                }
+            }
+            _ => return Err(invalid("expected '.' or '['")),
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Format segments back into a path string.
+pub fn format(segments: &[Segment]) -> String {
+    let mut path = "$".to_string();
+    for segment in segments {
+        match segment {
+            Segment::Field(name) if is_identifier(name) => {
+                path.push('.');
+                path.push_str(name);
+            }
+            Segment::Field(name) => {
+                path.push_str("['");
+                for c in name.chars() {
+                    if c == '\'' || c == '\\' {
+                        path.push('\\');
+                    }
+                    path.push(c);
+                }
+                path.push_str("']");
+            }
+            Segment::Index(index) => {
+                path.push_str(&format!("[{}]", index));
+            }
+        }
+    }
+    path
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Resolve segments against a document.
+pub fn resolve<'a>(doc: &'a JsonValue, segments: &[Segment]) -> Option<&'a JsonValue> {
+    segments
+        .iter()
+        .try_fold(doc, |current, segment| match (segment, current) {
+            (Segment::Field(name), JsonValue::Object(map)) => map.get(name),
+            (Segment::Index(index), JsonValue::Array(arr)) => arr.get(*index),
+            _ => None,
+        })
+}
+
+/// Index of the first segment that does not resolve, or `None` if the
+/// whole path resolves.
+pub fn first_missing(doc: &JsonValue, segments: &[Segment]) -> Option<usize> {
+    (0..segments.len()).find(|&i| resolve(doc, &segments[..=i]).is_none())
+}
+
+/// Set a value at a path, creating missing intermediate objects and arrays.
+///
+/// A missing field becomes an object when the next segment is a field and an
+/// array when it is an index. Arrays are padded with nulls up to the index,
+/// by at most [`MAX_PADDING`] elements. Fails when the path runs into a
+/// scalar or an index further past the end.
+pub fn set(doc: &mut JsonValue, segments: &[Segment], value: JsonValue) -> Result<()> {
+    let Some((first, rest)) = segments.split_first() else {
+        *doc = value;
+        return Ok(());
+    };
+
+    if doc.is_null() {
+        *doc = match first {
+            Segment::Field(_) => JsonValue::Object(Map::new()),
+            Segment::Index(_) => JsonValue::Array(Vec::new()),
+        };
+    }
+
+    let child = match (first, doc) {
+        (Segment::Field(name), JsonValue::Object(map)) => {
+            map.entry(name.clone()).or_insert(JsonValue::Null)
+        }
+        (Segment::Index(index), JsonValue::Array(arr)) => {
+            if index.saturating_sub(arr.len()) > MAX_PADDING {
+                return Err(McpError::InvalidArg {
+                    name: "path".to_string(),
+                    reason: format!(
+                        "index {} is more than {} past the end of an array of length {}",
+                        index,
+                        MAX_PADDING,
+                        arr.len()
+                    ),
+                });
+            }
+            if arr.len() <= *index {
+                arr.resize(*index + 1, JsonValue::Null);
+            }
+            &mut arr[*index]
+        }
+        (segment, other) => {
+            return Err(McpError::InvalidArg {
+                name: "path".to_string(),
+                reason: format!(
+                    "cannot address {} inside {}",
+                    format(std::slice::from_ref(segment)).trim_start_matches('$'),
+                    type_name(other)
+                ),
+            })
+        }
+    };
+
+    set(child, rest, value)
+}
+
+/// Visit every node of a document, root first, with the path leading to it.
+pub fn walk(doc: &JsonValue, visit: &mut impl FnMut(&[Segment], &JsonValue)) {
+    let mut path = Vec::new();
+    walk_at(doc, &mut path, visit);
+}
+
+fn walk_at(
+    node: &JsonValue,
+    path: &mut Vec<Segment>,
+    visit: &mut impl FnMut(&[Segment], &JsonValue),
+) {
+    visit(path, node);
+    match node {
+        JsonValue::Object(map) => {
+            for (name, child) in map {
+                path.push(Segment::Field(name.clone()));
+                walk_at(child, path, visit);
+                path.pop();
+            }
+        }
+        JsonValue::Array(arr) => {
+            for (index, child) in arr.iter().enumerate() {
+                path.push(Segment::Index(index));
+                walk_at(child, path, visit);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// JSON type name of a value, as reported to agents.
+pub fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_round_trip() {
+        let segments = parse("$.a['b c'][2].d").unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Field("a".to_string()),
+                Segment::Field("b c".to_string()),
+                Segment::Index(2),
+                Segment::Field("d".to_string()),
+            ]
+        );
+        assert_eq!(format(&segments), "$.a['b c'][2].d");
+        assert!(parse("$").unwrap().is_empty());
+
+        // Names a dotted path can't carry are bracket-quoted, escaping quotes
+        for name in ["a.b", "a[0]", "it's", "x y", "back\\slash", "]"] {
+            let segments = vec![Segment::Field(name.to_string())];
+            assert!(format(&segments).starts_with("$['"));
+            assert_eq!(parse(&format(&segments)).unwrap(), segments);
+        }
+        assert_eq!(format(&[Segment::Field("it's".to_string())]), "$['it\\'s']");
+        assert!(parse("a.b").is_err());
+        assert!(parse("$.a[x]").is_err());
+    }
+
+    #[test]
+    fn test_resolve_and_first_missing() {
+        let doc = json!({"a": {"b": [10, 20]}});
+        let segments = parse("$.a.b[1]").unwrap();
+        assert_eq!(resolve(&doc, &segments), Some(&json!(20)));
+        assert_eq!(first_missing(&doc, &segments), None);
+        assert_eq!(first_missing(&doc, &parse("$.a.x.y").unwrap()), Some(1));
+    }
+
+    #[test]
+    fn test_set_creates_parents() {
+        let mut doc = JsonValue::Null;
+        set(&mut doc, &parse("$.a.items[1].name").unwrap(), json!("x")).unwrap();
+        assert_eq!(doc, json!({"a": {"items": [null, {"name": "x"}]}}));
+
+        let mut scalar = json!({"a": 5});
+        assert!(set(&mut scalar, &parse("$.a.b").unwrap(), json!(1)).is_err());
+
+        let mut far = json!({"a": []});
+        assert!(set(&mut far, &parse("$.a[4000000000]").unwrap(), json!(1)).is_err());
+        assert_eq!(far, json!({"a": []}));
+    }
+
+    #[test]
+    fn test_walk_visits_every_node() {
+        let doc = json!({"a": [1, {"b": true}]});
+        let mut paths = Vec::new();
+        walk(&doc, &mut |path, _| paths.push(format(path)));
+        assert_eq!(paths, vec!["$", "$.a", "$.a[0]", "$.a[1]", "$.a[1].b"]);
+    }
+}
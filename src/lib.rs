@@ -3,11 +3,13 @@
 //! MCP (Model Context Protocol) server for Strata database.
 //!
 //! This crate provides an MCP server that exposes Strata database operations as tools
-//! for AI agents. It implements the MCP protocol over stdin/stdout using JSON-RPC 2.0.
+//! for AI agents. It implements the MCP protocol over stdin/stdout using JSON-RPC 2.0, with
+//! HTTP and WebSocket transports for long-lived services.
 //!
-//! ## 8 Agent-Friendly Tools
+//! ## 10 Agent-Friendly Tools
 //!
-//! `store`, `recall`, `search`, `forget`, `log`, `branch`, `history`, `status`
+//! `store`, `recall`, `search`, `forget`, `log`, `branch`, `history`, `status`, `orient`,
+//! `run_query`
 //!
 //! Backed by Strata's JSON document store with automatic semantic search indexing.
 //!
@@ -44,14 +46,34 @@
 
 #![warn(missing_docs)]
 
+mod completion;
 mod convert;
 mod error;
+mod http;
+mod jsonpath;
+mod prompts;
+mod resources;
+mod roots;
+mod sampling;
 mod server;
 mod session;
+mod shutdown;
 mod tools;
+mod ulid;
+#[cfg(unix)]
+mod unix;
+mod ws;
 
 pub use convert::{json_to_value, output_to_json, value_to_json};
 pub use error::{McpError, Result};
+pub use http::serve_http;
+pub use roots::OpenSession;
 pub use server::{JsonRpcRequest, JsonRpcResponse, McpServer};
-pub use session::McpSession;
+pub use session::{
+    EventTypeCase, McpSession, SessionBranch, SessionBranchEnd, ToolCallCounts, Warning,
+    WarningCode,
+};
 pub use tools::{ToolDef, ToolRegistry};
+#[cfg(unix)]
+pub use unix::{bind_unix_socket, serve_unix_socket};
+pub use ws::serve_websocket;
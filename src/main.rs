@@ -3,23 +3,41 @@
 //! Run with `strata-mcp --db /path/to/data` or `strata-mcp --cache` for in-memory mode.
 
 use clap::Parser;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use stratadb::{AccessMode, OpenOptions, Strata};
 use tracing_subscriber::EnvFilter;
 
+mod completion;
 mod convert;
 mod error;
+mod http;
+mod jsonpath;
+mod prompts;
+mod resources;
+mod roots;
+mod sampling;
 mod server;
 mod session;
+mod shutdown;
 mod tools;
+mod ulid;
+#[cfg(unix)]
+mod unix;
+mod ws;
 
+use roots::OpenSession;
 use server::McpServer;
-use session::McpSession;
+use session::{EventTypeCase, McpSession, SessionBranchEnd};
+use tools::ToolRegistry;
 
 /// MCP server for Strata database.
 ///
 /// Exposes Strata database operations as MCP tools for AI agents.
-/// Communicates via JSON-RPC 2.0 over stdin/stdout.
-#[derive(Parser)]
+/// Communicates via JSON-RPC 2.0 over stdin/stdout, or over HTTP (--http),
+/// WebSockets (--listen), or a Unix socket (--socket).
+#[derive(Clone, Parser)]
 #[command(name = "strata-mcp")]
 #[command(version, about, long_about = None)]
 struct Args {
@@ -33,6 +51,10 @@ struct Args {
     #[arg(long)]
     cache: bool,
 
+    /// Start sessions in SPACE instead of the default space.
+    #[arg(long, value_name = "SPACE")]
+    space: Option<String>,
+
     /// Open the database in read-only mode.
     /// Write operations will be rejected.
     #[arg(long)]
@@ -43,6 +65,146 @@ struct Args {
     #[arg(long)]
     auto_embed: bool,
 
+    /// Pin the embedding model (name and version) the index must be built with.
+    /// Startup fails if auto-embed runs, or the index was built with, a different model.
+    #[arg(long, value_name = "MODEL", requires = "auto_embed")]
+    embed_model: Option<String>,
+
+    /// Rebuild the embedding index when it was built with a model other than
+    /// --embed-model. Rewrites every document once.
+    #[arg(long, requires = "embed_model")]
+    reindex_embeddings: bool,
+
+    /// Fork a fresh branch from BASE at startup and work on it for this session.
+    /// Isolates concurrent agents sharing one database.
+    #[arg(long, value_name = "BASE")]
+    session_branch: Option<String>,
+
+    /// What to do with the session branch on exit: merge, delete, or keep.
+    #[arg(long, value_name = "MODE", default_value = "delete", requires = "session_branch")]
+    session_branch_end: SessionBranchEnd,
+
+    /// Fork a working branch from BASE (default: default) for each client
+    /// at its first write, named after the client and its session. The branch
+    /// is kept when the client leaves; agents merge it back with strata_branch.
+    #[arg(
+        long,
+        value_name = "BASE",
+        num_args = 0..=1,
+        default_missing_value = "default",
+        conflicts_with = "session_branch"
+    )]
+    branch_per_session: Option<String>,
+
+    /// Start a stdin/stdout session on the default branch and space instead
+    /// of the ones the last stdin/stdout session on this database left off on.
+    #[arg(long)]
+    no_resume: bool,
+
+    /// Return bare null for missing keys from strata_recall and strata_history,
+    /// instead of the { found: false } shape.
+    #[arg(long)]
+    legacy_null_reads: bool,
+
+    /// Report failed tool calls as JSON-RPC errors instead of results with
+    /// isError: true.
+    #[arg(long)]
+    legacy_tool_errors: bool,
+
+    /// Return tools/list in pages of N tools, linked by nextCursor.
+    #[arg(long, value_name = "N")]
+    tools_page_size: Option<usize>,
+
+    /// Reject strata_store calls that would replace an existing document
+    /// unless they pass an explicit path or replace: true.
+    #[arg(long)]
+    require_explicit_replace: bool,
+
+    /// Normalize strata_log event types: exact (keep as given), lower, or
+    /// snake (UserAction and user-action both become user_action).
+    #[arg(long, value_name = "MODE", default_value = "exact")]
+    event_type_case: EventTypeCase,
+
+    /// Add the strata_developer tool, giving agents on-demand access to the
+    /// granular developer tools.
+    #[arg(long)]
+    developer_access: bool,
+
+    /// Add the strata_attach tool, letting agents attach other databases
+    /// read-only and read them with alias:key keys.
+    #[arg(long)]
+    allow_attach: bool,
+
+    /// Add the strata_debug_* tools: an echo of parsed arguments and session
+    /// context, a list of recent writes, tool schemas, and per-tool call
+    /// counts. For debugging integrations only.
+    #[arg(long)]
+    debug: bool,
+
+    /// Coalesce repeated whole-document stores to the same key within MS
+    /// milliseconds into a single version. Intermediate values are not kept.
+    #[arg(long, value_name = "MS")]
+    coalesce_writes: Option<u64>,
+
+    /// Let strata_export_ndjson and the import tools write and read files
+    /// in DIR. Paths are relative to it; without it they are refused.
+    #[arg(long, value_name = "DIR")]
+    export_dir: Option<PathBuf>,
+
+    /// Give each workspace root the client reports its own database at
+    /// DIR inside the root (e.g. .strata). The --db or --cache database
+    /// serves clients until they report a root.
+    #[arg(long, value_name = "DIR", conflicts_with = "http")]
+    root_db: Option<PathBuf>,
+
+    /// Serve newline-delimited JSON-RPC on a Unix domain socket at PATH
+    /// instead of stdin/stdout, with a separate session for each connection.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["http", "listen"])]
+    socket: Option<String>,
+
+    /// Serve JSON-RPC over WebSockets at URL (ws://HOST:PORT) instead of
+    /// stdin/stdout, with a separate session for each connection.
+    #[arg(long, value_name = "URL", conflicts_with = "http")]
+    listen: Option<String>,
+
+    /// Accept --listen handshakes from web pages at ORIGIN (e.g.
+    /// https://app.example.com) as well as localhost. Repeatable.
+    #[arg(long, value_name = "ORIGIN", requires = "listen")]
+    allowed_origin: Vec<String>,
+
+    /// Serve the MCP Streamable HTTP transport at ADDR (e.g. 127.0.0.1:8080)
+    /// instead of stdin/stdout, with a separate session for each client.
+    #[arg(long, value_name = "ADDR", conflicts_with = "coalesce_writes")]
+    http: Option<String>,
+
+    /// Let --http bind a non-loopback address and serve clients on other
+    /// machines. The transport has no authentication: put a proxy that
+    /// authenticates clients in front of it.
+    #[arg(long)]
+    allow_remote: bool,
+
+    /// Run the stdin/stdout loop on the tokio core: pings are answered while
+    /// a tool call runs, and calls can report progress notifications.
+    #[arg(long = "async", conflicts_with_all = ["http", "listen", "socket"])]
+    async_core: bool,
+
+    /// With --async, run read-only tool calls in parallel; responses may
+    /// arrive out of order and are matched by request id.
+    #[arg(long, requires = "async_core")]
+    concurrent: bool,
+
+    /// Exit cleanly after SECS seconds without a request on stdin/stdout,
+    /// so servers orphaned by crashed clients don't pile up. Pings count.
+    #[arg(long, value_name = "SECS", conflicts_with_all = ["http", "listen", "socket"])]
+    idle_shutdown: Option<u64>,
+
+    /// Fail tool calls that run longer than SECS seconds with a timeout
+    /// error; TOOL=SECS sets one tool's limit, 0 removes the limit.
+    /// Repeatable. Defaults: 60, strata_models_pull 1800, and 300 for
+    /// strata_generate and strata_summarize.
+    #[arg(long, value_name = "[TOOL=]SECS")]
+    tool_timeout: Vec<String>,
+
     /// Enable debug logging to stderr.
     #[arg(long, short)]
     verbose: bool,
@@ -94,15 +256,7 @@ fn main() {
         }
     } else {
         let path = args.db.as_ref().unwrap();
-        let mut opts = OpenOptions::new();
-        if args.read_only {
-            opts = opts.access_mode(AccessMode::ReadOnly);
-        }
-        if args.auto_embed {
-            opts = opts.auto_embed(true);
-        }
-
-        match Strata::open_with(path, opts) {
+        match Strata::open_with(path, open_options(&args)) {
             Ok(db) => db,
             Err(e) => {
                 eprintln!("Error: Failed to open database at '{}': {}", path, e);
@@ -111,13 +265,301 @@ fn main() {
         }
     };
 
-    // Create session and server
-    let session = McpSession::new(db);
-    let mut server = McpServer::new(session);
+    // The pinned model is recorded with the database, so check it once
+    if let Some(model) = &args.embed_model {
+        let mut session = McpSession::new(db.clone());
+        if let Err(e) = pin_embed_model(&mut session, model, args.reindex_embeddings) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Workspace databases open once and are shared by their clients
+    let open_root = args.root_db.as_ref().map(|_| workspace_opener(&args));
+    let new_server = |db: Strata| build_server(db, &args, open_root.as_ref());
+
+    // One server per Unix socket connection
+    if let Some(path) = &args.socket {
+        #[cfg(unix)]
+        let result = unix::bind_unix_socket(std::path::Path::new(path))
+            .and_then(|listener| {
+                tracing::info!("Serving MCP on Unix socket {}", path);
+                // Remove the socket file on the way out, so it doesn't linger
+                let socket = std::path::PathBuf::from(path);
+                shutdown::watch(move |signal| {
+                    tracing::info!("{}; removing {}", signal, socket.display());
+                    let _ = std::fs::remove_file(&socket);
+                    std::process::exit(0);
+                });
+                let served = unix::serve_unix_socket(listener, || new_server(db.clone()));
+                let _ = std::fs::remove_file(path);
+                served
+            })
+            .map_err(|e| e.to_string());
+        #[cfg(not(unix))]
+        let result: Result<(), String> =
+            Err(format!("--socket {} needs a Unix platform", path));
+        if let Err(e) = result {
+            eprintln!("Error: Server error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // One server per WebSocket connection
+    if let Some(url) = &args.listen {
+        let result = ws_address(url)
+            .and_then(|addr| std::net::TcpListener::bind(addr).map_err(|e| e.to_string()))
+            .and_then(|listener| {
+                tracing::info!("Serving MCP over WebSockets at {}", url);
+                let new_client = || new_server(db.clone());
+                ws::serve_websocket(listener, new_client, &args.allowed_origin)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            eprintln!("Error: Server error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // One server per HTTP client session
+    if let Some(addr) = &args.http {
+        let result = std::net::TcpListener::bind(addr)
+            .map_err(|e| e.to_string())
+            .and_then(|listener| {
+                tracing::info!("Serving MCP over HTTP at http://{}{}", addr, http::ENDPOINT);
+                let new_client = || new_server(db.clone());
+                http::serve_http(listener, new_client, args.allow_remote).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            eprintln!("Error: Server error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut server = match new_server(db) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Pick up on the branch and space the last stdio session left off on;
+    // a session branch is new each run, and --space says where to start
+    let fresh = args.session_branch.is_some() || args.branch_per_session.is_some();
+    if !args.no_resume && !fresh && args.space.is_none() {
+        match server.session_mut().resume_position() {
+            Ok(Some((branch, space))) => {
+                tracing::info!("Resumed on branch '{}', space '{}'", branch, space)
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Warning: Failed to resume the last session's position: {}", e),
+        }
+    }
+
+    // SIGINT and SIGTERM drain stdio instead of killing it mid-request
+    server.set_graceful_shutdown(true);
+
+    // The async core winds the session down itself
+    if args.async_core {
+        server.set_concurrent_reads(args.concurrent);
+        let result = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())
+            .and_then(|runtime| {
+                let result = runtime.block_on(server.run_async()).map_err(|e| e.to_string());
+                // After an early shutdown a stdin read is still blocked; don't wait on it
+                runtime.shutdown_background();
+                result
+            });
+        if let Err(e) = result {
+            eprintln!("Error: Server error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     // Run the server
-    if let Err(e) = server.run_sync() {
+    let result = server.run_sync();
+
+    // Persist any coalesced write still buffered
+    if let Err(e) = server.session_mut().flush_pending() {
+        eprintln!("Warning: Failed to flush buffered write: {}", e);
+    }
+
+    // Apply the session branch policy even if the server failed
+    if let Err(e) = server.session_mut().end_session_branch() {
+        eprintln!("Warning: Failed to close session branch: {}", e);
+    }
+
+    if let Err(e) = result {
         eprintln!("Error: Server error: {}", e);
         std::process::exit(1);
     }
 }
+
+/// How the command line asks for databases to be opened.
+fn open_options(args: &Args) -> OpenOptions {
+    let mut opts = OpenOptions::new();
+    if args.read_only {
+        opts = opts.access_mode(AccessMode::ReadOnly);
+    }
+    if args.auto_embed {
+        opts = opts.auto_embed(true);
+    }
+    opts
+}
+
+/// Open the session for a workspace database directory, opening the
+/// database itself (and checking its pinned model) only the first time.
+fn workspace_opener(args: &Args) -> OpenSession {
+    let args = args.clone();
+    let opened: Mutex<HashMap<PathBuf, Strata>> = Mutex::default();
+    Arc::new(move |dir: &Path| {
+        let db = {
+            let mut opened = opened.lock().unwrap_or_else(|e| e.into_inner());
+            match opened.get(dir) {
+                Some(db) => db.clone(),
+                None => {
+                    let db = Strata::open_with(dir, open_options(&args)).map_err(|e| {
+                        format!("Failed to open database at '{}': {}", dir.display(), e)
+                    })?;
+                    if let Some(model) = &args.embed_model {
+                        let mut session = McpSession::new(db.clone());
+                        pin_embed_model(&mut session, model, args.reindex_embeddings)?;
+                    }
+                    opened.insert(dir.to_path_buf(), db.clone());
+                    db
+                }
+            }
+        };
+        build_session(db, &args)
+    })
+}
+
+/// Create a session configured from the command line.
+fn build_session(db: Strata, args: &Args) -> Result<McpSession, String> {
+    let mut session = McpSession::new(db);
+    session.set_legacy_reads(args.legacy_null_reads);
+    session.set_require_explicit_replace(args.require_explicit_replace);
+    session.set_event_type_case(args.event_type_case);
+    session.set_embed_model(args.embed_model.clone());
+    session.set_export_dir(args.export_dir.clone());
+    if let Some(space) = &args.space {
+        session.switch_space(space);
+    }
+    if let Some(ms) = args.coalesce_writes {
+        // Nothing is buffered yet, so enabling can't fail
+        let _ = session.set_coalesce_window(Some(std::time::Duration::from_millis(ms)));
+    }
+    if let Some(base) = &args.session_branch {
+        let branch = session
+            .start_session_branch(base, args.session_branch_end)
+            .map_err(|e| format!("Failed to create session branch from '{}': {}", base, e))?;
+        tracing::info!("Session branch '{}' forked from '{}'", branch.name, base);
+    }
+    Ok(session)
+}
+
+/// Create a session configured from the command line, and a server for it.
+/// `open_root` opens the database for each workspace root under --root-db.
+fn build_server(
+    db: Strata,
+    args: &Args,
+    open_root: Option<&OpenSession>,
+) -> Result<McpServer, String> {
+    let session = build_session(db, args)?;
+    let mut registry = ToolRegistry::new();
+    if args.developer_access {
+        registry = registry.with_developer_access();
+    }
+    if args.allow_attach {
+        registry = registry.with_attach();
+    }
+    if args.debug {
+        registry = registry.with_debug_tools();
+    }
+    let mut server = McpServer::with_registry(session, registry);
+    server.set_legacy_tool_errors(args.legacy_tool_errors);
+    server.set_tools_page_size(args.tools_page_size);
+    server.set_idle_shutdown(args.idle_shutdown.map(std::time::Duration::from_secs));
+    server.set_branch_per_session(args.branch_per_session.clone());
+    for spec in &args.tool_timeout {
+        let (tool, secs) = match spec.split_once('=') {
+            Some((tool, secs)) => (Some(tool), secs),
+            None => (None, spec.as_str()),
+        };
+        let secs: u64 = secs
+            .parse()
+            .map_err(|_| format!("--tool-timeout expects [TOOL=]SECS, got '{}'", spec))?;
+        let limit = (secs > 0).then(|| std::time::Duration::from_secs(secs));
+        server.set_tool_timeout(tool, limit);
+    }
+    if let (Some(dir), Some(open)) = (&args.root_db, open_root) {
+        server.set_roots_dir(dir, open.clone());
+    }
+    Ok(server)
+}
+
+/// The `host:port` to bind for a `--listen` URL such as `ws://0.0.0.0:8765`.
+fn ws_address(url: &str) -> Result<&str, String> {
+    match url.strip_prefix("ws://") {
+        Some(rest) => Ok(rest.trim_end_matches('/')),
+        None if url.starts_with("wss://") => Err(
+            "wss:// is not supported; terminate TLS in a proxy in front of a ws:// listener"
+                .to_string(),
+        ),
+        None => Err(format!("--listen expects a ws://HOST:PORT URL, got '{}'", url)),
+    }
+}
+
+/// Check the index against the pinned embedding model, recording it on first
+/// use and rebuilding the index only when asked.
+fn pin_embed_model(session: &mut McpSession, model: &str, reindex: bool) -> Result<(), String> {
+    // A pin only means something if it is the model the engine embeds with
+    let engine = tools::embed::engine_model(session)
+        .map_err(|e| format!("Failed to read the engine's embedding model: {}", e))?;
+    match engine {
+        Some(engine) if engine == model => {}
+        Some(engine) => {
+            return Err(format!(
+                "auto-embed runs '{}', not '{}'; --embed-model must name the model in use",
+                engine, model
+            ))
+        }
+        None => {
+            return Err(format!(
+                "no embedding model is installed, so '{}' can't be pinned",
+                model
+            ))
+        }
+    }
+
+    session.set_embed_model(Some(model.to_string()));
+    let indexed = session
+        .indexed_embed_model()
+        .map_err(|e| format!("Failed to read the index's embedding model: {}", e))?;
+
+    match indexed {
+        Some(indexed) if indexed == model => Ok(()),
+        None if session.is_read_only() => Ok(()),
+        None => session
+            .record_embed_model(model)
+            .map_err(|e| format!("Failed to record the embedding model: {}", e)),
+        Some(indexed) if reindex => {
+            let rewritten = tools::embed::reindex(session, model)
+                .map_err(|e| format!("Failed to rebuild the embedding index: {}", e))?;
+            tracing::info!("Re-indexed {} documents with '{}' (was '{}')", rewritten, model, indexed);
+            Ok(())
+        }
+        Some(indexed) => Err(format!(
+            "the embedding index was built with '{}' but --embed-model is '{}'; \
+             pass --reindex-embeddings to rebuild it, or pin '{}'",
+            indexed, model, indexed
+        )),
+    }
+}
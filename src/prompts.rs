@@ -0,0 +1,157 @@
+//! MCP prompts: built-in workflows an agent or user can pull up by name.
+//!
+//! Each prompt is a single user message naming the tools to call and in what
+//! order, with the current branch, space, and status filled in so the agent
+//! starts from where the session actually is.
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::error::{McpError, Result};
+use crate::session::McpSession;
+use crate::tools::agent;
+
+/// A built-in prompt and the arguments it takes.
+struct Prompt {
+    name: &'static str,
+    description: &'static str,
+    /// `(name, description, required)` for each argument
+    arguments: &'static [(&'static str, &'static str, bool)],
+}
+
+const PROMPTS: &[Prompt] = &[
+    Prompt {
+        name: "summarize_memory",
+        description: "Survey what is stored on the current branch and summarize it",
+        arguments: &[("focus", "Topic to concentrate the summary on", false)],
+    },
+    Prompt {
+        name: "safe_experiment_workflow",
+        description: "Try a risky change on a forked branch, then merge or discard it",
+        arguments: &[("goal", "What the experiment should achieve", false)],
+    },
+    Prompt {
+        name: "recover_deleted_data",
+        description: "Find an earlier version of a deleted or overwritten key and restore it",
+        arguments: &[("key", "Key to recover", true)],
+    },
+];
+
+/// Handle `prompts/list`.
+pub fn list() -> JsonValue {
+    let prompts: Vec<JsonValue> = PROMPTS
+        .iter()
+        .map(|p| {
+            let arguments: Vec<JsonValue> = p
+                .arguments
+                .iter()
+                .map(|(name, description, required)| {
+                    serde_json::json!({
+                        "name": name,
+                        "description": description,
+                        "required": required,
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "name": p.name,
+                "description": p.description,
+                "arguments": arguments,
+            })
+        })
+        .collect();
+    serde_json::json!({ "prompts": prompts })
+}
+
+/// Handle `prompts/get`: the prompt's message with the session's context
+/// filled in, or `None` if no prompt is called `name`.
+pub fn get(
+    session: &mut McpSession,
+    name: &str,
+    arguments: &Map<String, JsonValue>,
+) -> Result<Option<JsonValue>> {
+    let Some(prompt) = PROMPTS.iter().find(|p| p.name == name) else {
+        return Ok(None);
+    };
+    let arg = |arg_name: &str| arguments.get(arg_name).and_then(|v| v.as_str());
+    for (arg_name, _, required) in prompt.arguments {
+        if *required && arg(arg_name).is_none() {
+            return Err(McpError::MissingArg(arg_name.to_string()));
+        }
+    }
+
+    let context = context(session)?;
+    let text = match name {
+        "summarize_memory" => {
+            let focus = match arg("focus") {
+                Some(focus) => format!(" Concentrate on anything about {}.", focus),
+                None => String::new(),
+            };
+            format!(
+                "{}\n\nSummarize what this database remembers.{} Start with strata_orient, \
+                 then use strata_search for the main topics you find and strata_recall to read \
+                 the documents that matter. Group the summary by topic, cite the keys you read, \
+                 and say what looks stale or contradictory. Don't modify anything.",
+                context, focus
+            )
+        }
+        "safe_experiment_workflow" => {
+            let goal = arg("goal").unwrap_or("the change you're about to make");
+            format!(
+                "{}\n\nTry {} without risking the current branch:\n\
+                 1. strata_branch {{\"action\": \"fork\", \"name\": \"<experiment>\"}} copies \
+                 '{branch}' with all its data; switch to the copy with strata_branch \
+                 {{\"action\": \"switch\", \"name\": \"<experiment>\"}}.\n\
+                 2. Make the changes there.\n\
+                 3. Switch back to '{branch}' and run strata_branch {{\"action\": \"diff\", \
+                 \"compare\": \"<experiment>\"}} to review them.\n\
+                 4. If they're good, strata_branch {{\"action\": \"merge\", \"source\": \
+                 \"<experiment>\"}}; if not, delete or archive the experiment branch.",
+                context,
+                goal,
+                branch = session.branch()
+            )
+        }
+        "recover_deleted_data" => {
+            let key = arg("key").unwrap_or_default();
+            format!(
+                "{}\n\nRecover '{}'. Call strata_history {{\"key\": \"{}\"}} to list its \
+                 versions; if it has none here, check the other branches with strata_branch \
+                 {{\"action\": \"list\"}}. Pick the last version from before the unwanted \
+                 change, confirm its value with strata_recall and 'as_of', then write it back \
+                 with strata_store. Say which version you restored and why.",
+                context, key, key
+            )
+        }
+        other => {
+            return Err(McpError::Internal(format!("prompt '{}' has no message", other)));
+        }
+    };
+
+    Ok(Some(serde_json::json!({
+        "description": prompt.description,
+        "messages": [{
+            "role": "user",
+            "content": { "type": "text", "text": text },
+        }],
+    })))
+}
+
+/// One line describing the session: branch, space, and database status.
+fn context(session: &mut McpSession) -> Result<String> {
+    let status = agent::dispatch(session, "strata_status", Map::new())?;
+    let mut line = format!(
+        "You are on branch '{}' in space '{}'.",
+        session.branch(),
+        session.space()
+    );
+    if let (Some(keys), Some(branches)) = (status["keys"].as_u64(), status["branches"].as_u64()) {
+        line.push_str(&format!(
+            " The database holds {} keys across {} branches.",
+            keys, branches
+        ));
+    }
+    if session.is_read_only() {
+        line.push_str(" It is open read-only, so nothing can be written.");
+    }
+    Ok(line)
+}
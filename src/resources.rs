@@ -0,0 +1,361 @@
+//! MCP resources: stored documents and event streams addressable by URI.
+//!
+//! Every JSON document is a resource at `strata://{branch}/{space}/doc/{key}`
+//! and every event type at `strata://{branch}/{space}/events/{type}`, or
+//! `strata://{branch}/events/{type}` in the default space, so clients can
+//! pull them into context without a tool call. An event resource holds the
+//! first `MAX_EVENTS` events of its type. Branch, space, key, and type are
+//! each percent-encoded into one segment, so a `/` in any of them is written
+//! `%2F`. Listing covers the documents in every space of the current branch,
+//! paged with the engine's key cursor; reading takes any branch.
+//! `resources/templates/list` publishes the URI shapes.
+
+use serde_json::Value as JsonValue;
+use stratadb::{Command, Output};
+
+use crate::convert::{output_to_json, value_to_json};
+use crate::error::{McpError, Result};
+use crate::roots::percent_decode;
+use crate::session::McpSession;
+use crate::tools::{meta, scan};
+
+/// URI scheme of every resource.
+pub const SCHEME: &str = "strata://";
+
+/// MIME type of every resource.
+const MIME_TYPE: &str = "application/json";
+
+/// Resources returned per `resources/list` page.
+const PAGE_SIZE: usize = 100;
+
+/// Most events an event resource holds.
+const MAX_EVENTS: u64 = 1000;
+
+/// Space of event URIs that name none.
+const DEFAULT_SPACE: &str = "default";
+
+/// What a resource URI points at.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// The document stored under a key.
+    Document(String),
+    /// Every event logged with a type.
+    Events(String),
+}
+
+/// A parsed resource URI.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ResourceUri {
+    /// Branch holding the resource.
+    pub branch: String,
+    /// Space holding the resource.
+    pub space: String,
+    /// The document or event stream within the space.
+    pub kind: ResourceKind,
+}
+
+impl ResourceUri {
+    /// Parse `strata://{branch}/{space}/doc/{key}`,
+    /// `strata://{branch}/{space}/events/{type}`, or
+    /// `strata://{branch}/events/{type}`.
+    pub fn parse(uri: &str) -> Result<Self> {
+        let invalid = |reason: &str| McpError::InvalidArg {
+            name: "uri".to_string(),
+            reason: format!("{} in '{}'", reason, uri),
+        };
+        let rest = uri
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| invalid("URI must start with 'strata://'"))?;
+        let parts: Vec<&str> = rest.split('/').collect();
+        let (branch, space, kind, name) = match parts[..] {
+            [branch, space, kind, name] => (branch, space, kind, name),
+            [branch, "events", name] => (branch, DEFAULT_SPACE, "events", name),
+            _ => {
+                return Err(invalid("expected strata://{branch}/{space}/{doc|events}/{name}"));
+            }
+        };
+        let decode = |part: &str| {
+            percent_decode(part).ok_or_else(|| invalid("malformed percent-encoding"))
+        };
+        let (branch, space, name) = (decode(branch)?, decode(space)?, decode(name)?);
+        if branch.is_empty() || space.is_empty() || name.is_empty() {
+            return Err(invalid("branch, space, and name must not be empty"));
+        }
+        let kind = match kind {
+            "doc" => ResourceKind::Document(name),
+            "events" => ResourceKind::Events(name),
+            other => return Err(invalid(&format!("unknown resource kind '{}'", other))),
+        };
+        Ok(Self {
+            branch,
+            space,
+            kind,
+        })
+    }
+
+    /// Format as a resource URI.
+    pub fn to_uri(&self) -> String {
+        let kind = match &self.kind {
+            ResourceKind::Document(_) => "doc",
+            ResourceKind::Events(event_type) if self.space == DEFAULT_SPACE => {
+                let branch = percent_encode(&self.branch);
+                return format!("{}{}/events/{}", SCHEME, branch, percent_encode(event_type));
+            }
+            ResourceKind::Events(_) => "events",
+        };
+        format!(
+            "{}{}/{}/{}/{}",
+            SCHEME,
+            percent_encode(&self.branch),
+            percent_encode(&self.space),
+            kind,
+            percent_encode(self.name())
+        )
+    }
+
+    /// The document key or event type.
+    pub fn name(&self) -> &str {
+        match &self.kind {
+            ResourceKind::Document(key) => key,
+            ResourceKind::Events(event_type) => event_type,
+        }
+    }
+}
+
+/// Percent-encode every byte of `s` but the URI unreserved characters, so it
+/// fits in one path segment.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Handle `resources/templates/list`: the URI shapes clients can fill in.
+pub fn templates() -> JsonValue {
+    serde_json::json!({
+        "resourceTemplates": [
+            {
+                "uriTemplate": "strata://{branch}/{space}/doc/{key}",
+                "name": "Document",
+                "description": "The latest version of the JSON document stored under a key",
+                "mimeType": MIME_TYPE,
+            },
+            {
+                "uriTemplate": "strata://{branch}/events/{type}",
+                "name": "Events",
+                "description": events_description(DEFAULT_SPACE),
+                "mimeType": MIME_TYPE,
+            },
+            {
+                "uriTemplate": "strata://{branch}/{space}/events/{type}",
+                "name": "Events in a space",
+                "description": events_description("a space"),
+                "mimeType": MIME_TYPE,
+            },
+        ]
+    })
+}
+
+/// What an event resource template holds, for events logged in `space`.
+fn events_description(space: &str) -> String {
+    format!(
+        "The first {} events logged with a type in {}, oldest first",
+        MAX_EVENTS, space
+    )
+}
+
+/// One page of `resources/list`: every document on the current branch,
+/// ordered by space then key. `cursor` is the opaque `nextCursor` of the
+/// previous page: the space it stopped in and the engine's cursor there.
+pub fn list(session: &mut McpSession, cursor: Option<&str>) -> Result<JsonValue> {
+    let spaces = session.execute(Command::SpaceList {
+        branch: session.branch_id(),
+    })?;
+    let mut spaces: Vec<String> = match spaces {
+        Output::SpaceList(spaces) => spaces,
+        _ => Vec::new(),
+    };
+    spaces.sort();
+
+    let (mut index, mut key_cursor) = match cursor {
+        None => (0, None),
+        Some(c) => {
+            let unknown = || McpError::InvalidArg {
+                name: "cursor".to_string(),
+                reason: format!("unknown cursor '{}'", c),
+            };
+            let (space, key_cursor) = c.split_once('/').ok_or_else(unknown)?;
+            let space = percent_decode(space).ok_or_else(unknown)?;
+            // A space deleted since the last page resumes at the one after it
+            let index = spaces.partition_point(|s| *s < space);
+            let key_cursor = if spaces.get(index) == Some(&space) && !key_cursor.is_empty() {
+                Some(key_cursor.to_string())
+            } else {
+                None
+            };
+            (index, key_cursor)
+        }
+    };
+
+    let branch = session.branch().to_string();
+    let mut resources = Vec::new();
+    let mut next = None;
+    while let Some(space) = spaces.get(index) {
+        if resources.len() == PAGE_SIZE {
+            next = Some(format!("{}/{}", percent_encode(space), key_cursor.unwrap_or_default()));
+            break;
+        }
+        let limit = (PAGE_SIZE - resources.len()) as u64;
+        let page = scan::list_page_in(session, space, key_cursor.take(), limit)?;
+        for key in page.keys {
+            let uri = ResourceUri {
+                branch: branch.clone(),
+                space: space.clone(),
+                kind: ResourceKind::Document(key),
+            };
+            resources.push(serde_json::json!({
+                "uri": uri.to_uri(),
+                "name": uri.name(),
+                "description": format!("Document in space '{}' on branch '{}'", space, branch),
+                "mimeType": MIME_TYPE,
+            }));
+        }
+        match page.cursor {
+            Some(cursor) => key_cursor = Some(cursor),
+            None => index += 1,
+        }
+    }
+
+    let mut page = serde_json::json!({ "resources": resources });
+    if let Some(next) = next {
+        page["nextCursor"] = JsonValue::String(next);
+    }
+    Ok(page)
+}
+
+/// Handle `resources/read`: the document or events at `uri` as JSON text,
+/// or `None` if there's no such document.
+pub fn read(session: &mut McpSession, uri: &str) -> Result<Option<JsonValue>> {
+    let resource = ResourceUri::parse(uri)?;
+    let branch = Some(resource.branch.clone().into());
+    let space = Some(resource.space.clone());
+    let value = match &resource.kind {
+        ResourceKind::Document(key) if meta::is_reserved(key) => return Ok(None),
+        ResourceKind::Document(key) => {
+            let cmd = Command::JsonGet {
+                branch,
+                space,
+                key: key.clone(),
+                path: "$".to_string(),
+                as_of: None,
+            };
+            match session.execute(cmd)? {
+                Output::MaybeVersioned(Some(vv)) => value_to_json(vv.value),
+                _ => return Ok(None),
+            }
+        }
+        // A type with no events yet is an empty stream, not a missing resource
+        ResourceKind::Events(event_type) => {
+            let cmd = Command::EventGetByType {
+                branch,
+                space,
+                event_type: event_type.clone(),
+                limit: Some(MAX_EVENTS),
+                after_sequence: None,
+                as_of: None,
+            };
+            match output_to_json(session.execute(cmd)?) {
+                JsonValue::Null => JsonValue::Array(Vec::new()),
+                events => events,
+            }
+        }
+    };
+    Ok(Some(serde_json::json!({
+        "contents": [{
+            "uri": resource.to_uri(),
+            "mimeType": MIME_TYPE,
+            "text": value.to_string(),
+        }]
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resource_uri() {
+        let doc = ResourceUri::parse("strata://main/default/doc/notes%2Ftoday").unwrap();
+        assert_eq!(doc.branch, "main");
+        assert_eq!(doc.space, "default");
+        assert_eq!(doc.kind, ResourceKind::Document("notes/today".to_string()));
+        assert_eq!(doc.to_uri(), "strata://main/default/doc/notes%2Ftoday");
+
+        // Branch names may hold '/' too
+        let doc = ResourceUri {
+            branch: "feature/x".to_string(),
+            space: "default".to_string(),
+            kind: ResourceKind::Document("a b".to_string()),
+        };
+        assert_eq!(doc.to_uri(), "strata://feature%2Fx/default/doc/a%20b");
+        assert_eq!(ResourceUri::parse(&doc.to_uri()).unwrap(), doc);
+
+        let events = ResourceUri::parse("strata://dev/default/events/tool_call").unwrap();
+        assert_eq!(events.kind, ResourceKind::Events("tool_call".to_string()));
+        assert_eq!(events.to_uri(), "strata://dev/events/tool_call");
+        assert_eq!(ResourceUri::parse("strata://dev/events/tool_call").unwrap(), events);
+        let events = ResourceUri::parse("strata://dev/audit/events/login").unwrap();
+        assert_eq!(events.space, "audit");
+        assert_eq!(events.to_uri(), "strata://dev/audit/events/login");
+
+        assert!(ResourceUri::parse("http://main/default/doc/k").is_err());
+        assert!(ResourceUri::parse("strata://main/default/k").is_err());
+        assert!(ResourceUri::parse("strata://main/default/blob/k").is_err());
+        assert!(ResourceUri::parse("strata://main//doc/k").is_err());
+        assert!(ResourceUri::parse("strata://feature/x/default/doc/k").is_err());
+        assert!(ResourceUri::parse("strata://main/default/doc/%zz").is_err());
+    }
+
+    #[test]
+    fn test_list_pages_through_every_space() {
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let mut session = McpSession::new(db);
+        let registry = crate::tools::ToolRegistry::new();
+        for (space, count) in [("default", PAGE_SIZE + 20), ("other", 30)] {
+            for i in 0..count {
+                let key = format!("k{}", i);
+                let args = serde_json::json!({"key": key, "value": i, "space": space});
+                let args = args.as_object().cloned().unwrap();
+                registry.dispatch(&mut session, "strata_store", args).unwrap();
+            }
+        }
+
+        let mut uris = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut pages = 0;
+        loop {
+            let page = list(&mut session, cursor.as_deref()).unwrap();
+            pages += 1;
+            let resources = page["resources"].as_array().unwrap();
+            assert!(resources.len() <= PAGE_SIZE);
+            uris.extend(resources.iter().map(|r| r["uri"].as_str().unwrap().to_string()));
+            match page["nextCursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 2);
+        assert_eq!(uris.len(), PAGE_SIZE + 50);
+        uris.sort();
+        uris.dedup();
+        assert_eq!(uris.len(), PAGE_SIZE + 50);
+        assert!(uris.contains(&"strata://default/other/doc/k0".to_string()));
+        assert!(list(&mut session, Some("no-separator")).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Workspace roots: a separate database for each project a client opens.
+//!
+//! With a roots directory set (`--root-db`), the server asks a client that
+//! declared the `roots` capability for its workspace roots once it sends
+//! `initialized`, and again on `notifications/roots/list_changed`. The
+//! database for a `file://` root lives at `{root}/{dir}`, and the session
+//! moves to the first root's database. Until a root arrives, and for clients
+//! without roots, the server keeps the database it started with, and a
+//! session with a transaction open moves once the transaction ends. Every
+//! socket or WebSocket connection asks its own client, so one server keeps
+//! each project's data apart.
+
+use serde_json::Value as JsonValue;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::session::McpSession;
+
+/// Opens the session for a workspace's database directory.
+pub type OpenSession = Arc<dyn Fn(&Path) -> std::result::Result<McpSession, String> + Send + Sync>;
+
+/// Prefix of the ids of `roots/list` requests the server sends.
+const REQUEST_ID_PREFIX: &str = "strata-roots-";
+
+/// One client's workspace roots and the database they map to.
+pub(crate) struct Roots {
+    /// Directory, relative to a root, that holds its database
+    dir: PathBuf,
+    open: OpenSession,
+    /// The client declared the `roots` capability
+    supported: bool,
+    /// A `roots/list` request should go out with the next notifications
+    due: bool,
+    /// Id of the `roots/list` request awaiting a response
+    pending: Option<JsonValue>,
+    /// `roots/list` requests sent so far
+    sent: u64,
+    /// Database directory the session was moved to
+    active: Option<PathBuf>,
+    /// Database directory to move to once the open transaction ends
+    waiting: Option<PathBuf>,
+}
+
+impl Roots {
+    pub(crate) fn new(dir: PathBuf, open: OpenSession) -> Self {
+        Self {
+            dir,
+            open,
+            supported: false,
+            due: false,
+            pending: None,
+            sent: 0,
+            active: None,
+            waiting: None,
+        }
+    }
+
+    /// Note whether the client's `initialize` capabilities include roots.
+    pub(crate) fn set_client_capabilities(&mut self, capabilities: Option<&JsonValue>) {
+        self.supported = capabilities.and_then(|c| c.get("roots")).is_some();
+    }
+
+    /// Ask the client for its roots, if it has any to give.
+    pub(crate) fn refresh(&mut self) {
+        self.due = self.supported;
+    }
+
+    /// The serialized `roots/list` request to send, if one is due.
+    pub(crate) fn take_request(&mut self) -> Option<String> {
+        if !std::mem::take(&mut self.due) {
+            return None;
+        }
+        self.sent += 1;
+        let id = JsonValue::String(format!("{}{}", REQUEST_ID_PREFIX, self.sent));
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "roots/list",
+        });
+        self.pending = Some(id);
+        Some(request.to_string())
+    }
+
+    /// The database directory a client response asks to move to, or `None`
+    /// if it isn't the awaited `roots/list` response, failed, names no
+    /// `file://` root, or maps to the database already in use.
+    pub(crate) fn database_for(&mut self, response: &JsonValue) -> Option<PathBuf> {
+        if self.pending.is_none() || response.get("id") != self.pending.as_ref() {
+            return None;
+        }
+        self.pending = None;
+        // The latest roots replace any the session was waiting to move to
+        self.waiting = None;
+        if let Some(error) = response.get("error") {
+            tracing::warn!("Client failed to list its roots: {}", error);
+            return None;
+        }
+        let roots = response.get("result")?.get("roots")?.as_array()?;
+        let root = roots
+            .iter()
+            .find_map(|root| file_uri_path(root.get("uri")?.as_str()?))?;
+        let dir = root.join(&self.dir);
+        (self.active.as_ref() != Some(&dir)).then_some(dir)
+    }
+
+    /// Open the session for the database at `dir`, which becomes the one in use.
+    /// Move to `dir` once the session's open transaction ends.
+    pub(crate) fn wait_for(&mut self, dir: PathBuf) {
+        self.waiting = Some(dir);
+    }
+
+    /// The database directory the session was waiting to move to, clearing it.
+    pub(crate) fn take_waiting(&mut self) -> Option<PathBuf> {
+        self.waiting.take()
+    }
+
+    pub(crate) fn open(&mut self, dir: &Path) -> std::result::Result<McpSession, String> {
+        let session = (self.open)(dir)?;
+        self.active = Some(dir.to_path_buf());
+        Ok(session)
+    }
+}
+
+/// The local path of a `file://` URI, or `None` for any other URI.
+fn file_uri_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        return None;
+    }
+    let path = percent_decode(path)?;
+    // file:///C:/work names C:/work
+    #[cfg(windows)]
+    let path = match path.strip_prefix('/') {
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => rest.to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Decode `%XX` escapes, or `None` if one is malformed or the result isn't UTF-8.
+pub(crate) fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_uri_path() {
+        assert_eq!(
+            file_uri_path("file:///home/me/my%20project"),
+            Some(PathBuf::from("/home/me/my project"))
+        );
+        assert_eq!(
+            file_uri_path("file://localhost/srv/app"),
+            Some(PathBuf::from("/srv/app"))
+        );
+        assert_eq!(file_uri_path("https://example.com/repo"), None);
+        assert_eq!(file_uri_path("file://host/share"), None);
+        assert_eq!(file_uri_path("file:///bad%zz"), None);
+    }
+}
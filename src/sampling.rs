@@ -0,0 +1,191 @@
+//! Sampling: having the client's model write text for a tool call.
+//!
+//! A client that declares the `sampling` capability at `initialize` can
+//! answer `sampling/createMessage` requests with its own model. The server
+//! sends one for each strata_summarize call, sets the call aside, and
+//! answers it when the client's response arrives; other requests keep being
+//! served meanwhile. A call stops waiting when it is cancelled, runs out of
+//! time, or gets no answer within [`RESPONSE_WAIT`], and the client is told
+//! to drop the request.
+
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::error::McpError;
+use crate::tools::inference::SummaryRequest;
+
+/// Prefix of the ids of `sampling/createMessage` requests the server sends.
+const REQUEST_ID_PREFIX: &str = "strata-sampling-";
+
+/// Longest a call waits on the client's model before giving up on it.
+pub(crate) const RESPONSE_WAIT: Duration = Duration::from_secs(5 * 60);
+
+/// System prompt sent with every summary request.
+const SYSTEM_PROMPT: &str = "You summarize documents from a database for an AI agent. \
+                             Be concise and factual, and use only what the documents say.";
+
+/// A strata_summarize call waiting on the client's model.
+pub(crate) struct PendingSummary {
+    /// Id of the `tools/call` request to answer
+    pub id: Option<JsonValue>,
+    /// Keys of the documents being summarized
+    pub keys: Vec<String>,
+    /// The call's arguments, for the local fallback
+    pub args: Map<String, JsonValue>,
+    /// When the call runs out of time, and the error it gets then
+    pub timeout: Option<(Instant, McpError)>,
+    /// When to stop waiting on the client's answer
+    pub wait_until: Instant,
+}
+
+impl PendingSummary {
+    /// When the call stops waiting, out of time or out of patience.
+    fn deadline(&self) -> Instant {
+        match &self.timeout {
+            Some((at, _)) => self.wait_until.min(*at),
+            None => self.wait_until,
+        }
+    }
+}
+
+/// Text the client's model wrote.
+pub(crate) struct Sample {
+    pub text: String,
+    /// Model the client chose
+    pub model: String,
+}
+
+/// One client's sampling requests.
+#[derive(Default)]
+pub(crate) struct Sampling {
+    /// The client declared the `sampling` capability
+    supported: bool,
+    /// Requests to send with the next notifications
+    outgoing: Vec<String>,
+    /// Calls waiting on a response, by the id of the request sent for each
+    pending: HashMap<String, PendingSummary>,
+    /// Requests sent so far
+    sent: u64,
+}
+
+impl Sampling {
+    /// Note whether the client's `initialize` capabilities include sampling.
+    pub(crate) fn set_client_capabilities(&mut self, capabilities: Option<&JsonValue>) {
+        self.supported = capabilities.and_then(|c| c.get("sampling")).is_some();
+    }
+
+    /// Whether the client can be asked to sample.
+    pub(crate) fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    /// Queue a `sampling/createMessage` request for a strata_summarize call.
+    pub(crate) fn request_summary(
+        &mut self,
+        id: Option<JsonValue>,
+        summary: SummaryRequest,
+        args: Map<String, JsonValue>,
+        timeout: Option<(Instant, McpError)>,
+    ) {
+        self.sent += 1;
+        let request_id = format!("{}{}", REQUEST_ID_PREFIX, self.sent);
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": "sampling/createMessage",
+            "params": {
+                "messages": [{
+                    "role": "user",
+                    "content": { "type": "text", "text": summary.prompt },
+                }],
+                "systemPrompt": SYSTEM_PROMPT,
+                "includeContext": "none",
+                "maxTokens": summary.max_tokens,
+            },
+        });
+        self.outgoing.push(request.to_string());
+        self.pending.insert(
+            request_id,
+            PendingSummary {
+                id,
+                keys: summary.keys,
+                args,
+                timeout,
+                wait_until: Instant::now() + RESPONSE_WAIT,
+            },
+        );
+    }
+
+    /// The serialized requests to send, clearing them.
+    pub(crate) fn take_requests(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    /// When the first waiting call stops waiting.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().map(PendingSummary::deadline).min()
+    }
+
+    /// Stop waiting on the calls whose deadline is past by `now`, returning
+    /// them.
+    pub(crate) fn take_expired(&mut self, now: Instant) -> Vec<PendingSummary> {
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, call)| call.deadline() <= now)
+            .map(|(request_id, _)| request_id.clone())
+            .collect();
+        expired.iter().filter_map(|request_id| self.abandon(request_id)).collect()
+    }
+
+    /// Stop waiting on the call answering the `tools/call` request `id`.
+    pub(crate) fn cancel(&mut self, id: &JsonValue) -> Option<PendingSummary> {
+        let request_id = self
+            .pending
+            .iter()
+            .find(|(_, call)| call.id.as_ref() == Some(id))
+            .map(|(request_id, _)| request_id.clone())?;
+        self.abandon(&request_id)
+    }
+
+    /// Stop waiting on every call, returning them.
+    pub(crate) fn take_all(&mut self) -> Vec<PendingSummary> {
+        let all: Vec<String> = self.pending.keys().cloned().collect();
+        all.iter().filter_map(|request_id| self.abandon(request_id)).collect()
+    }
+
+    /// Drop a call's request, telling the client to stop working on it.
+    fn abandon(&mut self, request_id: &str) -> Option<PendingSummary> {
+        let call = self.pending.remove(request_id)?;
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/cancelled",
+            "params": { "requestId": request_id, "reason": "no longer needed" },
+        });
+        self.outgoing.push(notification.to_string());
+        Some(call)
+    }
+
+    /// The call a client response answers, with the model's text or the
+    /// reason there is none, or `None` if it answers no sampling request.
+    pub(crate) fn complete(
+        &mut self,
+        response: &JsonValue,
+    ) -> Option<(PendingSummary, std::result::Result<Sample, String>)> {
+        let call = self.pending.remove(response.get("id")?.as_str()?)?;
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("sampling failed");
+            return Some((call, Err(message.to_string())));
+        }
+        let result = &response["result"];
+        let sample = match result["content"]["text"].as_str() {
+            Some(text) => Ok(Sample {
+                text: text.to_string(),
+                model: result["model"].as_str().unwrap_or_default().to_string(),
+            }),
+            None => Err("the client's model returned no text".to_string()),
+        };
+        Some((call, sample))
+    }
+}
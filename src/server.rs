@@ -1,21 +1,86 @@
 //! MCP server implementation.
 //!
-//! Handles JSON-RPC 2.0 over stdio according to the MCP protocol specification.
+//! Handles JSON-RPC 2.0 over stdio according to the MCP protocol specification,
+//! or over HTTP, WebSockets, or a Unix socket through the transports in
+//! `crate::http`, `crate::ws`, and `crate::unix`.
 
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
 use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use crate::completion;
 use crate::error::{rpc_codes, McpError, Result};
-use crate::session::McpSession;
-use crate::tools::ToolRegistry;
+use crate::prompts;
+use crate::resources;
+use crate::roots::{OpenSession, Roots};
+use crate::sampling::{PendingSummary, Sample, Sampling};
+use crate::session::{McpSession, SessionBranchEnd, MAX_BRANCH_NAME_LEN};
+use crate::shutdown;
+use crate::tools::{inference, ToolRegistry};
 
-/// MCP protocol version we support.
-const PROTOCOL_VERSION: &str = "2024-11-05";
+/// MCP protocol versions we support, oldest first.
+///
+/// `2025-03-26` added tool annotations; `2025-06-18` added structured tool
+/// results. Responses leave out what the negotiated version lacks.
+const PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
+/// First version whose tool definitions carry `annotations`.
+const ANNOTATIONS_VERSION: &str = "2025-03-26";
+
+/// First version whose tool results carry `structuredContent`, and whose
+/// tool definitions carry `outputSchema`.
+const STRUCTURED_CONTENT_VERSION: &str = "2025-06-18";
+
+/// MCP log levels, least severe first.
+const LOG_LEVELS: &[&str] = &[
+    "debug",
+    "info",
+    "notice",
+    "warning",
+    "error",
+    "critical",
+    "alert",
+    "emergency",
+];
+
+/// How long a tool call may run unless configured otherwise.
+const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Tools expected to outlast the default, with their own limits in seconds.
+const LONG_RUNNING_TOOLS: &[(&str, u64)] = &[
+    ("strata_models_pull", 30 * 60),
+    ("strata_generate", 5 * 60),
+    ("strata_summarize", 5 * 60),
+];
 
 /// Server information.
 const SERVER_NAME: &str = "strata-mcp";
-const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub(crate) const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// JSON-RPC methods the server handles, with a one-line description each.
+const METHODS: &[(&str, &str)] = &[
+    ("initialize", "Initialize the server and negotiate capabilities"),
+    ("initialized", "Client acknowledgment after initialize"),
+    ("tools/list", "List available tools"),
+    ("tools/call", "Execute a tool"),
+    ("resources/list", "List stored documents as strata:// resources"),
+    ("resources/read", "Read a document or event stream by its strata:// URI"),
+    ("resources/templates/list", "List the strata:// URI templates for documents and events"),
+    ("prompts/list", "List the built-in workflow prompts"),
+    ("prompts/get", "Get a prompt with the current branch, space, and status filled in"),
+    ("completion/complete", "Suggest values for a tool, prompt, or resource template argument"),
+    ("logging/setLevel", "Set the least severe notifications/message level to send"),
+    ("ping", "Health check"),
+    ("notifications/cancelled", "Abort a running call (under --async) or one awaiting sampling"),
+    ("notifications/roots/list_changed", "Re-read the client's workspace roots"),
+    ("rpc.discover", "Describe every method and tool the server supports"),
+];
 
 /// JSON-RPC 2.0 request.
 #[derive(Debug, Deserialize)]
@@ -86,7 +151,37 @@ impl JsonRpcResponse {
 
     /// Create an error response from an McpError.
     pub fn from_error(id: Option<JsonValue>, err: McpError) -> Self {
-        Self::error(id, err.rpc_code(), err.to_string())
+        let mut response = Self::error(id, err.rpc_code(), err.to_string());
+        if let Some(error) = &mut response.error {
+            error.data = err.rpc_data();
+        }
+        response
+    }
+}
+
+/// How long each tool's calls may run; `None` lets them run as long as they take.
+#[derive(Clone)]
+struct ToolTimeouts {
+    default: Option<Duration>,
+    overrides: HashMap<String, Option<Duration>>,
+}
+
+impl Default for ToolTimeouts {
+    fn default() -> Self {
+        let overrides = LONG_RUNNING_TOOLS
+            .iter()
+            .map(|&(tool, secs)| (tool.to_string(), Some(Duration::from_secs(secs))))
+            .collect();
+        Self {
+            default: Some(DEFAULT_TOOL_TIMEOUT),
+            overrides,
+        }
+    }
+}
+
+impl ToolTimeouts {
+    fn get(&self, tool: &str) -> Option<Duration> {
+        self.overrides.get(tool).copied().unwrap_or(self.default)
     }
 }
 
@@ -95,26 +190,475 @@ pub struct McpServer {
     session: McpSession,
     registry: ToolRegistry,
     initialized: bool,
+    /// Report tool failures as JSON-RPC errors instead of `isError` results
+    legacy_tool_errors: bool,
+    /// Run read-only tool calls in parallel under `serve_async`
+    concurrent_reads: bool,
+    /// The tool list changed since clients were last told
+    tools_changed: bool,
+    /// Protocol version agreed at `initialize`
+    protocol_version: &'static str,
+    /// Tools per `tools/list` page, or `None` to list every tool at once
+    tools_page_size: Option<usize>,
+    /// Workspace roots mapped to their own databases
+    roots: Option<Roots>,
+    /// Tool calls waiting on the client's model
+    sampling: Sampling,
+    /// Responses to calls set aside, to send with the next notifications
+    answers: Vec<String>,
+    /// The request in hand may be answered after a later client response
+    can_defer: bool,
+    /// The request just handled will be answered after a client response
+    deferred: bool,
+    /// Stop serving stdio after this long without a request
+    idle_shutdown: Option<Duration>,
+    /// Drain and wind down on SIGINT or SIGTERM while serving stdio
+    graceful_shutdown: bool,
+    /// Index in `LOG_LEVELS` of the least severe message to send
+    log_level: usize,
+    /// How long tool calls may run
+    tool_timeouts: ToolTimeouts,
+    /// Branch to fork each client's working branch from at `initialize`
+    branch_per_session: Option<String>,
+    /// Name of this client's working branch, once chosen
+    client_branch: Option<String>,
+    /// Id of the client's session, set by transports that issue one
+    session_id: Option<String>,
 }
 
 impl McpServer {
     /// Create a new MCP server with the given session.
     pub fn new(session: McpSession) -> Self {
+        Self::with_registry(session, ToolRegistry::new())
+    }
+
+    /// Create a new MCP server exposing the tools in `registry`.
+    pub fn with_registry(session: McpSession, registry: ToolRegistry) -> Self {
         Self {
             session,
-            registry: ToolRegistry::new(),
+            registry,
             initialized: false,
+            legacy_tool_errors: false,
+            concurrent_reads: false,
+            tools_changed: false,
+            protocol_version: PROTOCOL_VERSIONS[0],
+            tools_page_size: None,
+            roots: None,
+            sampling: Sampling::default(),
+            answers: Vec::new(),
+            can_defer: false,
+            deferred: false,
+            idle_shutdown: None,
+            graceful_shutdown: false,
+            log_level: 1,
+            tool_timeouts: ToolTimeouts::default(),
+            branch_per_session: None,
+            client_branch: None,
+            session_id: None,
+        }
+    }
+
+    /// Report tool failures as JSON-RPC errors, as older releases did.
+    ///
+    /// By default a tool that fails returns a normal result with
+    /// `isError: true` and the error text as content, so the agent sees it;
+    /// only protocol problems (bad params, unknown tool) are JSON-RPC errors.
+    pub fn set_legacy_tool_errors(&mut self, legacy: bool) {
+        self.legacy_tool_errors = legacy;
+    }
+
+    /// Split `tools/list` into pages of at most `size` tools.
+    ///
+    /// Each page but the last carries a `nextCursor` for the client to pass
+    /// back as `cursor`. `None`, the default, lists every tool at once.
+    pub fn set_tools_page_size(&mut self, size: Option<usize>) {
+        self.tools_page_size = size.filter(|&n| n > 0);
+    }
+
+    /// Stop serving stdin/stdout once no request has arrived for `idle`.
+    ///
+    /// The period counts from the last request read, pings included, so a
+    /// client that pings now and then keeps the server up, while one left
+    /// behind by a crashed client exits cleanly instead of waiting forever.
+    /// Applies to [`McpServer::run_sync`] and [`McpServer::serve_async`];
+    /// `None`, the default, waits indefinitely.
+    pub fn set_idle_shutdown(&mut self, idle: Option<Duration>) {
+        self.idle_shutdown = idle.filter(|d| !d.is_zero());
+    }
+
+    /// Shut down gracefully on SIGINT or SIGTERM while serving stdin/stdout.
+    ///
+    /// The server stops reading and finishes the requests it already read,
+    /// then flushes the session's buffered write, closes its session branch,
+    /// syncs the WAL, and sends the client a `notifications/message` naming
+    /// the signal before returning. A second signal exits at once. Applies to
+    /// [`McpServer::run_sync`] and [`McpServer::run_async`].
+    pub fn set_graceful_shutdown(&mut self, graceful: bool) {
+        self.graceful_shutdown = graceful;
+    }
+
+    /// Fail calls to `tool`, or with `None` to any tool without a limit of
+    /// its own, once they run past `limit`.
+    ///
+    /// A call out of time gets a JSON-RPC error with code
+    /// [`rpc_codes::REQUEST_TIMEOUT`] and `data: { tool, timeout_ms }`
+    /// instead of leaving the client waiting. The limit is checked before
+    /// each engine command, so the call stops at its next one, and a
+    /// transaction the call opened itself is rolled back. Under
+    /// [`McpServer::serve_async`] a read-only tool's error goes out on time
+    /// even while a command is stuck, and the call's late response is
+    /// dropped; write tools still stop at their next command, so the error
+    /// never reports a write that went on to land. A strata_summarize call
+    /// waiting on the client's model gets the error once its limit passes.
+    /// Calls through strata_developer get the limit of the tool they call. By
+    /// default calls get 60 seconds, strata_models_pull 30 minutes, and
+    /// strata_generate and strata_summarize 5 minutes; a `limit` of `None`
+    /// lets them run as long as they take.
+    pub fn set_tool_timeout(&mut self, tool: Option<&str>, limit: Option<Duration>) {
+        match tool {
+            Some(tool) => {
+                self.tool_timeouts.overrides.insert(tool.to_string(), limit);
+            }
+            None => self.tool_timeouts.default = limit,
+        }
+    }
+
+    /// How long a call to `name` may run, and the error it gets past that.
+    fn call_timeout(
+        &self,
+        name: &str,
+        arguments: Option<&Map<String, JsonValue>>,
+    ) -> Option<(Duration, McpError)> {
+        let argument = |key: &str| arguments.and_then(|a| a.get(key)).and_then(|v| v.as_str());
+        let tool = match (name, argument("action")) {
+            (crate::tools::developer::NAME, Some("call")) => argument("tool").unwrap_or(name),
+            _ => name,
+        };
+        let limit = self.tool_timeouts.get(tool)?;
+        let error = McpError::Timeout {
+            tool: tool.to_string(),
+            timeout_ms: limit.as_millis() as u64,
+        };
+        Some((limit, error))
+    }
+
+    /// Fork a working branch from `base` for the client at its first call to
+    /// a tool that isn't read-only, and work on it from then on.
+    ///
+    /// The branch is named at `initialize` as `session-{client}-{id}`, after
+    /// the client's `clientInfo.name` and its session id: the
+    /// `Mcp-Session-Id` over HTTP, a fresh ULID otherwise. Clients that only
+    /// read never get a branch; they read `base`. The branch is kept when
+    /// the client disconnects, so its changes reach `base` only when an
+    /// agent merges them with strata_branch. A client that has switched off
+    /// `base` by then isn't moved. The call fails if the branch can't be
+    /// forked. `None`, the default, leaves the session on its branch.
+    pub fn set_branch_per_session(&mut self, base: Option<String>) {
+        self.branch_per_session = base;
+    }
+
+    /// Use `id`, the client's session id, in its working branch's name.
+    pub(crate) fn set_session_id(&mut self, id: String) {
+        self.session_id = Some(id);
+    }
+
+    /// Name the client's working branch from its `initialize` params, unless
+    /// no base is set or it already has a name.
+    fn name_client_branch(&mut self, params: Option<&JsonValue>) {
+        if self.branch_per_session.is_none() || self.client_branch.is_some() {
+            return;
+        }
+        let client = params
+            .and_then(|p| p.get("clientInfo"))
+            .and_then(|c| c.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or_default();
+        let id = self.session_id.clone().unwrap_or_else(crate::ulid::generate);
+        self.client_branch = Some(client_branch_name(client, &id));
+    }
+
+    /// Fork and switch to the client's working branch before a call to
+    /// `tool`, unless it's read-only, the branch has no name yet, or the
+    /// session is off the base branch or already on a session branch.
+    fn start_client_branch(&mut self, tool: &str) -> Result<()> {
+        let (Some(base), Some(name)) = (&self.branch_per_session, &self.client_branch) else {
+            return Ok(());
+        };
+        if self.registry.is_read_only(tool)
+            || self.session.session_branch().is_some()
+            || self.session.branch() != base.as_str()
+        {
+            return Ok(());
+        }
+        self.session.start_named_session_branch(base, name, SessionBranchEnd::Keep)?;
+        tracing::info!("Working on branch '{}', forked from '{}'", name, base);
+        Ok(())
+    }
+
+    /// Give each of the client's workspace roots its own database at
+    /// `{root}/{dir}`, opened with `open`.
+    ///
+    /// Once initialized, a client that declared the `roots` capability is
+    /// sent a `roots/list` request, and again after it sends
+    /// `notifications/roots/list_changed`. When its response names a
+    /// `file://` root, the session's buffered write is flushed, its session
+    /// branch closed, and later requests go to the first root's database.
+    /// Until then, and when `open` fails, the current session stays; a
+    /// transaction open when the response arrives commits or rolls back in
+    /// the current database, and the session moves after the call ending it.
+    pub fn set_roots_dir(&mut self, dir: impl Into<PathBuf>, open: OpenSession) {
+        self.roots = Some(Roots::new(dir.into(), open));
+    }
+
+    /// Replace the tools this server exposes.
+    ///
+    /// Connected clients get a `notifications/tools/list_changed` after the
+    /// response to the request in flight, so they re-fetch `tools/list`.
+    pub fn set_registry(&mut self, registry: ToolRegistry) {
+        self.registry = registry;
+        self.tools_changed = true;
+    }
+
+    /// Notifications and requests for the client to send after the last
+    /// response, clearing them.
+    pub(crate) fn take_notifications(&mut self) -> Vec<String> {
+        let mut messages = std::mem::take(&mut self.answers);
+        if std::mem::take(&mut self.tools_changed) && self.initialized {
+            let notification = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/tools/list_changed",
+            });
+            messages.push(notification.to_string());
+        }
+        if let Some(request) = self.roots.as_mut().and_then(Roots::take_request) {
+            messages.push(request);
+        }
+        messages.extend(self.sampling.take_requests());
+        messages
+    }
+
+    /// Handle a client's response to a request this server sent, returning
+    /// the response to a call that was waiting on it.
+    fn handle_client_response(&mut self, response: &JsonValue) -> Option<JsonRpcResponse> {
+        if let Some((call, sample)) = self.sampling.complete(response) {
+            return Some(self.finish_summary(call, sample));
+        }
+        let roots = self.roots.as_mut()?;
+        let dir = roots.database_for(response)?;
+        if self.session.in_transaction() {
+            // Moving now would strand the transaction in the old database
+            tracing::info!("Moving to {} once the open transaction ends", dir.display());
+            roots.wait_for(dir);
+            return None;
+        }
+        self.switch_root(&dir);
+        None
+    }
+
+    /// Move the session to the workspace database at `dir`, keeping the
+    /// current one if it can't be opened.
+    fn switch_root(&mut self, dir: &Path) {
+        let Some(roots) = self.roots.as_mut() else {
+            return;
+        };
+        match roots.open(dir) {
+            Ok(session) => {
+                self.end_client();
+                self.session = session;
+                tracing::info!("Using the workspace database at {}", dir.display());
+            }
+            Err(e) => tracing::warn!("Failed to open the workspace database: {}", e),
+        }
+    }
+
+    /// When a call set aside for the client's model next runs out of time.
+    pub(crate) fn next_expiry(&self) -> Option<Instant> {
+        self.sampling.next_deadline()
+    }
+
+    /// Answer the calls set aside for the client's model that stopped
+    /// waiting, queueing their responses with the next notifications.
+    ///
+    /// A call out of time gets its timeout error. One the client didn't
+    /// answer in time falls back to its local `model`, or fails without one.
+    pub(crate) fn expire_pending(&mut self) {
+        let now = Instant::now();
+        for mut call in self.sampling.take_expired(now) {
+            let response = match call.timeout.take() {
+                Some((at, error)) if at <= now => {
+                    self.session.record_tool_call("strata_summarize", false);
+                    JsonRpcResponse::from_error(call.id, error)
+                }
+                _ => {
+                    let reason = format!(
+                        "no answer from the client within {}s",
+                        crate::sampling::RESPONSE_WAIT.as_secs()
+                    );
+                    self.finish_summary(call, Err(reason))
+                }
+            };
+            self.answers.push(to_line(&response));
+        }
+    }
+
+    /// Answer a call set aside for the client's model with a cancelled
+    /// error, if the `tools/call` request `id` is one.
+    fn cancel_pending(&mut self, id: &JsonValue) {
+        if let Some(call) = self.sampling.cancel(id) {
+            self.session.record_tool_call("strata_summarize", false);
+            self.answers.push(to_line(&JsonRpcResponse::from_error(call.id, McpError::Cancelled)));
+        }
+    }
+
+    /// Fail every call set aside for the client's model as the server stops,
+    /// queueing their responses with the next notifications.
+    fn abandon_pending(&mut self) {
+        for call in self.sampling.take_all() {
+            self.session.record_tool_call("strata_summarize", false);
+            let error = McpError::Internal(
+                "the server shut down before the client's model answered".to_string(),
+            );
+            let response = self.tool_response(call.id, Err(error));
+            self.answers.push(to_line(&response));
+        }
+    }
+
+    /// Handle a request that may be answered only after the client responds
+    /// to a request sent for it, or `None` when it will be.
+    fn handle_deferrable(&mut self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        self.can_defer = true;
+        let response = self.handle_request(request);
+        self.can_defer = false;
+        (!std::mem::take(&mut self.deferred)).then_some(response)
+    }
+
+    /// Run read-only tool calls concurrently under [`McpServer::serve_async`].
+    ///
+    /// Calls to tools annotated read-only run in parallel, each on its own
+    /// reader session, and their responses go out as they finish, so
+    /// clients must match them by `id`. Anything else still runs alone, after
+    /// the reads before it, so writes stay serialized and a call always sees
+    /// the writes requested before it.
+    pub fn set_concurrent_reads(&mut self, concurrent: bool) {
+        self.concurrent_reads = concurrent;
+    }
+
+    /// A server for one read-only call alongside this one, or `None` when
+    /// `request` isn't a read-only tool call or can't leave this session.
+    fn reader_for(&self, request: &JsonRpcRequest) -> Option<McpServer> {
+        if !self.concurrent_reads || !self.initialized || request.method != "tools/call" {
+            return None;
+        }
+        let name = request.params.as_ref()?.get("name")?.as_str()?;
+        // Debug tools report on this session, so they can't run on a reader
+        if name.starts_with(crate::tools::debug::PREFIX) || !self.registry.is_read_only(name) {
+            return None;
+        }
+        Some(Self {
+            session: self.session.reader()?,
+            registry: self.registry.clone(),
+            initialized: true,
+            legacy_tool_errors: self.legacy_tool_errors,
+            concurrent_reads: false,
+            tools_changed: false,
+            protocol_version: self.protocol_version,
+            tools_page_size: self.tools_page_size,
+            roots: None,
+            sampling: Sampling::default(),
+            answers: Vec::new(),
+            can_defer: false,
+            deferred: false,
+            idle_shutdown: None,
+            graceful_shutdown: false,
+            log_level: self.log_level,
+            tool_timeouts: self.tool_timeouts.clone(),
+            branch_per_session: None,
+            client_branch: None,
+            session_id: None,
+        })
+    }
+
+    /// Wind down after a shutdown signal: as [`McpServer::end_client`], then
+    /// sync the WAL. Returns the serialized notification telling the client;
+    /// calls waiting on the client's model are answered with the next
+    /// notifications.
+    pub(crate) fn shut_down(&mut self, signal: &str) -> Option<String> {
+        tracing::info!("{} received; shutting down", signal);
+        self.abandon_pending();
+        self.end_client();
+        if !self.session.is_read_only() {
+            if let Err(e) = self.session.execute(stratadb::Command::Flush) {
+                tracing::warn!("Failed to sync the WAL: {}", e);
+            }
+        }
+        self.log_message(
+            "notice",
+            serde_json::json!({ "message": "Server shutting down", "signal": signal }),
+        )
+    }
+
+    /// A serialized `notifications/message`, or `None` if the client isn't
+    /// initialized or asked for more severe messages only.
+    fn log_message(&self, level: &str, data: JsonValue) -> Option<String> {
+        let severity = LOG_LEVELS.iter().position(|l| *l == level)?;
+        if !self.initialized || severity < self.log_level {
+            return None;
+        }
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/message",
+            "params": { "level": level, "logger": SERVER_NAME, "data": data },
+        });
+        Some(notification.to_string())
+    }
+
+    /// Wind down after a client disconnects: flush the session's buffered
+    /// write and apply its session branch policy, logging any failure.
+    pub(crate) fn end_client(&mut self) {
+        if let Err(e) = self.session.flush_pending() {
+            tracing::warn!("Failed to flush buffered write: {}", e);
         }
+        if let Err(e) = self.session.end_session_branch() {
+            tracing::warn!("Failed to close session branch: {}", e);
+        }
+    }
+
+    /// Mutable access to the server's session.
+    pub fn session_mut(&mut self) -> &mut McpSession {
+        &mut self.session
     }
 
     /// Run the server synchronously, reading from stdin and writing to stdout.
     pub fn run_sync(&mut self) -> Result<()> {
-        let stdin = std::io::stdin();
-        let mut stdout = std::io::stdout();
-        let mut line = String::new();
+        let stdout = std::io::stdout();
+        // Read on a thread so that waiting for a request can end early
+        let (tx, rx) = std::sync::mpsc::channel();
+        if self.graceful_shutdown {
+            let signals = tx.clone();
+            shutdown::watch(move |signal| {
+                let _ = signals.send(Input::Signal(signal));
+            });
+        }
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                if tx.send(Input::Line(line)).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(Input::Eof);
+        });
+        self.serve_input(rx, stdout.lock())
+    }
 
-        let stdin_lock = stdin.lock();
-        let mut reader = std::io::BufReader::new(stdin_lock);
+    /// Serve newline-delimited JSON-RPC requests until the reader reaches EOF.
+    ///
+    /// Clients may pipeline requests without waiting for responses. Requests
+    /// are processed strictly in the order they are read, and each response is
+    /// written (in that same order) before the next request is processed, so a
+    /// later request always observes the effects of earlier ones and responses
+    /// can be correlated by `id` or by position.
+    pub fn serve<R: BufRead, W: Write>(&mut self, mut reader: R, mut writer: W) -> Result<()> {
+        let mut line = String::new();
 
         loop {
             line.clear();
@@ -125,32 +669,426 @@ impl McpServer {
                 break;
             }
 
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
+            self.expire_pending();
+            self.serve_line(&line, &mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Like [`McpServer::serve`], for lines read elsewhere and sent on
+    /// `input`, but also stops after the idle period or a shutdown signal.
+    pub(crate) fn serve_input<W: Write>(
+        &mut self,
+        input: Receiver<Input>,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut last_input = Instant::now();
+        loop {
+            let idle_at = self.idle_shutdown.map(|idle| last_input + idle);
+            // Wake for calls set aside for the client's model running out of time
+            let wake_at = match (idle_at, self.next_expiry()) {
+                (Some(idle_at), Some(expiry)) => Some(idle_at.min(expiry)),
+                (idle_at, expiry) => idle_at.or(expiry),
+            };
+            let next = match wake_at {
+                Some(at) => input.recv_timeout(at.saturating_duration_since(Instant::now())),
+                None => input.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match next {
+                Ok(Input::Line(line)) => {
+                    last_input = Instant::now();
+                    self.expire_pending();
+                    self.serve_line(&line?, &mut writer)?
+                }
+                Ok(Input::Signal(signal)) => {
+                    let notification = self.shut_down(signal);
+                    for message in self.take_notifications().into_iter().chain(notification) {
+                        writeln!(writer, "{}", message)?;
+                    }
+                    writer.flush()?;
+                    return Ok(());
+                }
+                Err(RecvTimeoutError::Timeout)
+                    if idle_at.is_some_and(|at| Instant::now() >= at) =>
+                {
+                    tracing::info!("No requests for {:?}; shutting down", self.idle_shutdown);
+                    self.abandon_pending();
+                    for message in self.take_notifications() {
+                        writeln!(writer, "{}", message)?;
+                    }
+                    writer.flush()?;
+                    return Ok(());
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.expire_pending();
+                    for notification in self.take_notifications() {
+                        writeln!(writer, "{}", notification)?;
+                    }
+                    writer.flush()?;
+                }
+                // EOF - client disconnected
+                Ok(Input::Eof) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
             }
+        }
+    }
 
-            // Parse the request
-            let response = match serde_json::from_str::<JsonRpcRequest>(line) {
-                Ok(request) => self.handle_request(request),
-                Err(e) => JsonRpcResponse::error(
+    /// Handle one line of input and write its response and notifications.
+    fn serve_line<W: Write>(&mut self, line: &str, writer: &mut W) -> Result<()> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(response_json) = self.handle_text(line)? {
+            writeln!(writer, "{}", response_json)?;
+        }
+        for notification in self.take_notifications() {
+            writeln!(writer, "{}", notification)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Run the server on tokio, reading from stdin and writing to stdout.
+    ///
+    /// See [`McpServer::serve_async`].
+    pub async fn run_async(self) -> Result<()> {
+        self.serve_async(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// Serve newline-delimited JSON-RPC requests on tokio until the reader
+    /// reaches EOF.
+    ///
+    /// Requests keep being read while a tool call is in flight. A `ping` is
+    /// answered at once; everything else runs on a blocking worker strictly
+    /// in the order received, so a later request still observes the effects
+    /// of earlier ones. A `tools/call` with `_meta.progressToken` in its
+    /// params gets `notifications/progress` messages as the call starts and
+    /// finishes. `notifications/cancelled` aborts a queued or running call:
+    /// it gets a [`rpc_codes::REQUEST_CANCELLED`] error unless it finished
+    /// first, and a running call stops before its next engine command. At
+    /// EOF, after [`McpServer::set_idle_shutdown`]'s period without a
+    /// request, or on a signal under [`McpServer::set_graceful_shutdown`],
+    /// the queued requests finish, then the session is wound down: its
+    /// buffered write is flushed and its session branch closed.
+    pub async fn serve_async<R, W>(self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+        use tokio::sync::mpsc;
+
+        let idle = self.idle_shutdown;
+        let graceful = self.graceful_shutdown;
+        let signalled = Arc::new(std::sync::OnceLock::<&'static str>::new());
+        let read_signalled = signalled.clone();
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+        let (request_tx, mut request_rx) = mpsc::unbounded_channel::<Job>();
+        let cancellations = Cancellations::default();
+        let worker_cancellations = cancellations.clone();
+
+        let worker_out = out_tx.clone();
+        let dispatch = async move {
+            let mut server = self;
+            let mut reads = tokio::task::JoinSet::new();
+            loop {
+                // Wake for calls set aside for the client's model running out of time
+                let job = match server.next_expiry() {
+                    Some(at) => tokio::select! {
+                        job = request_rx.recv() => job,
+                        _ = tokio::time::sleep_until(at.into()) => Some(Job::Expire),
+                    },
+                    None => request_rx.recv().await,
+                };
+                let Some(job) = job else {
+                    break;
+                };
+                let request = match job {
+                    Job::Request(request) => request,
+                    job => {
+                        while let Some(calls) = reads.join_next().await {
+                            server.session.merge_tool_calls(&join_result(calls)?);
+                        }
+                        let out = worker_out.clone();
+                        server = join_result(
+                            tokio::task::spawn_blocking(move || {
+                                if let Some(line) = server.run_job(job)? {
+                                    let _ = out.send(line);
+                                }
+                                for notification in server.take_notifications() {
+                                    let _ = out.send(notification);
+                                }
+                                Ok::<_, McpError>(server)
+                            })
+                            .await,
+                        )??;
+                        continue;
+                    }
+                };
+                if let Some(mut reader) = server.reader_for(&request) {
+                    let out = worker_out.clone();
+                    let cancellations = worker_cancellations.clone();
+                    reads.spawn_blocking(move || {
+                        run_request(&mut reader, request, &out, &cancellations);
+                        reader.session.tool_calls().clone()
+                    });
+                    continue;
+                }
+                // Everything else waits for the reads requested before it
+                while let Some(calls) = reads.join_next().await {
+                    server.session.merge_tool_calls(&join_result(calls)?);
+                }
+                let out = worker_out.clone();
+                let cancellations = worker_cancellations.clone();
+                server = join_result(
+                    tokio::task::spawn_blocking(move || {
+                        run_request(&mut server, request, &out, &cancellations);
+                        server
+                    })
+                    .await,
+                )?;
+            }
+            while let Some(calls) = reads.join_next().await {
+                server.session.merge_tool_calls(&join_result(calls)?);
+            }
+            let signal = signalled.get().copied();
+            join_result(
+                tokio::task::spawn_blocking(move || {
+                    let notification = match signal {
+                        Some(signal) => server.shut_down(signal),
+                        None => {
+                            server.abandon_pending();
+                            server.end_client();
+                            None
+                        }
+                    };
+                    for message in server.take_notifications().into_iter().chain(notification) {
+                        let _ = worker_out.send(message);
+                    }
+                })
+                .await,
+            )
+        };
+
+        let read = async move {
+            let mut lines = tokio::io::BufReader::new(reader).lines();
+            let mut signal = std::pin::pin!(async move {
+                match graceful {
+                    true => shutdown::signal().await,
+                    false => std::future::pending().await,
+                }
+            });
+            loop {
+                let next_line = async {
+                    match idle {
+                        None => Ok(lines.next_line().await),
+                        Some(idle) => tokio::time::timeout(idle, lines.next_line()).await,
+                    }
+                };
+                let line = tokio::select! {
+                    line = next_line => match line {
+                        Ok(line) => line?,
+                        Err(_) => {
+                            tracing::info!("No requests for {:?}; shutting down", idle);
+                            break;
+                        }
+                    },
+                    signal = &mut signal => {
+                        let _ = read_signalled.set(signal);
+                        // Draining can't be interrupted but by another signal
+                        tokio::spawn(shutdown::exit_on_signal());
+                        break;
+                    }
+                };
+                let Some(line) = line else {
+                    break;
+                };
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                // Batches run whole, in order with everything else
+                if line.starts_with('[') {
+                    if request_tx.send(Job::Text(line.to_string())).is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                match serde_json::from_str::<JsonRpcRequest>(line) {
+                    Ok(request) if request.method == "ping" && request.jsonrpc == "2.0" => {
+                        let pong = JsonRpcResponse::success(request.id, serde_json::json!({}));
+                        let _ = out_tx.send(to_line(&pong));
+                    }
+                    Ok(request) if request.method == "notifications/cancelled" => {
+                        let params = request.params.as_ref();
+                        if let Some(id) = params.and_then(|p| p.get("requestId")) {
+                            cancellations.cancel(id);
+                            // A call waiting on the client's model is reached in turn
+                            if request_tx.send(Job::Cancel(id.clone())).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Ok(request) => {
+                        if let Some(id) = &request.id {
+                            cancellations.register(id);
+                        }
+                        if request_tx.send(Job::Request(request)).is_err() {
+                            break;
+                        }
+                    }
+                    // Client responses may move the session, so they queue too
+                    Err(_) if client_response(line).is_some() => {
+                        if request_tx.send(Job::Text(line.to_string())).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let error = JsonRpcResponse::error(
+                            None,
+                            rpc_codes::PARSE_ERROR,
+                            format!("Parse error: {}", e),
+                        );
+                        let _ = out_tx.send(to_line(&error));
+                    }
+                }
+            }
+            // Closing the queue lets the worker finish and drop its sender
+            drop(request_tx);
+            Ok::<_, std::io::Error>(())
+        };
+
+        let write = async {
+            while let Some(line) = out_rx.recv().await {
+                writer.write_all(line.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (dispatched, read, write) = tokio::join!(dispatch, read, write);
+        dispatched?;
+        read?;
+        write?;
+        Ok(())
+    }
+
+    /// Run a job for the async core on the primary session, serializing its
+    /// response, if it has one.
+    fn run_job(&mut self, job: Job) -> Result<Option<String>> {
+        match job {
+            Job::Request(request) => Ok(self.handle_deferrable(request).map(|r| to_line(&r))),
+            Job::Text(text) => self.handle_text(&text),
+            Job::Expire => {
+                self.expire_pending();
+                Ok(None)
+            }
+            Job::Cancel(id) => {
+                self.cancel_pending(&id);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Handle one serialized JSON-RPC request or batch and serialize its
+    /// response, or `None` for a client response or a batch of
+    /// notifications and client responses only.
+    pub(crate) fn handle_text(&mut self, text: &str) -> Result<Option<String>> {
+        self.handle_text_deferring(text, true)
+    }
+
+    /// Like [`handle_text`](Self::handle_text), but answer every request
+    /// before returning, for transports that can't send a late answer, and
+    /// answer a lone notification with nothing, as in a batch.
+    pub(crate) fn handle_text_now(&mut self, text: &str) -> Result<Option<String>> {
+        self.handle_text_deferring(text, false)
+    }
+
+    fn handle_text_deferring(&mut self, text: &str, defer: bool) -> Result<Option<String>> {
+        if text.starts_with('[') {
+            let response = match serde_json::from_str::<Vec<JsonValue>>(text) {
+                Ok(messages) => self.handle_batch(messages),
+                Err(e) => Some(serde_json::to_value(JsonRpcResponse::error(
                     None,
                     rpc_codes::PARSE_ERROR,
                     format!("Parse error: {}", e),
-                ),
+                ))?),
             };
-
-            // Send response
-            let response_json = serde_json::to_string(&response)?;
-            writeln!(stdout, "{}", response_json)?;
-            stdout.flush()?;
+            return Ok(response.map(|r| r.to_string()));
         }
+        let response = match serde_json::from_str::<JsonRpcRequest>(text) {
+            Ok(request) if !defer && request.id.is_none() => {
+                self.handle_request(request);
+                return Ok(None);
+            }
+            Ok(request) if !defer => self.handle_request(request),
+            Ok(request) => match self.handle_deferrable(request) {
+                Some(response) => response,
+                None => return Ok(None),
+            },
+            Err(e) => match client_response(text) {
+                Some(message) => match self.handle_client_response(&message) {
+                    Some(response) => response,
+                    None => return Ok(None),
+                },
+                _ => JsonRpcResponse::error(
+                    None,
+                    rpc_codes::PARSE_ERROR,
+                    format!("Parse error: {}", e),
+                ),
+            },
+        };
+        Ok(Some(serde_json::to_string(&response)?))
+    }
 
-        Ok(())
+    /// Handle a JSON-RPC batch in order.
+    ///
+    /// Returns the array of responses, without entries for notifications, or
+    /// `None` if every message was a notification. An empty batch is answered
+    /// with a single error, as JSON-RPC 2.0 requires.
+    pub(crate) fn handle_batch(&mut self, messages: Vec<JsonValue>) -> Option<JsonValue> {
+        if messages.is_empty() {
+            let error = JsonRpcResponse::error(
+                None,
+                rpc_codes::INVALID_REQUEST,
+                "Invalid request: empty batch".to_string(),
+            );
+            return serde_json::to_value(error).ok();
+        }
+        let mut responses = Vec::new();
+        for message in messages {
+            if is_response(&message) {
+                if let Some(response) = self.handle_client_response(&message) {
+                    responses.push(serde_json::to_value(response).unwrap_or(JsonValue::Null));
+                }
+                continue;
+            }
+            let response = match serde_json::from_value::<JsonRpcRequest>(message) {
+                Ok(request) if request.id.is_none() => {
+                    self.handle_request(request);
+                    continue;
+                }
+                Ok(request) => self.handle_request(request),
+                Err(e) => JsonRpcResponse::error(
+                    None,
+                    rpc_codes::INVALID_REQUEST,
+                    format!("Invalid request: {}", e),
+                ),
+            };
+            responses.push(serde_json::to_value(response).unwrap_or(JsonValue::Null));
+        }
+        if responses.is_empty() {
+            None
+        } else {
+            Some(JsonValue::Array(responses))
+        }
     }
 
     /// Handle a single JSON-RPC request.
-    fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+    pub(crate) fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
         // Validate JSON-RPC version
         if request.jsonrpc != "2.0" {
             return JsonRpcResponse::error(
@@ -163,14 +1101,47 @@ impl McpServer {
         // Route to appropriate handler
         match request.method.as_str() {
             "initialize" => self.handle_initialize(request),
-            "initialized" => {
+            "initialized" | "notifications/initialized" => {
+                if let Some(roots) = &mut self.roots {
+                    roots.refresh();
+                }
                 // Client acknowledgment - no response needed for notifications
                 // but we'll still respond with null to be safe
                 JsonRpcResponse::success(request.id, JsonValue::Null)
             }
+            "notifications/roots/list_changed" => {
+                if let Some(roots) = &mut self.roots {
+                    roots.refresh();
+                }
+                JsonRpcResponse::success(request.id, JsonValue::Null)
+            }
+            // Requests run one at a time here, so the cancelled one already
+            // finished, unless it's waiting on the client's model
+            "notifications/cancelled" => {
+                let params = request.params.as_ref();
+                if let Some(id) = params.and_then(|p| p.get("requestId")) {
+                    self.cancel_pending(id);
+                }
+                JsonRpcResponse::success(request.id, JsonValue::Null)
+            }
             "tools/list" => self.handle_tools_list(request),
             "tools/call" => self.handle_tools_call(request),
+            "resources/list" => self.handle_resources_list(request),
+            "resources/read" => self.handle_resources_read(request),
+            "resources/templates/list" => {
+                JsonRpcResponse::success(request.id, resources::templates())
+            }
+            "prompts/list" => JsonRpcResponse::success(request.id, prompts::list()),
+            "prompts/get" => self.handle_prompts_get(request),
+            "completion/complete" => {
+                match completion::complete(&mut self.session, request.params.as_ref()) {
+                    Ok(result) => JsonRpcResponse::success(request.id, result),
+                    Err(err) => JsonRpcResponse::from_error(request.id, err),
+                }
+            }
+            "logging/setLevel" => self.handle_set_level(request),
             "ping" => JsonRpcResponse::success(request.id, serde_json::json!({})),
+            "rpc.discover" => self.handle_discover(request),
             _ => JsonRpcResponse::error(
                 request.id,
                 rpc_codes::METHOD_NOT_FOUND,
@@ -180,15 +1151,42 @@ impl McpServer {
     }
 
     /// Handle the initialize request.
+    ///
+    /// Agrees on the client's protocol version when we support it, otherwise
+    /// offers our newest one; a client sending none gets the oldest.
     fn handle_initialize(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
         self.initialized = true;
+        let requested = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("protocolVersion"))
+            .and_then(|v| v.as_str());
+        let capabilities = request.params.as_ref().and_then(|p| p.get("capabilities"));
+        self.sampling.set_client_capabilities(capabilities);
+        if let Some(roots) = &mut self.roots {
+            roots.set_client_capabilities(capabilities);
+        }
+        self.protocol_version = match requested {
+            None => PROTOCOL_VERSIONS[0],
+            Some(version) => PROTOCOL_VERSIONS
+                .iter()
+                .find(|v| **v == version)
+                .or(PROTOCOL_VERSIONS.last())
+                .copied()
+                .unwrap_or(PROTOCOL_VERSIONS[0]),
+        };
+        self.name_client_branch(request.params.as_ref());
 
         JsonRpcResponse::success(
             request.id,
             serde_json::json!({
-                "protocolVersion": PROTOCOL_VERSION,
+                "protocolVersion": self.protocol_version,
                 "capabilities": {
-                    "tools": {}
+                    "tools": { "listChanged": true },
+                    "resources": {},
+                    "prompts": {},
+                    "completions": {},
+                    "logging": {}
                 },
                 "serverInfo": {
                     "name": SERVER_NAME,
@@ -198,40 +1196,145 @@ impl McpServer {
         )
     }
 
+    /// Handle the logging/setLevel request.
+    fn handle_set_level(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let level = request.params.as_ref().and_then(|p| p.get("level")).and_then(|l| l.as_str());
+        match level.and_then(|level| LOG_LEVELS.iter().position(|l| *l == level)) {
+            Some(severity) => {
+                self.log_level = severity;
+                JsonRpcResponse::success(request.id, serde_json::json!({}))
+            }
+            None => JsonRpcResponse::error(
+                request.id,
+                rpc_codes::INVALID_PARAMS,
+                format!("'level' must be one of: {}", LOG_LEVELS.join(", ")),
+            ),
+        }
+    }
+
     /// Handle the tools/list request.
     fn handle_tools_list(&self, request: JsonRpcRequest) -> JsonRpcResponse {
-        let tools: Vec<JsonValue> = self
-            .registry
-            .tools()
+        let all = self.registry.tools();
+        let cursor = request.params.as_ref().and_then(|p| p.get("cursor"));
+        let start = match cursor {
+            None | Some(JsonValue::Null) => 0,
+            Some(c) => match c.as_str().and_then(|c| c.parse::<usize>().ok()) {
+                Some(start) if start <= all.len() => start,
+                _ => {
+                    return JsonRpcResponse::error(
+                        request.id,
+                        rpc_codes::INVALID_PARAMS,
+                        format!("Invalid cursor: {}", c),
+                    )
+                }
+            },
+        };
+        let end = match self.tools_page_size {
+            Some(size) => (start + size).min(all.len()),
+            None => all.len(),
+        };
+
+        let tools: Vec<JsonValue> = all[start..end]
             .iter()
             .map(|t| {
-                serde_json::json!({
+                let mut tool = serde_json::json!({
                     "name": t.name,
                     "description": t.description,
                     "inputSchema": t.input_schema
+                });
+                if !t.examples.is_empty() {
+                    tool["examples"] = serde_json::json!(t.examples);
+                }
+                if let Some(annotations) = &t.annotations {
+                    if self.protocol_version >= ANNOTATIONS_VERSION {
+                        tool["annotations"] = serde_json::json!(annotations);
+                    }
+                }
+                if let Some(output_schema) = &t.output_schema {
+                    if self.protocol_version >= STRUCTURED_CONTENT_VERSION {
+                        tool["outputSchema"] = output_schema.clone();
+                    }
+                }
+                tool
+            })
+            .collect();
+
+        let mut page = serde_json::json!({ "tools": tools });
+        if end < all.len() {
+            page["nextCursor"] = JsonValue::String(end.to_string());
+        }
+        JsonRpcResponse::success(request.id, page)
+    }
+
+    /// Handle the rpc.discover request: the whole API surface in one document.
+    fn handle_discover(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let methods: Vec<JsonValue> = METHODS
+            .iter()
+            .map(|(name, description)| {
+                serde_json::json!({
+                    "name": name,
+                    "description": description,
                 })
             })
             .collect();
 
-        JsonRpcResponse::success(request.id, serde_json::json!({ "tools": tools }))
+        JsonRpcResponse::success(
+            request.id,
+            serde_json::json!({
+                "serverInfo": {
+                    "name": SERVER_NAME,
+                    "version": SERVER_VERSION
+                },
+                "protocolVersion": self.protocol_version,
+                "protocolVersions": PROTOCOL_VERSIONS,
+                "methods": methods,
+                "tools": self.registry.tools(),
+            }),
+        )
     }
 
-    /// Handle the tools/call request.
-    fn handle_tools_call(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
-        // Extract name and arguments from params
-        let params = match &request.params {
-            Some(JsonValue::Object(obj)) => obj,
-            _ => {
+    /// Handle the resources/list request.
+    fn handle_resources_list(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let cursor = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("cursor"))
+            .and_then(|c| c.as_str());
+        match resources::list(&mut self.session, cursor) {
+            Ok(page) => JsonRpcResponse::success(request.id, page),
+            Err(err) => JsonRpcResponse::from_error(request.id, err),
+        }
+    }
+
+    /// Handle the resources/read request.
+    fn handle_resources_read(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let uri = request.params.as_ref().and_then(|p| p.get("uri")).and_then(|u| u.as_str());
+        let uri = match uri {
+            Some(uri) => uri.to_string(),
+            None => {
                 return JsonRpcResponse::error(
                     request.id,
                     rpc_codes::INVALID_PARAMS,
-                    "Missing params object".to_string(),
+                    "Missing 'uri' in params".to_string(),
                 )
             }
         };
+        match resources::read(&mut self.session, &uri) {
+            Ok(Some(contents)) => JsonRpcResponse::success(request.id, contents),
+            Ok(None) => JsonRpcResponse::error(
+                request.id,
+                rpc_codes::RESOURCE_NOT_FOUND,
+                format!("Resource not found: {}", uri),
+            ),
+            Err(err) => JsonRpcResponse::from_error(request.id, err),
+        }
+    }
 
-        let name = match params.get("name").and_then(|v| v.as_str()) {
-            Some(n) => n.to_string(),
+    /// Handle the prompts/get request.
+    fn handle_prompts_get(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let params = request.params.as_ref();
+        let name = match params.and_then(|p| p.get("name")).and_then(|n| n.as_str()) {
+            Some(name) => name.to_string(),
             None => {
                 return JsonRpcResponse::error(
                     request.id,
@@ -240,8 +1343,47 @@ impl McpServer {
                 )
             }
         };
-
-        let arguments = match params.get("arguments") {
+        let arguments = match params.and_then(|p| p.get("arguments")) {
+            Some(JsonValue::Object(obj)) => obj.clone(),
+            _ => Map::new(),
+        };
+        match prompts::get(&mut self.session, &name, &arguments) {
+            Ok(Some(prompt)) => JsonRpcResponse::success(request.id, prompt),
+            Ok(None) => JsonRpcResponse::error(
+                request.id,
+                rpc_codes::INVALID_PARAMS,
+                format!("Unknown prompt: {}", name),
+            ),
+            Err(err) => JsonRpcResponse::from_error(request.id, err),
+        }
+    }
+
+    /// Handle the tools/call request.
+    fn handle_tools_call(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        // Extract name and arguments from params
+        let params = match &request.params {
+            Some(JsonValue::Object(obj)) => obj,
+            _ => {
+                return JsonRpcResponse::error(
+                    request.id,
+                    rpc_codes::INVALID_PARAMS,
+                    "Missing params object".to_string(),
+                )
+            }
+        };
+
+        let name = match params.get("name").and_then(|v| v.as_str()) {
+            Some(n) => n.to_string(),
+            None => {
+                return JsonRpcResponse::error(
+                    request.id,
+                    rpc_codes::INVALID_PARAMS,
+                    "Missing 'name' in params".to_string(),
+                )
+            }
+        };
+
+        let arguments = match params.get("arguments") {
             Some(JsonValue::Object(obj)) => obj.clone(),
             Some(JsonValue::Null) | None => Map::new(),
             _ => {
@@ -253,29 +1395,1274 @@ impl McpServer {
             }
         };
 
-        // Dispatch the tool call
-        match self.registry.dispatch(&mut self.session, &name, arguments) {
+        // The client's model writes summaries when it can, answering later
+        self.session.take_warnings();
+        if name == "strata_summarize"
+            && self.can_defer
+            && self.sampling.is_supported()
+            && self.registry.tools().iter().any(|t| t.name == name)
+        {
+            match inference::summary_request(&mut self.session, &arguments) {
+                Ok(summary) => {
+                    let timeout = self
+                        .call_timeout(&name, Some(&arguments))
+                        .map(|(limit, error)| (Instant::now() + limit, error));
+                    self.sampling.request_summary(request.id, summary, arguments, timeout);
+                    self.deferred = true;
+                    return JsonRpcResponse::success(None, JsonValue::Null);
+                }
+                Err(err) => {
+                    self.session.record_tool_call(&name, false);
+                    return self.tool_response(request.id, Err(err));
+                }
+            }
+        }
+
+        // A client's first write forks its working branch
+        if let Err(err) = self.start_client_branch(&name) {
+            self.session.record_tool_call(&name, false);
+            return self.tool_response(request.id, Err(err));
+        }
+
+        // Dispatch the tool call, failing it once it runs out of time
+        let deadline = self.call_timeout(&name, Some(&arguments));
+        self.session
+            .set_deadline(deadline.map(|(limit, error)| (Instant::now() + limit, error)));
+        let open_before = self.session.in_transaction();
+        let outcome = self.registry.dispatch(&mut self.session, &name, arguments);
+        self.session.set_deadline(None);
+        self.roll_back_abandoned(open_before, &outcome);
+        if let Err(e) = self.session.save_position() {
+            tracing::warn!("Failed to record the session's branch and space: {}", e);
+        }
+        if self.session.take_developer_tools_request() && !self.registry.lists_developer_tools() {
+            self.set_registry(self.registry.clone().with_developer_tools());
+        }
+        let response = self.tool_response(request.id, outcome);
+        if !self.session.in_transaction() {
+            if let Some(dir) = self.roots.as_mut().and_then(Roots::take_waiting) {
+                self.switch_root(&dir);
+            }
+        }
+        response
+    }
+
+    /// Roll back a transaction that a call opened itself and left open when
+    /// it was cancelled or ran out of time, so the next request doesn't run
+    /// inside it.
+    ///
+    /// Tools that batch their writes in a transaction commit it at the end,
+    /// but once the call is cancelled or out of time its commit is refused. A
+    /// transaction the client opened before the call stays open.
+    fn roll_back_abandoned(&mut self, open_before: bool, outcome: &Result<JsonValue>) {
+        let abandoned = matches!(outcome, Err(McpError::Cancelled | McpError::Timeout { .. }));
+        if !abandoned || open_before || !self.session.in_transaction() {
+            return;
+        }
+        if let Err(e) = self.session.execute(stratadb::Command::TxnRollback) {
+            tracing::warn!("Failed to roll back the abandoned transaction: {}", e);
+        }
+    }
+
+    /// Answer a strata_summarize call set aside for the client's model, with
+    /// its summary or, when it couldn't write one, the call's local `model`.
+    fn finish_summary(
+        &mut self,
+        call: PendingSummary,
+        sample: std::result::Result<Sample, String>,
+    ) -> JsonRpcResponse {
+        const NAME: &str = "strata_summarize";
+        self.session.take_warnings();
+        let outcome = match sample {
+            Ok(sample) => Ok(serde_json::json!({
+                "summary": sample.text,
+                "keys": call.keys,
+                "model": sample.model,
+                "source": "sampling",
+            })),
+            Err(reason) if call.args.contains_key("model") => {
+                tracing::warn!("Client couldn't sample, summarizing locally: {}", reason);
+                let outcome = self.registry.dispatch(&mut self.session, NAME, call.args);
+                return self.tool_response(call.id, outcome);
+            }
+            Err(reason) => Err(McpError::Internal(format!(
+                "the client's model didn't write a summary: {}",
+                reason
+            ))),
+        };
+        self.session.record_tool_call(NAME, outcome.is_ok());
+        self.tool_response(call.id, outcome)
+    }
+
+    /// Wrap a tool's outcome, and any warnings it raised, as a `tools/call`
+    /// response.
+    fn tool_response(
+        &mut self,
+        id: Option<JsonValue>,
+        outcome: Result<JsonValue>,
+    ) -> JsonRpcResponse {
+        let warnings: Vec<JsonValue> =
+            self.session.take_warnings().iter().map(|w| w.to_json()).collect();
+        match outcome {
             Ok(result) => {
                 // MCP tool responses are wrapped in content array
-                JsonRpcResponse::success(
-                    request.id,
-                    serde_json::json!({
-                        "content": [{
-                            "type": "text",
-                            "text": serde_json::to_string(&result).unwrap_or_else(|_| "null".to_string())
-                        }]
-                    }),
-                )
+                let content = vec![serde_json::json!({
+                    "type": "text",
+                    "text": serde_json::to_string(&result).unwrap_or_else(|_| "null".to_string())
+                })];
+                let mut envelope = serde_json::json!({ "content": content });
+                // Structured content must be an object, so other results are wrapped
+                if self.protocol_version >= STRUCTURED_CONTENT_VERSION {
+                    envelope["structuredContent"] = match result {
+                        JsonValue::Object(_) => result,
+                        JsonValue::Array(_) => serde_json::json!({ "items": result }),
+                        _ => serde_json::json!({ "value": result }),
+                    };
+                }
+                // Extra data goes under _meta, which MCP reserves for it
+                if !warnings.is_empty() {
+                    envelope["_meta"] = serde_json::json!({ "warnings": warnings });
+                }
+                JsonRpcResponse::success(id, envelope)
             }
-            Err(err) => JsonRpcResponse::from_error(request.id, err),
+            Err(
+                err @ (McpError::UnknownTool(_)
+                | McpError::Protocol(_)
+                | McpError::Cancelled
+                | McpError::Timeout { .. }),
+            ) => JsonRpcResponse::from_error(id, err),
+            Err(err) if self.legacy_tool_errors => JsonRpcResponse::from_error(id, err),
+            Err(err) => JsonRpcResponse::success(
+                id,
+                serde_json::json!({
+                    "content": [{
+                        "type": "text",
+                        "text": err.to_string()
+                    }],
+                    "isError": true
+                }),
+            ),
         }
     }
 }
 
+/// Whether `message` is a response from the client rather than a request.
+fn is_response(message: &JsonValue) -> bool {
+    message.is_object() && message.get("method").is_none() && message.get("id").is_some()
+}
+
+/// `text` parsed, if it's a single response from the client.
+fn client_response(text: &str) -> Option<JsonValue> {
+    serde_json::from_str(text).ok().filter(is_response)
+}
+
+/// The progress token of a `tools/call` request, if the client sent one.
+fn progress_token(request: &JsonRpcRequest) -> Option<JsonValue> {
+    if request.method != "tools/call" {
+        return None;
+    }
+    request.params.as_ref()?.get("_meta")?.get("progressToken").cloned()
+}
+
+/// Handle one request on the async core, sending its progress
+/// notifications and response to `out`.
+fn run_request(
+    server: &mut McpServer,
+    request: JsonRpcRequest,
+    out: &tokio::sync::mpsc::UnboundedSender<String>,
+    cancellations: &Cancellations,
+) {
+    let id = request.id.clone();
+    let flag = id.as_ref().and_then(|id| cancellations.flag(id));
+    if flag.as_ref().is_some_and(|f| f.load(Ordering::Relaxed)) {
+        let error = JsonRpcResponse::from_error(request.id, McpError::Cancelled);
+        let _ = out.send(to_line(&error));
+        cancellations.finish(id.as_ref());
+        return;
+    }
+
+    let token = progress_token(&request);
+    if let Some(token) = &token {
+        let _ = out.send(progress_notification(token, 0));
+    }
+    // Answer reads on time even while an engine command is stuck past the
+    // limit; a write may still land after it, so it ends at its next command
+    let answered = Arc::new(AtomicBool::new(false));
+    let watchdog = request_timeout(server, &request).and_then(|(limit, error)| {
+        let name = request.params.as_ref()?.get("name")?.as_str()?;
+        if !server.registry.is_read_only(name) {
+            return None;
+        }
+        let runtime = tokio::runtime::Handle::try_current().ok()?;
+        let answered = answered.clone();
+        let out = out.clone();
+        let timeout = JsonRpcResponse::from_error(request.id.clone(), error);
+        Some(runtime.spawn(async move {
+            tokio::time::sleep(limit).await;
+            if !answered.swap(true, Ordering::SeqCst) {
+                let _ = out.send(to_line(&timeout));
+            }
+        }))
+    });
+    server.session.set_cancel_flag(flag);
+    let response = server.handle_deferrable(request);
+    server.session.set_cancel_flag(None);
+    cancellations.finish(id.as_ref());
+    if let Some(watchdog) = watchdog {
+        watchdog.abort();
+    }
+    // A deferred call reports progress and answers when it's resumed
+    if let Some(response) = response.filter(|_| !answered.swap(true, Ordering::SeqCst)) {
+        if let Some(token) = &token {
+            let _ = out.send(progress_notification(token, 1));
+        }
+        let _ = out.send(to_line(&response));
+    }
+    for notification in server.take_notifications() {
+        let _ = out.send(notification);
+    }
+}
+
+/// Name of a client's working branch, `session-{client}-{id}`, with the
+/// client's name cut down to lowercase letters, digits, and dashes.
+fn client_branch_name(client: &str, id: &str) -> String {
+    let mut slug = String::new();
+    for c in client.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    // Leave room for the prefix, the id, and their dashes
+    let room = MAX_BRANCH_NAME_LEN.saturating_sub("session--".len() + id.len());
+    slug.truncate(room);
+    let slug = match slug.trim_end_matches('-') {
+        "" => "client",
+        slug => slug,
+    };
+    format!("session-{}-{}", slug, id)
+}
+
+/// The time limit of a `tools/call` request, and the error it gets past it.
+fn request_timeout(server: &McpServer, request: &JsonRpcRequest) -> Option<(Duration, McpError)> {
+    if request.method != "tools/call" {
+        return None;
+    }
+    let params = request.params.as_ref()?;
+    let name = params.get("name")?.as_str()?;
+    server.call_timeout(name, params.get("arguments").and_then(|a| a.as_object()))
+}
+
+/// What the blocking stdio loop waits on, see [`McpServer::serve_input`].
+pub(crate) enum Input {
+    /// A line read from the client
+    Line(std::io::Result<String>),
+    /// The client closed its end
+    Eof,
+    /// A shutdown signal, by name
+    Signal(&'static str),
+}
+
+/// Work queued for the async core's dispatcher.
+enum Job {
+    /// One request, which may run alongside reads or be cancelled.
+    Request(JsonRpcRequest),
+    /// A JSON-RPC batch or client response, run whole on the primary session.
+    Text(String),
+    /// Answer the calls set aside for the client's model that stopped waiting.
+    Expire,
+    /// Stop a call waiting on the client's model, by request id.
+    Cancel(JsonValue),
+}
+
+/// Requests on the async core that `notifications/cancelled` can still
+/// reach, by serialized id.
+#[derive(Clone, Default)]
+struct Cancellations(Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>);
+
+impl Cancellations {
+    /// Start tracking a request as it's read.
+    fn register(&self, id: &JsonValue) {
+        self.lock().insert(id.to_string(), Arc::default());
+    }
+
+    /// Mark a request cancelled; unknown or finished ids are ignored.
+    fn cancel(&self, id: &JsonValue) {
+        if let Some(flag) = self.lock().get(&id.to_string()) {
+            flag.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// The flag a request's session watches.
+    fn flag(&self, id: &JsonValue) -> Option<Arc<AtomicBool>> {
+        self.lock().get(&id.to_string()).cloned()
+    }
+
+    /// Stop tracking a request once its response is decided.
+    fn finish(&self, id: Option<&JsonValue>) {
+        if let Some(id) = id {
+            self.lock().remove(&id.to_string());
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Unwrap a finished blocking task, reporting a panic as an internal error.
+fn join_result<T>(result: std::result::Result<T, tokio::task::JoinError>) -> Result<T> {
+    result.map_err(|e| McpError::Internal(format!("request worker failed: {}", e)))
+}
+
+/// A serialized `notifications/progress` message; calls report 0 of 1 when
+/// they start and 1 of 1 when they finish.
+fn progress_notification(token: &JsonValue, progress: u64) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/progress",
+        "params": { "progressToken": token, "progress": progress, "total": 1 },
+    })
+    .to_string()
+}
+
+fn to_line(response: &JsonRpcResponse) -> String {
+    serde_json::to_string(response).unwrap_or_else(|_| "null".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn test_server() -> McpServer {
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        McpServer::new(McpSession::new(db))
+    }
+
+    /// Feed newline-delimited requests through `serve` and parse the responses.
+    fn serve_lines(server: &mut McpServer, requests: &[JsonValue]) -> Vec<JsonValue> {
+        let input: Vec<String> = requests.iter().map(|r| r.to_string()).collect();
+        let input = input.join("\n");
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).expect("serve failed");
+        String::from_utf8(output)
+            .expect("Response was not UTF-8")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("Response was not JSON"))
+            .collect()
+    }
+
+    /// Parse the JSON payload from a tools/call text content response.
+    fn tool_payload(response: &JsonValue) -> JsonValue {
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .expect("Expected text content");
+        serde_json::from_str(text).expect("Tool text was not JSON")
+    }
+
+    #[test]
+    fn test_protocol_version_negotiation() {
+        let initialize = |version: Option<&str>| {
+            let params = match version {
+                Some(v) => serde_json::json!({"protocolVersion": v}),
+                None => serde_json::json!({}),
+            };
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": params})
+        };
+        let list = serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"});
+        let store = serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+            "params": {"name": "strata_store", "arguments": {"key": "k", "value": 1}}});
+        let negotiate = |version: Option<&str>| {
+            serve_lines(&mut test_server(), &[initialize(version), list.clone(), store.clone()])
+        };
+
+        let old = negotiate(Some("2024-11-05"));
+        assert_eq!(old[0]["result"]["protocolVersion"], "2024-11-05");
+        assert!(old[1]["result"]["tools"][0]["annotations"].is_null());
+        assert!(old[1]["result"]["tools"][0]["outputSchema"].is_null());
+        assert!(old[2]["result"]["structuredContent"].is_null());
+
+        let current = negotiate(Some("2025-06-18"));
+        assert_eq!(current[0]["result"]["protocolVersion"], "2025-06-18");
+        assert!(current[1]["result"]["tools"][0]["annotations"].is_object());
+        assert_eq!(current[1]["result"]["tools"][0]["outputSchema"]["type"], "object");
+        assert_eq!(current[2]["result"]["structuredContent"], tool_payload(&current[2]));
+
+        // Unknown versions get our newest; none at all gets the oldest
+        let future = negotiate(Some("2099-01-01"));
+        assert_eq!(future[0]["result"]["protocolVersion"], "2025-06-18");
+        let unspecified = negotiate(None);
+        assert_eq!(unspecified[0]["result"]["protocolVersion"], "2024-11-05");
+    }
+
+    #[test]
+    fn test_structured_content_wraps_non_object_results() {
+        let initialize = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": {"protocolVersion": "2025-06-18"}});
+        let list = serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+            "params": {"name": "strata_branch", "arguments": {"action": "list"}}});
+        let responses = serve_lines(&mut test_server(), &[initialize, list]);
+
+        let payload = tool_payload(&responses[1]);
+        assert!(payload.is_array());
+        let structured = &responses[1]["result"]["structuredContent"];
+        assert_eq!(structured, &serde_json::json!({ "items": payload }));
+    }
+
+    #[test]
+    fn test_tools_list_pagination() {
+        let mut server = test_server();
+        server.set_tools_page_size(Some(4));
+        let total = server.registry.tools().len();
+
+        let mut names = Vec::new();
+        let mut cursor = JsonValue::Null;
+        loop {
+            let list = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list",
+                "params": {"cursor": cursor}});
+            let response = serve_lines(&mut server, &[list]).remove(0);
+            let tools = response["result"]["tools"].as_array().expect("Expected tools");
+            assert!(tools.len() <= 4);
+            names.extend(tools.iter().filter_map(|t| t["name"].as_str().map(String::from)));
+            cursor = response["result"]["nextCursor"].clone();
+            if cursor.is_null() {
+                break;
+            }
+        }
+        let expected: Vec<String> = server.registry.tools().iter().map(|t| t.name.clone()).collect();
+        assert_eq!(names, expected);
+        assert_eq!(names.len(), total);
+
+        let bad = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list",
+            "params": {"cursor": "nope"}});
+        let response = serve_lines(&mut server, &[bad]).remove(0);
+        assert_eq!(response["error"]["code"], rpc_codes::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_batch_requests() {
+        let mut server = test_server();
+        let store = |id: u64, key: &str| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "strata_store", "arguments": {"key": key, "value": id}}})
+        };
+        let recall = |id: u64, key: &str| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "strata_recall", "arguments": {"key": key}}})
+        };
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!([store(1, "a"), store(2, "b")]),
+                serde_json::json!([
+                    recall(3, "a"),
+                    {"jsonrpc": "2.0", "method": "initialized"},
+                    recall(4, "b"),
+                    "not a request"
+                ]),
+                serde_json::json!([{"jsonrpc": "2.0", "method": "initialized"}]),
+                serde_json::json!([]),
+            ],
+        );
+
+        // The notification-only batch gets no response at all
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].as_array().map(Vec::len), Some(2));
+        let recalls = responses[1].as_array().expect("Expected a batch response");
+        assert_eq!(recalls.len(), 3);
+        assert_eq!(tool_payload(&recalls[0])["value"], 1);
+        assert_eq!(tool_payload(&recalls[1])["value"], 2);
+        assert_eq!(recalls[2]["error"]["code"], rpc_codes::INVALID_REQUEST);
+        assert_eq!(responses[2]["error"]["code"], rpc_codes::INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_idle_shutdown_stops_serving() {
+        let mut server = test_server();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let ping = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+        tx.send(Input::Line(Ok(ping.to_string()))).unwrap();
+
+        // The client never disconnects, so only the idle period ends serving
+        server.set_idle_shutdown(Some(Duration::from_millis(50)));
+        let mut output = Vec::new();
+        server.serve_input(rx, &mut output).expect("serve failed");
+        let response: JsonValue =
+            serde_json::from_slice(&output).expect("Response was not JSON");
+        assert_eq!(response["id"], 1);
+        drop(tx);
+    }
+
+    #[test]
+    fn test_shutdown_signal_sends_final_message() {
+        let mut server = test_server();
+        let (tx, rx) = std::sync::mpsc::channel();
+        for request in [
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": {"name": "strata_store", "arguments": {"key": "k", "value": 1}}}),
+        ] {
+            tx.send(Input::Line(Ok(request.to_string()))).unwrap();
+        }
+        tx.send(Input::Signal("SIGTERM")).unwrap();
+        let late = serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "ping"});
+        tx.send(Input::Line(Ok(late.to_string()))).unwrap();
+
+        let mut output = Vec::new();
+        server.serve_input(rx, &mut output).expect("serve failed");
+        let messages: Vec<JsonValue> = String::from_utf8(output)
+            .expect("Response was not UTF-8")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("Response was not JSON"))
+            .collect();
+
+        // Requests read before the signal are answered; later ones aren't
+        assert_eq!(messages[0]["result"]["capabilities"]["logging"], serde_json::json!({}));
+        assert_eq!(messages[1]["id"], 2);
+        assert_eq!(messages[2]["method"], "notifications/message");
+        assert_eq!(messages[2]["params"]["level"], "notice");
+        assert_eq!(messages[2]["params"]["data"]["signal"], "SIGTERM");
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[test]
+    fn test_branch_per_session() {
+        let mut server = test_server();
+        server.set_branch_per_session(Some("default".to_string()));
+        server.set_session_id("01ABC".to_string());
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": {"clientInfo": {"name": "Claude Code", "version": "1.0"}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_status", "arguments": {}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                    "params": {"name": "strata_store", "arguments": {"key": "k", "value": 1}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "tools/call",
+                    "params": {"name": "strata_status", "arguments": {}}}),
+            ],
+        );
+        // Reads stay on the base branch; the first write forks
+        assert_eq!(tool_payload(&responses[1])["branch"], "default");
+        let status = tool_payload(&responses[3]);
+        assert_eq!(status["branch"], "session-claude-code-01ABC");
+        assert_eq!(status["session_branch"]["base"], "default");
+
+        // The branch outlives the client
+        server.end_client();
+        assert_eq!(server.session.branch(), "session-claude-code-01ABC");
+
+        assert_eq!(client_branch_name("  ", "01ABC"), "session-client-01ABC");
+        let long = client_branch_name(&"x".repeat(80), &crate::ulid::generate());
+        assert_eq!(long.len(), MAX_BRANCH_NAME_LEN);
+    }
+
+    #[test]
+    fn test_logging_set_level() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "logging/setLevel",
+                    "params": {"level": "error"}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "logging/setLevel",
+                    "params": {"level": "loud"}}),
+            ],
+        );
+        assert_eq!(responses[1]["result"], serde_json::json!({}));
+        assert_eq!(responses[2]["error"]["code"], rpc_codes::INVALID_PARAMS);
+
+        // A notice is now below the client's threshold
+        assert!(server.shut_down("SIGINT").is_none());
+    }
+
+    #[test]
+    fn test_tool_timeout() {
+        let mut server = test_server();
+        server.set_tool_timeout(Some("strata_store"), Some(Duration::ZERO));
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": {"name": "strata_store", "arguments": {"key": "k", "value": "v"}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_recall", "arguments": {"key": "k"}}}),
+            ],
+        );
+
+        let error = &responses[0]["error"];
+        assert_eq!(error["code"], rpc_codes::REQUEST_TIMEOUT);
+        assert_eq!(error["data"]["tool"], "strata_store");
+        assert_eq!(error["data"]["timeout_ms"], 0);
+        // The next call isn't held to the limit, and the store never ran
+        assert!(responses[1]["error"].is_null());
+        assert_eq!(tool_payload(&responses[1])["found"], false);
+    }
+
+    #[test]
+    fn test_pipelined_requests_preserve_order() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": {"name": "strata_store", "arguments": {"key": "k", "value": "v1"}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_recall", "arguments": {"key": "k"}}}),
+            ],
+        );
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(tool_payload(&responses[1])["value"], "v1");
+    }
+
+    #[test]
+    fn test_tools_list_includes_examples() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"})],
+        );
+
+        let tools = responses[0]["result"]["tools"].as_array().expect("Expected tools array");
+        let store = tools.iter().find(|t| t["name"] == "strata_store").expect("strata_store listed");
+        assert_eq!(store["examples"][0]["output"]["stored"], true);
+        assert!(tools.iter().all(|t| t["examples"].is_array()));
+    }
+
+    #[test]
+    fn test_tools_list_includes_annotations() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"})],
+        );
+
+        let tools = responses[0]["result"]["tools"].as_array().expect("Expected tools array");
+        let hints = |name: &str| {
+            tools.iter().find(|t| t["name"] == name).expect("tool listed")["annotations"].clone()
+        };
+        assert_eq!(hints("strata_recall")["readOnlyHint"], true);
+        assert_eq!(hints("strata_forget")["destructiveHint"], true);
+        assert_eq!(hints("strata_store")["destructiveHint"], true);
+        assert_eq!(hints("strata_store")["idempotentHint"], false);
+        assert_eq!(hints("strata_log")["readOnlyHint"], false);
+    }
+
+    #[test]
+    fn test_rpc_discover_lists_methods_and_tools() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "rpc.discover"})],
+        );
+
+        let result = &responses[0]["result"];
+        let methods = result["methods"].as_array().expect("Expected methods array");
+        assert!(methods.iter().any(|m| m["name"] == "tools/call"));
+        let tools = result["tools"].as_array().expect("Expected tools array");
+        assert_eq!(tools.len(), server.registry.tools().len());
+        assert!(tools.iter().all(|t| t["inputSchema"]["type"] == "object"));
+    }
+
+    #[test]
+    fn test_resources_list_and_read_documents() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": {"name": "strata_store",
+                        "arguments": {"key": "notes/today", "value": {"done": true}}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "resources/list"}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "resources/read",
+                    "params": {"uri": "strata://default/default/doc/notes%2Ftoday"}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "resources/read",
+                    "params": {"uri": "strata://default/default/doc/missing"}}),
+            ],
+        );
+
+        let listed = responses[1]["result"]["resources"].as_array().expect("Expected resources");
+        assert!(listed.iter().any(|r| r["uri"] == "strata://default/default/doc/notes%2Ftoday"));
+        assert!(responses[1]["result"]["nextCursor"].is_null());
+
+        let contents = &responses[2]["result"]["contents"][0];
+        assert_eq!(contents["mimeType"], "application/json");
+        let text = contents["text"].as_str().expect("Expected text");
+        let doc: JsonValue = serde_json::from_str(text).expect("Resource text was not JSON");
+        assert_eq!(doc, serde_json::json!({"done": true}));
+
+        assert_eq!(responses[3]["error"]["code"], rpc_codes::RESOURCE_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_resource_templates_and_event_streams() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "resources/templates/list"}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_log",
+                        "arguments": {"event": "deploy", "data": {"ok": true}}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "resources/read",
+                    "params": {"uri": "strata://default/events/deploy"}}),
+            ],
+        );
+
+        let templates = responses[0]["result"]["resourceTemplates"]
+            .as_array()
+            .expect("Expected resourceTemplates");
+        let uris: Vec<&str> = templates.iter().filter_map(|t| t["uriTemplate"].as_str()).collect();
+        assert_eq!(
+            uris,
+            [
+                "strata://{branch}/{space}/doc/{key}",
+                "strata://{branch}/events/{type}",
+                "strata://{branch}/{space}/events/{type}"
+            ]
+        );
+
+        let text = responses[2]["result"]["contents"][0]["text"]
+            .as_str()
+            .expect("Expected text");
+        let events: JsonValue = serde_json::from_str(text).expect("Resource text was not JSON");
+        assert_eq!(events.as_array().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_prompts_list_and_get() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "prompts/list"}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "prompts/get",
+                    "params": {"name": "recover_deleted_data", "arguments": {"key": "notes"}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "prompts/get",
+                    "params": {"name": "recover_deleted_data"}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "prompts/get",
+                    "params": {"name": "no_such_prompt"}}),
+            ],
+        );
+
+        let listed = responses[0]["result"]["prompts"].as_array().expect("Expected prompts");
+        let names: Vec<&str> = listed.iter().filter_map(|p| p["name"].as_str()).collect();
+        assert_eq!(
+            names,
+            ["summarize_memory", "safe_experiment_workflow", "recover_deleted_data"]
+        );
+
+        let message = &responses[1]["result"]["messages"][0];
+        assert_eq!(message["role"], "user");
+        let text = message["content"]["text"].as_str().expect("Expected text");
+        assert!(text.contains("branch 'default'") && text.contains("'notes'"));
+
+        assert_eq!(responses[2]["error"]["code"], rpc_codes::INVALID_PARAMS);
+        assert_eq!(responses[3]["error"]["code"], rpc_codes::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_enabling_developer_tools_notifies_list_changed() {
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let registry = ToolRegistry::new().with_developer_access();
+        let mut server = McpServer::with_registry(McpSession::new(db), registry);
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_developer", "arguments": {"action": "enable"}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                    "params": {"name": "strata_kv_put",
+                        "arguments": {"key": "k", "value": "v"}}}),
+            ],
+        );
+
+        assert_eq!(responses[0]["result"]["capabilities"]["tools"]["listChanged"], true);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[2]["method"], "notifications/tools/list_changed");
+        assert!(responses[2]["id"].is_null());
+        assert_eq!(responses[3]["id"], 3);
+        assert!(responses[3]["result"]["isError"].is_null());
+        assert_eq!(responses.len(), 4);
+
+        let listed = server.registry.tools();
+        assert!(listed.iter().any(|t| t.name == "strata_kv_put"));
+        assert_eq!(listed.iter().filter(|t| t.name == "strata_search").count(), 1);
+    }
+
+    #[test]
+    fn test_completion_suggests_keys_and_branches() {
+        let mut server = test_server();
+        let complete = |id: u64, reference: JsonValue, name: &str, value: &str| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "completion/complete",
+                "params": {"ref": reference, "argument": {"name": name, "value": value}}})
+        };
+        let store = |id: u64, key: &str| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "strata_store", "arguments": {"key": key, "value": 1}}})
+        };
+        let tool = |name: &str| serde_json::json!({"type": "ref/tool", "name": name});
+        let responses = serve_lines(
+            &mut server,
+            &[
+                store(1, "user:alice"),
+                store(2, "user:bob"),
+                store(3, "config"),
+                complete(4, tool("strata_recall"), "key", "user:"),
+                complete(5, tool("strata_branch"), "name", "def"),
+                complete(6, tool("strata_store"), "value", ""),
+                complete(7, serde_json::json!({"type": "ref/nope"}), "key", ""),
+            ],
+        );
+
+        let values = |i: usize| responses[i]["result"]["completion"]["values"].clone();
+        assert_eq!(values(3), serde_json::json!(["user:alice", "user:bob"]));
+        assert_eq!(responses[3]["result"]["completion"]["total"], 2);
+        assert_eq!(values(4), serde_json::json!(["default"]));
+        assert_eq!(values(5), serde_json::json!([]));
+        assert_eq!(responses[6]["error"]["code"], rpc_codes::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_roots_move_the_session_to_the_workspace_database() {
+        let workspace = tempfile::tempdir().expect("Failed to create temp dir");
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let mut server = McpServer::new(McpSession::new(db.clone()));
+        server.set_roots_dir(
+            ".strata",
+            Arc::new(|dir: &std::path::Path| {
+                let db = stratadb::Strata::open_with(dir, stratadb::OpenOptions::new())
+                    .map_err(|e| e.to_string())?;
+                Ok(McpSession::new(db))
+            }),
+        );
+        let root = format!("file://{}", workspace.path().display());
+        let recall = serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+            "params": {"name": "strata_recall", "arguments": {"key": "k"}}});
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": {"capabilities": {"roots": {"listChanged": true}}}}),
+                serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+                serde_json::json!({"jsonrpc": "2.0", "id": "strata-roots-1",
+                    "result": {"roots": [{"uri": root, "name": "project"}]}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_store", "arguments": {"key": "k", "value": 1}}}),
+                recall.clone(),
+            ],
+        );
+
+        // The roots/list request follows the initialized acknowledgment, and
+        // its response gets no reply
+        assert_eq!(responses[2]["method"], "roots/list");
+        assert_eq!(responses[2]["id"], "strata-roots-1");
+        assert_eq!(responses[3]["id"], 2);
+        assert_eq!(tool_payload(&responses[4])["found"], true);
+        assert_eq!(responses.len(), 5);
+        assert!(workspace.path().join(".strata").exists());
+
+        // The database the server started with never saw the write
+        let mut original = McpServer::new(McpSession::new(db));
+        let responses = serve_lines(&mut original, &[recall]);
+        assert_eq!(tool_payload(&responses[0])["found"], false);
+    }
+
+    #[test]
+    fn test_roots_wait_for_an_open_transaction_to_end() {
+        let workspace = tempfile::tempdir().expect("Failed to create temp dir");
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let mut server =
+            McpServer::with_registry(McpSession::new(db.clone()), ToolRegistry::developer());
+        server.set_roots_dir(
+            ".strata",
+            Arc::new(|dir: &std::path::Path| {
+                let db = stratadb::Strata::open_with(dir, stratadb::OpenOptions::new())
+                    .map_err(|e| e.to_string())?;
+                Ok(McpSession::new(db))
+            }),
+        );
+        let root = format!("file://{}", workspace.path().display());
+        let call = |id: u64, name: &str, arguments: JsonValue| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": name, "arguments": arguments}})
+        };
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": {"capabilities": {"roots": {"listChanged": true}}}}),
+                call(2, "strata_txn_begin", serde_json::json!({})),
+                call(3, "strata_kv_put", serde_json::json!({"key": "k", "value": 1})),
+                serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+                serde_json::json!({"jsonrpc": "2.0", "id": "strata-roots-1",
+                    "result": {"roots": [{"uri": root, "name": "project"}]}}),
+                call(4, "strata_txn_active", serde_json::json!({})),
+                call(5, "strata_txn_commit", serde_json::json!({})),
+                call(6, "strata_kv_get", serde_json::json!({"key": "k"})),
+            ],
+        );
+
+        // The transaction outlives the roots response and commits where it
+        // began; only then does the session move
+        assert_eq!(responses[4]["method"], "roots/list");
+        assert_eq!(tool_payload(&responses[5]), true);
+        assert_ne!(responses[6]["result"]["isError"], true);
+        assert!(tool_payload(&responses[7]).is_null());
+        assert_eq!(responses.len(), 8);
+        assert!(workspace.path().join(".strata").exists());
+
+        let mut original = McpServer::with_registry(McpSession::new(db), ToolRegistry::developer());
+        let get = call(1, "strata_kv_get", serde_json::json!({"key": "k"}));
+        let responses = serve_lines(&mut original, &[get]);
+        assert_eq!(tool_payload(&responses[0])["value"], 1);
+    }
+
+    #[test]
+    fn test_summarize_through_client_sampling() {
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let registry = ToolRegistry::new().with_developer_tools();
+        let mut server = McpServer::with_registry(McpSession::new(db), registry);
+        let store = |id: u64, key: &str, text: &str| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "strata_store",
+                    "arguments": {"key": key, "value": {"text": text}}}})
+        };
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": {"capabilities": {"sampling": {}}}}),
+                store(2, "notes/a", "The launch moved to May."),
+                store(3, "notes/b", "Launch is in May."),
+                serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "tools/call",
+                    "params": {"name": "strata_summarize", "arguments": {"prefix": "notes/"}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 5, "method": "ping"}),
+                serde_json::json!({"jsonrpc": "2.0", "id": "strata-sampling-1", "result": {
+                    "role": "assistant",
+                    "content": {"type": "text", "text": "The launch is in May."},
+                    "model": "client-model",
+                    "stopReason": "endTurn"}}),
+            ],
+        );
+
+        // The call waits for the client's model while other requests are served
+        assert_eq!(responses[3]["method"], "sampling/createMessage");
+        assert_eq!(responses[3]["id"], "strata-sampling-1");
+        let params = &responses[3]["params"];
+        assert_eq!(params["maxTokens"], 512);
+        let prompt = params["messages"][0]["content"]["text"].as_str().unwrap();
+        assert!(prompt.contains("notes/a") && prompt.contains("Launch is in May."));
+        assert_eq!(responses[4]["id"], 5);
+        assert_eq!(responses[5]["id"], 4);
+        let summary = tool_payload(&responses[5]);
+        assert_eq!(summary["summary"], "The launch is in May.");
+        assert_eq!(summary["keys"], serde_json::json!(["notes/a", "notes/b"]));
+        assert_eq!(summary["model"], "client-model");
+        assert_eq!(summary["source"], "sampling");
+        assert_eq!(responses.len(), 6);
+
+        // Without sampling the call needs a local model
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 6, "method": "initialize",
+                    "params": {}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 7, "method": "tools/call",
+                    "params": {"name": "strata_summarize", "arguments": {"keys": ["notes/a"]}}}),
+            ],
+        );
+        let result = &responses[1]["result"];
+        assert_eq!(result["isError"], true);
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("model"), "{}", text);
+    }
+
+    #[test]
+    fn test_summary_waiting_on_the_client_times_out() {
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let registry = ToolRegistry::new().with_developer_tools();
+        let mut server = McpServer::with_registry(McpSession::new(db), registry);
+        server.set_tool_timeout(Some("strata_summarize"), Some(Duration::ZERO));
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": {"capabilities": {"sampling": {}}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_store",
+                        "arguments": {"key": "notes/a", "value": {"text": "May."}}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                    "params": {"name": "strata_summarize", "arguments": {"keys": ["notes/a"]}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "ping"}),
+            ],
+        );
+
+        // The client never answers, so the call fails once its limit passes
+        assert_eq!(responses[2]["method"], "sampling/createMessage");
+        assert_eq!(responses[3]["id"], 4);
+        assert_eq!(responses[4]["id"], 3);
+        assert_eq!(responses[4]["error"]["code"], rpc_codes::REQUEST_TIMEOUT);
+        assert_eq!(server.next_expiry(), None);
+    }
+
+    #[test]
+    fn test_summary_waiting_on_the_client_is_cancelled_or_shut_down() {
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let registry = ToolRegistry::new().with_developer_tools();
+        let mut server = McpServer::with_registry(McpSession::new(db), registry);
+        let summarize = |id: u64| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "strata_summarize", "arguments": {"keys": ["notes/a"]}}})
+        };
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": {"capabilities": {"sampling": {}}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_store",
+                        "arguments": {"key": "notes/a", "value": {"text": "May."}}}}),
+                summarize(3),
+                serde_json::json!({"jsonrpc": "2.0", "method": "notifications/cancelled",
+                    "params": {"requestId": 3}}),
+                summarize(4),
+            ],
+        );
+        let answer = responses.iter().find(|r| r["id"] == 3 && r.get("method").is_none());
+        let answer = answer.expect("Expected the cancelled call to be answered");
+        assert_eq!(answer["error"]["code"], rpc_codes::REQUEST_CANCELLED);
+        // The client is told to drop the sampling request
+        assert!(responses.iter().any(|r| r["method"] == "notifications/cancelled"
+            && r["params"]["requestId"] == "strata-sampling-1"));
+
+        // Shutting down answers the call still waiting
+        server.shut_down("SIGTERM");
+        let messages: Vec<JsonValue> = server
+            .take_notifications()
+            .iter()
+            .map(|m| serde_json::from_str(m).expect("Message was not JSON"))
+            .collect();
+        let answer = messages.iter().find(|m| m["id"] == 4).expect("Expected an answer");
+        assert_eq!(answer["result"]["isError"], true);
+        assert_eq!(server.next_expiry(), None);
+    }
+
+    #[test]
+    fn test_tool_failure_is_an_error_result() {
+        let mut server = test_server();
+        let call = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+            "params": {"name": "strata_store", "arguments": {"value": "no key"}}});
+        let responses = serve_lines(&mut server, &[call.clone()]);
+
+        assert!(responses[0]["error"].is_null());
+        assert_eq!(responses[0]["result"]["isError"], true);
+        let text = responses[0]["result"]["content"][0]["text"].as_str().expect("Expected text");
+        assert!(text.contains("key"));
+
+        server.set_legacy_tool_errors(true);
+        let responses = serve_lines(&mut server, &[call]);
+        assert_eq!(responses[0]["error"]["code"], rpc_codes::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_tool_warnings_attached_to_result() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": {"name": "strata_store", "arguments": {"key": "k", "value": "some text"}}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                    "params": {"name": "strata_search", "arguments": {"query": "text"}}}),
+            ],
+        );
+
+        // The cache database has no auto-embed, so search is keyword-only
+        assert!(responses[0]["result"].get("_meta").is_none());
+        let warnings = &responses[1]["result"]["_meta"]["warnings"];
+        assert_eq!(warnings[0]["code"], "semantic_unavailable");
+        assert!(responses[1]["result"].get("warnings").is_none());
+        assert_eq!(responses[1]["result"]["content"].as_array().map(|c| c.len()), Some(1));
+        assert!(tool_payload(&responses[1]).is_array());
+    }
+
+    #[test]
+    fn test_unknown_tool_stays_rpc_error() {
+        let mut server = test_server();
+        let responses = serve_lines(
+            &mut server,
+            &[serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": {"name": "strata_nope", "arguments": {}}})],
+        );
+
+        assert!(responses[0]["result"].is_null());
+        assert!(responses[0]["error"]["code"].is_i64());
+    }
+
+    #[test]
+    fn test_serve_async_answers_pings_and_reports_progress() {
+        let server = test_server();
+        let input = [
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": {"name": "strata_store", "arguments": {"key": "k", "value": "v"},
+                    "_meta": {"progressToken": "t1"}}}),
+            serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "ping"}),
+            serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": {"name": "strata_recall", "arguments": {"key": "k"}}}),
+        ]
+        .map(|r| r.to_string())
+        .join("\n");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("Failed to build runtime");
+        let mut output = Vec::new();
+        runtime
+            .block_on(server.serve_async(input.as_bytes(), &mut output))
+            .expect("serve_async failed");
+        let messages: Vec<JsonValue> = String::from_utf8(output)
+            .expect("Output was not UTF-8")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("Output was not JSON"))
+            .collect();
+
+        let position = |pred: &dyn Fn(&JsonValue) -> bool| {
+            messages.iter().position(pred).expect("Expected message missing")
+        };
+        let progress = |n: u64| {
+            position(&|m| m["params"]["progressToken"] == "t1" && m["params"]["progress"] == n)
+        };
+        let (started, finished) = (progress(0), progress(1));
+        let stored = position(&|m| m["id"] == 1);
+        let recalled = position(&|m| m["id"] == 3);
+        assert!(started < finished && finished < stored && stored < recalled);
+        assert_eq!(messages[position(&|m| m["id"] == 2)]["result"], serde_json::json!({}));
+        assert_eq!(tool_payload(&messages[recalled])["value"], "v");
+        assert_eq!(messages.len(), 5);
+    }
+
+    #[test]
+    fn test_serve_async_concurrent_reads() {
+        let mut server = test_server();
+        server.set_concurrent_reads(true);
+        let call = |id: u64, name: &str, arguments: JsonValue| {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": name, "arguments": arguments}})
+        };
+        let mut input = vec![
+            serde_json::json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {}}),
+            call(1, "strata_store", serde_json::json!({"key": "k", "value": "v1"})),
+        ];
+        input.extend((2..6).map(|id| call(id, "strata_recall", serde_json::json!({"key": "k"}))));
+        input.push(call(6, "strata_store", serde_json::json!({"key": "k", "value": "v2"})));
+        input.push(call(7, "strata_recall", serde_json::json!({"key": "k"})));
+        let input = input.iter().map(|r| r.to_string()).collect::<Vec<_>>().join("\n");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("Failed to build runtime");
+        let mut output = Vec::new();
+        runtime
+            .block_on(server.serve_async(input.as_bytes(), &mut output))
+            .expect("serve_async failed");
+        let messages: Vec<JsonValue> = String::from_utf8(output)
+            .expect("Output was not UTF-8")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("Output was not JSON"))
+            .collect();
+        assert_eq!(messages.len(), 8);
+
+        let by_id = |id: u64| {
+            let position = messages.iter().position(|m| m["id"] == id).expect("Missing response");
+            (position, &messages[position])
+        };
+        // Reads see the write before them and finish before the write after them
+        let (second_write, _) = by_id(6);
+        for id in 2..6 {
+            let (position, response) = by_id(id);
+            assert_eq!(tool_payload(response)["value"], "v1");
+            assert!(position < second_write);
+        }
+        assert_eq!(tool_payload(by_id(7).1)["value"], "v2");
+    }
+
+    #[test]
+    fn test_cancelled_request_gets_cancelled_error() {
+        let mut server = test_server();
+        let (out_tx, mut out_rx) = tokio::sync::mpsc::unbounded_channel();
+        let cancellations = Cancellations::default();
+        let call = |id: u64| {
+            serde_json::from_value::<JsonRpcRequest>(serde_json::json!({
+                "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "strata_store", "arguments": {"key": "k", "value": id}}
+            }))
+            .expect("Invalid request")
+        };
+
+        cancellations.register(&serde_json::json!(1));
+        cancellations.cancel(&serde_json::json!(1));
+        run_request(&mut server, call(1), &out_tx, &cancellations);
+        let response: JsonValue =
+            serde_json::from_str(&out_rx.try_recv().expect("No response")).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], rpc_codes::REQUEST_CANCELLED);
+        assert!(cancellations.flag(&serde_json::json!(1)).is_none());
+
+        // Cancelling an unknown or finished id is a no-op
+        cancellations.cancel(&serde_json::json!(1));
+        cancellations.register(&serde_json::json!(2));
+        run_request(&mut server, call(2), &out_tx, &cancellations);
+        let response: JsonValue =
+            serde_json::from_str(&out_rx.try_recv().expect("No response")).unwrap();
+        assert_eq!(response["id"], 2);
+        assert!(response["error"].is_null());
+    }
+
+    #[test]
+    fn test_cancelled_call_rolls_back_its_transaction() {
+        let mut server = test_server();
+        let flag = Arc::new(AtomicBool::new(false));
+        server.session.set_cancel_flag(Some(flag.clone()));
+        let begin = stratadb::Command::TxnBegin { branch: None, options: None };
+        server.session.execute(begin).expect("Failed to begin");
+        flag.store(true, Ordering::Relaxed);
+
+        // One the client opened before the call is left to the client
+        server.roll_back_abandoned(true, &Err(McpError::Cancelled));
+        assert!(server.session.in_transaction());
+        server.roll_back_abandoned(false, &Err(McpError::Cancelled));
+        assert!(!server.session.in_transaction());
+        server.session.set_cancel_flag(None);
+
+        let begin = stratadb::Command::TxnBegin { branch: None, options: None };
+        server.session.execute(begin).expect("Failed to begin");
+        let timeout = McpError::Timeout {
+            tool: "strata_import_file".to_string(),
+            timeout_ms: 0,
+        };
+        server.roll_back_abandoned(false, &Err(timeout));
+        assert!(!server.session.in_transaction());
+    }
+
+    #[test]
+    fn test_cancel_flag_stops_further_commands() {
+        let db = stratadb::Strata::cache().expect("Failed to create cache database");
+        let mut session = McpSession::new(db);
+        let flag = Arc::new(AtomicBool::new(true));
+        session.set_cancel_flag(Some(flag));
+        assert!(matches!(session.execute(stratadb::Command::Ping), Err(McpError::Cancelled)));
+        session.set_cancel_flag(None);
+        assert!(session.execute(stratadb::Command::Ping).is_ok());
+    }
+
     #[test]
     fn test_json_rpc_response_success() {
         let response = JsonRpcResponse::success(Some(JsonValue::Number(1.into())), serde_json::json!({"ok": true}));
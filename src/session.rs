@@ -2,13 +2,254 @@
 //!
 //! Wraps a stratadb Session with branch/space context, similar to the CLI's SessionState.
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use stratadb::{
-    AccessMode, BranchDiffResult, Command, ForkInfo, MergeInfo, MergeStrategy, Output, Session,
-    Strata,
+    AccessMode, BranchDiffResult, BranchId, Command, ForkInfo, MergeInfo, MergeStrategy, Output,
+    Session, Strata, Value,
 };
 
 use crate::error::{McpError, Result};
 
+/// Longest branch name accepted.
+pub const MAX_BRANCH_NAME_LEN: usize = 64;
+
+/// Branch naming rules, as reported to agents.
+pub const BRANCH_NAME_RULES: &str = "branch names are 1-64 characters of ASCII letters, digits, \
+     '-', '_', '.', or '/', and must start with a letter or digit";
+
+/// Check a branch name against the naming rules.
+pub fn validate_branch_name(name: &str) -> Result<()> {
+    let valid = name.len() <= MAX_BRANCH_NAME_LEN
+        && name.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'));
+    if valid {
+        Ok(())
+    } else {
+        Err(McpError::InvalidArg {
+            name: "branch".to_string(),
+            reason: format!("invalid branch name '{}': {}", name, BRANCH_NAME_RULES),
+        })
+    }
+}
+
+/// What to do with an auto-created session branch when the session ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionBranchEnd {
+    /// Merge the session branch back into its base, then delete it.
+    Merge,
+    /// Delete the session branch, discarding its changes.
+    Delete,
+    /// Leave the session branch in place.
+    Keep,
+}
+
+impl SessionBranchEnd {
+    /// Name used on the command line and in status output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionBranchEnd::Merge => "merge",
+            SessionBranchEnd::Delete => "delete",
+            SessionBranchEnd::Keep => "keep",
+        }
+    }
+}
+
+impl FromStr for SessionBranchEnd {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(SessionBranchEnd::Merge),
+            "delete" => Ok(SessionBranchEnd::Delete),
+            "keep" => Ok(SessionBranchEnd::Keep),
+            other => Err(format!(
+                "unknown session branch mode '{}' (expected merge, delete, or keep)",
+                other
+            )),
+        }
+    }
+}
+
+/// How `strata_log` rewrites event types before appending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTypeCase {
+    /// Keep the type exactly as given.
+    Exact,
+    /// Lowercase the type: `UserAction` → `useraction`.
+    Lower,
+    /// Convert to snake_case: `UserAction` and `user-action` → `user_action`.
+    Snake,
+}
+
+impl EventTypeCase {
+    /// Name used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventTypeCase::Exact => "exact",
+            EventTypeCase::Lower => "lower",
+            EventTypeCase::Snake => "snake",
+        }
+    }
+
+    /// Rewrite an event type according to this mode.
+    pub fn apply(&self, event_type: &str) -> String {
+        match self {
+            EventTypeCase::Exact => event_type.to_string(),
+            EventTypeCase::Lower => event_type.to_lowercase(),
+            EventTypeCase::Snake => snake_case(event_type),
+        }
+    }
+}
+
+impl FromStr for EventTypeCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(EventTypeCase::Exact),
+            "lower" => Ok(EventTypeCase::Lower),
+            "snake" => Ok(EventTypeCase::Snake),
+            other => Err(format!(
+                "unknown event type case '{}' (expected exact, lower, or snake)",
+                other
+            )),
+        }
+    }
+}
+
+/// Convert `UserAction`, `userAction`, `HTTPError`, or `user-action` to
+/// snake_case. Word breaks come from case changes and from any character
+/// that isn't a letter or digit; runs of breaks collapse to one `_`.
+fn snake_case(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::with_capacity(s.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            continue;
+        }
+        if c.is_uppercase() && i > 0 && !out.is_empty() && !out.ends_with('_') {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    while out.ends_with('_') {
+        out.pop();
+    }
+    out
+}
+
+/// A branch forked automatically for the lifetime of a session.
+#[derive(Debug, Clone)]
+pub struct SessionBranch {
+    /// Name of the auto-created branch
+    pub name: String,
+    /// Branch it was forked from, and merged back into on `Merge`
+    pub base: String,
+    /// What happens to the branch when the session ends
+    pub on_end: SessionBranchEnd,
+}
+
+/// Kinds of non-fatal advisory attached to a successful tool result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningCode {
+    /// The write succeeded but its embedding is still queued.
+    DeferredEmbed,
+    /// Search ran keyword-only because semantic search is unavailable.
+    SemanticUnavailable,
+    /// The result was cut short.
+    Truncated,
+    /// The write was buffered for coalescing and isn't a version yet.
+    Coalesced,
+}
+
+impl WarningCode {
+    /// Code reported to clients.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::DeferredEmbed => "deferred_embed",
+            WarningCode::SemanticUnavailable => "semantic_unavailable",
+            WarningCode::Truncated => "truncated",
+            WarningCode::Coalesced => "coalesced",
+        }
+    }
+}
+
+/// A non-fatal advisory about an operation that succeeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// What kind of caveat applies
+    pub code: WarningCode,
+    /// Human-readable explanation
+    pub message: String,
+}
+
+impl Warning {
+    /// JSON form, as listed in a tool result's `warnings`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "code": self.code.as_str(),
+            "message": self.message,
+        })
+    }
+}
+
+/// Branch holding the server's own bookkeeping, such as which branches are
+/// archived and which model built the embedding index, so it stays out of
+/// the forks, merges, and diffs of the user's branches. Branch listings
+/// leave it out, and tools can't switch to it or write to it.
+pub const SYSTEM_BRANCH: &str = "_strata_mcp";
+
+/// Reserved document, on the system branch, listing archived branches.
+const ARCHIVED_BRANCHES_KEY: &str = "__archived_branches";
+
+/// Reserved document, on the system branch, recording which embedding model
+/// built the database's index.
+const EMBED_MODEL_KEY: &str = "__embed_model";
+
+/// Reserved document, on the default branch, recording the branch and space
+/// the last resumable session was using.
+const POSITION_KEY: &str = "__session_position";
+
+/// How often one tool was called in this session, and how those calls ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ToolCallCounts {
+    /// Calls that returned a result
+    pub ok: u64,
+    /// Calls that returned an error
+    pub errors: u64,
+}
+
+impl ToolCallCounts {
+    /// Total calls.
+    pub fn calls(&self) -> u64 {
+        self.ok + self.errors
+    }
+}
+
+/// A whole-document write held back so rapid rewrites can be coalesced.
+struct PendingWrite {
+    branch: String,
+    space: String,
+    key: String,
+    value: Value,
+    first_at: Instant,
+}
+
 /// MCP session state.
 ///
 /// Holds both a `Strata` handle (for branch power ops like fork/diff/merge)
@@ -25,6 +266,46 @@ pub struct McpSession {
     space: String,
     /// Whether a transaction is active
     in_transaction: bool,
+    /// Models loaded into memory by generation and not yet unloaded
+    loaded_models: BTreeSet<String>,
+    /// Branch forked for this session, if session isolation is enabled
+    session_branch: Option<SessionBranch>,
+    /// Return bare `null` for missing keys instead of `{ found: false }`
+    legacy_reads: bool,
+    /// Reject `strata_store` overwrites of existing keys without `path` or `replace`
+    require_explicit_replace: bool,
+    /// Window within which repeated whole-document writes to a key are coalesced
+    coalesce_window: Option<Duration>,
+    /// Buffered write awaiting coalescing or flush
+    pending_write: Option<PendingWrite>,
+    /// Embedding model pinned by the operator, if any
+    embed_model: Option<String>,
+    /// Advisories raised by the tool call in progress
+    warnings: Vec<Warning>,
+    /// How `strata_log` normalizes event types
+    event_type_case: EventTypeCase,
+    /// Calls made through the tool registry this session, by tool name
+    tool_calls: BTreeMap<String, ToolCallCounts>,
+    /// When an embed status read first found items pending, cleared when one
+    /// finds the queue empty
+    embed_pending_since: Option<Instant>,
+    /// Whether auto-embed is on, from the last embed status read; fixed when
+    /// the database is opened
+    auto_embed: Option<bool>,
+    /// A tool asked for the developer tools to be listed directly
+    developer_tools_requested: bool,
+    /// Set when the client cancels the request in progress
+    cancelled: Option<Arc<AtomicBool>>,
+    /// When the tool call in progress runs out of time, and the error it gets
+    deadline: Option<(Instant, McpError)>,
+    /// Branch and space last recorded for the next session to resume, or
+    /// `None` when this session doesn't record them
+    saved_position: Option<(String, String)>,
+    /// Databases attached read-only by alias, with the paths they came from
+    attached: BTreeMap<String, (PathBuf, Strata)>,
+    /// Directory NDJSON export and import files are confined to, or `None`
+    /// when file export and import are disabled
+    export_dir: Option<PathBuf>,
 }
 
 impl McpSession {
@@ -37,16 +318,91 @@ impl McpSession {
             branch: "default".to_string(),
             space: "default".to_string(),
             in_transaction: false,
+            loaded_models: BTreeSet::new(),
+            session_branch: None,
+            legacy_reads: false,
+            require_explicit_replace: false,
+            coalesce_window: None,
+            pending_write: None,
+            embed_model: None,
+            warnings: Vec::new(),
+            event_type_case: EventTypeCase::Exact,
+            tool_calls: BTreeMap::new(),
+            embed_pending_since: None,
+            auto_embed: None,
+            developer_tools_requested: false,
+            cancelled: None,
+            deadline: None,
+            saved_position: None,
+            attached: BTreeMap::new(),
+            export_dir: None,
         }
     }
 
+    /// A second session on the same database and context, for running a
+    /// read-only call alongside this one.
+    ///
+    /// Copies the branch, space, session branch, and read settings, so a
+    /// read-only call such as `strata_status` reports what this session
+    /// would. Ending the session branch stays this session's job. Returns
+    /// `None` while a transaction is open or a write is buffered, since
+    /// another session couldn't see them.
+    pub fn reader(&self) -> Option<McpSession> {
+        if self.in_transaction || self.pending_write.is_some() {
+            return None;
+        }
+        let mut reader = McpSession::new(self.strata.clone());
+        reader.branch = self.branch.clone();
+        reader.space = self.space.clone();
+        reader.session_branch = self.session_branch.clone();
+        reader.legacy_reads = self.legacy_reads;
+        reader.require_explicit_replace = self.require_explicit_replace;
+        reader.embed_model = self.embed_model.clone();
+        reader.event_type_case = self.event_type_case;
+        reader.attached = self.attached.clone();
+        reader.export_dir = self.export_dir.clone();
+        Some(reader)
+    }
+
+    /// Attach `db`, opened from `path`, under `alias`, replacing any database
+    /// attached under it before.
+    pub fn attach(&mut self, alias: &str, path: PathBuf, db: Strata) {
+        self.attached.insert(alias.to_string(), (path, db));
+    }
+
+    /// Detach the database attached under `alias`, returning whether there was one.
+    pub fn detach(&mut self, alias: &str) -> bool {
+        self.attached.remove(alias).is_some()
+    }
+
+    /// Attached aliases and the paths their databases came from, by alias.
+    pub fn attached(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.attached.iter().map(|(alias, (path, _))| (alias.as_str(), path.as_path()))
+    }
+
+    /// A session on the database attached under `alias`, on its default
+    /// branch and space and reading like this one.
+    ///
+    /// It carries this session's cancel flag, deadline, and tool-call counts,
+    /// so a call routed there stops and reports like one run here.
+    pub fn attached_session(&self, alias: &str) -> Option<McpSession> {
+        let (_, db) = self.attached.get(alias)?;
+        let mut session = McpSession::new(db.clone());
+        session.legacy_reads = self.legacy_reads;
+        session.event_type_case = self.event_type_case;
+        session.cancelled = self.cancelled.clone();
+        session.deadline = self.deadline.clone();
+        session.tool_calls = self.tool_calls.clone();
+        Some(session)
+    }
+
     /// Returns `true` if the database was opened in read-only mode.
     pub fn is_read_only(&self) -> bool {
         self.strata().access_mode() == AccessMode::ReadOnly
     }
 
     /// Reject write operations when the database is read-only.
-    fn check_write_access(&self, operation: &str) -> Result<()> {
+    pub(crate) fn check_write_access(&self, operation: &str) -> Result<()> {
         if self.is_read_only() {
             return Err(McpError::Strata {
                 code: "ACCESS_DENIED".to_string(),
@@ -59,6 +415,202 @@ impl McpSession {
         Ok(())
     }
 
+    /// Reject writes to the current branch when it is archived.
+    fn check_not_archived(&mut self, operation: &str) -> Result<()> {
+        let branch = self.branch.clone();
+        self.check_branch_not_archived(&branch, operation)
+    }
+
+    /// Reject writes to `branch` when it is archived or the system branch.
+    fn check_branch_not_archived(&mut self, branch: &str, operation: &str) -> Result<()> {
+        if branch == SYSTEM_BRANCH {
+            return Err(McpError::Strata {
+                code: "ACCESS_DENIED".to_string(),
+                message: format!(
+                    "access denied: {} rejected — branch '{}' is reserved for the server",
+                    operation, branch
+                ),
+            });
+        }
+        if self.is_archived(branch)? {
+            return Err(McpError::Strata {
+                code: "ACCESS_DENIED".to_string(),
+                message: format!(
+                    "access denied: {} rejected — branch '{}' is archived; unarchive it first",
+                    operation, branch
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Names of archived branches, read from the system branch on every
+    /// call so an archive made by another session applies at once.
+    pub fn archived_branches(&mut self) -> Result<BTreeSet<String>> {
+        Ok(match self.read_system_doc(ARCHIVED_BRANCHES_KEY)? {
+            Some(Value::Array(names)) => names
+                .into_iter()
+                .filter_map(|v| match v {
+                    Value::String(name) => Some(name),
+                    _ => None,
+                })
+                .collect(),
+            _ => BTreeSet::new(),
+        })
+    }
+
+    /// A reserved document on [`SYSTEM_BRANCH`], or `None` if it hasn't been
+    /// written yet.
+    fn read_system_doc(&mut self, key: &str) -> Result<Option<Value>> {
+        let cmd = Command::JsonGet {
+            branch: Some(SYSTEM_BRANCH.into()),
+            space: Some("default".to_string()),
+            key: key.to_string(),
+            path: "$".to_string(),
+            as_of: None,
+        };
+        match self.session.execute(cmd) {
+            Ok(Output::MaybeVersioned(Some(vv))) => Ok(Some(vv.value)),
+            Ok(_) => Ok(None),
+            // Nothing has been written before the system branch exists
+            Err(_) if !self.system_branch_exists()? => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write a reserved document on [`SYSTEM_BRANCH`], creating the branch
+    /// on first use. Callers check write access first.
+    fn write_system_doc(&mut self, key: &str, value: Value) -> Result<()> {
+        if !self.system_branch_exists()? {
+            self.session.execute(Command::BranchCreate {
+                branch_id: Some(SYSTEM_BRANCH.to_string()),
+                metadata: None,
+            })?;
+        }
+        self.session.execute(Command::JsonSet {
+            branch: Some(SYSTEM_BRANCH.into()),
+            space: Some("default".to_string()),
+            key: key.to_string(),
+            path: "$".to_string(),
+            value,
+        })?;
+        Ok(())
+    }
+
+    fn system_branch_exists(&mut self) -> Result<bool> {
+        let output = self.session.execute(Command::BranchExists {
+            branch: SYSTEM_BRANCH.into(),
+        })?;
+        Ok(matches!(output, Output::Bool(true)))
+    }
+
+    /// Whether `branch` has been archived (made read-only).
+    pub fn is_archived(&mut self, branch: &str) -> Result<bool> {
+        Ok(self.archived_branches()?.contains(branch))
+    }
+
+    /// Archive or unarchive a branch. Archived branches reject every write
+    /// until unarchived but can still be read, diffed, and forked.
+    ///
+    /// The list is kept in a reserved document on [`SYSTEM_BRANCH`] so it
+    /// survives restarts and is shared by every session on the database.
+    pub fn set_archived(&mut self, branch: &str, archived: bool) -> Result<()> {
+        self.check_write_access("BranchArchive")?;
+        if archived {
+            self.flush_pending()?;
+        }
+        let mut names = self.archived_branches()?;
+        let changed = if archived {
+            names.insert(branch.to_string())
+        } else {
+            names.remove(branch)
+        };
+        if !changed {
+            return Ok(());
+        }
+        let value = Value::Array(names.into_iter().map(Value::String).collect());
+        self.write_system_doc(ARCHIVED_BRANCHES_KEY, value)
+    }
+
+    /// Switch the database's durability mode (`always`, `standard`, or `cache`).
+    ///
+    /// Config commands aren't document writes, so the read-only check is explicit.
+    pub fn set_durability(&mut self, mode: &str) -> Result<()> {
+        self.check_write_access("ConfigSetDurability")?;
+        self.execute(Command::ConfigSetDurability {
+            mode: mode.to_string(),
+        })?;
+        Ok(())
+    }
+
+    /// Attach a non-fatal advisory to the current tool call's result.
+    pub fn warn(&mut self, code: WarningCode, message: impl Into<String>) {
+        self.warnings.push(Warning {
+            code,
+            message: message.into(),
+        });
+    }
+
+    /// Advisories raised since the last call, clearing them.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Ask the server to list every developer tool directly from now on.
+    pub fn request_developer_tools(&mut self) {
+        self.developer_tools_requested = true;
+    }
+
+    /// Whether a tool asked for the developer tools since the last check,
+    /// clearing the request.
+    pub fn take_developer_tools_request(&mut self) -> bool {
+        std::mem::take(&mut self.developer_tools_requested)
+    }
+
+    /// Count a finished tool call, successful or not.
+    pub fn record_tool_call(&mut self, name: &str, ok: bool) {
+        let counts = self.tool_calls.entry(name.to_string()).or_default();
+        if ok {
+            counts.ok += 1;
+        } else {
+            counts.errors += 1;
+        }
+    }
+
+    /// Add calls counted by another session, such as a [`reader`](Self::reader).
+    pub fn merge_tool_calls(&mut self, calls: &BTreeMap<String, ToolCallCounts>) {
+        for (name, counts) in calls {
+            let total = self.tool_calls.entry(name.clone()).or_default();
+            total.ok += counts.ok;
+            total.errors += counts.errors;
+        }
+    }
+
+    /// Tool calls made so far in this session, by tool name.
+    pub fn tool_calls(&self) -> &BTreeMap<String, ToolCallCounts> {
+        &self.tool_calls
+    }
+
+    /// How long embeddings have been pending without the queue emptying, as
+    /// seen by this session's embed status reads, or `None` when the last
+    /// read found nothing pending. The engine doesn't say when items were
+    /// queued, so this is measured from the first read that saw the queue
+    /// non-empty.
+    pub fn embed_pending_for(&self) -> Option<Duration> {
+        self.embed_pending_since.map(|since| since.elapsed())
+    }
+
+    /// Whether the database embeds writes automatically. Read from the engine
+    /// once and remembered, since it is set when the database is opened.
+    pub fn auto_embed(&mut self) -> bool {
+        if let Some(auto_embed) = self.auto_embed {
+            return auto_embed;
+        }
+        // execute() records the answer
+        let _ = self.execute(Command::EmbedStatus);
+        *self.auto_embed.get_or_insert(false)
+    }
+
     /// Get the current branch name.
     pub fn branch(&self) -> &str {
         &self.branch
@@ -69,19 +621,258 @@ impl McpSession {
         &self.space
     }
 
+    /// Embedding model pinned for this database, if any.
+    pub fn embed_model(&self) -> Option<&str> {
+        self.embed_model.as_deref()
+    }
+
+    /// Pin the embedding model the index is expected to be built with.
+    pub fn set_embed_model(&mut self, model: Option<String>) {
+        self.embed_model = model;
+    }
+
+    /// Embedding model recorded as having built the database's index. One
+    /// record on [`SYSTEM_BRANCH`] covers every branch and space.
+    pub fn indexed_embed_model(&mut self) -> Result<Option<String>> {
+        Ok(match self.read_system_doc(EMBED_MODEL_KEY)? {
+            Some(Value::Object(mut record)) => match record.remove("model") {
+                Some(Value::String(model)) => Some(model),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Record `model` as the one that built the database's index.
+    pub fn record_embed_model(&mut self, model: &str) -> Result<()> {
+        self.check_write_access("JsonSet")?;
+        let mut record = HashMap::new();
+        record.insert("model".to_string(), Value::String(model.to_string()));
+        self.write_system_doc(EMBED_MODEL_KEY, Value::Object(record))
+    }
+
     /// Whether a transaction is currently active.
     ///
-    /// Exposed for library consumers; the MCP server itself tracks transactions
-    /// via the `execute()` method's output matching.
-    #[allow(dead_code)]
+    /// Tracked via the `execute()` method's output matching.
     pub fn in_transaction(&self) -> bool {
         self.in_transaction
     }
 
+    /// Models currently resident in memory.
+    ///
+    /// Generation loads a model on first use and keeps it resident until
+    /// `GenerateUnload`; this tracks the models loaded through this session.
+    pub fn loaded_models(&self) -> &BTreeSet<String> {
+        &self.loaded_models
+    }
+
+    /// Whether agent reads use the pre-`found` response shapes.
+    ///
+    /// When enabled, `strata_recall` returns bare `null` for a missing key and
+    /// `strata_history` returns a bare array (or `null`), as before the
+    /// `{ found, ... }` convention was introduced.
+    pub fn legacy_reads(&self) -> bool {
+        self.legacy_reads
+    }
+
+    /// Enable or disable legacy read response shapes.
+    pub fn set_legacy_reads(&mut self, enabled: bool) {
+        self.legacy_reads = enabled;
+    }
+
+    /// Whether replacing an existing document requires an explicit intent.
+    ///
+    /// When enabled, `strata_store` without `path` rejects a write to a key
+    /// that already exists unless `replace: true` is passed.
+    pub fn require_explicit_replace(&self) -> bool {
+        self.require_explicit_replace
+    }
+
+    /// Enable or disable the explicit-replace requirement.
+    pub fn set_require_explicit_replace(&mut self, enabled: bool) {
+        self.require_explicit_replace = enabled;
+    }
+
+    /// Directory export and import files are confined to, if any.
+    pub fn export_dir(&self) -> Option<&Path> {
+        self.export_dir.as_deref()
+    }
+
+    /// Confine export and import files to `dir`; `None` (the default)
+    /// disables file export and import.
+    pub fn set_export_dir(&mut self, dir: Option<PathBuf>) {
+        self.export_dir = dir;
+    }
+
+    /// How `strata_log` normalizes event types.
+    pub fn event_type_case(&self) -> EventTypeCase {
+        self.event_type_case
+    }
+
+    /// Set how `strata_log` normalizes event types (default `Exact`).
+    pub fn set_event_type_case(&mut self, case: EventTypeCase) {
+        self.event_type_case = case;
+    }
+
+    /// Coalesce repeated whole-document writes to the same key within `window`.
+    ///
+    /// With a window set, `store_document` buffers the write instead of
+    /// executing it. Further writes to the same key within the window replace
+    /// the buffered value, so only the final value becomes a version; the
+    /// intermediate values never reach history. Any other command (including
+    /// reads) flushes the buffer first, so reads always see the latest value.
+    /// Pass `None` to disable; this flushes any buffered write.
+    pub fn set_coalesce_window(&mut self, window: Option<Duration>) -> Result<()> {
+        self.flush_pending()?;
+        self.coalesce_window = window;
+        Ok(())
+    }
+
+    /// Write a whole document, coalescing with a buffered write when enabled.
+    ///
+    /// Returns the new version, or `None` if the write was buffered.
+    pub fn store_document(&mut self, key: String, value: Value) -> Result<Option<u64>> {
+        let Some(window) = self.coalesce_window else {
+            let output = self.execute(Command::JsonSet {
+                branch: self.branch_id(),
+                space: self.space_id(),
+                key,
+                path: "$".to_string(),
+                value,
+            })?;
+            return Ok(match output {
+                Output::Version(v) => Some(v),
+                _ => None,
+            });
+        };
+
+        self.check_write_access("JsonSet")?;
+        self.check_not_archived("JsonSet")?;
+        if let Some(pending) = &mut self.pending_write {
+            if pending.key == key
+                && pending.branch == self.branch
+                && pending.space == self.space
+                && pending.first_at.elapsed() < window
+            {
+                pending.value = value;
+                return Ok(None);
+            }
+        }
+
+        self.flush_pending()?;
+        self.pending_write = Some(PendingWrite {
+            branch: self.branch.clone(),
+            space: self.space.clone(),
+            key,
+            value,
+            first_at: Instant::now(),
+        });
+        Ok(None)
+    }
+
+    /// Execute the buffered coalesced write, if any. Returns its version.
+    ///
+    /// A write to a branch archived since it was buffered is dropped, and
+    /// the rejection returned. Any other failure keeps the write buffered,
+    /// so the next flush retries it.
+    pub fn flush_pending(&mut self) -> Result<Option<u64>> {
+        let Some(pending) = self.pending_write.take() else {
+            return Ok(None);
+        };
+        self.check_branch_not_archived(&pending.branch, "JsonSet")?;
+        let cmd = Command::JsonSet {
+            branch: Some(pending.branch.clone().into()),
+            space: Some(pending.space.clone()),
+            key: pending.key.clone(),
+            path: "$".to_string(),
+            value: pending.value.clone(),
+        };
+        let output = match self.session.execute(cmd) {
+            Ok(output) => output,
+            Err(e) => {
+                self.pending_write = Some(pending);
+                return Err(e.into());
+            }
+        };
+        Ok(match output {
+            Output::Version(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// The branch auto-created for this session, if any.
+    pub fn session_branch(&self) -> Option<&SessionBranch> {
+        self.session_branch.as_ref()
+    }
+
+    /// Fork a fresh branch from `base` and switch to it for the rest of the session.
+    ///
+    /// The branch is named `session-<pid>-<timestamp>` so concurrent servers
+    /// sharing a database never collide. Call `end_session_branch` on shutdown
+    /// to apply `on_end`.
+    pub fn start_session_branch(
+        &mut self,
+        base: &str,
+        on_end: SessionBranchEnd,
+    ) -> Result<&SessionBranch> {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_micros())
+            .unwrap_or(0);
+        let name = format!("session-{}-{}", std::process::id(), stamp);
+        self.start_named_session_branch(base, &name, on_end)
+    }
+
+    /// Like [`start_session_branch`](Self::start_session_branch), naming the
+    /// branch `name`.
+    pub fn start_named_session_branch(
+        &mut self,
+        base: &str,
+        name: &str,
+        on_end: SessionBranchEnd,
+    ) -> Result<&SessionBranch> {
+        validate_branch_name(name)?;
+        self.switch_branch(base)?;
+        self.fork_branch(name)?;
+        self.switch_branch(name)?;
+        Ok(self.session_branch.insert(SessionBranch {
+            name: name.to_string(),
+            base: base.to_string(),
+            on_end,
+        }))
+    }
+
+    /// Apply the end-of-session policy to the session branch, if one was started.
+    ///
+    /// Switches back to the base branch unless the policy is `Keep`. Returns
+    /// the branch that was handled.
+    pub fn end_session_branch(&mut self) -> Result<Option<SessionBranch>> {
+        let Some(session_branch) = self.session_branch.take() else {
+            return Ok(None);
+        };
+        if session_branch.on_end == SessionBranchEnd::Keep {
+            return Ok(Some(session_branch));
+        }
+
+        self.switch_branch(&session_branch.base)?;
+        if session_branch.on_end == SessionBranchEnd::Merge {
+            self.merge_branch(&session_branch.name, MergeStrategy::LastWriterWins)?;
+        }
+        self.execute(Command::BranchDelete {
+            branch: session_branch.name.clone().into(),
+        })?;
+        Ok(Some(session_branch))
+    }
+
     /// Switch to a different branch.
     ///
-    /// Verifies the branch exists before switching.
+    /// Verifies the branch exists before switching. Naming rules only apply
+    /// to new branches, so one made before they existed can still be used.
     pub fn switch_branch(&mut self, name: &str) -> Result<()> {
+        if name == SYSTEM_BRANCH {
+            return Err(McpError::BranchNotFound(name.to_string()));
+        }
+
         // Check if branch exists
         let exists = match self.session.execute(Command::BranchExists {
             branch: name.into(),
@@ -107,20 +898,172 @@ impl McpSession {
         self.space = name.to_string();
     }
 
+    /// Switch to the branch and space the last resumable session on this
+    /// database was using, and record this session's from now on.
+    ///
+    /// Returns the branch and space resumed, or `None` when nothing was
+    /// recorded or the branch has since been deleted. Call
+    /// [`save_position`](Self::save_position) after each request to keep the
+    /// record current.
+    pub fn resume_position(&mut self) -> Result<Option<(String, String)>> {
+        let output = self.session.execute(Command::JsonGet {
+            branch: Some("default".into()),
+            space: Some("default".to_string()),
+            key: POSITION_KEY.to_string(),
+            path: "$".to_string(),
+            as_of: None,
+        })?;
+        self.saved_position = Some((self.branch.clone(), self.space.clone()));
+        let field = |record: &HashMap<String, Value>, name: &str| match record.get(name) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let (branch, space) = match output {
+            Output::MaybeVersioned(Some(vv)) => match vv.value {
+                Value::Object(record) => (field(&record, "branch"), field(&record, "space")),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let (Some(branch), Some(space)) = (branch, space) else {
+            return Ok(None);
+        };
+        match self.switch_branch(&branch) {
+            Ok(()) => {}
+            Err(McpError::BranchNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        self.switch_space(&space);
+        self.saved_position = Some((branch.clone(), space.clone()));
+        Ok(Some((branch, space)))
+    }
+
+    /// Record the current branch and space for the next session to resume,
+    /// if this session records them and they changed.
+    ///
+    /// Inside a transaction the record waits for a later call, since a
+    /// rollback would discard it; read-only databases are never written.
+    pub fn save_position(&mut self) -> Result<()> {
+        let position = (self.branch.clone(), self.space.clone());
+        let changed = self.saved_position.as_ref().is_some_and(|saved| *saved != position);
+        if !changed || self.in_transaction || self.is_read_only() {
+            return Ok(());
+        }
+        let mut record = HashMap::new();
+        record.insert("branch".to_string(), Value::String(position.0.clone()));
+        record.insert("space".to_string(), Value::String(position.1.clone()));
+        self.session.execute(Command::JsonSet {
+            branch: Some("default".into()),
+            space: Some("default".to_string()),
+            key: POSITION_KEY.to_string(),
+            path: "$".to_string(),
+            value: Value::Object(record),
+        })?;
+        self.saved_position = Some(position);
+        Ok(())
+    }
+
+    /// Watch `flag` while running the current request, or stop watching.
+    ///
+    /// Once the flag is set, [`execute`](Self::execute) refuses further
+    /// commands with [`McpError::Cancelled`], so a multi-step tool stops at
+    /// its next command. A command already running inside the engine runs
+    /// to completion.
+    pub fn set_cancel_flag(&mut self, flag: Option<Arc<AtomicBool>>) {
+        self.cancelled = flag;
+    }
+
+    /// Fail commands with `error` from `at` on, or stop doing so.
+    ///
+    /// Like a cancel flag, the deadline is checked before each command, so a
+    /// multi-step tool stops at its next command once time is up, and a
+    /// rollback still runs.
+    pub fn set_deadline(&mut self, deadline: Option<(Instant, McpError)>) {
+        self.deadline = deadline;
+    }
+
+    /// The branch a write command changes: the one it names, or the current
+    /// branch when it names none. `None` for a branch being created, which
+    /// touches no existing branch.
+    fn write_target(&self, cmd: &Command) -> Option<String> {
+        let branch = match cmd {
+            Command::BranchCreate { .. } => return None,
+            Command::BranchDelete { branch } => return Some(branch.as_str().to_string()),
+            Command::JsonSet { branch, .. }
+            | Command::JsonDelete { branch, .. }
+            | Command::KvPut { branch, .. }
+            | Command::KvDelete { branch, .. }
+            | Command::EventAppend { branch, .. }
+            | Command::StateSet { branch, .. }
+            | Command::StateCas { branch, .. }
+            | Command::StateDelete { branch, .. }
+            | Command::StateInit { branch, .. }
+            | Command::SpaceCreate { branch, .. }
+            | Command::SpaceDelete { branch, .. }
+            | Command::VectorUpsert { branch, .. }
+            | Command::VectorBatchUpsert { branch, .. }
+            | Command::VectorDelete { branch, .. }
+            | Command::VectorCreateCollection { branch, .. }
+            | Command::VectorDeleteCollection { branch, .. }
+            | Command::RetentionApply { branch, .. } => branch.as_ref(),
+            _ => None,
+        };
+        Some(branch.map_or_else(|| self.branch.clone(), |b| b.as_str().to_string()))
+    }
+
     /// Execute a command via the session.
     ///
-    /// Rejects write commands when the database is read-only.
-    /// Updates transaction state tracking based on output.
+    /// Rejects write commands when the database is read-only, and every
+    /// command but a rollback once the request has been cancelled.
+    /// Updates transaction and loaded-model state tracking based on output.
     pub fn execute(&mut self, cmd: Command) -> Result<Output> {
+        let cancelled = self.cancelled.as_ref().is_some_and(|f| f.load(Ordering::Relaxed));
+        if cancelled && !matches!(cmd, Command::TxnRollback) {
+            return Err(McpError::Cancelled);
+        }
+        if let Some((at, error)) = &self.deadline {
+            if Instant::now() >= *at && !matches!(cmd, Command::TxnRollback) {
+                return Err(error.clone());
+            }
+        }
         if cmd.is_write() {
             self.check_write_access(cmd.name())?;
+            if let Some(branch) = self.write_target(&cmd) {
+                self.check_branch_not_archived(&branch, cmd.name())?;
+            }
         }
+        self.flush_pending()?;
+        let model_change = match &cmd {
+            Command::Generate { model, .. } => Some((model.clone(), true)),
+            Command::GenerateUnload { model } => Some((model.clone(), false)),
+            _ => None,
+        };
         let output = self.session.execute(cmd)?;
 
+        // Track resident models
+        match model_change {
+            Some((model, true)) => {
+                self.loaded_models.insert(model);
+            }
+            Some((model, false)) => {
+                self.loaded_models.remove(&model);
+            }
+            None => {}
+        }
+
         // Track transaction state changes
         match &output {
             Output::TxnBegun => self.in_transaction = true,
             Output::TxnCommitted { .. } | Output::TxnAborted => self.in_transaction = false,
+            // Track auto-embed, and how long its queue has been non-empty
+            Output::EmbedStatus(embed) => {
+                self.auto_embed = Some(embed.auto_embed);
+                if embed.pending == 0 {
+                    self.embed_pending_since = None;
+                } else {
+                    self.embed_pending_since.get_or_insert_with(Instant::now);
+                }
+            }
             _ => {}
         }
 
@@ -128,8 +1071,10 @@ impl McpSession {
     }
 
     /// Fork the current branch to a new branch.
-    pub fn fork_branch(&self, destination: &str) -> Result<ForkInfo> {
+    pub fn fork_branch(&mut self, destination: &str) -> Result<ForkInfo> {
+        validate_branch_name(destination)?;
         self.check_write_access("BranchFork")?;
+        self.flush_pending()?;
         self.strata
             .branches()
             .fork(&self.branch, destination)
@@ -137,7 +1082,8 @@ impl McpSession {
     }
 
     /// Diff two branches.
-    pub fn diff_branches(&self, branch_a: &str, branch_b: &str) -> Result<BranchDiffResult> {
+    pub fn diff_branches(&mut self, branch_a: &str, branch_b: &str) -> Result<BranchDiffResult> {
+        self.flush_pending()?;
         self.strata
             .branches()
             .diff(branch_a, branch_b)
@@ -145,8 +1091,10 @@ impl McpSession {
     }
 
     /// Merge a source branch into the current branch.
-    pub fn merge_branch(&self, source: &str, strategy: MergeStrategy) -> Result<MergeInfo> {
+    pub fn merge_branch(&mut self, source: &str, strategy: MergeStrategy) -> Result<MergeInfo> {
         self.check_write_access("BranchMerge")?;
+        self.check_not_archived("BranchMerge")?;
+        self.flush_pending()?;
         self.strata
             .branches()
             .merge(source, &self.branch, strategy)
@@ -154,11 +1102,18 @@ impl McpSession {
     }
 
     /// Get the current branch ID for use in commands.
-    pub fn branch_id(&self) -> Option<stratadb::BranchId> {
+    ///
+    /// The result is a snapshot: a command built with it keeps targeting that
+    /// branch even if the session switches before the command executes. Tools
+    /// must capture branch and space here when building a command, never
+    /// leave them `None` for the engine to resolve at execution time.
+    pub fn branch_id(&self) -> Option<BranchId> {
         Some(self.branch().to_string().into())
     }
 
     /// Get the current space for use in commands.
+    ///
+    /// A snapshot, like [`branch_id`](Self::branch_id).
     pub fn space_id(&self) -> Option<String> {
         Some(self.space().to_string())
     }
@@ -0,0 +1,61 @@
+//! Graceful shutdown on SIGINT and SIGTERM.
+//!
+//! The first signal stops a stdio server from reading requests: those
+//! already read finish, the session winds down, the WAL is synced, and the
+//! client gets a final `notifications/message` naming the signal. A second
+//! signal exits at once, for when draining takes too long.
+
+/// Exit status when a second signal cuts draining short.
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Wait for SIGINT, or SIGTERM on Unix, and return the signal's name.
+///
+/// Never resolves if no handler can be installed.
+pub(crate) async fn signal() -> &'static str {
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => terminate.recv().await,
+            Err(_) => None,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<Option<()>>();
+
+    tokio::select! {
+        Ok(()) = tokio::signal::ctrl_c() => "SIGINT",
+        Some(()) = terminate => "SIGTERM",
+        else => std::future::pending().await,
+    }
+}
+
+/// Exit at once on the next signal.
+pub(crate) async fn exit_on_signal() {
+    let signal = signal().await;
+    tracing::warn!("{} while shutting down; exiting now", signal);
+    std::process::exit(FORCED_EXIT_CODE);
+}
+
+/// Watch for signals on a thread of its own, for the blocking stdio loop.
+///
+/// `on_signal` gets the first signal's name; the next one exits at once.
+pub(crate) fn watch<F>(on_signal: F)
+where
+    F: FnOnce(&'static str) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                tracing::warn!("Failed to watch for shutdown signals: {}", e);
+                return;
+            }
+        };
+        on_signal(runtime.block_on(signal()));
+        runtime.block_on(exit_on_signal());
+    });
+}
@@ -1,6 +1,6 @@
 //! Agent-friendly tools.
 //!
-//! Provides 8 high-level, intent-driven tools designed for AI agent consumption.
+//! Provides 10 high-level, intent-driven tools designed for AI agent consumption.
 //! These collapse Strata's granular developer operations into a simple cognitive interface:
 //!
 //! - `strata_store`   — Store data (JSON documents with optional path updates)
@@ -11,65 +11,218 @@
 //! - `strata_branch`  — Branching for safe experimentation
 //! - `strata_history` — Time-travel and version history
 //! - `strata_status`  — Database introspection
+//! - `strata_orient`  — Prose summary of where the agent stands
+//! - `strata_run_query` — Save and rerun named searches
 //!
 //! All data operations are backed by the JSON document store, which gives agents
 //! structured document access with optional JSONPath targeting.
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::{BranchId, Command, MergeStrategy, Output, SearchQuery};
+use stratadb::{BranchId, Command, MergeStrategy, Output, SearchQuery, Value};
 
 use crate::convert::{
-    get_optional_string, get_optional_u64, get_string_arg, get_value_arg, output_to_json,
+    apply_value_type, get_optional_bool, get_optional_string, get_optional_u64, get_string_arg,
+    get_typed_value_arg, get_value_arg, json_to_value, output_to_json, value_to_json,
+    value_type_tree, versioned_to_json,
 };
 use crate::error::{McpError, Result};
+use crate::jsonpath;
 use crate::schema;
-use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::session::{validate_branch_name, McpSession, WarningCode};
+use crate::tools::embed::SEARCH_INDEX_COLLECTION;
+use crate::tools::{branch, meta, scan, ToolAnnotations, ToolDef};
+use crate::ulid;
+
+/// Tools that take a `space` for one call.
+const SPACE_TOOLS: &[&str] = &[
+    "strata_store",
+    "strata_recall",
+    "strata_search",
+    "strata_forget",
+    "strata_log",
+    "strata_history",
+];
+
+/// Description of the `space` argument the [`SPACE_TOOLS`] take.
+const SPACE_DESCRIPTION: &str =
+    "Space to use for this call only, leaving the session's space as is";
 
 /// Get all agent tool definitions.
 pub fn tools() -> Vec<ToolDef> {
-    vec![
+    let mut tools = vec![
         // ── Core Data Tools ──────────────────────────────────────────────
         ToolDef::new(
             "strata_store",
             "Store a JSON document by key. Use this whenever you need to persist structured data — \
              configuration, user profiles, conversation state, analysis results, or any data you'll \
-             need later. The value can be any JSON type (string, number, boolean, object, array). Use \
+             need later. The value can be any JSON type (string, number, boolean, object, array); \
+             integers too large for a signed 64-bit int (e.g. big IDs) are stored as decimal \
+             strings so no precision is lost. To control how numbers are stored, pass \
+             'value_type': 'float' stores every number in the value as Float (so 3 stays 3.0), \
+             'int' requires every number to be whole and stores it as Int, 'string' stores every \
+             number as its decimal text, and 'auto' (the default) infers Int or Float; read the \
+             stored types back with strata_recall's with_types. Use \
              the optional 'path' parameter with JSONPath syntax (e.g. '$.settings.theme') to update a \
              specific nested field without overwriting the whole document — omit 'path' to store the \
-             entire value. Every write is versioned — nothing is ever lost. When auto-embed is enabled, \
-             text content is automatically indexed for semantic search via strata_search. \
-             Returns { key, version, stored: true }.",
+             entire value. Missing intermediate objects and arrays along 'path' are created \
+             automatically; pass 'create_parents: false' to instead get an error naming the first \
+             missing segment. Every write is versioned — nothing is ever lost. When auto-embed is \
+             enabled, text content is automatically indexed for semantic search via strata_search. \
+             If the server requires explicit replaces, storing to an existing key without \
+             'path' fails unless you pass replace: true. \
+             To append a document without inventing a key, omit 'key' and pass auto_key: true; \
+             the server generates a ULID (26 characters, e.g. 01J9Z3K6QX4V8T2M5N7B1C0D9E) that \
+             sorts by creation time, so listing keys returns them in the order stored. \
+             To leave a 'where I left off' note for a later session, omit 'key' and pass \
+             snapshot: true with the note as 'value'; the server saves it together with the \
+             current strata_status fields, the strata_orient summary, and the 10 most recent \
+             writes under the reserved key '__snapshot' on the current branch, replacing the \
+             previous snapshot (older ones stay in history). Read it back with \
+             strata_recall key '__snapshot'. \
+             Returns { key, version, stored: true }. If the server coalesces rapid rewrites, a \
+             held-back write returns { key, version: null, stored: false, buffered: true }: \
+             later reads see it, but it isn't durable until the server's next operation \
+             writes it.",
             schema!(object {
-                required: { "key": string, "value": any },
-                optional: { "path": string }
+                required: { "value": any },
+                optional: {
+                    "key": string,
+                    "auto_key": boolean,
+                    "snapshot": boolean,
+                    "path": string,
+                    "create_parents": boolean,
+                    "replace": boolean,
+                    "value_type": string,
+                    "space": string
+                }
             }),
-        ),
+        )
+        .with_example(
+            serde_json::json!({"key": "user:alice", "value": {"name": "Alice", "role": "admin"}}),
+            serde_json::json!({"key": "user:alice", "version": 1, "stored": true}),
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "key": {"type": "string"},
+                "version": {"type": ["integer", "null"]},
+                "stored": {"type": "boolean"},
+                "buffered": {"type": "boolean"}
+            },
+            "required": ["key", "stored"]
+        }))
+        // Overwrites the previous value, and every call adds a version
+        .with_annotations(ToolAnnotations::write(true, false)),
         ToolDef::new(
             "strata_recall",
             "Retrieve a document by key. Returns the stored value with version metadata, or null if \
              the key doesn't exist. Use 'path' with JSONPath syntax (e.g. '$.settings.theme') to read \
              a specific nested field — omit to get the entire document. Pass 'as_of' (microsecond \
              timestamp) to read what this key contained at any past point in time — every write is \
-             versioned and nothing is lost. Returns { value, version, timestamp } or null.",
+             versioned and nothing is lost. Returns { found, value, version, timestamp }; when the \
+             key (or path) doesn't exist you get { found: false, value: null }, so a stored null \
+             is always distinguishable from absence (found: true, value: null). Set \
+             'with_types: true' to also get 'types', mirroring the value with the stored type of \
+             each element (Null, Bool, Int, Float, String, Bytes) — useful to check whether a \
+             number was stored as Int or Float. For very large values, pass 'offset' and/or \
+             'length' to page through them: strings are sliced by character, arrays by element, \
+             and any other value by character of its serialized JSON text (returned as a \
+             string). A chunked read returns the slice as 'value' plus { chunked, offset, total, \
+             has_more, next_offset } — pass 'next_offset' as 'offset' to get the next chunk. \
+             To peek at a large array or object, pass 'limit' (optionally with 'path', e.g. \
+             path '$.items', limit 10): only the first 'limit' elements or fields are returned, \
+             and when anything was cut the result also has truncated: true and 'total'. 'limit' \
+             cannot be combined with 'offset'/'length' and has no effect on scalars. To read \
+             several fields of one document at once, pass 'paths' (e.g. ['$.db.host', \
+             '$.db.port']) instead of 'path': returns { found, values, missing, version, \
+             timestamp } where 'values' maps each path to its value, and paths that don't exist \
+             map to null and are also listed in 'missing'; a missing key returns { found: \
+             false, values: null }. 'paths' works with 'as_of' but not with 'path', 'offset', \
+             'length', or 'limit'.",
             schema!(object {
                 required: { "key": string },
-                optional: { "path": string, "as_of": integer }
+                optional: {
+                    "path": string,
+                    "paths": array_string,
+                    "as_of": integer,
+                    "with_types": boolean,
+                    "offset": integer,
+                    "length": integer,
+                    "limit": integer,
+                    "space": string
+                }
+            }),
+        )
+        .with_example(
+            serde_json::json!({"key": "user:alice", "path": "$.role"}),
+            serde_json::json!({
+                "value": "admin",
+                "version": 1,
+                "timestamp": 1760000000000000_u64,
+                "found": true
             }),
-        ),
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "found": {"type": "boolean"},
+                "value": {},
+                "version": {"type": "integer"},
+                "timestamp": {"type": "integer"},
+                "types": {},
+                "values": {"type": ["object", "null"]},
+                "missing": {"type": "array", "items": {"type": "string"}},
+                "chunked": {"type": "string"},
+                "offset": {"type": "integer"},
+                "total": {"type": "integer"},
+                "has_more": {"type": "boolean"},
+                "next_offset": {"type": ["integer", "null"]},
+                "truncated": {"type": "boolean"}
+            }
+        }))
+        .with_annotations(ToolAnnotations::read_only()),
         ToolDef::new(
             "strata_search",
             "Find relevant data across everything stored using natural language. Use this when you \
              don't know the exact key — describe what you're looking for and get ranked results. \
              Searches across all documents and events simultaneously. Uses fast keyword matching \
              (BM25) by default; adds semantic similarity when auto-embed is enabled. Returns an \
-             array of { key, score, snippet } ranked by relevance. Use 'k' to control how many \
-             results to return (default 10).",
+             array of { key, score, snippet } ranked by relevance; results with equal scores are \
+             ordered by key, so repeated queries return a stable order. Use 'k' to control how \
+             many results to return (default 10). Pass 'group_by_prefix' with a delimiter (e.g. \
+             ':') to instead get an object mapping each key prefix (the part before the first \
+             delimiter, or \"\" for keys without one) to its ranked results. Pass 'metadata' \
+             (an object) to keep only keys whose metadata (tags and other fields kept alongside \
+             a value, not in it) has all of the given fields with equal values. To favor \
+             current information, pass 'max_age' (seconds) to drop documents last written \
+             longer ago than that, and/or 'recency_boost' (0 to 1) to blend recency into the \
+             ranking: each score becomes \
+             (1 - boost) × relevance + boost × recency, where relevance is the engine score \
+             divided by the top candidate's and recency runs from 0 for the oldest candidate to \
+             1 for the newest, so 0 keeps pure relevance order and 1 sorts newest first. Pass \
+             explain: true to get { results, explain } instead, where 'explain' reports which \
+             backend answered ('backend' is 'keyword' or 'hybrid', with the contributing \
+             indexes in 'backends'), keys in the database, whether semantic search ran, \
+             pending embeddings, how many candidates were filtered out, and — when nothing was \
+             returned — a 'reason' suggesting what to try next.",
             schema!(object {
                 required: { "query": string },
-                optional: { "k": integer }
+                optional: {
+                    "k": integer,
+                    "group_by_prefix": string,
+                    "metadata": any,
+                    "max_age": integer,
+                    "recency_boost": number,
+                    "explain": boolean,
+                    "space": string
+                }
             }),
-        ),
+        )
+        .with_example(
+            serde_json::json!({"query": "admin users", "k": 3}),
+            serde_json::json!([{"key": "user:alice", "score": 0.92, "snippet": "Alice admin"}]),
+        )
+        .with_annotations(ToolAnnotations::read_only()),
         ToolDef::new(
             "strata_forget",
             "Delete a document by key. Returns { deleted: true } if the key existed, { deleted: false } \
@@ -77,21 +230,49 @@ pub fn tools() -> Vec<ToolDef> {
              strata_history, and time-travel queries via strata_recall with 'as_of' will still return \
              the value as it existed before deletion.",
             schema!(object {
-                required: { "key": string }
+                required: { "key": string },
+                optional: { "space": string }
             }),
-        ),
+        )
+        .with_example(
+            serde_json::json!({"key": "user:alice"}),
+            serde_json::json!({"deleted": true}),
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {"deleted": {"type": "boolean"}},
+            "required": ["deleted"]
+        }))
+        .with_annotations(ToolAnnotations::write(true, true)),
         ToolDef::new(
             "strata_log",
             "Append an immutable event to the log. Use this for recording actions, decisions, \
              observations, errors, or any sequential data that should never be modified after the fact. \
              Unlike strata_store, events cannot be overwritten or deleted — they form a permanent, \
              ordered, timestamped record grouped by event type. The 'event' parameter is the type tag \
-             (e.g. \"user_action\", \"error\", \"decision\") and 'data' is any JSON payload. Returns \
-             { sequence, logged: true }.",
+             (e.g. \"user_action\", \"error\", \"decision\") and 'data' is any JSON payload. The \
+             server may be configured to normalize the type's casing (e.g. \"UserAction\" to \
+             \"user_action\"); 'event' in the result is the type actually logged. Returns \
+             { sequence, logged: true, event }.",
             schema!(object {
-                required: { "event": string, "data": any }
+                required: { "event": string, "data": any },
+                optional: { "space": string }
             }),
-        ),
+        )
+        .with_example(
+            serde_json::json!({"event": "decision", "data": {"chose": "plan-b"}}),
+            serde_json::json!({"sequence": 12, "logged": true, "event": "decision"}),
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "sequence": {"type": "integer"},
+                "logged": {"type": "boolean"},
+                "event": {"type": "string"}
+            },
+            "required": ["sequence", "logged", "event"]
+        }))
+        .with_annotations(ToolAnnotations::write(false, false)),
         // ── Power Tools ──────────────────────────────────────────────────
         ToolDef::new(
             "strata_branch",
@@ -100,20 +281,30 @@ pub fn tools() -> Vec<ToolDef> {
              risky experiments, 'merge' to apply results back, or 'diff' to compare. Actions: 'create' \
              (empty branch), 'switch' (change active branch), 'list' (all branches), 'fork' (copy \
              current branch with all data), 'merge' (apply source branch into current), 'diff' (compare \
-             current vs another), 'delete' (remove branch). Recommended workflow: fork → experiment → \
-             merge if good, delete if bad. Params: 'name' for create/switch/fork/delete, 'source' for \
-             merge, 'compare' for diff.",
+             current vs another), 'delete' (remove branch), 'archive' (freeze a branch read-only; its \
+             status becomes 'archived'), 'unarchive' (allow writes again). Recommended workflow: fork → \
+             experiment → merge if good, delete or archive if bad. Params: 'name' for \
+             create/switch/fork/delete/archive/unarchive, 'source' for \
+             merge, 'compare' for diff. Pass compact: true with merge to then apply the retention \
+             policy to the current branch, collapsing superseded versions; the result's \
+             'compacted' reports document versions before and after, or nulls with counted: \
+             false when the space holds more than 1000 documents. Branch names are 1-64 \
+             characters of ASCII letters, digits, '-', '_', '.', or '/', starting with a letter \
+             or digit.",
             serde_json::json!({
                 "type": "object",
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["create", "switch", "list", "fork", "merge", "diff", "delete"],
+                        "enum": [
+                            "create", "switch", "list", "fork", "merge", "diff", "delete", "archive",
+                            "unarchive"
+                        ],
                         "description": "The branch operation to perform"
                     },
                     "name": {
                         "type": "string",
-                        "description": "Branch name — used by create, switch, fork, delete"
+                        "description": "Branch name — used by create, switch, fork, delete, archive, unarchive"
                     },
                     "source": {
                         "type": "string",
@@ -122,30 +313,175 @@ pub fn tools() -> Vec<ToolDef> {
                     "compare": {
                         "type": "string",
                         "description": "Branch to compare against current — used by diff"
+                    },
+                    "compact": {
+                        "type": "boolean",
+                        "description": "Apply retention after merging to trim superseded history — used by merge"
                     }
                 },
                 "required": ["action"]
             }),
-        ),
+        )
+        .with_example(
+            serde_json::json!({"action": "fork", "name": "experiment"}),
+            serde_json::json!({
+                "forked": true,
+                "source": "default",
+                "destination": "experiment",
+                "keys_copied": 42
+            }),
+        )
+        .with_annotations(ToolAnnotations::write(true, false)),
         ToolDef::new(
             "strata_history",
             "View the complete version history of a key, or discover the time range available for \
-             time-travel. With 'key': returns every historical version with values, version numbers, \
-             and timestamps — useful for undo, audit, or understanding how data evolved. Without 'key': \
+             time-travel. With 'key': returns { found, versions } where 'versions' holds every \
+             historical version with values, version numbers, and timestamps — useful for undo, \
+             audit, or understanding how data evolved; found is false if the key never existed. Without 'key': \
              returns the oldest and latest timestamps on the current branch, so you know the full range \
-             available for 'as_of' queries in strata_recall.",
+             available for 'as_of' queries in strata_recall. With 'since' (microsecond timestamp) \
+             instead of 'key': returns { changes, cursor } listing the keys on the current branch \
+             whose latest write is newer than 'since', as { key, version, timestamp } (plus \
+             'value' when with_values is true). Results are paginated — 'limit' keys are scanned \
+             per page (default 100), so a page can hold fewer changes than 'limit'; pass the \
+             returned 'cursor' to continue until it is null.",
             schema!(object {
-                optional: { "key": string, "as_of": integer }
+                optional: {
+                    "key": string,
+                    "as_of": integer,
+                    "since": integer,
+                    "with_values": boolean,
+                    "cursor": string,
+                    "limit": integer,
+                    "space": string
+                }
+            }),
+        )
+        .with_example(
+            serde_json::json!({"key": "user:alice"}),
+            serde_json::json!({
+                "found": true,
+                "versions": [{
+                    "value": {"name": "Alice", "role": "admin"},
+                    "version": 1,
+                    "timestamp": 1760000000000000_u64
+                }]
             }),
-        ),
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "found": {"type": "boolean"},
+                "versions": {"type": "array", "items": {"type": "object"}},
+                "branch": {"type": "string"},
+                "oldest": {"type": ["integer", "null"]},
+                "latest": {"type": ["integer", "null"]},
+                "changes": {"type": "array", "items": {"type": "object"}},
+                "cursor": {"type": ["string", "null"]}
+            }
+        }))
+        .with_annotations(ToolAnnotations::read_only()),
         ToolDef::new(
             "strata_status",
             "Get database status. Returns current branch name, namespace, version, branch count, key \
-             count, uptime, and whether auto-embed is active. Use this to orient yourself — especially \
+             count, uptime, and whether auto-embed is active. When an embedding model is pinned or \
+             recorded, 'embed_model' gives { pinned, index, consistent } — the configured \
+             model, the one the index was built with, and whether they match. When the server \
+             isolates each session on its own branch, 'session_branch' gives its name, base, and what happens on exit. Use this to orient yourself — especially \
              at the start of a session to understand what branch you're on and what data exists.",
             schema!(object {}),
-        ),
-    ]
+        )
+        .with_example(
+            serde_json::json!({}),
+            serde_json::json!({
+                "version": "0.6.0",
+                "branch": "default",
+                "namespace": "default",
+                "branches": 2,
+                "keys": 42,
+                "uptime_secs": 3600,
+                "auto_embed": true
+            }),
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "version": {"type": "string"},
+                "branch": {"type": "string"},
+                "namespace": {"type": "string"},
+                "branches": {"type": "integer"},
+                "keys": {"type": "integer"},
+                "uptime_secs": {"type": "integer"},
+                "auto_embed": {"type": "boolean"},
+                "embed_model": {"type": "object"},
+                "session_branch": {"type": "object"}
+            },
+            "required": ["branch", "namespace"]
+        }))
+        .with_annotations(ToolAnnotations::read_only()),
+        ToolDef::new(
+            "strata_orient",
+            "Get a short plain-English summary of where you stand: current branch and key count, \
+             how many other branches exist, whether auto-embed is on and idle, whether writes are \
+             allowed, and how recently data changed. Call this once at the start of a session for \
+             immediate situational awareness; use strata_status when you need the raw fields. \
+             Returns { summary }.",
+            schema!(object {}),
+        )
+        .with_example(
+            serde_json::json!({}),
+            serde_json::json!({
+                "summary": "You're on branch 'default' with 42 keys. 1 other branch exists. \
+                            Auto-embed is on and idle, so semantic search is up to date. The \
+                            last change on this branch was 5 minutes ago."
+            }),
+        )
+        .with_output_schema(serde_json::json!({
+            "type": "object",
+            "properties": {"summary": {"type": "string"}},
+            "required": ["summary"]
+        }))
+        .with_annotations(ToolAnnotations::read_only()),
+        ToolDef::new(
+            "strata_run_query",
+            "Run a saved strata_search by name, so a recurring search doesn't have to be respelled \
+             every time. Actions: 'save' stores 'params' (the strata_search arguments — query \
+             plus any of k, group_by_prefix, metadata, explain) under 'name', replacing any \
+             query already saved there; 'run' (default) executes the saved query and returns \
+             exactly what strata_search would; 'list' returns [{ name, params }] for every saved \
+             query on the current branch; 'delete' removes 'name' and returns { deleted }. \
+             Saved queries are versioned and branch with the rest of the data, and never appear \
+             in search results.",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["run", "save", "list", "delete"],
+                        "description": "What to do (default run)"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Saved query name — used by run, save, delete"
+                    },
+                    "params": {
+                        "type": "object",
+                        "description": "strata_search arguments to save — used by save"
+                    }
+                }
+            }),
+        )
+        .with_example(
+            serde_json::json!({"name": "roadmap"}),
+            serde_json::json!([{"key": "notes:1", "score": 0.87, "snippet": "quarterly roadmap"}]),
+        )
+        .with_annotations(ToolAnnotations::write(true, true)),
+    ];
+    for tool in tools.iter_mut().filter(|t| SPACE_TOOLS.contains(&t.name.as_str())) {
+        tool.input_schema["properties"]["space"]["description"] =
+            JsonValue::String(SPACE_DESCRIPTION.to_string());
+    }
+    tools
 }
 
 /// Dispatch an agent tool call.
@@ -153,6 +489,30 @@ pub fn dispatch(
     session: &mut McpSession,
     name: &str,
     args: Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    // A per-call space applies to this call only
+    if SPACE_TOOLS.contains(&name) {
+        if let Some(space) = get_optional_string(&args, "space") {
+            if space.is_empty() {
+                return Err(McpError::InvalidArg {
+                    name: "space".to_string(),
+                    reason: "must not be empty".to_string(),
+                });
+            }
+            let previous = session.space().to_string();
+            session.switch_space(&space);
+            let outcome = dispatch_tool(session, name, args);
+            session.switch_space(&previous);
+            return outcome;
+        }
+    }
+    dispatch_tool(session, name, args)
+}
+
+fn dispatch_tool(
+    session: &mut McpSession,
+    name: &str,
+    args: Map<String, JsonValue>,
 ) -> Result<JsonValue> {
     match name {
         "strata_store" => dispatch_store(session, args),
@@ -163,6 +523,8 @@ pub fn dispatch(
         "strata_branch" => dispatch_branch(session, args),
         "strata_history" => dispatch_history(session, args),
         "strata_status" => dispatch_status(session),
+        "strata_orient" => dispatch_orient(session),
+        "strata_run_query" => dispatch_run_query(session, args),
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
@@ -170,22 +532,212 @@ pub fn dispatch(
 // ── Store ────────────────────────────────────────────────────────────────
 
 fn dispatch_store(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
-    let key = get_string_arg(&args, "key")?;
-    let value = get_value_arg(&args, "value")?;
+    if get_optional_bool(&args, "snapshot").unwrap_or(false) {
+        return store_snapshot(session, args);
+    }
+    let key = if get_optional_bool(&args, "auto_key").unwrap_or(false) {
+        if args.contains_key("key") {
+            return Err(McpError::InvalidArg {
+                name: "auto_key".to_string(),
+                reason: "pass either 'key' or auto_key: true, not both".to_string(),
+            });
+        }
+        ulid::generate()
+    } else {
+        get_string_arg(&args, "key")?
+    };
+    if meta::is_reserved(&key) {
+        return Err(meta::reserved_key_error(&key));
+    }
+    let value_type = get_optional_string(&args, "value_type");
+    let value = get_typed_value_arg(&args, "value", value_type.as_deref())?;
     let path = get_optional_string(&args, "path").unwrap_or_else(|| "$".to_string());
+    let create_parents = get_optional_bool(&args, "create_parents").unwrap_or(true);
+    let replace = get_optional_bool(&args, "replace").unwrap_or(false);
 
-    let cmd = Command::JsonSet {
+    // Guard against replacing a whole document when a field update was meant
+    if session.require_explicit_replace()
+        && !args.contains_key("path")
+        && !replace
+        && read_document(session, &key)?.is_some()
+    {
+        return Err(McpError::InvalidArg {
+            name: "replace".to_string(),
+            reason: format!(
+                "'{}' already exists; pass replace: true to overwrite the whole document, \
+                 or a 'path' to update part of it",
+                key
+            ),
+        });
+    }
+
+    // Whole-document writes may be coalesced by the session
+    if path == "$" {
+        return Ok(match session.store_document(key.clone(), value)? {
+            Some(v) => {
+                warn_if_embedding_deferred(session);
+                serde_json::json!({
+                    "key": key,
+                    "version": v,
+                    "stored": true,
+                })
+            }
+            None => {
+                session.warn(
+                    WarningCode::Coalesced,
+                    "The write is buffered and becomes a version when the coalescing window \
+                     closes; reads already see it.",
+                );
+                serde_json::json!({
+                    "key": key,
+                    "version": null,
+                    "stored": false,
+                    "buffered": true,
+                })
+            }
+        });
+    }
+
+    // Reading the document and writing it back must not interleave with
+    // another writer, so nested writes run in their own transaction
+    let value_json = match &value_type {
+        Some(value_type) => {
+            apply_value_type(args.get("value").cloned().unwrap_or(JsonValue::Null), value_type)?
+        }
+        None => args.get("value").cloned().unwrap_or(JsonValue::Null),
+    };
+    let own_txn = !session.in_transaction();
+    if own_txn {
+        session.execute(Command::TxnBegin {
+            branch: session.branch_id(),
+            options: None,
+        })?;
+    }
+    let result = store_at_path(session, &key, &path, value, value_json, create_parents);
+    if own_txn {
+        match &result {
+            Ok(_) => {
+                session.execute(Command::TxnCommit)?;
+            }
+            // The write's error is the one to report, not the rollback's
+            Err(_) => {
+                let _ = session.execute(Command::TxnRollback);
+            }
+        }
+    }
+
+    match result? {
+        Output::Version(v) => {
+            warn_if_embedding_deferred(session);
+            Ok(serde_json::json!({
+                "key": key,
+                "version": v,
+                "stored": true,
+            }))
+        }
+        other => Ok(output_to_json(other)),
+    }
+}
+
+/// Write `value` at `path` inside `key`, handling missing parents here so the
+/// behavior is explicit rather than left to the engine. Paths the local parser
+/// doesn't understand (filters, wildcards, ...) go to the engine unchanged.
+fn store_at_path(
+    session: &mut McpSession,
+    key: &str,
+    path: &str,
+    value: Value,
+    value_json: JsonValue,
+    create_parents: bool,
+) -> Result<Output> {
+    let mut path = path.to_string();
+    let mut value = value;
+    if let Ok(segments) = jsonpath::parse(&path) {
+        if let Some((_, parents)) = segments.split_last() {
+            let doc = read_document(session, key)?;
+            let missing = match &doc {
+                Some(doc) => jsonpath::first_missing(doc, parents)
+                    .map(|i| jsonpath::format(&parents[..=i])),
+                None => Some("$".to_string()),
+            };
+
+            if let Some(missing) = missing {
+                if !create_parents {
+                    return Err(McpError::InvalidArg {
+                        name: "path".to_string(),
+                        reason: format!(
+                            "parent '{}' does not exist in '{}'; \
+                             pass create_parents: true to create it",
+                            missing, key
+                        ),
+                    });
+                }
+
+                // Build the nested structure locally and write the whole document
+                let mut doc = doc.unwrap_or(JsonValue::Null);
+                jsonpath::set(&mut doc, &segments, value_json)?;
+                value = json_to_value(doc)?;
+                path = "$".to_string();
+            }
+        }
+    }
+
+    session.execute(Command::JsonSet {
         branch: session.branch_id(),
         space: session.space_id(),
-        key: key.clone(),
+        key: key.to_string(),
         path,
         value,
-    };
-    let output = session.execute(cmd)?;
+    })
+}
 
-    match output {
+/// Reserved key of the session snapshot written by `strata_store` with `snapshot: true`.
+const SNAPSHOT_KEY: &str = "__snapshot";
+
+/// Recent writes included in a session snapshot.
+const SNAPSHOT_RECENT_WRITES: usize = 10;
+
+/// Save a "where I left off" snapshot: the caller's note plus current status,
+/// orientation summary, and recent writes.
+fn store_snapshot(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    for conflicting in ["key", "auto_key", "path"] {
+        if args.contains_key(conflicting) {
+            return Err(McpError::InvalidArg {
+                name: "snapshot".to_string(),
+                reason: format!(
+                    "snapshot: true stores under '{}'; omit '{}'",
+                    SNAPSHOT_KEY, conflicting
+                ),
+            });
+        }
+    }
+    let note = args.get("value").cloned().unwrap_or(JsonValue::Null);
+
+    let status = dispatch_status(session)?;
+    let summary = dispatch_orient(session)?["summary"].take();
+    let recent = scan::recent_writes(session, SNAPSHOT_RECENT_WRITES)?;
+    let saved_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+    let snapshot = serde_json::json!({
+        "note": note,
+        "saved_at": saved_at,
+        "status": status,
+        "summary": summary,
+        "recent_writes": recent,
+    });
+
+    let cmd = Command::JsonSet {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: SNAPSHOT_KEY.to_string(),
+        path: "$".to_string(),
+        value: json_to_value(snapshot)?,
+    };
+    match session.execute(cmd)? {
         Output::Version(v) => Ok(serde_json::json!({
-            "key": key,
+            "key": SNAPSHOT_KEY,
             "version": v,
             "stored": true,
         })),
@@ -193,12 +745,72 @@ fn dispatch_store(session: &mut McpSession, args: Map<String, JsonValue>) -> Res
     }
 }
 
+/// Warn when auto-embed has writes queued, so a just-stored document may not
+/// be found by semantic search yet.
+fn warn_if_embedding_deferred(session: &mut McpSession) {
+    // Without auto-embed nothing is queued, so skip the status read
+    if !session.auto_embed() {
+        return;
+    }
+    if let Ok(Output::EmbedStatus(embed)) = session.execute(Command::EmbedStatus) {
+        if embed.auto_embed && embed.pending > 0 {
+            session.warn(
+                WarningCode::DeferredEmbed,
+                format!(
+                    "Stored, but embedding is queued ({} pending); semantic search may not \
+                     find it yet.",
+                    embed.pending
+                ),
+            );
+        }
+    }
+}
+
+/// Read the current value of a document, or `None` if the key doesn't exist.
+pub(crate) fn read_document(session: &mut McpSession, key: &str) -> Result<Option<JsonValue>> {
+    let cmd = Command::JsonGet {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: key.to_string(),
+        path: "$".to_string(),
+        as_of: None,
+    };
+    match session.execute(cmd)? {
+        Output::MaybeVersioned(opt) => Ok(opt.map(|vv| value_to_json(vv.value))),
+        Output::Maybe(opt) => Ok(opt.map(value_to_json)),
+        _ => Ok(None),
+    }
+}
+
 // ── Recall ───────────────────────────────────────────────────────────────
 
 fn dispatch_recall(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
     let key = get_string_arg(&args, "key")?;
     let path = get_optional_string(&args, "path").unwrap_or_else(|| "$".to_string());
     let as_of = get_optional_u64(&args, "as_of");
+    let with_types = get_optional_bool(&args, "with_types").unwrap_or(false);
+    let offset = get_optional_u64(&args, "offset");
+    let length = get_optional_u64(&args, "length");
+    let limit = get_optional_u64(&args, "limit");
+    if limit.is_some() && (offset.is_some() || length.is_some()) {
+        return Err(McpError::InvalidArg {
+            name: "limit".to_string(),
+            reason: "cannot be combined with offset/length; use one or the other".to_string(),
+        });
+    }
+
+    if args.contains_key("paths") {
+        if let Some(conflict) = ["path", "offset", "length", "limit"]
+            .into_iter()
+            .find(|name| args.contains_key(*name))
+        {
+            return Err(McpError::InvalidArg {
+                name: "paths".to_string(),
+                reason: format!("cannot be combined with '{}'", conflict),
+            });
+        }
+        return recall_paths(session, key, as_of, &args);
+    }
 
     let cmd = Command::JsonGet {
         branch: session.branch_id(),
@@ -208,7 +820,167 @@ fn dispatch_recall(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
         as_of,
     };
     let output = session.execute(cmd)?;
-    Ok(output_to_json(output))
+
+    match output {
+        Output::MaybeVersioned(Some(vv)) => {
+            let types = with_types.then(|| value_type_tree(&vv.value));
+            let mut result = versioned_to_json(vv);
+            if let Some(obj) = result.as_object_mut() {
+                if !session.legacy_reads() {
+                    obj.insert("found".to_string(), JsonValue::Bool(true));
+                }
+                if let Some(types) = types {
+                    obj.insert("types".to_string(), types);
+                }
+                if offset.is_some() || length.is_some() {
+                    let value = obj.remove("value").unwrap_or(JsonValue::Null);
+                    let offset = offset.unwrap_or(0) as usize;
+                    let length = length.unwrap_or(DEFAULT_CHUNK_LENGTH) as usize;
+                    obj.extend(chunk_value(value, offset, length));
+                }
+                if let Some(limit) = limit {
+                    if let Some(total) = truncate_value(obj.get_mut("value"), limit as usize) {
+                        obj.insert("truncated".to_string(), JsonValue::Bool(true));
+                        obj.insert("total".to_string(), serde_json::json!(total));
+                        session.warn(
+                            WarningCode::Truncated,
+                            format!("Showing {} of {} elements (limit).", limit, total),
+                        );
+                    }
+                }
+            }
+            Ok(result)
+        }
+        Output::MaybeVersioned(None) | Output::Maybe(None) if !session.legacy_reads() => {
+            Ok(serde_json::json!({ "found": false, "value": null }))
+        }
+        other => Ok(output_to_json(other)),
+    }
+}
+
+/// Read several paths of one document from a single fetch.
+fn recall_paths(
+    session: &mut McpSession,
+    key: String,
+    as_of: Option<u64>,
+    args: &Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    let paths: Vec<String> = args
+        .get("paths")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| McpError::InvalidArg {
+            name: "paths".to_string(),
+            reason: "Expected an array of JSONPath strings".to_string(),
+        })?
+        .iter()
+        .map(|v| {
+            v.as_str().map(String::from).ok_or_else(|| McpError::InvalidArg {
+                name: "paths".to_string(),
+                reason: "Expected an array of JSONPath strings".to_string(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    let parsed = paths
+        .iter()
+        .map(|path| jsonpath::parse(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let cmd = Command::JsonGet {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key,
+        path: "$".to_string(),
+        as_of,
+    };
+    let vv = match session.execute(cmd)? {
+        Output::MaybeVersioned(Some(vv)) => vv,
+        _ if session.legacy_reads() => return Ok(JsonValue::Null),
+        _ => return Ok(serde_json::json!({ "found": false, "values": null })),
+    };
+    let doc = value_to_json(vv.value);
+
+    let mut values = Map::new();
+    let mut missing = Vec::new();
+    for (path, segments) in paths.into_iter().zip(&parsed) {
+        let value = jsonpath::resolve(&doc, segments).cloned();
+        if value.is_none() {
+            missing.push(path.clone());
+        }
+        values.insert(path, value.unwrap_or(JsonValue::Null));
+    }
+    Ok(serde_json::json!({
+        "found": true,
+        "values": values,
+        "missing": missing,
+        "version": vv.version,
+        "timestamp": vv.timestamp,
+    }))
+}
+
+/// Chunk size used when `offset` is given without `length`.
+const DEFAULT_CHUNK_LENGTH: u64 = 10_000;
+
+/// Slice `length` units of a value starting at `offset`.
+///
+/// Strings are addressed by character and arrays by element; anything else
+/// is serialized and addressed by character of its JSON text.
+fn chunk_value(value: JsonValue, offset: usize, length: usize) -> Map<String, JsonValue> {
+    let (mode, total, chunk) = match value {
+        JsonValue::Array(arr) => {
+            let total = arr.len();
+            let chunk: Vec<JsonValue> = arr.into_iter().skip(offset).take(length).collect();
+            ("array", total, JsonValue::Array(chunk))
+        }
+        JsonValue::String(text) => {
+            let (total, chunk) = slice_chars(&text, offset, length);
+            ("string", total, JsonValue::String(chunk))
+        }
+        other => {
+            let text = other.to_string();
+            let (total, chunk) = slice_chars(&text, offset, length);
+            ("serialized", total, JsonValue::String(chunk))
+        }
+    };
+
+    let end = offset.saturating_add(length).min(total);
+    let has_more = end < total;
+    let mut map = Map::new();
+    map.insert("value".to_string(), chunk);
+    map.insert("chunked".to_string(), JsonValue::String(mode.to_string()));
+    map.insert("offset".to_string(), serde_json::json!(offset));
+    map.insert("total".to_string(), serde_json::json!(total));
+    map.insert("has_more".to_string(), JsonValue::Bool(has_more));
+    map.insert(
+        "next_offset".to_string(),
+        if has_more { serde_json::json!(end) } else { JsonValue::Null },
+    );
+    map
+}
+
+/// Keep only the first `limit` elements of an array or fields of an object.
+///
+/// Returns the original size when something was cut, `None` otherwise.
+fn truncate_value(value: Option<&mut JsonValue>, limit: usize) -> Option<usize> {
+    match value? {
+        JsonValue::Array(arr) if arr.len() > limit => {
+            let total = arr.len();
+            arr.truncate(limit);
+            Some(total)
+        }
+        JsonValue::Object(map) if map.len() > limit => {
+            let total = map.len();
+            let kept: Map<String, JsonValue> = std::mem::take(map).into_iter().take(limit).collect();
+            *map = kept;
+            Some(total)
+        }
+        _ => None,
+    }
+}
+
+/// Character count of `text` and the `length` characters starting at `offset`.
+fn slice_chars(text: &str, offset: usize, length: usize) -> (usize, String) {
+    let total = text.chars().count();
+    (total, text.chars().skip(offset).take(length).collect())
 }
 
 // ── Search ───────────────────────────────────────────────────────────────
@@ -216,13 +988,57 @@ fn dispatch_recall(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
 fn dispatch_search(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
     let query = get_string_arg(&args, "query")?;
     let k = get_optional_u64(&args, "k");
+    let explain = get_optional_bool(&args, "explain").unwrap_or(false);
+    let delimiter = get_optional_string(&args, "group_by_prefix");
+    if delimiter.as_deref() == Some("") {
+        return Err(McpError::InvalidArg {
+            name: "group_by_prefix".to_string(),
+            reason: "delimiter must not be empty".to_string(),
+        });
+    }
+
+    let metadata_filter = match args.get("metadata") {
+        None | Some(JsonValue::Null) => None,
+        Some(JsonValue::Object(map)) => Some(map.clone()),
+        Some(_) => {
+            return Err(McpError::InvalidArg {
+                name: "metadata".to_string(),
+                reason: "Expected an object of field values to match".to_string(),
+            })
+        }
+    };
+
+    let max_age = get_optional_u64(&args, "max_age");
+    let recency_boost = match args.get("recency_boost") {
+        None | Some(JsonValue::Null) => None,
+        Some(v) => match v.as_f64() {
+            Some(boost) if (0.0..=1.0).contains(&boost) => Some(boost),
+            _ => {
+                return Err(McpError::InvalidArg {
+                    name: "recency_boost".to_string(),
+                    reason: "Expected a number between 0 and 1".to_string(),
+                })
+            }
+        },
+    };
+    let reranks = max_age.is_some() || recency_boost.is_some();
 
+    // Over-fetch when filtering or re-ranking so the filter doesn't starve
+    // the result set and recent documents just outside the top k can rise
+    let result_limit = k.unwrap_or(DEFAULT_SEARCH_K) as usize;
+    let engine_k = if metadata_filter.is_some() || reranks {
+        Some(result_limit as u64 * METADATA_FILTER_OVERFETCH)
+    } else {
+        k
+    };
+
+    let backend = search_backend(session)?;
     let sq = SearchQuery {
-        query,
-        k,
+        query: query.clone(),
+        k: engine_k,
         primitives: None, // search all primitives
         time_range: None,
-        mode: None, // engine picks best available (hybrid when auto-embed is on)
+        mode: Some(backend.mode().to_string()),
         expand: None,
         rerank: None,
     };
@@ -233,24 +1049,359 @@ fn dispatch_search(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
         search: sq,
     };
     let output = session.execute(cmd)?;
+    if !backend.semantic() {
+        session.warn(
+            WarningCode::SemanticUnavailable,
+            "Auto-embed is off, so this search matched keywords only.",
+        );
+    }
 
     // Simplify search results for agent consumption
-    match output {
-        Output::SearchResults(results) => {
-            let arr: Vec<JsonValue> = results
-                .into_iter()
-                .map(|r| {
-                    serde_json::json!({
-                        "key": r.entity,
-                        "score": r.score,
-                        "snippet": r.snippet,
-                    })
-                })
-                .collect();
-            Ok(JsonValue::Array(arr))
+    let results = match output {
+        Output::SearchResults(results) => results,
+        other => return Ok(output_to_json(other)),
+    };
+
+    let mut stats = SearchStats {
+        candidates: results.len(),
+        ..SearchStats::default()
+    };
+    let mut hits: Vec<SearchHit> = results
+        .into_iter()
+        .filter(|r| !meta::is_reserved(&r.entity))
+        .map(|r| SearchHit {
+            key: r.entity,
+            primitive: r.primitive,
+            score: r.score,
+            snippet: serde_json::json!(r.snippet),
+        })
+        .collect();
+    stats.reserved_filtered = stats.candidates - hits.len();
+    if reranks {
+        hits = apply_freshness(session, hits, max_age, recency_boost, &mut stats)?;
+    }
+    sort_hits(&mut hits);
+
+    if let Some(filter) = &metadata_filter {
+        let mut kept = Vec::new();
+        for hit in hits {
+            if kept.len() >= result_limit {
+                break;
+            }
+            if meta::matches(meta::read_meta(session, &hit.key)?.as_ref(), filter) {
+                kept.push(hit);
+            } else {
+                stats.metadata_filtered += 1;
+            }
         }
-        other => Ok(output_to_json(other)),
+        hits = kept;
     }
+    if reranks {
+        hits.truncate(result_limit);
+    }
+    stats.returned = hits.len();
+
+    let results = match delimiter {
+        Some(delimiter) => group_hits(hits, &delimiter),
+        None => JsonValue::Array(hits.into_iter().map(SearchHit::into_json).collect()),
+    };
+
+    if !explain {
+        return Ok(results);
+    }
+    let explain = explain_search(session, &query, &backend, &stats);
+    Ok(serde_json::json!({
+        "results": results,
+        "explain": explain,
+    }))
+}
+
+/// The engine's embedding state, read once per search, which decides the
+/// backend the search asks for.
+struct SearchBackend {
+    /// Auto-embed is on, so new writes are embedded
+    auto_embed: bool,
+    /// Embeddings still queued
+    pending: u64,
+    /// The search index holds vectors, from auto-embed or strata_embed_key
+    indexed: bool,
+}
+
+impl SearchBackend {
+    /// The search mode to request: hybrid (BM25 + vector) when there are
+    /// vectors to rank, keyword-only otherwise.
+    fn mode(&self) -> &'static str {
+        if self.semantic() {
+            "hybrid"
+        } else {
+            "keyword"
+        }
+    }
+
+    fn semantic(&self) -> bool {
+        self.auto_embed || self.indexed
+    }
+}
+
+/// Read the engine's embed status and whether the search index has vectors
+/// in the current branch and space.
+fn search_backend(session: &mut McpSession) -> Result<SearchBackend> {
+    let (auto_embed, pending) = match session.execute(Command::EmbedStatus) {
+        Ok(Output::EmbedStatus(embed)) => (embed.auto_embed, embed.pending as u64),
+        _ => (false, 0),
+    };
+    let cmd = Command::VectorListCollections {
+        branch: session.branch_id(),
+        space: session.space_id(),
+    };
+    let indexed = match session.execute(cmd)? {
+        Output::VectorCollectionList(collections) => collections
+            .iter()
+            .any(|c| c.name == SEARCH_INDEX_COLLECTION && c.count > 0),
+        _ => false,
+    };
+    Ok(SearchBackend {
+        auto_embed,
+        pending,
+        indexed,
+    })
+}
+
+/// Counts gathered while post-processing engine search results.
+#[derive(Default)]
+struct SearchStats {
+    /// Results returned by the engine
+    candidates: usize,
+    /// Candidates dropped because they are reserved bookkeeping keys
+    reserved_filtered: usize,
+    /// Candidates dropped by the metadata filter
+    metadata_filtered: usize,
+    /// Candidates dropped by `max_age`
+    age_filtered: usize,
+    /// Results returned to the agent
+    returned: usize,
+}
+
+/// Diagnose a search: which backend answered, what was in scope, whether
+/// semantic search ran, and what was filtered. When nothing came back,
+/// `reason` says the likely cause.
+fn explain_search(
+    session: &mut McpSession,
+    query: &str,
+    backend: &SearchBackend,
+    stats: &SearchStats,
+) -> JsonValue {
+    let total_keys = match session.execute(Command::Info) {
+        Ok(Output::DatabaseInfo(info)) => Some(info.total_keys),
+        _ => None,
+    };
+    let semantic = backend.semantic();
+    let pending = backend.pending;
+    let has_pending = backend.auto_embed && pending > 0;
+
+    let reason = if stats.returned > 0 {
+        None
+    } else if query.trim().is_empty() {
+        Some("The query is empty; describe what you're looking for.".to_string())
+    } else if total_keys == Some(0) {
+        Some("Nothing is stored yet, so there is nothing to search.".to_string())
+    } else if stats.metadata_filtered > 0 {
+        Some(format!(
+            "{} matching candidates were filtered out by the metadata filter.",
+            stats.metadata_filtered
+        ))
+    } else if stats.age_filtered > 0 {
+        Some(format!(
+            "{} matching candidates were older than max_age; raise it to include them.",
+            stats.age_filtered
+        ))
+    } else if stats.candidates == 0 && !semantic {
+        Some(
+            "No keyword matches, and semantic search is off (auto-embed disabled). \
+             Try other words that appear in the data."
+                .to_string(),
+        )
+    } else if stats.candidates == 0 && has_pending {
+        Some(format!(
+            "No matches yet; {} items are still waiting to be embedded for semantic search.",
+            pending
+        ))
+    } else if stats.candidates == 0 {
+        Some("Neither keyword nor semantic search found a match. Try rephrasing.".to_string())
+    } else {
+        Some("All candidates were internal bookkeeping records.".to_string())
+    };
+
+    // The mode the search asked the engine for, so this is what answered
+    let backends = if semantic {
+        serde_json::json!(["keyword", "semantic"])
+    } else {
+        serde_json::json!(["keyword"])
+    };
+
+    serde_json::json!({
+        "backend": backend.mode(),
+        "backends": backends,
+        "keys_in_database": total_keys,
+        "semantic_search": semantic,
+        "pending_embeddings": pending,
+        "candidates": stats.candidates,
+        "filtered_out": {
+            "reserved": stats.reserved_filtered,
+            "metadata": stats.metadata_filtered,
+            "age": stats.age_filtered,
+        },
+        "returned": stats.returned,
+        "reason": reason,
+    })
+}
+
+/// Results returned when `k` is not given (the engine default).
+const DEFAULT_SEARCH_K: u64 = 10;
+
+/// How many engine results to fetch per requested result when filtering by metadata.
+const METADATA_FILTER_OVERFETCH: u64 = 5;
+
+/// A search result simplified for agent consumption.
+struct SearchHit {
+    key: String,
+    primitive: String,
+    score: f32,
+    snippet: JsonValue,
+}
+
+impl SearchHit {
+    fn into_json(self) -> JsonValue {
+        serde_json::json!({
+            "key": self.key,
+            "score": self.score,
+            "snippet": self.snippet,
+        })
+    }
+}
+
+/// Drop hits last written more than `max_age` seconds ago and blend
+/// `recency_boost` into the scores of the rest, as `strata_search` documents.
+///
+/// A hit's age is its latest document version's timestamp; hits with no
+/// document behind them (e.g. events) count as oldest.
+fn apply_freshness(
+    session: &mut McpSession,
+    hits: Vec<SearchHit>,
+    max_age: Option<u64>,
+    recency_boost: Option<f64>,
+    stats: &mut SearchStats,
+) -> Result<Vec<SearchHit>> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+    let cutoff = max_age.map(|secs| now.saturating_sub(secs.saturating_mul(1_000_000)));
+
+    let mut dated = Vec::with_capacity(hits.len());
+    for hit in hits {
+        let written = written_at(session, &hit)?;
+        if let Some(cutoff) = cutoff {
+            if !matches!(written, Some(ts) if ts >= cutoff) {
+                stats.age_filtered += 1;
+                continue;
+            }
+        }
+        dated.push((hit, written));
+    }
+
+    let Some(boost) = recency_boost else {
+        return Ok(dated.into_iter().map(|(hit, _)| hit).collect());
+    };
+    let top = dated.iter().map(|(hit, _)| hit.score).fold(0.0f32, f32::max);
+    let times = dated.iter().filter_map(|(_, written)| *written);
+    let (oldest, newest) = (times.clone().min().unwrap_or(0), times.max().unwrap_or(0));
+    Ok(dated
+        .into_iter()
+        .map(|(mut hit, written)| {
+            let relevance = if top > 0.0 { (hit.score / top) as f64 } else { 0.0 };
+            let recency = match written {
+                Some(ts) if newest > oldest => (ts - oldest) as f64 / (newest - oldest) as f64,
+                Some(_) => 1.0,
+                None => 0.0,
+            };
+            hit.score = ((1.0 - boost) * relevance + boost * recency) as f32;
+            hit
+        })
+        .collect())
+}
+
+/// When the entity behind a hit was last written, read from the primitive
+/// that matched it, or `None` when that primitive keeps no write time.
+fn written_at(session: &mut McpSession, hit: &SearchHit) -> Result<Option<u64>> {
+    let (branch, space) = (session.branch_id(), session.space_id());
+    let key = hit.key.clone();
+    let cmd = match hit.primitive.as_str() {
+        "json" => Command::JsonGet {
+            branch,
+            space,
+            key,
+            path: "$".to_string(),
+            as_of: None,
+        },
+        "kv" => Command::KvGet {
+            branch,
+            space,
+            key,
+            as_of: None,
+        },
+        "state" => Command::StateGet {
+            branch,
+            space,
+            cell: key,
+            as_of: None,
+        },
+        "event" => match key.parse() {
+            Ok(sequence) => Command::EventGet {
+                branch,
+                space,
+                sequence,
+                as_of: None,
+            },
+            Err(_) => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    match session.execute(cmd)? {
+        Output::MaybeVersioned(Some(vv)) => Ok(Some(vv.timestamp)),
+        _ => Ok(None),
+    }
+}
+
+/// Order hits by descending score, breaking ties by key and then primitive
+/// so that equal-scoring results come back in the same order on every run.
+fn sort_hits(hits: &mut [SearchHit]) {
+    hits.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.key.cmp(&b.key))
+            .then_with(|| a.primitive.cmp(&b.primitive))
+    });
+}
+
+/// Group already-sorted hits by the key prefix before the first `delimiter`,
+/// keeping rank order within each group.
+fn group_hits(hits: Vec<SearchHit>, delimiter: &str) -> JsonValue {
+    let mut groups = Map::new();
+    for hit in hits {
+        let prefix = hit
+            .key
+            .split_once(delimiter)
+            .map(|(prefix, _)| prefix.to_string())
+            .unwrap_or_default();
+        let entry = groups
+            .entry(prefix)
+            .or_insert_with(|| JsonValue::Array(Vec::new()));
+        if let JsonValue::Array(arr) = entry {
+            arr.push(hit.into_json());
+        }
+    }
+    JsonValue::Object(groups)
 }
 
 // ── Forget ───────────────────────────────────────────────────────────────
@@ -261,11 +1412,14 @@ fn dispatch_forget(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
     let cmd = Command::JsonDelete {
         branch: session.branch_id(),
         space: session.space_id(),
-        key,
+        key: key.clone(),
         path: "$".to_string(),
     };
     let output = session.execute(cmd)?;
 
+    // Metadata goes with the document
+    meta::delete_meta(session, &key)?;
+
     match output {
         Output::Uint(n) => Ok(serde_json::json!({ "deleted": n > 0 })),
         other => Ok(output_to_json(other)),
@@ -275,13 +1429,24 @@ fn dispatch_forget(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
 // ── Log ──────────────────────────────────────────────────────────────────
 
 fn dispatch_log(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
-    let event = get_string_arg(&args, "event")?;
+    let given = get_string_arg(&args, "event")?;
+    let event = session.event_type_case().apply(&given);
+    if event.is_empty() {
+        return Err(McpError::InvalidArg {
+            name: "event".to_string(),
+            reason: format!(
+                "'{}' normalizes to an empty event type under --event-type-case {}",
+                given,
+                session.event_type_case().as_str()
+            ),
+        });
+    }
     let data = get_value_arg(&args, "data")?;
 
     let cmd = Command::EventAppend {
         branch: session.branch_id(),
         space: session.space_id(),
-        event_type: event,
+        event_type: event.clone(),
         payload: data,
     };
     let output = session.execute(cmd)?;
@@ -290,6 +1455,7 @@ fn dispatch_log(session: &mut McpSession, args: Map<String, JsonValue>) -> Resul
         Output::Version(v) => Ok(serde_json::json!({
             "sequence": v,
             "logged": true,
+            "event": event,
         })),
         other => Ok(output_to_json(other)),
     }
@@ -297,12 +1463,18 @@ fn dispatch_log(session: &mut McpSession, args: Map<String, JsonValue>) -> Resul
 
 // ── Branch ───────────────────────────────────────────────────────────────
 
+/// Documents whose versions a merge with compact: true counts, before giving up on counts.
+const COMPACT_COUNT_MAX_KEYS: u64 = 1000;
+
 fn dispatch_branch(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
     let action = get_string_arg(&args, "action")?;
 
     match action.as_str() {
         "create" => {
             let name = get_optional_string(&args, "name");
+            if let Some(name) = &name {
+                validate_branch_name(name)?;
+            }
             let cmd = Command::BranchCreate {
                 branch_id: name,
                 metadata: None,
@@ -326,8 +1498,9 @@ fn dispatch_branch(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
                 limit: None,
                 offset: None,
             };
-            let output = session.execute(cmd)?;
-            Ok(output_to_json(output))
+            let mut branches = output_to_json(session.execute(cmd)?);
+            branch::mark_archived(session, &mut branches)?;
+            Ok(branches)
         }
 
         "fork" => {
@@ -343,6 +1516,7 @@ fn dispatch_branch(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
 
         "merge" => {
             let source = get_string_arg(&args, "source")?;
+            let compact = get_optional_bool(&args, "compact").unwrap_or(false);
             let info = session.merge_branch(&source, MergeStrategy::LastWriterWins)?;
 
             let conflicts: Vec<JsonValue> = info
@@ -356,12 +1530,32 @@ fn dispatch_branch(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
                 })
                 .collect();
 
-            Ok(serde_json::json!({
+            let mut result = serde_json::json!({
                 "merged": true,
                 "keys_applied": info.keys_applied,
                 "spaces_merged": info.spaces_merged,
                 "conflicts": conflicts,
-            }))
+            });
+
+            // Optional retention pass to collapse superseded versions
+            if compact {
+                let before = scan::total_versions_up_to(session, COMPACT_COUNT_MAX_KEYS)?;
+                let cmd = Command::RetentionApply {
+                    branch: session.branch_id(),
+                };
+                session.execute(cmd)?;
+                let after = match before {
+                    Some(_) => scan::total_versions_up_to(session, COMPACT_COUNT_MAX_KEYS)?,
+                    None => None,
+                };
+                result["compacted"] = serde_json::json!({
+                    "counted": before.is_some() && after.is_some(),
+                    "versions_before": before,
+                    "versions_after": after,
+                    "versions_removed": before.zip(after).map(|(b, a)| b.saturating_sub(a)),
+                });
+            }
+            Ok(result)
         }
 
         "diff" => {
@@ -387,10 +1581,27 @@ fn dispatch_branch(session: &mut McpSession, args: Map<String, JsonValue>) -> Re
             Ok(output_to_json(output))
         }
 
+        "archive" | "unarchive" => {
+            let name = get_string_arg(&args, "name")?;
+            let exists = session.execute(Command::BranchExists {
+                branch: BranchId::from(name.clone()),
+            })?;
+            if !matches!(exists, Output::Bool(true)) {
+                return Err(McpError::BranchNotFound(name));
+            }
+            let archived = action == "archive";
+            session.set_archived(&name, archived)?;
+            Ok(serde_json::json!({
+                "branch": name,
+                "archived": archived,
+            }))
+        }
+
         other => Err(McpError::InvalidArg {
             name: "action".to_string(),
             reason: format!(
-                "Unknown action '{}'. Use: create, switch, list, fork, merge, diff, or delete.",
+                "Unknown action '{}'. Use: create, switch, list, fork, merge, diff, delete, \
+                 archive, or unarchive.",
                 other
             ),
         }),
@@ -403,6 +1614,17 @@ fn dispatch_history(session: &mut McpSession, args: Map<String, JsonValue>) -> R
     let key = get_optional_string(&args, "key");
     let as_of = get_optional_u64(&args, "as_of");
 
+    if let Some(since) = get_optional_u64(&args, "since") {
+        if key.is_some() {
+            return Err(McpError::InvalidArg {
+                name: "since".to_string(),
+                reason: "use either 'key' (one key's history) or 'since' (changed keys), not both"
+                    .to_string(),
+            });
+        }
+        return changes_since(session, since, &args);
+    }
+
     match key {
         Some(key) => {
             // Version history for a specific key (JSON document store)
@@ -413,7 +1635,19 @@ fn dispatch_history(session: &mut McpSession, args: Map<String, JsonValue>) -> R
                 as_of,
             };
             let output = session.execute(cmd)?;
-            Ok(output_to_json(output))
+
+            match output {
+                Output::VersionHistory(versions) if !session.legacy_reads() => {
+                    let found = versions.is_some();
+                    let versions: Vec<JsonValue> = versions
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(versioned_to_json)
+                        .collect();
+                    Ok(serde_json::json!({ "found": found, "versions": versions }))
+                }
+                other => Ok(output_to_json(other)),
+            }
         }
         None => {
             // Branch time range
@@ -437,6 +1671,46 @@ fn dispatch_history(session: &mut McpSession, args: Map<String, JsonValue>) -> R
     }
 }
 
+/// Keys scanned per page by `strata_history` with `since`.
+const DEFAULT_CHANGES_PAGE: u64 = 100;
+
+/// List keys on the current branch whose latest version is newer than `since`.
+fn changes_since(
+    session: &mut McpSession,
+    since: u64,
+    args: &Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    let with_values = get_optional_bool(args, "with_values").unwrap_or(false);
+    let cursor = get_optional_string(args, "cursor");
+    let limit = get_optional_u64(args, "limit").unwrap_or(DEFAULT_CHANGES_PAGE);
+
+    let page = scan::list_page(session, None, cursor, limit)?;
+    let mut changes = Vec::new();
+    for key in page.keys {
+        let Some(vv) = scan::latest(session, &key)? else {
+            continue;
+        };
+        if vv.timestamp <= since {
+            continue;
+        }
+        let mut change = serde_json::json!({
+            "key": key,
+            "version": vv.version,
+            "timestamp": vv.timestamp,
+        });
+        if with_values {
+            change["value"] = value_to_json(vv.value);
+        }
+        changes.push(change);
+    }
+
+    Ok(serde_json::json!({
+        "branch": session.branch(),
+        "changes": changes,
+        "cursor": page.cursor,
+    }))
+}
+
 // ── Status ───────────────────────────────────────────────────────────────
 
 fn dispatch_status(session: &mut McpSession) -> Result<JsonValue> {
@@ -464,5 +1738,256 @@ fn dispatch_status(session: &mut McpSession) -> Result<JsonValue> {
         }
     }
 
+    // Include the pinned and indexed embedding models, if either is known
+    let pinned = session.embed_model().map(String::from);
+    let indexed = session.indexed_embed_model()?;
+    if pinned.is_some() || indexed.is_some() {
+        if let Some(obj) = result.as_object_mut() {
+            obj.insert(
+                "embed_model".to_string(),
+                serde_json::json!({
+                    "pinned": pinned,
+                    "index": indexed,
+                    "consistent": pinned.is_none() || pinned == indexed,
+                }),
+            );
+        }
+    }
+
+    // Include the auto-created session branch, if session isolation is on
+    if let Some(branch) = session.session_branch() {
+        if let Some(obj) = result.as_object_mut() {
+            obj.insert(
+                "session_branch".to_string(),
+                serde_json::json!({
+                    "name": branch.name,
+                    "base": branch.base,
+                    "on_end": branch.on_end.as_str(),
+                }),
+            );
+        }
+    }
+
     Ok(result)
 }
+
+// ── Orient ───────────────────────────────────────────────────────────────
+
+fn dispatch_orient(session: &mut McpSession) -> Result<JsonValue> {
+    let status = dispatch_status(session)?;
+    let mut sentences = Vec::new();
+
+    // Where we are
+    let mut location = format!("You're on branch '{}'", session.branch());
+    if session.space() != "default" {
+        location.push_str(&format!(" in space '{}'", session.space()));
+    }
+    if let Some(keys) = status["keys"].as_u64() {
+        location.push_str(&format!(" with {} {}", keys, plural(keys, "key", "keys")));
+    }
+    sentences.push(location);
+
+    if let Some(branches) = status["branches"].as_u64() {
+        let others = branches.saturating_sub(1);
+        sentences.push(match others {
+            0 => "No other branches exist".to_string(),
+            1 => "1 other branch exists".to_string(),
+            n => format!("{} other branches exist", n),
+        });
+    }
+
+    // Capabilities
+    match session.execute(Command::EmbedStatus) {
+        Ok(Output::EmbedStatus(embed)) if embed.auto_embed => {
+            let is_idle = embed.pending == 0
+                && embed.scheduler_active_tasks == 0
+                && embed.scheduler_queue_depth == 0;
+            sentences.push(if is_idle {
+                "Auto-embed is on and idle, so semantic search is up to date".to_string()
+            } else {
+                format!(
+                    "Auto-embed is on and busy ({} pending), so very recent writes may not be \
+                     searchable by meaning yet",
+                    embed.pending
+                )
+            });
+        }
+        _ => sentences.push("Auto-embed is off, so search is keyword-only".to_string()),
+    }
+
+    if session.is_read_only() {
+        sentences.push("The database is read-only; writes will be rejected".to_string());
+    }
+
+    // Recent activity
+    let cmd = Command::TimeRange {
+        branch: session.branch_id(),
+    };
+    if let Ok(output) = session.execute(cmd) {
+        let range = output_to_json(output);
+        sentences.push(match range["latest_ts"].as_u64() {
+            Some(latest) => format!("The last change on this branch was {}", describe_age(latest)),
+            None => "Nothing has been written to this branch yet".to_string(),
+        });
+    }
+
+    // Where a previous session left off
+    if let Some(saved_at) = read_document(session, SNAPSHOT_KEY)?
+        .and_then(|snapshot| snapshot["saved_at"].as_u64())
+    {
+        sentences.push(format!(
+            "A snapshot was saved {}; recall '{}' to see where it left off",
+            describe_age(saved_at),
+            SNAPSHOT_KEY
+        ));
+    }
+
+    let mut summary = sentences.join(". ");
+    summary.push('.');
+    Ok(serde_json::json!({ "summary": summary }))
+}
+
+fn plural<'a>(n: u64, one: &'a str, many: &'a str) -> &'a str {
+    if n == 1 {
+        one
+    } else {
+        many
+    }
+}
+
+/// Describe a microsecond timestamp relative to now, e.g. "5 minutes ago".
+fn describe_age(ts_micros: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+    let secs = now.saturating_sub(ts_micros) / 1_000_000;
+
+    let (n, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (secs / 60, "minute"),
+        3_600..=86_399 => (secs / 3_600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+// ── Saved Queries ────────────────────────────────────────────────────────
+
+/// Prefix of the documents holding saved queries.
+const QUERY_PREFIX: &str = "__query:";
+
+/// strata_search arguments a saved query may hold.
+const QUERY_PARAMS: &[&str] = &["query", "k", "group_by_prefix", "metadata", "explain"];
+
+fn dispatch_run_query(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let action = get_optional_string(&args, "action").unwrap_or_else(|| "run".to_string());
+
+    match action.as_str() {
+        "run" => {
+            let name = get_string_arg(&args, "name")?;
+            match read_document(session, &query_key(&name))? {
+                Some(JsonValue::Object(params)) => dispatch_search(session, params),
+                _ => Err(McpError::InvalidArg {
+                    name: "name".to_string(),
+                    reason: format!(
+                        "No saved query named '{}'. Use action 'list' to see them.",
+                        name
+                    ),
+                }),
+            }
+        }
+
+        "save" => {
+            let name = get_string_arg(&args, "name")?;
+            if name.is_empty() {
+                return Err(McpError::InvalidArg {
+                    name: "name".to_string(),
+                    reason: "must not be empty".to_string(),
+                });
+            }
+            let params = match args.get("params") {
+                Some(JsonValue::Object(map)) => map.clone(),
+                Some(_) => {
+                    return Err(McpError::InvalidArg {
+                        name: "params".to_string(),
+                        reason: "Expected an object of strata_search arguments".to_string(),
+                    })
+                }
+                None => return Err(McpError::MissingArg("params".to_string())),
+            };
+            if let Some(unknown) = params.keys().find(|p| !QUERY_PARAMS.contains(&p.as_str())) {
+                return Err(McpError::InvalidArg {
+                    name: "params".to_string(),
+                    reason: format!(
+                        "'{}' is not a strata_search argument. Use: {}",
+                        unknown,
+                        QUERY_PARAMS.join(", ")
+                    ),
+                });
+            }
+            if !params.get("query").is_some_and(JsonValue::is_string) {
+                return Err(McpError::InvalidArg {
+                    name: "params".to_string(),
+                    reason: "'query' must be given as a string".to_string(),
+                });
+            }
+
+            let cmd = Command::JsonSet {
+                branch: session.branch_id(),
+                space: session.space_id(),
+                key: query_key(&name),
+                path: "$".to_string(),
+                value: json_to_value(JsonValue::Object(params.clone()))?,
+            };
+            let version = match session.execute(cmd)? {
+                Output::Version(v) => serde_json::json!(v),
+                other => output_to_json(other),
+            };
+
+            Ok(serde_json::json!({
+                "name": name,
+                "params": params,
+                "version": version,
+                "saved": true,
+            }))
+        }
+
+        "list" => {
+            let mut queries = Vec::new();
+            for key in scan::list_reserved(session, QUERY_PREFIX)? {
+                if let Some(params) = read_document(session, &key)? {
+                    queries.push(serde_json::json!({
+                        "name": &key[QUERY_PREFIX.len()..],
+                        "params": params,
+                    }));
+                }
+            }
+            Ok(JsonValue::Array(queries))
+        }
+
+        "delete" => {
+            let name = get_string_arg(&args, "name")?;
+            let cmd = Command::JsonDelete {
+                branch: session.branch_id(),
+                space: session.space_id(),
+                key: query_key(&name),
+                path: "$".to_string(),
+            };
+            match session.execute(cmd)? {
+                Output::Uint(n) => Ok(serde_json::json!({ "deleted": n > 0 })),
+                other => Ok(output_to_json(other)),
+            }
+        }
+
+        other => Err(McpError::InvalidArg {
+            name: "action".to_string(),
+            reason: format!("Unknown action '{}'. Use: run, save, list, or delete.", other),
+        }),
+    }
+}
+
+/// Key of the document holding the saved query `name`.
+fn query_key(name: &str) -> String {
+    format!("{}{}", QUERY_PREFIX, name)
+}
@@ -0,0 +1,157 @@
+//! Attached reference databases.
+//!
+//! Tool: strata_attach
+//!
+//! Opens another Strata database read-only under an alias, so an agent can
+//! consult a reference database while it writes to its working one. A `key`
+//! argument written `alias:key` sends an agent tool call to the attached
+//! database, on its default branch and space; writes there are rejected.
+//! An alias can't shadow local keys: attaching is refused while local keys
+//! start with `alias:`, and a qualified key that also exists locally is an
+//! error rather than a read of either database. Only registered when the
+//! server is started with `--allow-attach`.
+
+use serde_json::{Map, Value as JsonValue};
+use std::path::PathBuf;
+use stratadb::{AccessMode, OpenOptions, Strata};
+
+use crate::convert::{get_optional_bool, get_optional_string, get_string_arg};
+use crate::error::{McpError, Result};
+use crate::session::McpSession;
+use crate::tools::{scan, ToolAnnotations, ToolDef};
+
+/// Name of the attach tool.
+pub const NAME: &str = "strata_attach";
+
+/// Longest alias accepted.
+const MAX_ALIAS_LEN: usize = 32;
+
+/// Get the attach tool definition.
+pub fn tool() -> ToolDef {
+    ToolDef::new(
+        NAME,
+        "Attach another Strata database read-only under an alias, to consult it while working \
+         in this one. Afterwards write keys as 'alias:key' in strata_recall, strata_history, \
+         and the other tools taking a 'key' to read the attached database's default branch and \
+         space; writes to it are rejected. An alias that local keys already start with \
+         ('alias:...') is refused. detach: true removes the alias. Returns { attached: \
+         [{ alias, path }] }, every database attached now.",
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "alias": {
+                    "type": "string",
+                    "description": "Name to qualify keys with: letters, digits, '-', or '_'"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory of the database to attach; not needed to detach"
+                },
+                "detach": {
+                    "type": "boolean",
+                    "description": "Detach the alias instead of attaching it"
+                }
+            },
+            "required": ["alias"]
+        }),
+    )
+    .with_annotations(ToolAnnotations::write(false, true))
+    .with_example(
+        serde_json::json!({"alias": "ref", "path": "/data/reference-db"}),
+        serde_json::json!({"attached": [{"alias": "ref", "path": "/data/reference-db"}]}),
+    )
+}
+
+/// Dispatch an attach call.
+pub fn dispatch(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let alias = get_string_arg(&args, "alias")?;
+    let valid = !alias.is_empty()
+        && alias.len() <= MAX_ALIAS_LEN
+        && alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(McpError::InvalidArg {
+            name: "alias".to_string(),
+            reason: format!(
+                "must be 1-{} characters: letters, digits, '-', or '_'",
+                MAX_ALIAS_LEN
+            ),
+        });
+    }
+
+    if get_optional_bool(&args, "detach").unwrap_or(false) {
+        if !session.detach(&alias) {
+            return Err(McpError::InvalidArg {
+                name: "alias".to_string(),
+                reason: format!("no database is attached as '{}'", alias),
+            });
+        }
+    } else {
+        let path = get_optional_string(&args, "path")
+            .ok_or_else(|| McpError::MissingArg("path".to_string()))?;
+        let path = PathBuf::from(path);
+        let shadowed = scan::list_page(session, Some(format!("{}:", alias)), None, 1)?;
+        if let Some(key) = shadowed.keys.first() {
+            return Err(McpError::InvalidArg {
+                name: "alias".to_string(),
+                reason: format!(
+                    "local keys such as '{}' already start with '{}:'; pick another alias",
+                    key, alias
+                ),
+            });
+        }
+        if !path.is_dir() {
+            return Err(McpError::InvalidArg {
+                name: "path".to_string(),
+                reason: format!("no database directory at '{}'", path.display()),
+            });
+        }
+        let opts = OpenOptions::new().access_mode(AccessMode::ReadOnly);
+        let db = Strata::open_with(&path, opts).map_err(|e| McpError::InvalidArg {
+            name: "path".to_string(),
+            reason: format!("failed to open '{}' read-only: {}", path.display(), e),
+        })?;
+        session.attach(&alias, path, db);
+    }
+
+    let attached: Vec<JsonValue> = session
+        .attached()
+        .map(|(alias, path)| {
+            serde_json::json!({ "alias": alias, "path": path.display().to_string() })
+        })
+        .collect();
+    Ok(serde_json::json!({ "attached": attached }))
+}
+
+/// Point a call's `alias:key` argument at the attached database, returning
+/// a session on it and leaving `key` unqualified, or `None` when the key
+/// names no attached alias.
+///
+/// Fails when the qualified key was also written locally after attaching,
+/// since it could mean either database.
+pub fn route(
+    session: &mut McpSession,
+    args: &mut Map<String, JsonValue>,
+) -> Result<Option<McpSession>> {
+    let Some(qualified) = args.get("key").and_then(JsonValue::as_str) else {
+        return Ok(None);
+    };
+    let qualified = qualified.to_string();
+    let Some((alias, key)) = qualified.split_once(':') else {
+        return Ok(None);
+    };
+    let Some(attached) = session.attached_session(alias) else {
+        return Ok(None);
+    };
+    if scan::latest(session, &qualified)?.is_some() {
+        return Err(McpError::InvalidArg {
+            name: "key".to_string(),
+            reason: format!(
+                "'{}' is a local key and also names a key of the database attached as '{}'; \
+                 detach '{}' to use the local key",
+                qualified, alias, alias
+            ),
+        });
+    }
+    args.insert("key".to_string(), JsonValue::String(key.to_string()));
+    Ok(Some(attached))
+}
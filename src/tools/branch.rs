@@ -2,18 +2,31 @@
 //!
 //! Tools: strata_branch_create, strata_branch_get, strata_branch_list, strata_branch_exists,
 //!        strata_branch_delete, strata_branch_fork, strata_branch_diff, strata_branch_merge,
-//!        strata_branch_switch
+//!        strata_branch_switch, strata_branch_normalize, strata_branch_compare,
+//!        strata_branch_divergence, strata_branch_changes, strata_branch_tree,
+//!        strata_branch_keys
+
+use std::collections::{BTreeMap, BTreeSet};
 
 use serde_json::{Map, Value as JsonValue};
 use stratadb::{BranchId, Command, MergeStrategy};
 
 use crate::convert::{
-    get_optional_string, get_optional_u64, get_string_arg, json_to_value, output_to_json,
+    get_optional_bool, get_optional_string, get_optional_u64, get_string_arg, get_u64_arg,
+    json_to_value, output_to_json, value_to_json,
 };
 use crate::error::{McpError, Result};
 use crate::schema;
-use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::session::{
+    validate_branch_name, McpSession, BRANCH_NAME_RULES, MAX_BRANCH_NAME_LEN, SYSTEM_BRANCH,
+};
+use crate::tools::{scan, ToolDef};
+
+/// Most branches diffed by one strata_branch_divergence call unless `limit` says otherwise.
+const DEFAULT_DIVERGENCE_LIMIT: u64 = 50;
+
+/// Keys per page from strata_branch_keys unless `limit` says otherwise.
+const DEFAULT_KEY_SET_LIMIT: u64 = 100;
 
 /// Get all branch tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -91,9 +104,125 @@ pub fn tools() -> Vec<ToolDef> {
                 required: { "branch": string }
             }),
         ),
+        ToolDef::new(
+            "strata_branch_normalize",
+            "Turn a proposed branch name into a valid one and check whether it's free. Branch \
+             names are 1-64 characters of ASCII letters, digits, '-', '_', '.', or '/', starting \
+             with a letter or digit; other characters (such as spaces) become '-'. Returns \
+             { input, normalized, valid, available, rules } — 'valid' says whether the input was \
+             already valid, 'available' whether no branch named 'normalized' exists yet.",
+            schema!(object {
+                required: { "name": string }
+            }),
+        ),
+        ToolDef::new(
+            "strata_branch_compare",
+            "Compare one document key, or every key under a prefix, across two or more branches. \
+             Pass 'branches' and exactly one of 'key' or 'prefix'. Returns { branches, compared, \
+             divergent, keys: [{ key, diverges, values }] } where 'values' maps each branch to \
+             its value (null if the key is missing there). Pass only_divergent: true to list \
+             just the keys whose values differ.",
+            schema!(object {
+                required: { "branches": array_string },
+                optional: { "key": string, "prefix": string, "only_divergent": boolean }
+            }),
+        ),
+        ToolDef::new(
+            "strata_branch_divergence",
+            "List branches sorted by how far they have drifted from a base branch (default: \
+             'default'), most changed first. Each entry is { branch, added, removed, modified, \
+             total }. Every branch is diffed; the 'limit' most changed are returned (default \
+             50) and the rest are counted in 'skipped'.",
+            schema!(object {
+                optional: { "base": string, "limit": integer }
+            }),
+        ),
+        ToolDef::new(
+            "strata_branch_changes",
+            "See what changed on the current branch since a point in its own history. 'since' \
+             is a timestamp in microseconds since epoch (strata_history gives the branch's \
+             range). Returns { since, summary: { added, removed, modified }, added, removed, \
+             modified } where each list holds the document keys that changed that way. Pass \
+             detail: true to get { key, before, after } entries instead of bare keys.",
+            schema!(object {
+                required: { "since": integer },
+                optional: { "detail": boolean }
+            }),
+        ),
+        ToolDef::new(
+            "strata_branch_tree",
+            "Show every branch arranged by fork relationships. Returns { roots: [node] } where \
+             each node is { name, status, keys, children: [node] } and children are the \
+             branches forked from it. A branch whose parent no longer exists is listed as a root.",
+            schema!(object {}),
+        ),
+        ToolDef::new(
+            "strata_branch_keys",
+            "Compare which document keys exist on two branches, ignoring values: cheaper than \
+             strata_branch_diff when only presence matters. Returns { branch_a, branch_b, a_only, b_only, \
+             common, counts: { a_only, b_only, common }, cursor }. Keys are paged in sorted order \
+             across all three lists, 'limit' per page (default 100); 'counts' always covers \
+             every key. Pass the returned 'cursor' to continue until it is null. Optional \
+             'prefix' narrows both branches.",
+            schema!(object {
+                required: { "branch_a": string, "branch_b": string },
+                optional: { "prefix": string, "limit": integer, "cursor": string }
+            }),
+        ),
     ]
 }
 
+/// Map a proposed branch name onto the naming rules.
+///
+/// Disallowed characters become `-`, runs of `-` collapse, leading
+/// characters that can't start a name are dropped, and the result is capped
+/// at the maximum length. An input with nothing usable becomes `branch`.
+pub fn normalize_branch_name(name: &str) -> String {
+    let mut normalized = String::new();
+    for c in name.trim().chars() {
+        let c = if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '/') {
+            c
+        } else {
+            '-'
+        };
+        if c == '-' && normalized.ends_with('-') {
+            continue;
+        }
+        normalized.push(c);
+    }
+
+    let normalized = normalized.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+    let mut normalized: String = normalized.chars().take(MAX_BRANCH_NAME_LEN).collect();
+    while normalized.ends_with('-') {
+        normalized.pop();
+    }
+    if normalized.is_empty() {
+        "branch".to_string()
+    } else {
+        normalized
+    }
+}
+
+/// Report archived branches with status `archived` in branch info JSON,
+/// either one branch object or an array of them. An array leaves out the
+/// server's [`SYSTEM_BRANCH`].
+pub fn mark_archived(session: &mut McpSession, branches: &mut JsonValue) -> Result<()> {
+    let archived = session.archived_branches()?;
+    let infos = match branches {
+        JsonValue::Array(infos) => {
+            infos.retain(|info| info["id"] != SYSTEM_BRANCH);
+            infos.iter_mut().collect()
+        }
+        info => vec![info],
+    };
+    for info in infos {
+        if info["id"].as_str().is_some_and(|id| archived.contains(id)) {
+            info["status"] = JsonValue::String("archived".to_string());
+        }
+    }
+    Ok(())
+}
+
 /// Dispatch a branch tool call.
 pub fn dispatch(
     session: &mut McpSession,
@@ -103,6 +232,9 @@ pub fn dispatch(
     match name {
         "strata_branch_create" => {
             let branch_id = get_optional_string(&args, "branch_id");
+            if let Some(name) = &branch_id {
+                validate_branch_name(name)?;
+            }
             let metadata = match args.get("metadata") {
                 Some(serde_json::Value::Null) | None => None,
                 Some(v) => Some(json_to_value(v.clone())?),
@@ -122,8 +254,9 @@ pub fn dispatch(
             let cmd = Command::BranchGet {
                 branch: BranchId::from(branch),
             };
-            let output = session.execute(cmd)?;
-            Ok(output_to_json(output))
+            let mut info = output_to_json(session.execute(cmd)?);
+            mark_archived(session, &mut info)?;
+            Ok(info)
         }
 
         "strata_branch_list" => {
@@ -135,8 +268,9 @@ pub fn dispatch(
                 limit,
                 offset,
             };
-            let output = session.execute(cmd)?;
-            Ok(output_to_json(output))
+            let mut branches = output_to_json(session.execute(cmd)?);
+            mark_archived(session, &mut branches)?;
+            Ok(branches)
         }
 
         "strata_branch_exists" => {
@@ -274,6 +408,344 @@ pub fn dispatch(
             }))
         }
 
+        "strata_branch_normalize" => {
+            let input = get_string_arg(&args, "name")?;
+            let normalized = normalize_branch_name(&input);
+
+            let cmd = Command::BranchExists {
+                branch: BranchId::from(normalized.clone()),
+            };
+            let exists = matches!(session.execute(cmd)?, stratadb::Output::Bool(true));
+
+            Ok(serde_json::json!({
+                "input": input,
+                "normalized": normalized,
+                "valid": validate_branch_name(&input).is_ok(),
+                "available": !exists,
+                "rules": BRANCH_NAME_RULES,
+            }))
+        }
+
+        "strata_branch_compare" => compare(session, args),
+        "strata_branch_divergence" => divergence(session, args),
+        "strata_branch_changes" => changes(session, args),
+        "strata_branch_tree" => tree(session),
+        "strata_branch_keys" => key_sets(session, args),
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+/// Compare a key (or every key under a prefix) across several branches.
+fn compare(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let branches: Vec<String> = args
+        .get("branches")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| McpError::MissingArg("branches".to_string()))?
+        .iter()
+        .map(|v| {
+            v.as_str().map(String::from).ok_or_else(|| McpError::InvalidArg {
+                name: "branches".to_string(),
+                reason: "Branch names must be strings".to_string(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    if branches.len() < 2 {
+        return Err(McpError::InvalidArg {
+            name: "branches".to_string(),
+            reason: "Give at least two branches to compare".to_string(),
+        });
+    }
+
+    let key = get_optional_string(&args, "key");
+    let prefix = get_optional_string(&args, "prefix");
+    if key.is_some() == prefix.is_some() {
+        return Err(McpError::InvalidArg {
+            name: "key".to_string(),
+            reason: "Pass exactly one of 'key' or 'prefix'".to_string(),
+        });
+    }
+    let only_divergent = get_optional_bool(&args, "only_divergent").unwrap_or(false);
+
+    for branch in &branches {
+        let cmd = Command::BranchExists {
+            branch: BranchId::from(branch.clone()),
+        };
+        if !matches!(session.execute(cmd)?, stratadb::Output::Bool(true)) {
+            return Err(McpError::BranchNotFound(branch.clone()));
+        }
+    }
+
+    // Union of keys present on any branch, sorted for stable output.
+    let keys: BTreeSet<String> = match (key, prefix) {
+        (Some(key), _) => BTreeSet::from([key]),
+        (None, prefix) => {
+            let mut keys = BTreeSet::new();
+            for branch in &branches {
+                let branch = Some(BranchId::from(branch.clone()));
+                keys.extend(scan::list_all_on(session, branch, prefix.clone())?);
+            }
+            keys
+        }
+    };
+
+    let mut entries = Vec::new();
+    let mut divergent = 0;
+    for key in &keys {
+        let mut values = Map::new();
+        for branch in &branches {
+            let value = scan::latest_on(session, Some(BranchId::from(branch.clone())), key)?
+                .map_or(JsonValue::Null, |vv| value_to_json(vv.value));
+            values.insert(branch.clone(), value);
+        }
+
+        let mut distinct = values.values();
+        let first = distinct.next();
+        let diverges = distinct.any(|v| Some(v) != first);
+        if diverges {
+            divergent += 1;
+        } else if only_divergent {
+            continue;
+        }
+        entries.push(serde_json::json!({
+            "key": key,
+            "diverges": diverges,
+            "values": values,
+        }));
+    }
+
+    Ok(serde_json::json!({
+        "branches": branches,
+        "compared": keys.len(),
+        "divergent": divergent,
+        "keys": entries,
+    }))
+}
+
+/// Rank branches by the size of their diff against a base branch.
+fn divergence(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let base = get_optional_string(&args, "base").unwrap_or_else(|| "default".to_string());
+    let limit = get_optional_u64(&args, "limit").unwrap_or(DEFAULT_DIVERGENCE_LIMIT);
+
+    let cmd = Command::BranchExists {
+        branch: BranchId::from(base.clone()),
+    };
+    if !matches!(session.execute(cmd)?, stratadb::Output::Bool(true)) {
+        return Err(McpError::BranchNotFound(base));
+    }
+
+    let cmd = Command::BranchList {
+        state: None,
+        limit: None,
+        offset: None,
+    };
+    let names: Vec<String> = match session.execute(cmd)? {
+        stratadb::Output::BranchInfoList(branches) => branches
+            .into_iter()
+            .map(|bi| bi.info.id.as_str().to_string())
+            .filter(|name| *name != base && name != SYSTEM_BRANCH)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    // Rank every branch before cutting to the limit, so the most changed are kept
+    let mut entries = Vec::with_capacity(names.len());
+    for name in names {
+        let summary = session.diff_branches(&base, &name)?.summary;
+        let (added, removed, modified) =
+            (summary.total_added, summary.total_removed, summary.total_modified);
+        entries.push(serde_json::json!({
+            "branch": name,
+            "added": added,
+            "removed": removed,
+            "modified": modified,
+            "total": added + removed + modified,
+        }));
+    }
+    entries.sort_by(|a, b| {
+        b["total"].as_u64().cmp(&a["total"].as_u64()).then_with(|| {
+            a["branch"].as_str().cmp(&b["branch"].as_str())
+        })
+    });
+    let skipped = entries.len().saturating_sub(limit as usize);
+    entries.truncate(limit as usize);
+
+    Ok(serde_json::json!({
+        "base": base,
+        "branches": entries,
+        "skipped": skipped,
+    }))
+}
+
+/// Diff the current branch against its own state at an earlier time.
+fn changes(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let since = get_u64_arg(&args, "since")?;
+    let detail = get_optional_bool(&args, "detail").unwrap_or(false);
+
+    let before: BTreeSet<String> = scan::list_all_at(session, since)?.into_iter().collect();
+    let after: BTreeSet<String> = scan::list_all(session, None)?.into_iter().collect();
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut modified = Vec::new();
+    for key in before.union(&after) {
+        let then = if before.contains(key) {
+            scan::latest_at(session, key, since)?
+        } else {
+            None
+        };
+        let now = if after.contains(key) {
+            scan::latest(session, key)?
+        } else {
+            None
+        };
+        let list = match (&then, &now) {
+            (None, Some(_)) => &mut added,
+            (Some(_), None) => &mut removed,
+            (Some(a), Some(b)) if a.version != b.version => &mut modified,
+            _ => continue,
+        };
+        list.push(if detail {
+            serde_json::json!({
+                "key": key,
+                "before": then.map(|vv| value_to_json(vv.value)),
+                "after": now.map(|vv| value_to_json(vv.value)),
+            })
+        } else {
+            JsonValue::String(key.clone())
+        });
+    }
+
+    Ok(serde_json::json!({
+        "since": since,
+        "summary": {
+            "added": added.len(),
+            "removed": removed.len(),
+            "modified": modified.len(),
+        },
+        "added": added,
+        "removed": removed,
+        "modified": modified,
+    }))
+}
+
+/// Build the fork tree of every branch from their parent ids.
+fn tree(session: &mut McpSession) -> Result<JsonValue> {
+    let cmd = Command::BranchList {
+        state: None,
+        limit: None,
+        offset: None,
+    };
+    let mut branches = output_to_json(session.execute(cmd)?);
+    mark_archived(session, &mut branches)?;
+
+    let mut nodes = BTreeMap::new();
+    let mut parents = BTreeMap::new();
+    for info in branches.as_array().into_iter().flatten() {
+        let Some(name) = info["id"].as_str() else {
+            continue;
+        };
+        let branch = Some(BranchId::from(name.to_string()));
+        let keys = scan::list_all_on(session, branch, None)?.len();
+        nodes.insert(
+            name.to_string(),
+            serde_json::json!({
+                "name": name,
+                "status": info["status"],
+                "keys": keys,
+            }),
+        );
+        if let Some(parent) = info["parent_id"].as_str() {
+            parents.insert(name.to_string(), parent.to_string());
+        }
+    }
+
+    let mut children: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut roots = Vec::new();
+    for name in nodes.keys() {
+        match parents.get(name) {
+            Some(parent) if nodes.contains_key(parent) => {
+                children.entry(parent.clone()).or_default().push(name.clone())
+            }
+            _ => roots.push(name.clone()),
+        }
+    }
+
+    let roots: Vec<JsonValue> = roots
+        .iter()
+        .map(|name| tree_node(name, &mut nodes, &children))
+        .collect();
+    Ok(serde_json::json!({ "roots": roots }))
+}
+
+/// Take `name`'s node out of `nodes` and attach its subtree.
+fn tree_node(
+    name: &str,
+    nodes: &mut BTreeMap<String, JsonValue>,
+    children: &BTreeMap<String, Vec<String>>,
+) -> JsonValue {
+    let mut node = nodes.remove(name).unwrap_or(JsonValue::Null);
+    let kids: Vec<JsonValue> = children
+        .get(name)
+        .into_iter()
+        .flatten()
+        .map(|child| tree_node(child, nodes, children))
+        .collect();
+    node["children"] = JsonValue::Array(kids);
+    node
+}
+
+/// Split the document keys of two branches into A-only, B-only, and common.
+fn key_sets(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let branch_a = get_string_arg(&args, "branch_a")?;
+    let branch_b = get_string_arg(&args, "branch_b")?;
+    let prefix = get_optional_string(&args, "prefix");
+    let limit = get_optional_u64(&args, "limit").unwrap_or(DEFAULT_KEY_SET_LIMIT).max(1) as usize;
+    let cursor = get_optional_string(&args, "cursor");
+
+    let mut listed = Vec::new();
+    for branch in [&branch_a, &branch_b] {
+        let id = BranchId::from(branch.clone());
+        let cmd = Command::BranchExists { branch: id.clone() };
+        if !matches!(session.execute(cmd)?, stratadb::Output::Bool(true)) {
+            return Err(McpError::BranchNotFound(branch.clone()));
+        }
+        let keys: BTreeSet<String> =
+            scan::list_all_on(session, Some(id), prefix.clone())?.into_iter().collect();
+        listed.push(keys);
+    }
+    let (a, b) = (&listed[0], &listed[1]);
+
+    let mut a_only = Vec::new();
+    let mut b_only = Vec::new();
+    let mut common = Vec::new();
+    let mut remaining = a.union(b).filter(|key| match &cursor {
+        Some(after) => key.as_str() > after.as_str(),
+        None => true,
+    });
+    let mut last = None;
+    for key in remaining.by_ref().take(limit) {
+        match (a.contains(key), b.contains(key)) {
+            (true, false) => a_only.push(key),
+            (false, true) => b_only.push(key),
+            _ => common.push(key),
+        }
+        last = Some(key);
+    }
+    let next = remaining.next().and(last);
+
+    let shared = a.intersection(b).count();
+    Ok(serde_json::json!({
+        "branch_a": branch_a,
+        "branch_b": branch_b,
+        "a_only": a_only,
+        "b_only": b_only,
+        "common": common,
+        "counts": {
+            "a_only": a.len() - shared,
+            "b_only": b.len() - shared,
+            "common": shared,
+        },
+        "cursor": next,
+    }))
+}
@@ -1,15 +1,24 @@
 //! Branch bundle tools for data portability.
 //!
-//! Tools: strata_bundle_export, strata_bundle_import, strata_bundle_validate
+//! Tools: strata_bundle_export, strata_bundle_import, strata_bundle_validate,
+//!        strata_bundle_inspect
+//!
+//! An export with `after`/`before` is incremental: the documents changed in
+//! that window are copied to a short-lived staging branch, and that branch is
+//! exported. Importing with `into` lays such a bundle over an existing branch.
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::Command;
+use std::sync::OnceLock;
+use stratadb::{BranchId, Command, MergeStrategy, Output, Strata};
 
-use crate::convert::{get_string_arg, output_to_json};
+use crate::convert::{get_optional_string, get_optional_u64, get_string_arg, output_to_json};
 use crate::error::{McpError, Result};
 use crate::schema;
+use crate::server::SERVER_VERSION;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::{scan, ToolAnnotations, ToolDef};
+use crate::ulid;
+
 
 /// Get all bundle tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -17,17 +26,26 @@ pub fn tools() -> Vec<ToolDef> {
         ToolDef::new(
             "strata_bundle_export",
             "Export a branch to a portable bundle file. The bundle contains all data \
-             and can be imported into another database. Returns the file path and statistics.",
+             and can be imported into another database. Returns the file path and statistics. \
+             Pass after and/or before (microsecond timestamps, after inclusive, before \
+             exclusive) for an incremental bundle: only documents written in that window, \
+             each at its last value within it. Incremental bundles carry documents only, \
+             not deletions or KV/state/event/vector data.",
             schema!(object {
-                required: { "branch": string, "path": string }
+                required: { "branch": string, "path": string },
+                optional: { "after": integer, "before": integer }
             }),
         ),
         ToolDef::new(
             "strata_bundle_import",
             "Import a branch from a bundle file. Creates a new branch with all the \
-             data from the bundle. Returns the imported branch ID and statistics.",
+             data from the bundle. Returns the imported branch ID and statistics. \
+             Pass into to apply the bundle on top of an existing branch instead: the \
+             imported data is merged into it (bundle wins on conflicts) and no new branch \
+             is left behind. Use this to apply incremental bundles over a full one.",
             schema!(object {
-                required: { "path": string }
+                required: { "path": string },
+                optional: { "into": string }
             }),
         ),
         ToolDef::new(
@@ -38,6 +56,21 @@ pub fn tools() -> Vec<ToolDef> {
                 required: { "path": string }
             }),
         ),
+        ToolDef::new(
+            "strata_bundle_inspect",
+            "Pre-flight check before strata_bundle_import: inspect a bundle file without \
+             importing it and report whether this server can import it. Returns { path, \
+             branch_id, format_version, entry_count, checksums_valid, branch_exists, \
+             engine_format, server_version, engine_version, compatible, problems } where \
+             'problems' lists every reason the import would fail (a format version other than \
+             the engine_format this engine writes, \
+             bad checksums, or a branch of the same name already existing; use \
+             strata_bundle_import with 'into' to merge over an existing branch instead).",
+            schema!(object {
+                required: { "path": string }
+            }),
+        )
+        .with_annotations(ToolAnnotations::read_only()),
     ]
 }
 
@@ -51,6 +84,11 @@ pub fn dispatch(
         "strata_bundle_export" => {
             let branch_id = get_string_arg(&args, "branch")?;
             let path = get_string_arg(&args, "path")?;
+            let after = get_optional_u64(&args, "after");
+            let before = get_optional_u64(&args, "before");
+            if after.is_some() || before.is_some() {
+                return export_incremental(session, &branch_id, path, after, before);
+            }
 
             let cmd = Command::BranchExport { branch_id, path };
             let output = session.execute(cmd)?;
@@ -59,6 +97,9 @@ pub fn dispatch(
 
         "strata_bundle_import" => {
             let path = get_string_arg(&args, "path")?;
+            if let Some(into) = get_optional_string(&args, "into") {
+                return import_into(session, path, &into);
+            }
 
             let cmd = Command::BranchImport { path };
             let output = session.execute(cmd)?;
@@ -73,6 +114,228 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_bundle_inspect" => {
+            let path = get_string_arg(&args, "path")?;
+            inspect(session, path)
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+/// Report a bundle's metadata and whether this server can import it.
+fn inspect(session: &mut McpSession, path: String) -> Result<JsonValue> {
+    let cmd = Command::BranchBundleValidate { path: path.clone() };
+    let mut report = output_to_json(session.execute(cmd)?);
+
+    let mut problems = Vec::new();
+    let engine_format = engine_bundle_format();
+    match (report["format_version"].as_u64(), engine_format) {
+        (None, _) => problems.push("bundle has no format version".to_string()),
+        (Some(v), Ok(engine)) if v == engine => {}
+        (Some(v), Ok(engine)) => problems.push(format!(
+            "bundle format version {} is not supported (this server imports version {})",
+            v, engine
+        )),
+        (Some(_), Err(e)) => problems.push(format!(
+            "the engine's bundle format version is unknown: {}",
+            e
+        )),
+    }
+    if report["checksums_valid"] != JsonValue::Bool(true) {
+        problems.push("bundle checksums do not match its contents".to_string());
+    }
+    let branch_exists = match report["branch_id"].as_str() {
+        Some(branch) => {
+            let cmd = Command::BranchExists {
+                branch: BranchId::from(branch.to_string()),
+            };
+            let exists = matches!(session.execute(cmd)?, Output::Bool(true));
+            if exists {
+                problems.push(format!(
+                    "branch '{}' already exists; import with 'into' to merge over it",
+                    branch
+                ));
+            }
+            exists
+        }
+        None => false,
+    };
+    let engine_version = match session.execute(Command::Info)? {
+        Output::DatabaseInfo(info) => serde_json::json!(info.version),
+        _ => JsonValue::Null,
+    };
+
+    if let Some(obj) = report.as_object_mut() {
+        obj.insert("path".to_string(), serde_json::json!(path));
+        obj.insert("branch_exists".to_string(), JsonValue::Bool(branch_exists));
+        let engine_format = engine_format.ok();
+        obj.insert("engine_format".to_string(), serde_json::json!(engine_format));
+        obj.insert("server_version".to_string(), serde_json::json!(SERVER_VERSION));
+        obj.insert("engine_version".to_string(), engine_version);
+        obj.insert("compatible".to_string(), JsonValue::Bool(problems.is_empty()));
+        obj.insert("problems".to_string(), serde_json::json!(problems));
+    }
+    Ok(report)
+}
+
+/// The bundle format version the linked engine writes, found once by
+/// exporting an empty branch of a scratch database and validating it.
+fn engine_bundle_format() -> std::result::Result<u64, String> {
+    static FORMAT: OnceLock<std::result::Result<u64, String>> = OnceLock::new();
+    FORMAT
+        .get_or_init(|| {
+            let db = Strata::cache().map_err(|e| e.to_string())?;
+            let mut scratch = McpSession::new(db);
+            let path = std::env::temp_dir()
+                .join(format!("strata-mcp-format-{}.bundle", ulid::generate()))
+                .display()
+                .to_string();
+            let cmd = Command::BranchExport {
+                branch_id: "default".to_string(),
+                path: path.clone(),
+            };
+            let validated = scratch.execute(cmd).and_then(|_| {
+                scratch.execute(Command::BranchBundleValidate { path: path.clone() })
+            });
+            let _ = std::fs::remove_file(&path);
+            output_to_json(validated.map_err(|e| e.to_string())?)["format_version"]
+                .as_u64()
+                .ok_or_else(|| "its bundles carry no format version".to_string())
+        })
+        .clone()
+}
+
+/// Export the documents `branch` changed in `[after, before)`.
+fn export_incremental(
+    session: &mut McpSession,
+    branch: &str,
+    path: String,
+    after: Option<u64>,
+    before: Option<u64>,
+) -> Result<JsonValue> {
+    let after = after.unwrap_or(0);
+    let before = before.unwrap_or(u64::MAX);
+    if after >= before {
+        return Err(McpError::InvalidArg {
+            name: "after".to_string(),
+            reason: "must be earlier than before".to_string(),
+        });
+    }
+    let source = BranchId::from(branch.to_string());
+    if !matches!(
+        session.execute(Command::BranchExists {
+            branch: source.clone()
+        })?,
+        Output::Bool(true)
+    ) {
+        return Err(McpError::BranchNotFound(branch.to_string()));
+    }
+    // Staging writes a branch, so fail before creating one
+    session.check_write_access("BranchExport")?;
+
+    let staging = format!("incr-{}", ulid::generate().to_lowercase());
+    session.execute(Command::BranchCreate {
+        branch_id: Some(staging.clone()),
+        metadata: None,
+    })?;
+    let result = stage_window(session, &source, &staging, after, before).and_then(|keys| {
+        let cmd = Command::BranchExport {
+            branch_id: staging.clone(),
+            path,
+        };
+        let mut exported = output_to_json(session.execute(cmd)?);
+        exported["source_branch"] = serde_json::json!(branch);
+        exported["incremental"] = serde_json::json!({
+            "after": after,
+            "before": (before != u64::MAX).then_some(before),
+            "keys": keys,
+        });
+        Ok(exported)
+    });
+    // Remove the staging branch whether or not the export worked
+    let cleanup = session.execute(Command::BranchDelete {
+        branch: staging.into(),
+    });
+    let exported = result?;
+    cleanup?;
+    Ok(exported)
+}
+
+/// Copy each document of `source` whose last write in `[after, before)`
+/// falls in that window onto `staging`, in the same space. Returns the
+/// number of documents copied.
+fn stage_window(
+    session: &mut McpSession,
+    source: &BranchId,
+    staging: &str,
+    after: u64,
+    before: u64,
+) -> Result<u64> {
+    let spaces = output_to_json(session.execute(Command::SpaceList {
+        branch: Some(source.clone()),
+    })?);
+    let mut copied = 0;
+    for space in spaces.as_array().into_iter().flatten().filter_map(|s| s.as_str()) {
+        for key in scan::list_all_on_in(session, Some(source.clone()), space, None)? {
+            let cmd = Command::JsonGetv {
+                branch: Some(source.clone()),
+                space: Some(space.to_string()),
+                key: key.clone(),
+                as_of: None,
+            };
+            let Output::VersionHistory(Some(versions)) = session.execute(cmd)? else {
+                continue;
+            };
+            let Some(last) = versions
+                .into_iter()
+                .filter(|vv| vv.timestamp >= after && vv.timestamp < before)
+                .max_by_key(|vv| vv.version)
+            else {
+                continue;
+            };
+            session.execute(Command::JsonSet {
+                branch: Some(staging.to_string().into()),
+                space: Some(space.to_string()),
+                key,
+                path: "$".to_string(),
+                value: last.value,
+            })?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// Import a bundle and merge it into the existing branch `into`.
+fn import_into(session: &mut McpSession, path: String, into: &str) -> Result<JsonValue> {
+    let cmd = Command::BranchExists {
+        branch: BranchId::from(into.to_string()),
+    };
+    if !matches!(session.execute(cmd)?, Output::Bool(true)) {
+        return Err(McpError::BranchNotFound(into.to_string()));
+    }
+
+    let mut imported = output_to_json(session.execute(Command::BranchImport { path })?);
+    let Some(bundle_branch) = imported["branch_id"].as_str().map(String::from) else {
+        return Err(McpError::Internal("BranchImport returned no branch".to_string()));
+    };
+
+    // Remove the imported branch whether or not the merge worked
+    let original = session.branch().to_string();
+    let merged = session.switch_branch(into).and_then(|()| {
+        let merged = session.merge_branch(&bundle_branch, MergeStrategy::LastWriterWins);
+        session.switch_branch(&original)?;
+        merged
+    });
+    let cleanup = session.execute(Command::BranchDelete {
+        branch: bundle_branch.clone().into(),
+    });
+    let merged = merged?;
+    cleanup?;
+
+    imported["bundle_branch"] = serde_json::json!(bundle_branch);
+    imported["branch_id"] = serde_json::json!(into);
+    imported["keys_applied"] = serde_json::json!(merged.keys_applied);
+    Ok(imported)
+}
@@ -1,15 +1,27 @@
 //! Database-level tools.
 //!
-//! Tools: strata_db_ping, strata_db_info, strata_db_flush, strata_db_compact
+//! Tools: strata_db_ping, strata_db_info, strata_db_flush, strata_db_compact,
+//!        strata_db_time_range, strata_db_benchmark
+
+use std::time::Instant;
 
 use serde_json::{Map, Value as JsonValue};
 use stratadb::Command;
 
-use crate::convert::output_to_json;
-use crate::error::Result;
+use crate::convert::{get_optional_u64, get_string_arg, output_to_json};
+use crate::error::{McpError, Result};
 use crate::schema;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::{agent, ToolDef};
+
+/// Agent tools that can be benchmarked. Read-only, so repeating them is harmless.
+const BENCHMARKABLE_TOOLS: &[&str] = &["strata_recall", "strata_search", "strata_history"];
+
+/// Iterations run when none are requested.
+const DEFAULT_BENCHMARK_ITERATIONS: u64 = 10;
+
+/// Upper bound on iterations, so a benchmark can't monopolize the server.
+const MAX_BENCHMARK_ITERATIONS: u64 = 100;
 
 /// Get all database tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -45,6 +57,18 @@ pub fn tools() -> Vec<ToolDef> {
              if the branch has no data.",
             schema!(object {}),
         ),
+        ToolDef::new(
+            "strata_db_benchmark",
+            "Measure the server-side latency of a read operation, excluding client and LLM \
+             overhead. Runs 'tool' (strata_recall, strata_search, or strata_history) with 'args' \
+             'iterations' times (default 10, max 100) and returns { tool, iterations, min_us, \
+             max_us, mean_us, p95_us }. Use it to compare query parameters such as k, mode, or \
+             rerank.",
+            schema!(object {
+                required: { "tool": string },
+                optional: { "args": any, "iterations": integer }
+            }),
+        ),
     ]
 }
 
@@ -52,8 +76,12 @@ pub fn tools() -> Vec<ToolDef> {
 pub fn dispatch(
     session: &mut McpSession,
     name: &str,
-    _args: Map<String, JsonValue>,
+    args: Map<String, JsonValue>,
 ) -> Result<JsonValue> {
+    if name == "strata_db_benchmark" {
+        return benchmark(session, args);
+    }
+
     let cmd = match name {
         "strata_db_ping" => Command::Ping,
         "strata_db_info" => Command::Info,
@@ -62,9 +90,63 @@ pub fn dispatch(
         "strata_db_time_range" => Command::TimeRange {
             branch: session.branch_id(),
         },
-        _ => return Err(crate::error::McpError::UnknownTool(name.to_string())),
+        _ => return Err(McpError::UnknownTool(name.to_string())),
     };
 
     let output = session.execute(cmd)?;
     Ok(output_to_json(output))
 }
+
+fn benchmark(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let tool = get_string_arg(&args, "tool")?;
+    if !BENCHMARKABLE_TOOLS.contains(&tool.as_str()) {
+        return Err(McpError::InvalidArg {
+            name: "tool".to_string(),
+            reason: format!(
+                "'{}' cannot be benchmarked. Use one of: {}",
+                tool,
+                BENCHMARKABLE_TOOLS.join(", ")
+            ),
+        });
+    }
+
+    let iterations = get_optional_u64(&args, "iterations").unwrap_or(DEFAULT_BENCHMARK_ITERATIONS);
+    if iterations == 0 || iterations > MAX_BENCHMARK_ITERATIONS {
+        return Err(McpError::InvalidArg {
+            name: "iterations".to_string(),
+            reason: format!("must be between 1 and {}", MAX_BENCHMARK_ITERATIONS),
+        });
+    }
+
+    let tool_args = match args.get("args") {
+        Some(JsonValue::Object(map)) => map.clone(),
+        None | Some(JsonValue::Null) => Map::new(),
+        Some(_) => {
+            return Err(McpError::InvalidArg {
+                name: "args".to_string(),
+                reason: "Expected an object of tool arguments".to_string(),
+            })
+        }
+    };
+
+    let mut samples = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        agent::dispatch(session, &tool, tool_args.clone())?;
+        samples.push(start.elapsed().as_micros() as u64);
+    }
+    samples.sort_unstable();
+
+    let n = samples.len();
+    let mean = samples.iter().sum::<u64>() / n as u64;
+    let p95 = samples[((n * 95).div_ceil(100)).saturating_sub(1)];
+
+    Ok(serde_json::json!({
+        "tool": tool,
+        "iterations": n,
+        "min_us": samples[0],
+        "max_us": samples[n - 1],
+        "mean_us": mean,
+        "p95_us": p95,
+    }))
+}
@@ -0,0 +1,206 @@
+//! Integration debugging aids.
+//!
+//! Tools: strata_debug_echo, strata_debug_recent_writes, strata_debug_schema,
+//!        strata_debug_tool_calls
+//!
+//! `strata_debug_echo` echoes a call's arguments and the session context it
+//! would run in, without touching the database. `strata_debug_recent_writes`
+//! lists the latest committed writes. `strata_debug_schema` shows the schema
+//! the server advertises for a tool. `strata_debug_tool_calls` profiles this
+//! session's tool usage. Only registered when the server is started with
+//! `--debug`.
+
+use serde_json::{Map, Value as JsonValue};
+use stratadb::Command;
+
+use crate::convert::{get_optional_u64, get_string_arg, output_to_json};
+use crate::error::{McpError, Result};
+use crate::schema;
+use crate::session::McpSession;
+use crate::tools::{developer_registry, scan, ToolAnnotations, ToolDef};
+
+/// Prefix shared by every debug tool name.
+pub const PREFIX: &str = "strata_debug_";
+
+/// Writes strata_debug_recent_writes returns when no limit is given.
+const DEFAULT_RECENT_WRITES: u64 = 20;
+
+/// Most writes strata_debug_recent_writes returns in one call.
+const MAX_RECENT_WRITES: u64 = 200;
+
+/// Get all debug tool definitions.
+pub fn tools() -> Vec<ToolDef> {
+    vec![echo_tool(), recent_writes_tool(), schema_tool(), tool_calls_tool()]
+}
+
+/// Dispatch a debug tool call made through a registry advertising `registered`.
+pub fn dispatch(
+    session: &mut McpSession,
+    name: &str,
+    args: Map<String, JsonValue>,
+    registered: &[ToolDef],
+) -> Result<JsonValue> {
+    match name {
+        "strata_debug_echo" => echo(session, args),
+        "strata_debug_recent_writes" => recent_writes(session, args),
+        "strata_debug_schema" => schema(args, registered),
+        "strata_debug_tool_calls" => Ok(tool_calls(session)),
+        _ => Err(McpError::UnknownTool(name.to_string())),
+    }
+}
+
+fn echo_tool() -> ToolDef {
+    ToolDef::new(
+        "strata_debug_echo",
+        "Debugging aid: returns exactly what the server received and the context a call would \
+         run in, without executing anything. Pass any arguments. Returns { args, arg_types, \
+         context: { branch, space, read_only, in_transaction, session_branch, legacy_reads, \
+         require_explicit_replace } } where 'arg_types' gives the JSON type of each argument.",
+        serde_json::json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": true
+        }),
+    )
+    .with_annotations(ToolAnnotations::read_only())
+}
+
+fn recent_writes_tool() -> ToolDef {
+    ToolDef::new(
+        "strata_debug_recent_writes",
+        "Debugging aid: list the most recent committed document writes on the current branch, \
+         newest first, to confirm a write landed and in what order. Each entry is { op \
+         (create|update), space, key, version, timestamp }; values are not included. Reads \
+         version history, not raw WAL records, so deletes are not listed. Also returns the \
+         WAL durability counters. limit defaults to 20, at most 200. Returns { writes, \
+         counters }.",
+        schema!(object {
+            optional: { "limit": integer }
+        }),
+    )
+    .with_annotations(ToolAnnotations::read_only())
+}
+
+fn schema_tool() -> ToolDef {
+    ToolDef::new(
+        "strata_debug_schema",
+        "Debugging aid: show the exact definition the server advertises for one tool, to track \
+         down why calls to it fail validation. Looks in the tools this server lists, then in the \
+         developer tools. Returns { name, surface, description, inputSchema, examples, \
+         annotations, outputSchema } where 'surface' is 'listed' for a tool in tools/list or \
+         'developer' for one reachable only through the developer tools. outputSchema is \
+         omitted for tools that declare none.",
+        schema!(object {
+            required: { "tool": string }
+        }),
+    )
+    .with_annotations(ToolAnnotations::read_only())
+}
+
+fn tool_calls_tool() -> ToolDef {
+    ToolDef::new(
+        "strata_debug_tool_calls",
+        "Debugging aid: how this session has used the tools so far, e.g. to spot many single \
+         recalls that could be one call. Counts calls to listed tools in this session, \
+         including failed ones, but not the call in progress; nothing is persisted. Returns { calls, errors, tools: [{ tool, calls, ok, errors }] } \
+         with the most-called tools first.",
+        schema!(object {}),
+    )
+    .with_annotations(ToolAnnotations::read_only())
+}
+
+/// Echo the arguments and session context.
+fn echo(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let arg_types: Map<String, JsonValue> = args
+        .iter()
+        .map(|(name, value)| (name.clone(), JsonValue::String(json_type(value).to_string())))
+        .collect();
+
+    Ok(serde_json::json!({
+        "args": args,
+        "arg_types": arg_types,
+        "context": {
+            "branch": session.branch(),
+            "space": session.space(),
+            "read_only": session.is_read_only(),
+            "in_transaction": session.in_transaction(),
+            "session_branch": session.session_branch().map(|b| b.name.clone()),
+            "legacy_reads": session.legacy_reads(),
+            "require_explicit_replace": session.require_explicit_replace(),
+        },
+    }))
+}
+
+/// JSON Schema type name of a value.
+fn json_type(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// The latest committed document writes across every space of the current branch.
+fn recent_writes(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let limit = get_optional_u64(&args, "limit")
+        .unwrap_or(DEFAULT_RECENT_WRITES)
+        .clamp(1, MAX_RECENT_WRITES) as usize;
+
+    let writes = scan::recent_writes(session, limit)?;
+    let counters = output_to_json(session.execute(Command::DurabilityCounters)?);
+    Ok(serde_json::json!({ "writes": writes, "counters": counters }))
+}
+
+/// Per-tool call counts for this session, most-called first.
+fn tool_calls(session: &McpSession) -> JsonValue {
+    let mut usage: Vec<_> = session.tool_calls().iter().collect();
+    usage.sort_by(|a, b| b.1.calls().cmp(&a.1.calls()).then_with(|| a.0.cmp(b.0)));
+
+    let tools: Vec<JsonValue> = usage
+        .iter()
+        .map(|(tool, counts)| {
+            serde_json::json!({
+                "tool": tool,
+                "calls": counts.calls(),
+                "ok": counts.ok,
+                "errors": counts.errors,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "calls": usage.iter().map(|(_, c)| c.calls()).sum::<u64>(),
+        "errors": usage.iter().map(|(_, c)| c.errors).sum::<u64>(),
+        "tools": tools,
+    })
+}
+
+/// The definition of one tool, from the listed tools or the developer registry.
+fn schema(args: Map<String, JsonValue>, registered: &[ToolDef]) -> Result<JsonValue> {
+    let name = get_string_arg(&args, "tool")?;
+    let (def, surface) = match registered.iter().find(|t| t.name == name) {
+        Some(def) => (def.clone(), "listed"),
+        None => {
+            let developer = developer_registry();
+            let def = developer
+                .tools()
+                .iter()
+                .find(|t| t.name == name)
+                .cloned()
+                .ok_or_else(|| McpError::InvalidArg {
+                    name: "tool".to_string(),
+                    reason: format!("no tool named '{}'", name),
+                })?;
+            (def, "developer")
+        }
+    };
+
+    let mut result = serde_json::to_value(def)?;
+    if let Some(obj) = result.as_object_mut() {
+        obj.insert("surface".to_string(), JsonValue::String(surface.to_string()));
+    }
+    Ok(result)
+}
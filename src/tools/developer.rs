@@ -0,0 +1,118 @@
+//! Developer escape hatch.
+//!
+//! Tool: strata_developer
+//!
+//! Gives the default agent surface on-demand access to the granular developer
+//! tools without switching the whole server to developer mode. Only registered
+//! when the server is started with `--developer-access`.
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::convert::get_string_arg;
+use crate::error::{McpError, Result};
+use crate::session::McpSession;
+use crate::tools::{developer_registry, ToolDef, ToolRegistry};
+
+/// Name of the escape hatch tool.
+pub const NAME: &str = "strata_developer";
+
+/// Get the escape hatch tool definition.
+pub fn tool() -> ToolDef {
+    ToolDef::new(
+        NAME,
+        "Access Strata's granular developer tools (KV, state, vectors, transactions, bundles, \
+         ...) for operations the standard tools don't cover. Actions: 'list' (names and \
+         descriptions of all developer tools), 'describe' (full definition and input schema of \
+         'tool'), 'call' (run 'tool' with 'args'), 'enable' (list every developer tool directly \
+         from now on, so it can be called by name; the client is notified that the tool list \
+         changed). Prefer the standard tools when they fit.",
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list", "describe", "call", "enable"],
+                    "description": "What to do"
+                },
+                "tool": {
+                    "type": "string",
+                    "description": "Developer tool name — used by describe and call"
+                },
+                "args": {
+                    "type": "object",
+                    "description": "Arguments for the developer tool — used by call"
+                }
+            },
+            "required": ["action"]
+        }),
+    )
+}
+
+/// Dispatch an escape hatch call.
+pub fn dispatch(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let action = get_string_arg(&args, "action")?;
+    let registry = developer_registry();
+
+    match action.as_str() {
+        "list" => {
+            let tools: Vec<JsonValue> = registry
+                .tools()
+                .iter()
+                .map(|t| {
+                    serde_json::json!({
+                        "name": t.name,
+                        "description": t.description,
+                    })
+                })
+                .collect();
+            Ok(JsonValue::Array(tools))
+        }
+
+        "describe" => {
+            let name = get_string_arg(&args, "tool")?;
+            let def = find_tool(registry, &name)?;
+            Ok(serde_json::to_value(def)?)
+        }
+
+        "call" => {
+            let name = get_string_arg(&args, "tool")?;
+            find_tool(registry, &name)?;
+            let tool_args = match args.get("args") {
+                Some(JsonValue::Object(map)) => map.clone(),
+                None | Some(JsonValue::Null) => Map::new(),
+                Some(_) => {
+                    return Err(McpError::InvalidArg {
+                        name: "args".to_string(),
+                        reason: "Expected an object of tool arguments".to_string(),
+                    })
+                }
+            };
+            // Counted as the strata_developer call, not a second time
+            registry.dispatch_tool(session, &name, tool_args)
+        }
+
+        "enable" => {
+            session.request_developer_tools();
+            Ok(serde_json::json!({ "enabled": true, "tools": registry.tools().len() }))
+        }
+
+        other => Err(McpError::InvalidArg {
+            name: "action".to_string(),
+            reason: format!("Unknown action '{}'. Use: list, describe, call, or enable.", other),
+        }),
+    }
+}
+
+fn find_tool<'a>(registry: &'a ToolRegistry, name: &str) -> Result<&'a ToolDef> {
+    registry
+        .tools()
+        .iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| McpError::InvalidArg {
+            name: "tool".to_string(),
+            reason: format!(
+                "'{}' is not a developer tool. Use action 'list' to see them.",
+                name
+            ),
+        })
+}
@@ -1,31 +1,59 @@
 //! Durability tools.
 //!
-//! Tools: strata_durability_counters
+//! Tools: strata_durability_counters, strata_durability_mode
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::Command;
+use stratadb::{Command, Output};
 
-use crate::convert::output_to_json;
+use crate::convert::{get_optional_string, output_to_json};
 use crate::error::{McpError, Result};
 use crate::schema;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::{ToolAnnotations, ToolDef};
+
+/// Durability modes, strongest first.
+///
+/// - `always`: fsync the WAL on every commit. No acknowledged write is lost.
+/// - `standard`: fsync the WAL periodically. A crash can lose the last few
+///   hundred milliseconds of acknowledged writes.
+/// - `cache`: never fsync. A crash can lose everything since the last
+///   checkpoint. For bulk loads that can be rerun.
+pub const MODES: &[&str] = &["always", "standard", "cache"];
 
 /// Get all durability tool definitions.
 pub fn tools() -> Vec<ToolDef> {
-    vec![ToolDef::new(
-        "strata_durability_counters",
-        "Get WAL (Write-Ahead Log) durability counters for monitoring write \
-         performance. Returns wal_appends, sync_calls, bytes_written, and sync_nanos.",
-        schema!(object {}),
-    )]
+    vec![
+        ToolDef::new(
+            "strata_durability_counters",
+            "Get WAL (Write-Ahead Log) durability counters for monitoring write \
+             performance. Returns wal_appends, sync_calls, bytes_written, and sync_nanos.",
+            schema!(object {}),
+        ),
+        ToolDef::new(
+            "strata_durability_mode",
+            "Get or set the durability mode. Without mode, returns the current one. \
+             'always' fsyncs every commit (no acknowledged write is lost); 'standard' \
+             fsyncs periodically (a crash can lose the last few hundred ms); 'cache' \
+             never fsyncs (a crash can lose everything since the last checkpoint). \
+             Relax it for bulk writes, then restore it. Setting requires write access. \
+             Returns {durability, previous}.",
+            schema!(object {
+                optional: { "mode": string }
+            }),
+        )
+        .with_example(
+            serde_json::json!({ "mode": "cache" }),
+            serde_json::json!({ "durability": "cache", "previous": "standard" }),
+        )
+        .with_annotations(ToolAnnotations::write(false, true)),
+    ]
 }
 
 /// Dispatch a durability tool call.
 pub fn dispatch(
     session: &mut McpSession,
     name: &str,
-    _args: Map<String, JsonValue>,
+    args: Map<String, JsonValue>,
 ) -> Result<JsonValue> {
     match name {
         "strata_durability_counters" => {
@@ -33,6 +61,29 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_durability_mode" => {
+            let previous = current_mode(session)?;
+            let Some(mode) = get_optional_string(&args, "mode") else {
+                return Ok(serde_json::json!({ "durability": previous, "previous": null }));
+            };
+            if !MODES.contains(&mode.as_str()) {
+                return Err(McpError::InvalidArg {
+                    name: "mode".to_string(),
+                    reason: format!("must be one of: {}", MODES.join(", ")),
+                });
+            }
+            session.set_durability(&mode)?;
+            Ok(serde_json::json!({ "durability": current_mode(session)?, "previous": previous }))
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+/// The durability mode reported by the database config.
+fn current_mode(session: &mut McpSession) -> Result<JsonValue> {
+    match session.execute(Command::ConfigGet)? {
+        Output::Config(cfg) => Ok(serde_json::json!(cfg.durability)),
+        _ => Ok(JsonValue::Null),
+    }
+}
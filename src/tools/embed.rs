@@ -1,15 +1,30 @@
 //! Embedding tools.
 //!
-//! Tools: strata_embed, strata_embed_batch, strata_embed_status
+//! Tools: strata_embed, strata_embed_batch, strata_embed_status, strata_embed_key,
+//!        strata_embed_benchmark
+
+use std::time::Instant;
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::Command;
+use stratadb::{BranchId, Command, DistanceMetric, ModelTask, Output};
 
-use crate::convert::{get_string_arg, output_to_json};
+use crate::convert::{get_optional_u64, get_string_arg, output_to_json, value_to_json};
 use crate::error::{McpError, Result};
+use crate::jsonpath;
 use crate::schema;
-use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::session::{McpSession, SYSTEM_BRANCH};
+use crate::tools::{scan, ToolDef};
+
+/// Vector collection auto-embed fills from JSON documents, which search
+/// ranks semantic matches from. strata_embed_key writes to it, and
+/// strata_vector_orphans checks it by default.
+pub(crate) const SEARCH_INDEX_COLLECTION: &str = "_system_embed_json";
+
+/// Texts strata_embed_benchmark embeds when no sample count is given.
+const DEFAULT_BENCHMARK_SAMPLES: u64 = 16;
+
+/// Upper bound on benchmark samples, so a run can't monopolize the model.
+const MAX_BENCHMARK_SAMPLES: u64 = 256;
 
 /// Get all embedding tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -34,12 +49,132 @@ pub fn tools() -> Vec<ToolDef> {
             "strata_embed_status",
             "Get the status of the embedding pipeline. Returns auto_embed (bool), \
              batch_size, pending items, total_queued, total_embedded, total_failed, \
-             scheduler_queue_depth, scheduler_active_tasks, and is_idle.",
+             scheduler_queue_depth, scheduler_active_tasks, and is_idle, plus \
+             oldest_pending_age_ms: how long items have been pending without the queue \
+             emptying, measured from the first status read in this session that saw them \
+             (null when nothing is pending). A large age with pending items means the \
+             pipeline is stuck or starved rather than just busy.",
             schema!(object {}),
         ),
+        ToolDef::new(
+            "strata_embed_key",
+            "Index one existing document on demand: embed the text of the value stored at \
+             'key' (every string in it, in document order) and store the vector under the same \
+             key in the index search ranks semantic matches from, as auto-embed would have. \
+             Useful when auto-embed is off but a particular document should be found by \
+             meaning with strata_search. Returns { key, collection, dimension, model, version }; \
+             'model' is the embedding model used, left out when the engine doesn't name one.",
+            schema!(object {
+                required: { "key": string }
+            }),
+        ),
+        ToolDef::new(
+            "strata_embed_benchmark",
+            "Measure how fast the embedding model runs under current settings. Embeds \
+             'samples' generated texts (default 16, max 256) one at a time, then all at once \
+             as a batch, and returns { samples, min_us, max_us, mean_us, p95_us, \
+             embeddings_per_second, batch_us, batch_embeddings_per_second }. Use it to size \
+             batch settings and to tell whether embedding is the ingestion bottleneck; unlike \
+             strata_embed_status it times the model itself, not the queue.",
+            schema!(object {
+                optional: { "samples": integer }
+            }),
+        ),
     ]
 }
 
+/// Name of the embedding model auto-embed runs, from the engine's local
+/// model catalog, or `None` when no embedding model is installed.
+pub fn engine_model(session: &mut McpSession) -> Result<Option<String>> {
+    Ok(match session.execute(Command::ModelsLocal)? {
+        Output::ModelsList(models) => models
+            .into_iter()
+            .find(|m| m.task == ModelTask::Embed)
+            .map(|m| m.name),
+        _ => None,
+    })
+}
+
+/// Rebuild the database's embedding index.
+///
+/// Rewrites every document on every branch and in every space with its
+/// current value so auto-embed indexes it again with the active model, then
+/// records that model as the index's. Each document gains one version. A
+/// branch is rewritten in one transaction, so a failure leaves it as it was.
+/// Archived branches are read-only and keep their old embeddings. Returns
+/// the number of documents rewritten.
+pub fn reindex(session: &mut McpSession, model: &str) -> Result<u64> {
+    let archived = session.archived_branches()?;
+    let branches: Vec<String> = match session.execute(Command::BranchList {
+        state: None,
+        limit: None,
+        offset: None,
+    })? {
+        Output::BranchInfoList(branches) => branches
+            .into_iter()
+            .map(|bi| bi.info.id.as_str().to_string())
+            .filter(|name| name != SYSTEM_BRANCH && !archived.contains(name))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut rewritten = 0;
+    for branch in branches {
+        let branch = BranchId::from(branch);
+        session.execute(Command::TxnBegin {
+            branch: Some(branch.clone()),
+            options: None,
+        })?;
+        match reindex_branch(session, &branch) {
+            Ok(count) => {
+                session.execute(Command::TxnCommit)?;
+                rewritten += count;
+            }
+            Err(e) => {
+                let _ = session.execute(Command::TxnRollback);
+                return Err(e);
+            }
+        }
+    }
+    session.record_embed_model(model)?;
+    Ok(rewritten)
+}
+
+/// Rewrite every document in every space of `branch`.
+fn reindex_branch(session: &mut McpSession, branch: &BranchId) -> Result<u64> {
+    let spaces = match session.execute(Command::SpaceList {
+        branch: Some(branch.clone()),
+    })? {
+        Output::SpaceList(spaces) => spaces,
+        _ => Vec::new(),
+    };
+    let mut rewritten = 0;
+    for space in spaces {
+        for key in scan::list_all_on_in(session, Some(branch.clone()), &space, None)? {
+            let cmd = Command::JsonGet {
+                branch: Some(branch.clone()),
+                space: Some(space.clone()),
+                key: key.clone(),
+                path: "$".to_string(),
+                as_of: None,
+            };
+            let Output::MaybeVersioned(Some(vv)) = session.execute(cmd)? else {
+                continue;
+            };
+            let cmd = Command::JsonSet {
+                branch: Some(branch.clone()),
+                space: Some(space.clone()),
+                key,
+                path: "$".to_string(),
+                value: vv.value,
+            };
+            session.execute(cmd)?;
+            rewritten += 1;
+        }
+    }
+    Ok(rewritten)
+}
+
 /// Dispatch an embedding tool call.
 pub fn dispatch(
     session: &mut McpSession,
@@ -77,9 +212,149 @@ pub fn dispatch(
 
         "strata_embed_status" => {
             let output = session.execute(Command::EmbedStatus)?;
-            Ok(output_to_json(output))
+            let mut status = output_to_json(output);
+            let age = session.embed_pending_for().map(|age| age.as_millis() as u64);
+            if let Some(obj) = status.as_object_mut() {
+                obj.insert("oldest_pending_age_ms".to_string(), serde_json::json!(age));
+            }
+            Ok(status)
         }
 
+        "strata_embed_key" => embed_key(session, args),
+
+        "strata_embed_benchmark" => benchmark(session, args),
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+fn benchmark(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let samples = get_optional_u64(&args, "samples").unwrap_or(DEFAULT_BENCHMARK_SAMPLES);
+    if samples == 0 || samples > MAX_BENCHMARK_SAMPLES {
+        return Err(McpError::InvalidArg {
+            name: "samples".to_string(),
+            reason: format!("must be between 1 and {}", MAX_BENCHMARK_SAMPLES),
+        });
+    }
+
+    // Distinct texts, so no layer can serve a repeat from cache
+    let texts: Vec<String> = (0..samples)
+        .map(|i| format!("Sample passage {} used to measure embedding throughput.", i))
+        .collect();
+
+    let mut latencies = Vec::with_capacity(texts.len());
+    for text in &texts {
+        let start = Instant::now();
+        session.execute(Command::Embed { text: text.clone() })?;
+        latencies.push(start.elapsed().as_micros() as u64);
+    }
+    let total: u64 = latencies.iter().sum();
+    latencies.sort_unstable();
+
+    let start = Instant::now();
+    session.execute(Command::EmbedBatch { texts })?;
+    let batch_us = start.elapsed().as_micros() as u64;
+
+    let n = latencies.len();
+    let p95 = latencies[((n * 95).div_ceil(100)).saturating_sub(1)];
+    let per_second = |count: usize, us: u64| count as f64 * 1_000_000.0 / us.max(1) as f64;
+
+    Ok(serde_json::json!({
+        "samples": n,
+        "min_us": latencies[0],
+        "max_us": latencies[n - 1],
+        "mean_us": total / n as u64,
+        "p95_us": p95,
+        "embeddings_per_second": per_second(n, total),
+        "batch_us": batch_us,
+        "batch_embeddings_per_second": per_second(n, batch_us),
+    }))
+}
+
+/// Embed the text of one stored document into the search index.
+fn embed_key(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let key = get_string_arg(&args, "key")?;
+    let collection = SEARCH_INDEX_COLLECTION.to_string();
+
+    let value = match scan::latest(session, &key)? {
+        Some(vv) => value_to_json(vv.value),
+        None => {
+            return Err(McpError::InvalidArg {
+                name: "key".to_string(),
+                reason: format!("no document stored at '{}'", key),
+            })
+        }
+    };
+    let text = document_text(&value);
+    if text.is_empty() {
+        return Err(McpError::InvalidArg {
+            name: "key".to_string(),
+            reason: format!("'{}' has no text to embed", key),
+        });
+    }
+
+    let vector = match session.execute(Command::Embed { text })? {
+        Output::Embedding(vector) => vector,
+        _ => return Err(McpError::Internal("Embed returned no vector".to_string())),
+    };
+    let dimension = vector.len() as u64;
+
+    let cmd = Command::VectorListCollections {
+        branch: session.branch_id(),
+        space: session.space_id(),
+    };
+    let exists = match session.execute(cmd)? {
+        Output::VectorCollectionList(collections) => {
+            collections.iter().any(|c| c.name == collection)
+        }
+        _ => false,
+    };
+    if !exists {
+        let cmd = Command::VectorCreateCollection {
+            branch: session.branch_id(),
+            space: session.space_id(),
+            collection: collection.clone(),
+            dimension,
+            metric: DistanceMetric::Cosine,
+        };
+        session.execute(cmd)?;
+    }
+
+    let cmd = Command::VectorUpsert {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        collection: collection.clone(),
+        key: key.clone(),
+        vector,
+        metadata: None,
+    };
+    let stored = output_to_json(session.execute(cmd)?);
+
+    let mut result = serde_json::json!({
+        "key": key,
+        "collection": collection,
+        "dimension": dimension,
+        "version": stored["version"],
+    });
+    // The model Embed ran: the pinned one, else the installed one
+    let model = match session.embed_model() {
+        Some(model) => Some(model.to_string()),
+        None => engine_model(session)?,
+    };
+    if let Some(model) = model {
+        result["model"] = JsonValue::String(model);
+    }
+    Ok(result)
+}
+
+/// The text of a document: the value itself if it is a string, otherwise
+/// every string inside it, in document order, one per line.
+fn document_text(value: &JsonValue) -> String {
+    let mut parts = Vec::new();
+    jsonpath::walk(value, &mut |_, node| {
+        if let JsonValue::String(s) = node {
+            parts.push(s.clone());
+        }
+    });
+    parts.join("\n")
+}
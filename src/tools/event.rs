@@ -1,6 +1,7 @@
 //! Event log tools.
 //!
-//! Tools: strata_event_append, strata_event_get, strata_event_list, strata_event_len
+//! Tools: strata_event_append, strata_event_get, strata_event_list, strata_event_len,
+//! strata_event_tail
 
 use serde_json::{Map, Value as JsonValue};
 use stratadb::Command;
@@ -46,6 +47,18 @@ pub fn tools() -> Vec<ToolDef> {
             "Get the total count of events in the log.",
             schema!(object {}),
         ),
+        ToolDef::new(
+            "strata_event_tail",
+            "Get the most recent events across all types, newest first. Each entry is \
+             { sequence, type, timestamp, payload }. Pass 'types' to merge those event types \
+             and tag each entry with its type; without it the whole log is read and 'type' is \
+             null. 'limit' caps the number of entries (default 20). Pass the returned 'cursor' \
+             as 'since' to get the events logged after the previous call, oldest first, so \
+             repeating with each new cursor pages through them without skipping any.",
+            schema!(object {
+                optional: { "limit": integer, "since": integer, "types": array_string }
+            }),
+        ),
     ]
 }
 
@@ -111,6 +124,130 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_event_tail" => {
+            let limit = get_optional_u64(&args, "limit").unwrap_or(DEFAULT_TAIL_LIMIT) as usize;
+            let since = get_optional_u64(&args, "since");
+            let types: Option<Vec<String>> = args.get("types").and_then(|v| v.as_array()).map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect()
+            });
+
+            let mut entries = match types {
+                Some(types) => tail_by_type(session, &types, since)?,
+                None => tail_log(session, since, limit)?,
+            };
+            match since {
+                // Page forward from the cursor, so events past a full page aren't skipped
+                Some(_) => entries.sort_by_key(|e| e.sequence),
+                None => entries.sort_by(|a, b| {
+                    b.timestamp
+                        .cmp(&a.timestamp)
+                        .then_with(|| b.sequence.cmp(&a.sequence))
+                }),
+            }
+            entries.truncate(limit);
+
+            let cursor = entries.iter().map(|e| e.sequence).max().or(since);
+            let events: Vec<JsonValue> = entries.into_iter().map(TailEntry::into_json).collect();
+            Ok(serde_json::json!({
+                "events": events,
+                "cursor": cursor,
+            }))
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+/// Default number of entries returned by `strata_event_tail`.
+const DEFAULT_TAIL_LIMIT: u64 = 20;
+
+/// An event in the cross-type tail.
+struct TailEntry {
+    sequence: u64,
+    event_type: Option<String>,
+    timestamp: u64,
+    payload: JsonValue,
+}
+
+impl TailEntry {
+    /// Build an entry from a `{ value, version, timestamp }` event read.
+    fn from_json(event: JsonValue, event_type: Option<&str>) -> Option<Self> {
+        Some(Self {
+            sequence: event["version"].as_u64()?,
+            event_type: event_type.map(|t| t.to_string()),
+            timestamp: event["timestamp"].as_u64().unwrap_or(0),
+            payload: event["value"].clone(),
+        })
+    }
+
+    fn into_json(self) -> JsonValue {
+        serde_json::json!({
+            "sequence": self.sequence,
+            "type": self.event_type,
+            "timestamp": self.timestamp,
+            "payload": self.payload,
+        })
+    }
+}
+
+/// Read every event of the given types logged after `since`.
+fn tail_by_type(
+    session: &mut McpSession,
+    types: &[String],
+    since: Option<u64>,
+) -> Result<Vec<TailEntry>> {
+    let mut entries = Vec::new();
+    for event_type in types {
+        let cmd = Command::EventGetByType {
+            branch: session.branch_id(),
+            space: session.space_id(),
+            event_type: event_type.clone(),
+            limit: None,
+            after_sequence: since,
+            as_of: None,
+        };
+        if let JsonValue::Array(events) = output_to_json(session.execute(cmd)?) {
+            entries.extend(
+                events
+                    .into_iter()
+                    .filter_map(|e| TailEntry::from_json(e, Some(event_type))),
+            );
+        }
+    }
+    Ok(entries)
+}
+
+/// Walk the log backwards from the newest event, stopping after `limit`
+/// events, or forwards from just after `since`, stopping after `limit`
+/// events or at the newest.
+fn tail_log(session: &mut McpSession, since: Option<u64>, limit: usize) -> Result<Vec<TailEntry>> {
+    let cmd = Command::EventLen {
+        branch: session.branch_id(),
+        space: session.space_id(),
+    };
+    let len = output_to_json(session.execute(cmd)?).as_u64().unwrap_or(0);
+    let first = since.map_or(0, |s| s + 1);
+
+    let sequences: Box<dyn Iterator<Item = u64>> = match since {
+        Some(_) => Box::new(first..len),
+        None => Box::new((first..len).rev()),
+    };
+    let mut entries = Vec::new();
+    for sequence in sequences {
+        if entries.len() >= limit {
+            break;
+        }
+        let cmd = Command::EventGet {
+            branch: session.branch_id(),
+            space: session.space_id(),
+            sequence,
+            as_of: None,
+        };
+        if let Some(entry) = TailEntry::from_json(output_to_json(session.execute(cmd)?), None) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
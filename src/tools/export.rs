@@ -0,0 +1,132 @@
+//! Streaming document export.
+//!
+//! Tools: strata_export_ndjson
+//!
+//! Writes the current branch and space to an NDJSON file, one `{ key, value }`
+//! object per line (plus `meta` when the key has metadata), one key page at a
+//! time, so memory use stays flat however many documents there are.
+//! `strata_import_ndjson` reads the file back. Files live in the server's
+//! `--export-dir`; paths outside it are refused.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::convert::{get_optional_string, get_string_arg, value_to_json};
+use crate::error::{McpError, Result};
+use crate::schema;
+use crate::session::McpSession;
+use crate::tools::{meta, scan, ToolAnnotations, ToolDef};
+
+/// Keys fetched per listing page while exporting.
+const EXPORT_PAGE_SIZE: u64 = 500;
+
+/// Get all export tool definitions.
+pub fn tools() -> Vec<ToolDef> {
+    vec![ToolDef::new(
+        "strata_export_ndjson",
+        "Export every document on the current branch and space to an NDJSON file in the \
+         server's export directory, one { key, value } object per line, plus 'meta' for keys \
+         with metadata, streaming page by page so any number of documents can be exported. \
+         Optional 'prefix' limits the export to matching keys. Reserved bookkeeping keys are \
+         skipped. 'path' is relative to the export directory and can't leave it. The file is \
+         overwritten if it exists. Read it back with strata_import_ndjson. Returns { path, \
+         lines, bytes }.",
+        schema!(object {
+            required: { "path": string },
+            optional: { "prefix": string }
+        }),
+    )
+    .with_annotations(ToolAnnotations::write(true, true))]
+}
+
+/// Dispatch an export tool call.
+pub fn dispatch(
+    session: &mut McpSession,
+    name: &str,
+    args: Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    match name {
+        "strata_export_ndjson" => export_ndjson(session, args),
+        _ => Err(McpError::UnknownTool(name.to_string())),
+    }
+}
+
+/// Resolve a `path` argument inside the session's export directory.
+///
+/// The path must be relative and may not climb out with `..`; its parent is
+/// resolved through symlinks and must still be inside the directory, and the
+/// file itself may not be a symlink.
+pub(crate) fn resolve_path(session: &McpSession, path: &str) -> Result<PathBuf> {
+    let invalid = |reason: String| McpError::InvalidArg {
+        name: "path".to_string(),
+        reason,
+    };
+    let Some(dir) = session.export_dir() else {
+        let reason = "file export and import are disabled; start the server with --export-dir";
+        return Err(invalid(reason.to_string()));
+    };
+    let relative = Path::new(path);
+    if path.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(invalid(format!(
+            "'{}' must be a relative path inside the export directory, without '..'",
+            path
+        )));
+    }
+
+    let dir = dir.canonicalize()?;
+    let resolved = dir.join(relative);
+    let parent = resolved.parent().unwrap_or(&dir).canonicalize().map_err(|e| {
+        invalid(format!("cannot resolve '{}': {}", path, e))
+    })?;
+    let linked = resolved.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink());
+    if linked || !parent.starts_with(&dir) {
+        return Err(invalid(format!("'{}' leaves the export directory", path)));
+    }
+    Ok(resolved)
+}
+
+fn export_ndjson(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let path = get_string_arg(&args, "path")?;
+    let prefix = get_optional_string(&args, "prefix");
+
+    let file = File::create(resolve_path(session, &path)?).map_err(|e| McpError::InvalidArg {
+        name: "path".to_string(),
+        reason: format!("cannot create '{}': {}", path, e),
+    })?;
+    let mut out = BufWriter::new(file);
+    let mut lines = 0u64;
+    let mut bytes = 0u64;
+    let mut cursor = None;
+    loop {
+        let page = scan::list_page(session, prefix.clone(), cursor, EXPORT_PAGE_SIZE)?;
+        for key in page.keys {
+            // A key listed but deleted before it was read is simply skipped
+            let Some(vv) = scan::latest(session, &key)? else {
+                continue;
+            };
+            let mut doc = serde_json::json!({ "key": key, "value": value_to_json(vv.value) });
+            if let Some(metadata) = meta::read_meta(session, &key)? {
+                doc["meta"] = JsonValue::Object(metadata);
+            }
+            let mut line = serde_json::to_vec(&doc)?;
+            line.push(b'\n');
+            out.write_all(&line)?;
+            lines += 1;
+            bytes += line.len() as u64;
+        }
+        match page.cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    out.flush()?;
+
+    Ok(serde_json::json!({
+        "path": path,
+        "lines": lines,
+        "bytes": bytes,
+    }))
+}
@@ -0,0 +1,439 @@
+//! Bulk document import.
+//!
+//! Tools: strata_import_file, strata_import_ndjson
+//!
+//! Loads documents from a CSV file (one column holds the key, the other
+//! columns become the document's fields) or a JSONL file of `{ key, value }`
+//! objects. Each row is written through `strata_store`, so imports behave
+//! exactly like agent writes; a JSONL line's `meta` object becomes the key's
+//! metadata, as `strata_export_ndjson` writes it. `strata_import_ndjson` reads JSONL line by line
+//! and reports only counts and the first errors, for files too large to hold
+//! in memory. Files are read from the server's `--export-dir`.
+
+use std::io::BufRead;
+
+use serde_json::{Map, Value as JsonValue};
+use stratadb::{Command, TxnOptions};
+
+use crate::convert::{get_optional_bool, get_optional_string, get_optional_u64, get_string_arg};
+use crate::error::{McpError, Result};
+use crate::schema;
+use crate::session::McpSession;
+use crate::tools::{agent, export, meta, ToolDef};
+
+/// CSV column holding the key when `key_column` is not given.
+const DEFAULT_KEY_COLUMN: &str = "key";
+
+/// Failed lines strata_import_ndjson describes individually; the rest are only counted.
+const MAX_REPORTED_ERRORS: usize = 100;
+
+/// Get all import tool definitions.
+pub fn tools() -> Vec<ToolDef> {
+    vec![
+        ToolDef::new(
+            "strata_import_file",
+            "Import documents from a file in the server's export directory; 'path' is relative \
+             to it and can't leave it. 'format' is 'csv' or 'jsonl' (default: from the \
+             extension — .csv, .jsonl, or .ndjson). CSV needs a header row; the 'key_column' \
+             (default 'key') gives each row's key and the other columns become the document's \
+             string fields. JSONL has one { key, value } object per line, with an optional \
+             'meta' object stored as the key's metadata. Each row is stored as with \
+             strata_store. Pass batch_size to commit every batch_size rows in its own \
+             transaction. Pass validate_only: true to check the file without writing. Returns \
+             { imported, failed, rows: [{ line, key, version } or { line, error }] }; a \
+             malformed row is reported with its line number and the rest still import.",
+            schema!(object {
+                required: { "path": string },
+                optional: {
+                    "format": string,
+                    "key_column": string,
+                    "batch_size": integer,
+                    "validate_only": boolean
+                }
+            }),
+        ),
+        ToolDef::new(
+            "strata_import_ndjson",
+            "Import a large NDJSON file from the server's export directory ('path' is relative \
+             to it), one { key, value } object per line, as written by strata_export_ndjson; a \
+             line's optional 'meta' object is stored as the key's metadata. The file is read \
+             line by line, so it can be larger than memory. Each line is stored as with \
+             strata_store. Pass batch_size to commit every batch_size lines in its own \
+             transaction. Returns { imported, failed, errors: [{ line, error }], \
+             errors_truncated } where 'errors' describes at most the first 100 failed lines.",
+            schema!(object {
+                required: { "path": string },
+                optional: { "batch_size": integer }
+            }),
+        ),
+    ]
+}
+
+/// Dispatch an import tool call.
+pub fn dispatch(
+    session: &mut McpSession,
+    name: &str,
+    args: Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    match name {
+        "strata_import_file" => import_file(session, args),
+        "strata_import_ndjson" => import_ndjson(session, args),
+        _ => Err(McpError::UnknownTool(name.to_string())),
+    }
+}
+
+/// A parsed document: its key, value, and metadata if the line carried any.
+type Doc = (String, JsonValue, Option<Map<String, JsonValue>>);
+
+/// A parsed row: its 1-based line number and either a document or a parse error.
+struct Row {
+    line: usize,
+    parsed: std::result::Result<Doc, String>,
+}
+
+fn import_file(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let path = get_string_arg(&args, "path")?;
+    let format = match get_optional_string(&args, "format") {
+        Some(format) => format,
+        None => format_from_extension(&path)?,
+    };
+    let key_column =
+        get_optional_string(&args, "key_column").unwrap_or_else(|| DEFAULT_KEY_COLUMN.to_string());
+    let batch_size = get_optional_u64(&args, "batch_size");
+    let validate_only = get_optional_bool(&args, "validate_only").unwrap_or(false);
+    if batch_size == Some(0) {
+        return Err(McpError::InvalidArg {
+            name: "batch_size".to_string(),
+            reason: "must be at least 1".to_string(),
+        });
+    }
+    if batch_size.is_some() && session.in_transaction() {
+        return Err(McpError::TransactionAlreadyActive);
+    }
+
+    let resolved = export::resolve_path(session, &path)?;
+    let text = std::fs::read_to_string(resolved).map_err(|e| McpError::InvalidArg {
+        name: "path".to_string(),
+        reason: format!("cannot read '{}': {}", path, e),
+    })?;
+    let rows = match format.as_str() {
+        "csv" => parse_csv(&text, &key_column)?,
+        "jsonl" => parse_jsonl(&text),
+        other => {
+            return Err(McpError::InvalidArg {
+                name: "format".to_string(),
+                reason: format!("Unknown format '{}'. Use 'csv' or 'jsonl'.", other),
+            })
+        }
+    };
+
+    rolling_back(session, batch_size.is_some(), |session| {
+        let mut results = Vec::with_capacity(rows.len());
+        let mut imported = 0;
+        let mut failed = 0;
+        let mut in_batch = 0;
+
+        for row in rows {
+            let (key, value, metadata) = match row.parsed {
+                Ok(doc) => doc,
+                Err(error) => {
+                    failed += 1;
+                    results.push(serde_json::json!({ "line": row.line, "error": error }));
+                    continue;
+                }
+            };
+            if validate_only {
+                imported += 1;
+                results.push(serde_json::json!({ "line": row.line, "key": key, "version": null }));
+                continue;
+            }
+
+            if let Some(size) = batch_size {
+                if in_batch == 0 {
+                    begin_batch(session)?;
+                }
+                in_batch += 1;
+                if in_batch == size {
+                    in_batch = 0;
+                }
+            }
+
+            match store_row(session, &key, value, metadata) {
+                Ok(stored) => {
+                    imported += 1;
+                    results.push(serde_json::json!({
+                        "line": row.line,
+                        "key": key,
+                        "version": stored["version"],
+                    }));
+                }
+                Err(e) => {
+                    failed += 1;
+                    results.push(serde_json::json!({
+                        "line": row.line,
+                        "key": key,
+                        "error": e.to_string(),
+                    }));
+                }
+            }
+
+            if batch_size.is_some() && in_batch == 0 {
+                session.execute(Command::TxnCommit)?;
+            }
+        }
+        if in_batch > 0 {
+            session.execute(Command::TxnCommit)?;
+        }
+
+        Ok(serde_json::json!({
+            "imported": imported,
+            "failed": failed,
+            "rows": results,
+        }))
+    })
+}
+
+fn import_ndjson(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let path = get_string_arg(&args, "path")?;
+    let batch_size = get_optional_u64(&args, "batch_size");
+    if batch_size == Some(0) {
+        return Err(McpError::InvalidArg {
+            name: "batch_size".to_string(),
+            reason: "must be at least 1".to_string(),
+        });
+    }
+    if batch_size.is_some() && session.in_transaction() {
+        return Err(McpError::TransactionAlreadyActive);
+    }
+
+    let resolved = export::resolve_path(session, &path)?;
+    let file = std::fs::File::open(resolved).map_err(|e| McpError::InvalidArg {
+        name: "path".to_string(),
+        reason: format!("cannot read '{}': {}", path, e),
+    })?;
+
+    rolling_back(session, batch_size.is_some(), |session| {
+        let mut errors = Vec::new();
+        let mut imported = 0u64;
+        let mut failed = 0u64;
+        let mut in_batch = 0;
+        let mut fail = |line: usize, error: String, errors: &mut Vec<JsonValue>| {
+            failed += 1;
+            if errors.len() < MAX_REPORTED_ERRORS {
+                errors.push(serde_json::json!({ "line": line, "error": error }));
+            }
+        };
+
+        for (i, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, value, metadata) = match parse_jsonl_line(&line) {
+                Ok(doc) => doc,
+                Err(error) => {
+                    fail(i + 1, error, &mut errors);
+                    continue;
+                }
+            };
+
+            if let Some(size) = batch_size {
+                if in_batch == 0 {
+                    begin_batch(session)?;
+                }
+                in_batch += 1;
+                if in_batch == size {
+                    in_batch = 0;
+                }
+            }
+
+            match store_row(session, &key, value, metadata) {
+                Ok(_) => imported += 1,
+                Err(e) => fail(i + 1, e.to_string(), &mut errors),
+            }
+
+            if batch_size.is_some() && in_batch == 0 {
+                session.execute(Command::TxnCommit)?;
+            }
+        }
+        if in_batch > 0 {
+            session.execute(Command::TxnCommit)?;
+        }
+
+        Ok(serde_json::json!({
+            "imported": imported,
+            "failed": failed,
+            "errors_truncated": failed > errors.len() as u64,
+            "errors": errors,
+        }))
+    })
+}
+
+/// Store one imported document as `strata_store` would, then its metadata.
+fn store_row(
+    session: &mut McpSession,
+    key: &str,
+    value: JsonValue,
+    metadata: Option<Map<String, JsonValue>>,
+) -> Result<JsonValue> {
+    let mut store_args = Map::new();
+    store_args.insert("key".to_string(), JsonValue::String(key.to_string()));
+    store_args.insert("value".to_string(), value);
+    let stored = agent::dispatch(session, "strata_store", store_args)?;
+    if let Some(metadata) = metadata {
+        meta::write_meta(session, key, metadata)?;
+    }
+    Ok(stored)
+}
+
+/// Run an import, rolling back the batch transaction it leaves open when a
+/// read or commit fails part way, and returning that failure.
+fn rolling_back<T>(
+    session: &mut McpSession,
+    batched: bool,
+    run: impl FnOnce(&mut McpSession) -> Result<T>,
+) -> Result<T> {
+    let outcome = run(session);
+    if outcome.is_err() && batched && session.in_transaction() {
+        // The import's own error is the one to report, not the rollback's
+        let _ = session.execute(Command::TxnRollback);
+    }
+    outcome
+}
+
+fn begin_batch(session: &mut McpSession) -> Result<()> {
+    let cmd = Command::TxnBegin {
+        branch: session.branch_id(),
+        options: Some(TxnOptions { read_only: false }),
+    };
+    session.execute(cmd)?;
+    Ok(())
+}
+
+fn format_from_extension(path: &str) -> Result<String> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("csv") => Ok("csv".to_string()),
+        Some("jsonl") | Some("ndjson") => Ok("jsonl".to_string()),
+        _ => Err(McpError::InvalidArg {
+            name: "format".to_string(),
+            reason: format!(
+                "cannot tell the format of '{}' from its extension; pass 'csv' or 'jsonl'",
+                path
+            ),
+        }),
+    }
+}
+
+/// Parse JSONL: one `{ key, value }` object per non-blank line.
+fn parse_jsonl(text: &str) -> Vec<Row> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| Row {
+            line: i + 1,
+            parsed: parse_jsonl_line(line),
+        })
+        .collect()
+}
+
+fn parse_jsonl_line(line: &str) -> std::result::Result<Doc, String> {
+    let mut obj = match serde_json::from_str::<JsonValue>(line) {
+        Ok(JsonValue::Object(obj)) => obj,
+        Ok(_) => return Err("expected an object with 'key' and 'value'".to_string()),
+        Err(e) => return Err(format!("invalid JSON: {}", e)),
+    };
+    let key = match obj.remove("key") {
+        Some(JsonValue::String(key)) => key,
+        Some(_) => return Err("'key' must be a string".to_string()),
+        None => return Err("missing 'key'".to_string()),
+    };
+    let value = obj.remove("value").ok_or("missing 'value'")?;
+    let metadata = match obj.remove("meta") {
+        Some(JsonValue::Object(metadata)) => Some(metadata),
+        None => None,
+        Some(_) => return Err("'meta' must be an object".to_string()),
+    };
+    Ok((key, value, metadata))
+}
+
+/// Parse CSV with a header row. The key column becomes each row's key and
+/// every other column a string field of its document.
+fn parse_csv(text: &str, key_column: &str) -> Result<Vec<Row>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let header = match lines.next() {
+        Some((i, line)) => parse_csv_line(line).map_err(|e| McpError::InvalidArg {
+            name: "path".to_string(),
+            reason: format!("line {}: {}", i + 1, e),
+        })?,
+        None => return Ok(Vec::new()),
+    };
+    let key_index = header
+        .iter()
+        .position(|c| c == key_column)
+        .ok_or_else(|| McpError::InvalidArg {
+            name: "key_column".to_string(),
+            reason: format!("no column named '{}' in the header", key_column),
+        })?;
+
+    Ok(lines
+        .map(|(i, line)| Row {
+            line: i + 1,
+            parsed: parse_csv_line(line).and_then(|cells| {
+                if cells.len() != header.len() {
+                    return Err(format!(
+                        "expected {} columns, found {}",
+                        header.len(),
+                        cells.len()
+                    ));
+                }
+                let key = cells[key_index].clone();
+                if key.is_empty() {
+                    return Err(format!("empty '{}'", key_column));
+                }
+                let doc: Map<String, JsonValue> = header
+                    .iter()
+                    .zip(cells)
+                    .enumerate()
+                    .filter(|(c, _)| *c != key_index)
+                    .map(|(_, (name, cell))| (name.clone(), JsonValue::String(cell)))
+                    .collect();
+                Ok((key, JsonValue::Object(doc), None))
+            }),
+        })
+        .collect())
+}
+
+/// Split one CSV line into cells. Cells may be quoted with `"`, with `""`
+/// standing for a literal quote; quoted cells cannot span lines.
+fn parse_csv_line(line: &str) -> std::result::Result<Vec<String>, String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if cell.is_empty() => quoted = true,
+            (',', false) => cells.push(std::mem::take(&mut cell)),
+            (c, _) => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    cells.push(cell);
+    Ok(cells)
+}
+
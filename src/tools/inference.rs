@@ -1,17 +1,28 @@
 //! Inference tools (text generation, tokenization).
 //!
-//! Tools: strata_generate, strata_tokenize, strata_detokenize, strata_generate_unload
+//! Tools: strata_generate, strata_tokenize, strata_detokenize, strata_generate_unload,
+//! strata_summarize
+//!
+//! strata_summarize is answered by the connected client's model through
+//! `sampling/createMessage` when the client offers sampling; the server
+//! handles that round trip, and this module only runs the local fallback.
 
 use serde_json::{Map, Value as JsonValue};
 use stratadb::Command;
 
 use crate::convert::{
-    get_optional_bool, get_optional_u64, get_string_arg, output_to_json,
+    get_optional_bool, get_optional_string, get_optional_u64, get_string_arg, output_to_json,
 };
 use crate::error::{McpError, Result};
 use crate::schema;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::{agent, meta, scan, ToolDef};
+
+/// Most documents a strata_summarize `prefix` pulls in.
+const MAX_SUMMARY_DOCUMENTS: u64 = 50;
+
+/// Summary length when strata_summarize isn't given `max_tokens`.
+const DEFAULT_SUMMARY_TOKENS: u64 = 512;
 
 /// Get all inference tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -50,6 +61,23 @@ pub fn tools() -> Vec<ToolDef> {
                 required: { "model": string, "ids": array_number }
             }),
         ),
+        ToolDef::new(
+            "strata_summarize",
+            "Condense a set of stored documents into a short summary. Name them with 'keys', \
+             a 'prefix' (up to 50 documents), or both; 'focus' narrows the summary to a topic. \
+             When the client supports sampling, its own model writes the summary; otherwise \
+             the local 'model' (pulled with strata_models_pull) does. Returns summary, keys \
+             (the documents read), model, and source ('sampling' or 'local').",
+            schema!(object {
+                optional: {
+                    "keys": array_string,
+                    "prefix": string,
+                    "focus": string,
+                    "max_tokens": integer,
+                    "model": string
+                }
+            }),
+        ),
         ToolDef::new(
             "strata_generate_unload",
             "Unload a model from memory, freeing GPU/CPU resources. \
@@ -78,6 +106,19 @@ pub fn dispatch(
             let seed = get_optional_u64(&args, "seed");
             let stop_tokens = get_optional_u32_array(&args, "stop_tokens");
 
+            // The runtime's generation result carries only text and token counts,
+            // so a client asking for logprobs anyway is told rather than ignored
+            if get_optional_bool(&args, "logprobs").unwrap_or(false)
+                || get_optional_u64(&args, "top_logprobs").is_some_and(|n| n > 0)
+            {
+                return Err(McpError::Strata {
+                    code: "NOT_IMPLEMENTED".to_string(),
+                    message: "logprobs are not supported: the inference runtime does not \
+                              report per-token log probabilities"
+                        .to_string(),
+                });
+            }
+
             let output = session.execute(Command::Generate {
                 model,
                 prompt,
@@ -118,10 +159,114 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_summarize" => {
+            let request = summary_request(session, &args)?;
+            let model =
+                get_optional_string(&args, "model").ok_or_else(|| McpError::InvalidArg {
+                    name: "model".to_string(),
+                    reason: "the client can't sample, so name a local model to summarize with"
+                        .to_string(),
+                })?;
+            let output = output_to_json(session.execute(Command::Generate {
+                model,
+                prompt: request.prompt,
+                max_tokens: Some(request.max_tokens as usize),
+                temperature: None,
+                top_k: None,
+                top_p: None,
+                seed: None,
+                stop_tokens: None,
+            })?);
+            Ok(serde_json::json!({
+                "summary": output["text"],
+                "keys": request.keys,
+                "model": output["model"],
+                "source": "local",
+            }))
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
 
+/// The documents a strata_summarize call names, as a prompt for a model.
+pub(crate) struct SummaryRequest {
+    /// Instructions followed by every document read
+    pub prompt: String,
+    /// Keys of the documents in the prompt
+    pub keys: Vec<String>,
+    /// Longest summary to ask for, in tokens
+    pub max_tokens: u64,
+}
+
+/// Read the documents a strata_summarize call names into a prompt.
+///
+/// Keys without a document are skipped; it's an error if none are left.
+pub(crate) fn summary_request(
+    session: &mut McpSession,
+    args: &Map<String, JsonValue>,
+) -> Result<SummaryRequest> {
+    let invalid = |name: &str, reason: &str| McpError::InvalidArg {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut keys = match args.get("keys") {
+        None | Some(JsonValue::Null) => Vec::new(),
+        Some(JsonValue::Array(keys)) => keys
+            .iter()
+            .map(|k| k.as_str().map(String::from))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("keys", "every key must be a string"))?,
+        Some(_) => return Err(invalid("keys", "expected an array of keys")),
+    };
+    let prefix = get_optional_string(args, "prefix");
+    if keys.is_empty() && prefix.is_none() {
+        return Err(McpError::MissingArg("keys".to_string()));
+    }
+    if let Some(prefix) = prefix {
+        for key in scan::list_page(session, Some(prefix), None, MAX_SUMMARY_DOCUMENTS)?.keys {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut documents = String::new();
+    for key in keys {
+        if meta::is_reserved(&key) {
+            continue;
+        }
+        if let Some(document) = agent::read_document(session, &key)? {
+            documents.push_str(&format!(
+                "\n\n<document key=\"{}\">\n{}\n</document>",
+                key, document
+            ));
+            found.push(key);
+        }
+    }
+    if found.is_empty() {
+        return Err(invalid("keys", "none of the keys hold a document"));
+    }
+
+    let focus = match get_optional_string(args, "focus") {
+        Some(focus) => format!(" Concentrate on {}.", focus),
+        None => String::new(),
+    };
+    let prompt = format!(
+        "Summarize these {} stored documents in a few short paragraphs.{} Cite the keys behind \
+         each point, and say where documents contradict each other.{}",
+        found.len(),
+        focus,
+        documents
+    );
+    Ok(SummaryRequest {
+        prompt,
+        keys: found,
+        max_tokens: get_optional_u64(args, "max_tokens").unwrap_or(DEFAULT_SUMMARY_TOKENS),
+    })
+}
+
 /// Helper to get an optional f32 argument.
 fn get_optional_f32(args: &Map<String, JsonValue>, name: &str) -> Option<f32> {
     args.get(name).and_then(|v| v.as_f64()).map(|f| f as f32)
@@ -1,17 +1,35 @@
 //! JSON document store tools.
 //!
-//! Tools: strata_json_set, strata_json_get, strata_json_delete, strata_json_list, strata_json_history
+//! Tools: strata_json_set, strata_json_get, strata_json_delete, strata_json_list, strata_json_history,
+//!        strata_json_exists_many, strata_json_check_path, strata_json_fingerprint,
+//!        strata_json_find, strata_json_rotate, strata_json_canonicalize,
+//!        strata_json_dangling_refs
+
+use std::collections::HashSet;
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::Command;
+use sha2::{Digest, Sha256};
+use stratadb::{Command, Output, TxnOptions};
 
 use crate::convert::{
-    get_optional_string, get_optional_u64, get_string_arg, get_value_arg, output_to_json,
+    canonical_json, canonicalize, get_optional_bool, get_optional_string, get_optional_u64,
+    get_string_arg, get_u64_arg, get_value_arg, json_to_value, output_to_json, value_to_json,
 };
 use crate::error::{McpError, Result};
+use crate::jsonpath;
 use crate::schema;
-use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::session::{McpSession, WarningCode};
+use crate::tools::agent::read_document;
+use crate::tools::{meta, scan, ToolAnnotations, ToolDef};
+
+/// Metadata field `strata_json_fingerprint` records the digest under.
+const FINGERPRINT_FIELD: &str = "sha256";
+
+/// Matches returned by `strata_json_find` when no limit is given.
+const DEFAULT_FIND_LIMIT: u64 = 100;
+
+/// Dangling references returned by `strata_json_dangling_refs` when no limit is given.
+const DEFAULT_DANGLING_LIMIT: u64 = 100;
 
 /// Get all JSON tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -56,6 +74,109 @@ pub fn tools() -> Vec<ToolDef> {
                 optional: { "as_of": integer }
             }),
         ),
+        ToolDef::new(
+            "strata_json_exists_many",
+            "Check which of a set of document keys exist in a single call. Returns an object \
+             mapping each key to { exists }, answered from the key index without reading any \
+             values. Pass with_versions: true to add each existing key's latest version \
+             (null for missing keys); that reads each existing document.",
+            schema!(object {
+                required: { "keys": array_string },
+                optional: { "with_versions": boolean }
+            }),
+        ),
+        ToolDef::new(
+            "strata_json_check_path",
+            "Pre-flight check for a path-based write. Reports whether the path currently \
+             resolves in the document, the JSON type found there, and what a write at that path \
+             would do: 'overwrite' (path exists), 'create' (path or its parents would be \
+             created), or 'fail' (e.g. indexing into a scalar), with 'missing' naming the first \
+             absent segment and 'reason' explaining a failure. Does not modify data.",
+            schema!(object {
+                required: { "key": string, "path": string }
+            }),
+        ),
+        ToolDef::new(
+            "strata_json_fingerprint",
+            "Compute a SHA-256 fingerprint of a document's current value for change detection \
+             and deduplication. The value is canonicalized first (object keys sorted, no \
+             whitespace), so documents that differ only in field order hash identically. \
+             Returns { key, version, sha256 } for the version hashed, or null if the key \
+             doesn't exist. Pass store: true to also record the digest in the key's metadata \
+             under 'sha256' (other metadata fields are kept).",
+            schema!(object {
+                required: { "key": string },
+                optional: { "store": boolean }
+            }),
+        ),
+        ToolDef::new(
+            "strata_json_find",
+            "Find where a term appears inside one document — e.g. the setting named 'theme' \
+             anywhere in a large config. Matches field names and scalar values (strings, \
+             numbers, booleans) containing 'term', case-insensitively unless case_sensitive is \
+             true. Returns { key, term, matches: [{ path, matched, value }], truncated } where \
+             'path' is a JSONPath usable with strata_json_get, 'matched' is 'key' or 'value', \
+             and 'value' is the value at that path. At most 'limit' matches (default 100) are \
+             returned, in document order; truncated is true if more exist. Null if the \
+             document doesn't exist.",
+            schema!(object {
+                required: { "key": string, "term": string },
+                optional: { "case_sensitive": boolean, "limit": integer }
+            }),
+        ),
+        ToolDef::new(
+            "strata_json_rotate",
+            "Append 'value' to the array at 'path' (default '$', the whole document) and keep \
+             only the last 'keep' elements, as one atomic write: a bounded rolling window, e.g. \
+             the 20 most recent observations. The oldest elements are dropped from the front. \
+             A missing document or path starts a new array; anything else at the path that \
+             isn't an array is an error. Runs in its own transaction unless one is already \
+             open. Returns { key, path, length, dropped, version }.",
+            schema!(object {
+                required: { "key": string, "value": any, "keep": integer },
+                optional: { "path": string }
+            }),
+        )
+        .with_example(
+            serde_json::json!({"key": "recent", "value": {"temp": 21}, "keep": 3}),
+            serde_json::json!({
+                "key": "recent", "path": "$", "length": 3, "dropped": 1, "version": 8
+            }),
+        )
+        .with_annotations(ToolAnnotations::write(true, false)),
+        ToolDef::new(
+            "strata_json_canonicalize",
+            "Normalize a stored document in place: object keys sorted and whole-number floats \
+             (2.0) stored as integers, the same form strata_json_fingerprint hashes. The \
+             document is rewritten only if that changes it, so an already-canonical document \
+             gains no version. Returns { key, changed, version } with the new version when \
+             rewritten and the current one otherwise, or null if the key doesn't exist.",
+            schema!(object {
+                required: { "key": string }
+            }),
+        )
+        .with_annotations(ToolAnnotations::write(false, true)),
+        ToolDef::new(
+            "strata_json_dangling_refs",
+            "Check references between documents: for every document under 'prefix' (all when \
+             omitted), read the field at 'path' (e.g. '$.parent_key') and report the keys it \
+             names that don't exist. The field may hold a key or an array of keys; documents \
+             without it are skipped. Nothing is modified. Returns { scanned, references, \
+             dangling: [{ key, target }], truncated }, listing at most 'limit' dangling \
+             references (default 100).",
+            schema!(object {
+                required: { "path": string },
+                optional: { "prefix": string, "limit": integer }
+            }),
+        )
+        .with_example(
+            serde_json::json!({"path": "$.parent_key", "prefix": "task:"}),
+            serde_json::json!({
+                "scanned": 12, "references": 9,
+                "dangling": [{"key": "task:7", "target": "task:2"}], "truncated": false
+            }),
+        )
+        .with_annotations(ToolAnnotations::read_only()),
     ]
 }
 
@@ -144,6 +265,330 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_json_exists_many" => {
+            let keys = args
+                .get("keys")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| McpError::MissingArg("keys".to_string()))?;
+            let with_versions = get_optional_bool(&args, "with_versions").unwrap_or(false);
+
+            let mut results = Map::new();
+            for key_value in keys {
+                let key = key_value
+                    .as_str()
+                    .ok_or_else(|| McpError::InvalidArg {
+                        name: "keys".to_string(),
+                        reason: "Keys must be strings".to_string(),
+                    })?
+                    .to_string();
+
+                let exists = scan::exists(session, &key)?;
+                let entry = if !with_versions {
+                    serde_json::json!({ "exists": exists })
+                } else if exists {
+                    let version = scan::latest(session, &key)?.map(|vv| vv.version);
+                    serde_json::json!({ "exists": true, "version": version })
+                } else {
+                    serde_json::json!({ "exists": false, "version": null })
+                };
+                results.insert(key, entry);
+            }
+            Ok(JsonValue::Object(results))
+        }
+
+        "strata_json_check_path" => {
+            let key = get_string_arg(&args, "key")?;
+            let path = get_string_arg(&args, "path")?;
+            let segments = jsonpath::parse(&path)?;
+            let doc = read_document(session, &key)?;
+
+            let found = doc.as_ref().and_then(|d| jsonpath::resolve(d, &segments));
+            let missing = match &doc {
+                Some(d) => jsonpath::first_missing(d, &segments).map(|i| jsonpath::format(&segments[..=i])),
+                None => Some("$".to_string()),
+            };
+
+            // Dry-run the write against a local copy to see whether it fits
+            let mut scratch = doc.clone().unwrap_or(JsonValue::Null);
+            let (write, reason) = match (found, jsonpath::set(&mut scratch, &segments, JsonValue::Null)) {
+                (Some(_), _) => ("overwrite", None),
+                (None, Ok(())) => ("create", None),
+                (None, Err(McpError::InvalidArg { reason, .. })) => ("fail", Some(reason)),
+                (None, Err(e)) => return Err(e),
+            };
+
+            Ok(serde_json::json!({
+                "key": key,
+                "path": path,
+                "document_exists": doc.is_some(),
+                "resolves": found.is_some(),
+                "type": found.map(jsonpath::type_name),
+                "write": write,
+                "missing": missing,
+                "reason": reason,
+            }))
+        }
+
+        "strata_json_fingerprint" => {
+            let key = get_string_arg(&args, "key")?;
+            let store = get_optional_bool(&args, "store").unwrap_or(false);
+
+            let Some(vv) = scan::latest(session, &key)? else {
+                return Ok(JsonValue::Null);
+            };
+            let canonical = canonical_json(&value_to_json(vv.value));
+            let digest = format!("{:x}", Sha256::digest(canonical.as_bytes()));
+
+            if store {
+                let mut metadata = meta::read_meta(session, &key)?.unwrap_or_default();
+                metadata.insert(FINGERPRINT_FIELD.to_string(), JsonValue::String(digest.clone()));
+                meta::write_meta(session, &key, metadata)?;
+            }
+
+            Ok(serde_json::json!({
+                "key": key,
+                "version": vv.version,
+                "sha256": digest,
+            }))
+        }
+
+        "strata_json_find" => {
+            let key = get_string_arg(&args, "key")?;
+            let term = get_string_arg(&args, "term")?;
+            if term.is_empty() {
+                return Err(McpError::InvalidArg {
+                    name: "term".to_string(),
+                    reason: "must not be empty".to_string(),
+                });
+            }
+            let case_sensitive = get_optional_bool(&args, "case_sensitive").unwrap_or(false);
+            let limit = get_optional_u64(&args, "limit").unwrap_or(DEFAULT_FIND_LIMIT) as usize;
+
+            let Some(doc) = read_document(session, &key)? else {
+                return Ok(JsonValue::Null);
+            };
+
+            let fold = |text: &str| {
+                if case_sensitive {
+                    text.to_string()
+                } else {
+                    text.to_lowercase()
+                }
+            };
+            let needle = fold(&term);
+
+            let mut matches = Vec::new();
+            let mut truncated = false;
+            jsonpath::walk(&doc, &mut |path, node| {
+                let on_key = match path.last() {
+                    Some(jsonpath::Segment::Field(name)) => fold(name).contains(&needle),
+                    _ => false,
+                };
+                let on_value = match node {
+                    JsonValue::String(text) => fold(text).contains(&needle),
+                    JsonValue::Number(_) | JsonValue::Bool(_) => {
+                        fold(&node.to_string()).contains(&needle)
+                    }
+                    _ => false,
+                };
+                if !on_key && !on_value {
+                    return;
+                }
+                if matches.len() >= limit {
+                    truncated = true;
+                    return;
+                }
+                matches.push(serde_json::json!({
+                    "path": jsonpath::format(path),
+                    "matched": if on_key { "key" } else { "value" },
+                    "value": node,
+                }));
+            });
+            if truncated {
+                session.warn(
+                    WarningCode::Truncated,
+                    format!("Stopped after {} matches (limit).", limit),
+                );
+            }
+
+            Ok(serde_json::json!({
+                "key": key,
+                "term": term,
+                "matches": matches,
+                "truncated": truncated,
+            }))
+        }
+
+        "strata_json_rotate" => {
+            let key = get_string_arg(&args, "key")?;
+            let value = args
+                .get("value")
+                .cloned()
+                .ok_or_else(|| McpError::MissingArg("value".to_string()))?;
+            let keep = get_u64_arg(&args, "keep")?;
+            if keep == 0 {
+                return Err(McpError::InvalidArg {
+                    name: "keep".to_string(),
+                    reason: "must be at least 1".to_string(),
+                });
+            }
+            let path = get_optional_string(&args, "path").unwrap_or_else(|| "$".to_string());
+            let segments = jsonpath::parse(&path)?;
+
+            let own_txn = !session.in_transaction();
+            if own_txn {
+                session.execute(Command::TxnBegin {
+                    branch: session.branch_id(),
+                    options: Some(TxnOptions { read_only: false }),
+                })?;
+            }
+            let result = rotate(session, &key, &segments, value, keep as usize);
+            if own_txn {
+                match &result {
+                    Ok(_) => {
+                        session.execute(Command::TxnCommit)?;
+                    }
+                    // The rotation's error is the one to report, not the rollback's
+                    Err(_) => {
+                        let _ = session.execute(Command::TxnRollback);
+                    }
+                }
+            }
+            let (length, dropped, version) = result?;
+
+            Ok(serde_json::json!({
+                "key": key,
+                "path": path,
+                "length": length,
+                "dropped": dropped,
+                "version": version,
+            }))
+        }
+
+        "strata_json_canonicalize" => {
+            let key = get_string_arg(&args, "key")?;
+            let Some(vv) = scan::latest(session, &key)? else {
+                return Ok(JsonValue::Null);
+            };
+            let doc = value_to_json(vv.value);
+            let canonical = canonicalize(doc.clone());
+            // Compare serialized forms: `==` on JSON values ignores key order
+            let changed = doc.to_string() != canonical_json(&canonical);
+            if !changed {
+                return Ok(serde_json::json!({
+                    "key": key,
+                    "changed": false,
+                    "version": vv.version,
+                }));
+            }
+
+            let cmd = Command::JsonSet {
+                branch: session.branch_id(),
+                space: session.space_id(),
+                key: key.clone(),
+                path: "$".to_string(),
+                value: json_to_value(canonical)?,
+            };
+            let version = match session.execute(cmd)? {
+                Output::Version(v) => serde_json::json!(v),
+                other => output_to_json(other),
+            };
+            Ok(serde_json::json!({
+                "key": key,
+                "changed": true,
+                "version": version,
+            }))
+        }
+
+        "strata_json_dangling_refs" => {
+            let path = get_string_arg(&args, "path")?;
+            let segments = jsonpath::parse(&path)?;
+            let prefix = get_optional_string(&args, "prefix");
+            let limit = get_optional_u64(&args, "limit").unwrap_or(DEFAULT_DANGLING_LIMIT) as usize;
+
+            let existing: HashSet<String> = scan::list_all(session, None)?.into_iter().collect();
+            let mut scanned = 0;
+            let mut references = 0;
+            let mut dangling = Vec::new();
+            let mut truncated = false;
+            for key in scan::list_all(session, prefix)? {
+                let Some(doc) = read_document(session, &key)? else {
+                    continue;
+                };
+                scanned += 1;
+                let targets = match jsonpath::resolve(&doc, &segments) {
+                    Some(JsonValue::String(target)) => vec![target.as_str()],
+                    Some(JsonValue::Array(items)) => {
+                        items.iter().filter_map(|v| v.as_str()).collect()
+                    }
+                    _ => continue,
+                };
+                for target in targets {
+                    references += 1;
+                    if existing.contains(target) {
+                        continue;
+                    }
+                    if dangling.len() < limit {
+                        dangling.push(serde_json::json!({ "key": key, "target": target }));
+                    } else {
+                        truncated = true;
+                    }
+                }
+            }
+
+            Ok(serde_json::json!({
+                "scanned": scanned,
+                "references": references,
+                "dangling": dangling,
+                "truncated": truncated,
+            }))
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+/// Push `value` onto the array at `segments` in `key` and trim it to the
+/// last `keep` elements. Returns the new length, the number of elements
+/// dropped, and the write's version.
+fn rotate(
+    session: &mut McpSession,
+    key: &str,
+    segments: &[jsonpath::Segment],
+    value: JsonValue,
+    keep: usize,
+) -> Result<(usize, usize, JsonValue)> {
+    let mut doc = read_document(session, key)?.unwrap_or(JsonValue::Null);
+    let mut items = match jsonpath::resolve(&doc, segments) {
+        None | Some(JsonValue::Null) => Vec::new(),
+        Some(JsonValue::Array(items)) => items.clone(),
+        Some(other) => {
+            return Err(McpError::InvalidArg {
+                name: "path".to_string(),
+                reason: format!(
+                    "'{}' holds {}, not an array",
+                    jsonpath::format(segments),
+                    jsonpath::type_name(other)
+                ),
+            })
+        }
+    };
+    items.push(value);
+    let dropped = items.len().saturating_sub(keep);
+    items.drain(..dropped);
+    let length = items.len();
+
+    jsonpath::set(&mut doc, segments, JsonValue::Array(items))?;
+    let cmd = Command::JsonSet {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: key.to_string(),
+        path: "$".to_string(),
+        value: json_to_value(doc)?,
+    };
+    let version = match session.execute(cmd)? {
+        Output::Version(v) => serde_json::json!(v),
+        other => output_to_json(other),
+    };
+    Ok((length, dropped, version))
+}
@@ -0,0 +1,167 @@
+//! Key-level metadata tools.
+//!
+//! Tools: strata_meta_set, strata_meta_get
+//!
+//! The document store has no native per-key metadata, so metadata for `key`
+//! lives in a parallel JSON document at `__meta:<key>`. Keys starting with
+//! `__` are reserved for this and similar bookkeeping and are hidden from
+//! agent search results.
+
+use serde_json::{Map, Value as JsonValue};
+use stratadb::{Command, Output};
+
+use crate::convert::{get_optional_bool, get_string_arg, json_to_value, output_to_json};
+use crate::error::{McpError, Result};
+use crate::schema;
+use crate::session::McpSession;
+use crate::tools::agent::read_document;
+use crate::tools::ToolDef;
+
+/// Prefix marking keys reserved for server bookkeeping.
+pub const RESERVED_PREFIX: &str = "__";
+
+/// Prefix of the documents holding per-key metadata.
+const META_PREFIX: &str = "__meta:";
+
+/// Whether a key is reserved for server bookkeeping.
+pub fn is_reserved(key: &str) -> bool {
+    key.starts_with(RESERVED_PREFIX)
+}
+
+/// Key of the metadata document for `key`.
+pub(crate) fn meta_key(key: &str) -> String {
+    format!("{}{}", META_PREFIX, key)
+}
+
+/// Read the metadata object for `key`, or `None` if none has been set.
+pub fn read_meta(session: &mut McpSession, key: &str) -> Result<Option<Map<String, JsonValue>>> {
+    Ok(match read_document(session, &meta_key(key))? {
+        Some(JsonValue::Object(map)) => Some(map),
+        _ => None,
+    })
+}
+
+/// Replace the metadata object for `key`, returning the write's version.
+pub fn write_meta(
+    session: &mut McpSession,
+    key: &str,
+    metadata: Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    let cmd = Command::JsonSet {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: meta_key(key),
+        path: "$".to_string(),
+        value: json_to_value(JsonValue::Object(metadata))?,
+    };
+    Ok(match session.execute(cmd)? {
+        Output::Version(v) => serde_json::json!(v),
+        other => output_to_json(other),
+    })
+}
+
+/// Delete the metadata document for `key`, if any.
+pub fn delete_meta(session: &mut McpSession, key: &str) -> Result<()> {
+    let cmd = Command::JsonDelete {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: meta_key(key),
+        path: "$".to_string(),
+    };
+    session.execute(cmd)?;
+    Ok(())
+}
+
+/// Whether `meta` contains every field of `filter` with an equal value.
+pub fn matches(meta: Option<&Map<String, JsonValue>>, filter: &Map<String, JsonValue>) -> bool {
+    filter
+        .iter()
+        .all(|(field, expected)| meta.and_then(|m| m.get(field)) == Some(expected))
+}
+
+/// Get all metadata tool definitions.
+pub fn tools() -> Vec<ToolDef> {
+    vec![
+        ToolDef::new(
+            "strata_meta_set",
+            "Attach metadata (tags, source, confidence, ...) to a key without touching its value. \
+             'metadata' must be an object. By default it replaces any existing metadata; pass \
+             merge: true to update only the given fields. Stored separately from the value, so \
+             strata_recall output is unchanged. Returns { key, metadata, version }.",
+            schema!(object {
+                required: { "key": string, "metadata": any },
+                optional: { "merge": boolean }
+            }),
+        ),
+        ToolDef::new(
+            "strata_meta_get",
+            "Get the metadata attached to a key. Returns { key, metadata }, with metadata null \
+             if none has been set.",
+            schema!(object {
+                required: { "key": string }
+            }),
+        ),
+    ]
+}
+
+/// Dispatch a metadata tool call.
+pub fn dispatch(
+    session: &mut McpSession,
+    name: &str,
+    args: Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    match name {
+        "strata_meta_set" => {
+            let key = get_string_arg(&args, "key")?;
+            if is_reserved(&key) {
+                return Err(reserved_key_error(&key));
+            }
+            let mut metadata = match args.get("metadata") {
+                Some(JsonValue::Object(map)) => map.clone(),
+                Some(_) => {
+                    return Err(McpError::InvalidArg {
+                        name: "metadata".to_string(),
+                        reason: "Expected an object".to_string(),
+                    })
+                }
+                None => return Err(McpError::MissingArg("metadata".to_string())),
+            };
+
+            if get_optional_bool(&args, "merge").unwrap_or(false) {
+                if let Some(mut existing) = read_meta(session, &key)? {
+                    existing.extend(metadata);
+                    metadata = existing;
+                }
+            }
+
+            let version = write_meta(session, &key, metadata.clone())?;
+
+            Ok(serde_json::json!({
+                "key": key,
+                "metadata": metadata,
+                "version": version,
+            }))
+        }
+
+        "strata_meta_get" => {
+            let key = get_string_arg(&args, "key")?;
+            let metadata = read_meta(session, &key)?;
+            Ok(serde_json::json!({
+                "key": key,
+                "metadata": metadata,
+            }))
+        }
+
+        _ => Err(McpError::UnknownTool(name.to_string())),
+    }
+}
+
+pub(crate) fn reserved_key_error(key: &str) -> McpError {
+    McpError::InvalidArg {
+        name: "key".to_string(),
+        reason: format!(
+            "'{}' is reserved: keys starting with '{}' hold server bookkeeping",
+            key, RESERVED_PREFIX
+        ),
+    }
+}
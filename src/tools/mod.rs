@@ -1,30 +1,40 @@
 //! Tool registry and dispatch.
 //!
-//! Exposes 8 intent-driven tools for AI agents. The granular per-primitive tools
+//! Exposes 10 intent-driven tools for AI agents. The granular per-primitive tools
 //! (KV, JSON, State, Vector, etc.) are compiled for internal use and testing but
 //! are not registered in the MCP tool surface.
 
 pub mod agent;
 
 // Internal tool modules — compiled for tests, not exposed via MCP
+pub(crate) mod attach;
 pub(crate) mod branch;
 pub(crate) mod bundle;
 pub(crate) mod config;
 pub(crate) mod database;
+pub(crate) mod debug;
+pub(crate) mod developer;
 pub(crate) mod durability;
 pub(crate) mod embed;
 pub(crate) mod event;
+pub(crate) mod export;
+pub(crate) mod import;
 pub(crate) mod inference;
 pub(crate) mod json;
 pub(crate) mod kv;
+pub(crate) mod meta;
 pub(crate) mod models;
 pub(crate) mod retention;
+pub(crate) mod scan;
 pub(crate) mod search;
 pub(crate) mod space;
 pub(crate) mod state;
 pub(crate) mod txn;
 pub(crate) mod vector;
 
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value as JsonValue};
 
@@ -41,6 +51,67 @@ pub struct ToolDef {
     /// JSON Schema for the input parameters
     #[serde(rename = "inputSchema")]
     pub input_schema: JsonValue,
+    /// Sample calls showing the tool's contract
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ToolExample>,
+    /// Behavior hints for clients (read-only, destructive, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+    /// JSON Schema for the structured result, when it is an object
+    #[serde(rename = "outputSchema", default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<JsonValue>,
+}
+
+/// MCP tool annotations: hints about a tool's side effects.
+///
+/// Clients use these to decide when to ask the user for confirmation. They
+/// are hints, not guarantees.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    /// The tool does not modify any data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// The tool may overwrite or delete existing data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// Repeating a call with the same arguments has no additional effect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// The tool interacts with entities outside the database
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    /// Annotations for a tool that only reads the database.
+    pub fn read_only() -> Self {
+        Self {
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        }
+    }
+
+    /// Annotations for a tool that writes to the database.
+    pub fn write(destructive: bool, idempotent: bool) -> Self {
+        Self {
+            read_only_hint: Some(false),
+            destructive_hint: Some(destructive),
+            idempotent_hint: Some(idempotent),
+            open_world_hint: Some(false),
+        }
+    }
+}
+
+/// A sample input and the output it produces.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolExample {
+    /// Arguments passed to the tool
+    pub input: JsonValue,
+    /// Result the tool returns for them
+    pub output: JsonValue,
 }
 
 impl ToolDef {
@@ -50,26 +121,115 @@ impl ToolDef {
             name: name.to_string(),
             description: description.to_string(),
             input_schema,
+            examples: Vec::new(),
+            annotations: None,
+            output_schema: None,
         }
     }
+
+    /// Set the behavior hints listed in `tools/list`.
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Set the JSON Schema of the tool's structured result, listed in
+    /// `tools/list` as `outputSchema`.
+    pub fn with_output_schema(mut self, output_schema: JsonValue) -> Self {
+        self.output_schema = Some(output_schema);
+        self
+    }
+
+    /// Add a sample input/output pair, listed in `tools/list`.
+    pub fn with_example(mut self, input: JsonValue, output: JsonValue) -> Self {
+        self.examples.push(ToolExample { input, output });
+        self
+    }
 }
 
 /// Registry of available MCP tools.
+#[derive(Clone)]
 pub struct ToolRegistry {
     tools: Vec<ToolDef>,
     developer_mode: bool,
+    developer_access: bool,
+    debug_tools: bool,
+    attach: bool,
+    /// Developer tools listed alongside the agent tools
+    developer_tools: BTreeSet<String>,
 }
 
 impl ToolRegistry {
-    /// Create the tool registry with the 8 agent-friendly tools.
+    /// Create the tool registry with the 10 agent-friendly tools.
     pub fn new() -> Self {
         Self {
             tools: agent::tools(),
             developer_mode: false,
+            developer_access: false,
+            debug_tools: false,
+            attach: false,
+            developer_tools: BTreeSet::new(),
+        }
+    }
+
+    /// Add the `strata_developer` escape hatch to an agent registry.
+    ///
+    /// Lets agents list, inspect, and call individual developer tools while
+    /// keeping the default surface small. Enabled by `--developer-access`.
+    pub fn with_developer_access(mut self) -> Self {
+        if !self.developer_mode && !self.developer_access {
+            self.tools.push(developer::tool());
+            self.developer_access = true;
+        }
+        self
+    }
+
+    /// Add the `strata_debug_*` diagnostic tools to an agent registry.
+    ///
+    /// Enabled by `--debug`; never part of a production tool surface.
+    pub fn with_debug_tools(mut self) -> Self {
+        if !self.developer_mode && !self.debug_tools {
+            self.tools.extend(debug::tools());
+            self.debug_tools = true;
+        }
+        self
+    }
+
+    /// Add `strata_attach` to an agent registry.
+    ///
+    /// Lets agents attach other databases read-only and read them with
+    /// `alias:key` keys. Enabled by `--allow-attach`.
+    pub fn with_attach(mut self) -> Self {
+        if !self.developer_mode && !self.attach {
+            self.tools.push(attach::tool());
+            self.attach = true;
+        }
+        self
+    }
+
+    /// List every developer tool alongside the agent tools.
+    ///
+    /// Used when `strata_developer` enables them at runtime. A developer
+    /// tool named like an agent tool stays hidden behind the agent one.
+    pub fn with_developer_tools(mut self) -> Self {
+        if self.developer_mode || !self.developer_tools.is_empty() {
+            return self;
+        }
+        for tool in developer_registry().tools() {
+            if !self.tools.iter().any(|t| t.name == tool.name) {
+                self.developer_tools.insert(tool.name.clone());
+                self.tools.push(tool.clone());
+            }
         }
+        self
     }
 
-    /// Create a registry with all 74 granular developer tools.
+    /// Whether the developer tools are listed alongside the agent tools.
+    pub fn lists_developer_tools(&self) -> bool {
+        !self.developer_tools.is_empty()
+    }
+
+    /// Create a registry with all 104 granular developer tools.
     ///
     /// Not exposed via the MCP CLI. Used for integration testing of individual
     /// tool modules against the underlying Strata primitives.
@@ -80,6 +240,9 @@ impl ToolRegistry {
         tools.extend(state::tools());
         tools.extend(event::tools());
         tools.extend(json::tools());
+        tools.extend(import::tools());
+        tools.extend(export::tools());
+        tools.extend(meta::tools());
         tools.extend(space::tools());
         tools.extend(branch::tools());
         tools.extend(vector::tools());
@@ -95,6 +258,10 @@ impl ToolRegistry {
         Self {
             tools,
             developer_mode: true,
+            developer_access: false,
+            debug_tools: false,
+            attach: false,
+            developer_tools: BTreeSet::new(),
         }
     }
 
@@ -103,14 +270,55 @@ impl ToolRegistry {
         &self.tools
     }
 
+    /// Whether `name` is a listed tool annotated as read-only.
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.tools.iter().any(|t| {
+            t.name == name
+                && t.annotations.as_ref().and_then(|a| a.read_only_hint) == Some(true)
+        })
+    }
+
     /// Dispatch a tool call to the appropriate handler.
+    ///
+    /// Each call to a listed tool is counted once in the session's usage
+    /// profile, see [`McpSession::tool_calls`]; unknown names are not.
     pub fn dispatch(
         &self,
         session: &mut McpSession,
         name: &str,
         args: Map<String, JsonValue>,
+    ) -> Result<JsonValue> {
+        let outcome = self.dispatch_tool(session, name, args);
+        if self.tools.iter().any(|t| t.name == name) {
+            session.record_tool_call(name, outcome.is_ok());
+        }
+        outcome
+    }
+
+    fn dispatch_tool(
+        &self,
+        session: &mut McpSession,
+        name: &str,
+        args: Map<String, JsonValue>,
     ) -> Result<JsonValue> {
         if !self.developer_mode {
+            if self.developer_access && name == developer::NAME {
+                return developer::dispatch(session, args);
+            }
+            if self.debug_tools && name.starts_with(debug::PREFIX) {
+                return debug::dispatch(session, name, args, &self.tools);
+            }
+            if self.developer_tools.contains(name) {
+                return developer_registry().dispatch_tool(session, name, args);
+            }
+            if self.attach && name == attach::NAME {
+                return attach::dispatch(session, args);
+            }
+            // Keys qualified with an attached alias are read there
+            let mut args = args;
+            if let Some(mut attached) = attach::route(session, &mut args)? {
+                return agent::dispatch(&mut attached, name, args);
+            }
             return agent::dispatch(session, name, args);
         }
 
@@ -125,6 +333,12 @@ impl ToolRegistry {
             event::dispatch(session, name, args)
         } else if name.starts_with("strata_json_") {
             json::dispatch(session, name, args)
+        } else if name.starts_with("strata_import_") {
+            import::dispatch(session, name, args)
+        } else if name.starts_with("strata_export_") {
+            export::dispatch(session, name, args)
+        } else if name.starts_with("strata_meta_") {
+            meta::dispatch(session, name, args)
         } else if name.starts_with("strata_space_") {
             space::dispatch(session, name, args)
         } else if name.starts_with("strata_branch_") {
@@ -146,6 +360,7 @@ impl ToolRegistry {
         } else if name.starts_with("strata_generate")
             || name.starts_with("strata_tokenize")
             || name.starts_with("strata_detokenize")
+            || name == "strata_summarize"
         {
             inference::dispatch(session, name, args)
         } else if name.starts_with("strata_models_") {
@@ -158,6 +373,15 @@ impl ToolRegistry {
     }
 }
 
+/// The developer registry, built on first use and shared afterwards.
+///
+/// Developer tools are dispatched and described through it at runtime, so
+/// it is built once rather than on every call.
+pub(crate) fn developer_registry() -> &'static ToolRegistry {
+    static DEVELOPER: OnceLock<ToolRegistry> = OnceLock::new();
+    DEVELOPER.get_or_init(ToolRegistry::developer)
+}
+
 impl Default for ToolRegistry {
     fn default() -> Self {
         Self::new()
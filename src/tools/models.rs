@@ -1,9 +1,9 @@
 //! Model management tools.
 //!
-//! Tools: strata_models_list, strata_models_pull, strata_models_local
+//! Tools: strata_models_list, strata_models_pull, strata_models_local, strata_models_loaded
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::Command;
+use stratadb::{Command, Output};
 
 use crate::convert::{get_string_arg, output_to_json};
 use crate::error::{McpError, Result};
@@ -23,8 +23,11 @@ pub fn tools() -> Vec<ToolDef> {
         ),
         ToolDef::new(
             "strata_models_pull",
-            "Download a model by name from the registry. Returns a dict with the \
-             model name and local file path.",
+            "Download a model by name from the registry. Returns { name, path, already_local }. \
+             A model that is already on disk is not downloaded again: the call returns its \
+             local path with already_local: true and its size_bytes. \
+             Re-issuing a pull after an interrupted download starts that download over; \
+             partial files are not resumed.",
             schema!(object {
                 required: { "name": string }
             }),
@@ -35,6 +38,14 @@ pub fn tools() -> Vec<ToolDef> {
              strata_models_list but only includes models available on disk.",
             schema!(object {}),
         ),
+        ToolDef::new(
+            "strata_models_loaded",
+            "List models currently loaded in memory (resident after strata_generate and \
+             not yet released with strata_generate_unload). Distinct from strata_models_local, \
+             which lists models on disk. Returns an array of { name, size_bytes } where \
+             size_bytes is the model file size, a close estimate of its memory footprint.",
+            schema!(object {}),
+        ),
     ]
 }
 
@@ -52,8 +63,25 @@ pub fn dispatch(
 
         "strata_models_pull" => {
             let name_arg = get_string_arg(&args, "name")?;
+
+            let local = match session.execute(Command::ModelsLocal)? {
+                Output::ModelsList(models) => {
+                    models.into_iter().find(|m| m.name == name_arg).map(|m| m.size_bytes)
+                }
+                _ => None,
+            };
+
+            // For a model already on disk the engine finds the file in its
+            // cache and returns that path without downloading anything
             let output = session.execute(Command::ModelsPull { name: name_arg })?;
-            Ok(output_to_json(output))
+            let mut result = output_to_json(output);
+            if let Some(obj) = result.as_object_mut() {
+                obj.insert("already_local".to_string(), JsonValue::Bool(local.is_some()));
+                if let Some(size_bytes) = local {
+                    obj.insert("size_bytes".to_string(), serde_json::json!(size_bytes));
+                }
+            }
+            Ok(result)
         }
 
         "strata_models_local" => {
@@ -61,6 +89,32 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_models_loaded" => {
+            if session.loaded_models().is_empty() {
+                return Ok(JsonValue::Array(Vec::new()));
+            }
+
+            // Resident weights match the on-disk files, so use the local
+            // catalog for the footprint.
+            let local = match session.execute(Command::ModelsLocal)? {
+                Output::ModelsList(models) => models,
+                _ => Vec::new(),
+            };
+
+            let arr: Vec<JsonValue> = session
+                .loaded_models()
+                .iter()
+                .map(|name| {
+                    let size_bytes = local.iter().find(|m| &m.name == name).map(|m| m.size_bytes);
+                    serde_json::json!({
+                        "name": name,
+                        "size_bytes": size_bytes,
+                    })
+                })
+                .collect();
+            Ok(JsonValue::Array(arr))
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
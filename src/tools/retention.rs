@@ -1,31 +1,59 @@
 //! Retention tools.
 //!
-//! Tools: strata_retention_apply
+//! Tools: strata_retention_apply, strata_retention_version_stats
 
 use serde_json::{Map, Value as JsonValue};
 use stratadb::Command;
 
-use crate::convert::output_to_json;
+use crate::convert::{get_optional_u64, output_to_json};
 use crate::error::{McpError, Result};
 use crate::schema;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::{scan, ToolAnnotations, ToolDef};
+
+/// Keys listed by strata_retention_version_stats unless `top` says otherwise.
+const DEFAULT_TOP_KEYS: u64 = 10;
+
+/// Histogram buckets as (label, lowest count, highest count).
+const VERSION_BUCKETS: &[(&str, u64, u64)] = &[
+    ("1", 1, 1),
+    ("2-4", 2, 4),
+    ("5-9", 5, 9),
+    ("10-49", 10, 49),
+    ("50-99", 50, 99),
+    ("100+", 100, u64::MAX),
+];
 
 /// Get all retention tool definitions.
 pub fn tools() -> Vec<ToolDef> {
-    vec![ToolDef::new(
-        "strata_retention_apply",
-        "Apply the retention policy to the current branch, trimming old versions \
-         and expired data according to configured rules. Returns null on success.",
-        schema!(object {}),
-    )]
+    vec![
+        ToolDef::new(
+            "strata_retention_apply",
+            "Apply the retention policy to the current branch, trimming old versions \
+             and expired data according to configured rules. Returns null on success.",
+            schema!(object {}),
+        ),
+        ToolDef::new(
+            "strata_retention_version_stats",
+            "Show how stored versions are spread across the documents of the current branch \
+             and space, to find the keys driving history growth before applying retention. \
+             Returns { keys, versions, min, max, mean, histogram: [{ versions, keys }], top: \
+             [{ key, versions }] } where 'histogram' buckets keys by version count (1, 2-4, \
+             5-9, 10-49, 50-99, 100+) and 'top' lists the 'top' keys (default 10) with the \
+             most versions. Reads every key's history, so it is slow on large branches.",
+            schema!(object {
+                optional: { "top": integer }
+            }),
+        )
+        .with_annotations(ToolAnnotations::read_only()),
+    ]
 }
 
 /// Dispatch a retention tool call.
 pub fn dispatch(
     session: &mut McpSession,
     name: &str,
-    _args: Map<String, JsonValue>,
+    args: Map<String, JsonValue>,
 ) -> Result<JsonValue> {
     match name {
         "strata_retention_apply" => {
@@ -36,6 +64,46 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_retention_version_stats" => {
+            let top = get_optional_u64(&args, "top").unwrap_or(DEFAULT_TOP_KEYS) as usize;
+
+            let mut counts = Vec::new();
+            for key in scan::list_all(session, None)? {
+                let versions = scan::version_count(session, &key)?;
+                counts.push((key, versions));
+            }
+            let total: u64 = counts.iter().map(|(_, n)| n).sum();
+            let mean = if counts.is_empty() {
+                0.0
+            } else {
+                total as f64 / counts.len() as f64
+            };
+            let histogram: Vec<JsonValue> = VERSION_BUCKETS
+                .iter()
+                .map(|&(label, low, high)| {
+                    let keys = counts.iter().filter(|(_, n)| (low..=high).contains(n)).count();
+                    serde_json::json!({ "versions": label, "keys": keys })
+                })
+                .collect();
+
+            // Most versions first; ties by key for stable output
+            counts.sort_by(|(ka, a), (kb, b)| b.cmp(a).then_with(|| ka.cmp(kb)));
+            let result = serde_json::json!({
+                "keys": counts.len(),
+                "versions": total,
+                "min": counts.iter().map(|(_, n)| *n).min(),
+                "max": counts.first().map(|(_, n)| *n),
+                "mean": mean,
+                "histogram": histogram,
+                "top": counts
+                    .into_iter()
+                    .take(top)
+                    .map(|(key, versions)| serde_json::json!({ "key": key, "versions": versions }))
+                    .collect::<Vec<_>>(),
+            });
+            Ok(result)
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
@@ -0,0 +1,351 @@
+//! Key scanning helpers shared by tools that walk the document store.
+//!
+//! The engine lists keys in pages via `JsonList`; these helpers wrap that
+//! paging, existence checks, and the per-key latest-version lookup so tools don't each
+//! reimplement the loop. Reserved bookkeeping keys are skipped except by
+//! `list_reserved`.
+
+use serde_json::Value as JsonValue;
+use stratadb::{BranchId, Command, Output, VersionedValue};
+
+use crate::convert::output_to_json;
+use crate::error::Result;
+use crate::session::McpSession;
+use crate::tools::meta;
+
+/// Page size used when walking every key.
+const SCAN_PAGE_SIZE: u64 = 500;
+
+/// One page of document keys.
+pub struct KeyPage {
+    /// Keys in this page, reserved keys excluded.
+    pub keys: Vec<String>,
+    /// Cursor for the next page, or `None` at the end.
+    pub cursor: Option<String>,
+}
+
+/// List one page of document keys on the current branch and space.
+pub fn list_page(
+    session: &mut McpSession,
+    prefix: Option<String>,
+    cursor: Option<String>,
+    limit: u64,
+) -> Result<KeyPage> {
+    let branch = session.branch_id();
+    list_page_on(session, branch, prefix, cursor, limit)
+}
+
+/// List one page of document keys on `branch` in the current space.
+fn list_page_on(
+    session: &mut McpSession,
+    branch: Option<BranchId>,
+    prefix: Option<String>,
+    cursor: Option<String>,
+    limit: u64,
+) -> Result<KeyPage> {
+    let space = session.space_id();
+    let mut page = fetch_page(session, branch, space, prefix, cursor, limit, None)?;
+    page.keys.retain(|k| !meta::is_reserved(k));
+    Ok(page)
+}
+
+/// List one page of document keys in `space` on the current branch.
+pub fn list_page_in(
+    session: &mut McpSession,
+    space: &str,
+    cursor: Option<String>,
+    limit: u64,
+) -> Result<KeyPage> {
+    let branch = session.branch_id();
+    let space = Some(space.to_string());
+    let mut page = fetch_page(session, branch, space, None, cursor, limit, None)?;
+    page.keys.retain(|k| !meta::is_reserved(k));
+    Ok(page)
+}
+
+/// One raw page from `JsonList`, reserved keys included.
+fn fetch_page(
+    session: &mut McpSession,
+    branch: Option<BranchId>,
+    space: Option<String>,
+    prefix: Option<String>,
+    cursor: Option<String>,
+    limit: u64,
+    as_of: Option<u64>,
+) -> Result<KeyPage> {
+    let cmd = Command::JsonList {
+        branch,
+        space,
+        prefix,
+        cursor,
+        limit,
+        as_of,
+    };
+    match session.execute(cmd)? {
+        Output::JsonListResult { keys, cursor } => Ok(KeyPage { keys, cursor }),
+        Output::Keys(keys) => Ok(KeyPage { keys, cursor: None }),
+        _ => Ok(KeyPage {
+            keys: Vec::new(),
+            cursor: None,
+        }),
+    }
+}
+
+/// List every document key on the current branch and space.
+pub fn list_all(session: &mut McpSession, prefix: Option<String>) -> Result<Vec<String>> {
+    let branch = session.branch_id();
+    list_all_on(session, branch, prefix)
+}
+
+/// List every document key on `branch` in the current space.
+pub fn list_all_on(
+    session: &mut McpSession,
+    branch: Option<BranchId>,
+    prefix: Option<String>,
+) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = list_page_on(session, branch.clone(), prefix.clone(), cursor, SCAN_PAGE_SIZE)?;
+        keys.extend(page.keys);
+        match page.cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(keys),
+        }
+    }
+}
+
+/// List every document key in `space` on the current branch.
+pub fn list_all_in(
+    session: &mut McpSession,
+    space: &str,
+    prefix: Option<String>,
+) -> Result<Vec<String>> {
+    let branch = session.branch_id();
+    list_all_on_in(session, branch, space, prefix)
+}
+
+/// List every document key in `space` on `branch`.
+pub fn list_all_on_in(
+    session: &mut McpSession,
+    branch: Option<BranchId>,
+    space: &str,
+    prefix: Option<String>,
+) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut cursor = None;
+    loop {
+        let mut page = fetch_page(
+            session,
+            branch.clone(),
+            Some(space.to_string()),
+            prefix.clone(),
+            cursor,
+            SCAN_PAGE_SIZE,
+            None,
+        )?;
+        page.keys.retain(|k| !meta::is_reserved(k));
+        keys.extend(page.keys);
+        match page.cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(keys),
+        }
+    }
+}
+
+/// Oldest and newest version timestamps of a document in `space` on the
+/// current branch, or `None` if it has no history.
+pub fn time_range_in(
+    session: &mut McpSession,
+    space: &str,
+    key: &str,
+) -> Result<Option<(u64, u64)>> {
+    let cmd = Command::JsonGetv {
+        branch: session.branch_id(),
+        space: Some(space.to_string()),
+        key: key.to_string(),
+        as_of: None,
+    };
+    match session.execute(cmd)? {
+        Output::VersionHistory(Some(versions)) => {
+            let timestamps = versions.iter().map(|vv| vv.timestamp);
+            Ok(timestamps.clone().min().zip(timestamps.max()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// List every document key that existed on the current branch and space at
+/// `as_of` (microseconds since epoch).
+pub fn list_all_at(session: &mut McpSession, as_of: u64) -> Result<Vec<String>> {
+    let branch = session.branch_id();
+    let mut keys = Vec::new();
+    let mut cursor = None;
+    loop {
+        let space = session.space_id();
+        let mut page = fetch_page(
+            session,
+            branch.clone(),
+            space,
+            None,
+            cursor,
+            SCAN_PAGE_SIZE,
+            Some(as_of),
+        )?;
+        page.keys.retain(|k| !meta::is_reserved(k));
+        keys.extend(page.keys);
+        match page.cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(keys),
+        }
+    }
+}
+
+/// List every reserved bookkeeping key under `prefix` on the current branch and space.
+pub fn list_reserved(session: &mut McpSession, prefix: &str) -> Result<Vec<String>> {
+    let branch = session.branch_id();
+    let mut keys = Vec::new();
+    let mut cursor = None;
+    loop {
+        let space = session.space_id();
+        let page = fetch_page(
+            session,
+            branch.clone(),
+            space,
+            Some(prefix.to_string()),
+            cursor,
+            SCAN_PAGE_SIZE,
+            None,
+        )?;
+        keys.extend(page.keys);
+        match page.cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(keys),
+        }
+    }
+}
+
+/// Whether a document exists on the current branch and space, checked by
+/// listing keys so no value is read. Keys list in order, so `key` itself is
+/// the first match for its own prefix.
+pub fn exists(session: &mut McpSession, key: &str) -> Result<bool> {
+    let branch = session.branch_id();
+    let space = session.space_id();
+    let page = fetch_page(session, branch, space, Some(key.to_string()), None, 1, None)?;
+    Ok(page.keys.first().is_some_and(|k| k == key))
+}
+
+/// Number of stored versions of a document (0 if it has no history).
+pub fn version_count(session: &mut McpSession, key: &str) -> Result<u64> {
+    let cmd = Command::JsonGetv {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: key.to_string(),
+        as_of: None,
+    };
+    match session.execute(cmd)? {
+        Output::VersionHistory(Some(versions)) => Ok(versions.len() as u64),
+        _ => Ok(0),
+    }
+}
+
+/// Total stored versions across the documents on the current branch and
+/// space, or `None` if there are more than `max_keys` documents to read.
+pub fn total_versions_up_to(session: &mut McpSession, max_keys: u64) -> Result<Option<u64>> {
+    let page = list_page(session, None, None, max_keys)?;
+    if page.cursor.is_some() {
+        return Ok(None);
+    }
+    let mut total = 0;
+    for key in page.keys {
+        total += version_count(session, &key)?;
+    }
+    Ok(Some(total))
+}
+
+/// Latest version of a document, or `None` if the key doesn't exist.
+pub fn latest(session: &mut McpSession, key: &str) -> Result<Option<VersionedValue>> {
+    let branch = session.branch_id();
+    latest_on(session, branch, key)
+}
+
+/// Version of a document current at `as_of`, or `None` if it didn't exist then.
+pub fn latest_at(
+    session: &mut McpSession,
+    key: &str,
+    as_of: u64,
+) -> Result<Option<VersionedValue>> {
+    let cmd = Command::JsonGet {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: key.to_string(),
+        path: "$".to_string(),
+        as_of: Some(as_of),
+    };
+    match session.execute(cmd)? {
+        Output::MaybeVersioned(opt) => Ok(opt),
+        _ => Ok(None),
+    }
+}
+
+/// Latest version of a document on `branch` in the current space.
+pub fn latest_on(
+    session: &mut McpSession,
+    branch: Option<BranchId>,
+    key: &str,
+) -> Result<Option<VersionedValue>> {
+    let cmd = Command::JsonGet {
+        branch,
+        space: session.space_id(),
+        key: key.to_string(),
+        path: "$".to_string(),
+        as_of: None,
+    };
+    match session.execute(cmd)? {
+        Output::MaybeVersioned(opt) => Ok(opt),
+        _ => Ok(None),
+    }
+}
+
+/// The latest `limit` committed document writes across every space of the
+/// current branch, newest first, as `{ op, space, key, version, timestamp }`
+/// where `op` is `create` for a key's first version and `update` otherwise.
+pub fn recent_writes(session: &mut McpSession, limit: usize) -> Result<Vec<JsonValue>> {
+    let spaces = output_to_json(session.execute(Command::SpaceList {
+        branch: session.branch_id(),
+    })?);
+    let mut writes = Vec::new();
+    for space in spaces.as_array().into_iter().flatten().filter_map(|s| s.as_str()) {
+        for key in list_all_in(session, space, None)? {
+            let cmd = Command::JsonGetv {
+                branch: session.branch_id(),
+                space: Some(space.to_string()),
+                key: key.clone(),
+                as_of: None,
+            };
+            let Output::VersionHistory(Some(versions)) = session.execute(cmd)? else {
+                continue;
+            };
+            let first = versions.iter().map(|vv| vv.version).min();
+            for vv in versions {
+                let op = if Some(vv.version) == first { "create" } else { "update" };
+                writes.push((vv.timestamp, vv.version, op, space.to_string(), key.clone()));
+            }
+        }
+    }
+    writes.sort_by(|a, b| (b.0, b.1).cmp(&(a.0, a.1)));
+    writes.truncate(limit);
+
+    Ok(writes
+        .into_iter()
+        .map(|(timestamp, version, op, space, key)| {
+            serde_json::json!({
+                "op": op,
+                "space": space,
+                "key": key,
+                "version": version,
+                "timestamp": timestamp,
+            })
+        })
+        .collect())
+}
@@ -1,6 +1,6 @@
 //! Cross-primitive search tools.
 //!
-//! Tools: strata_search
+//! Tools: strata_search, strata_search_eval
 
 use serde_json::{Map, Value as JsonValue};
 use stratadb::{Command, SearchQuery, TimeRangeInput};
@@ -9,37 +9,55 @@ use crate::convert::{
     get_optional_bool, get_optional_string, get_optional_u64, get_string_arg, output_to_json,
 };
 use crate::error::{McpError, Result};
+use crate::schema;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::{agent, ToolDef};
+
+/// Results considered by strata_search_eval when `k` is not given.
+const DEFAULT_EVAL_K: u64 = 10;
 
 /// Get all search tool definitions.
 pub fn tools() -> Vec<ToolDef> {
-    vec![ToolDef::new(
-        "strata_search",
-        "Search across multiple primitives (kv, json, state, event) for matching content. \
-         Returns ranked results with scores and snippets. Use this to find data when you \
-         don't know which primitive contains it.",
-        serde_json::json!({
-            "type": "object",
-            "properties": {
-                "query": { "type": "string" },
-                "k": { "type": "integer" },
-                "primitives": { "type": "array", "items": { "type": "string" } },
-                "time_range": {
-                    "type": "object",
-                    "properties": {
-                        "start": { "type": "string" },
-                        "end": { "type": "string" }
+    vec![
+        ToolDef::new(
+            "strata_search",
+            "Search across multiple primitives (kv, json, state, event) for matching content. \
+             Returns ranked results with scores and snippets. Use this to find data when you \
+             don't know which primitive contains it.",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "k": { "type": "integer" },
+                    "primitives": { "type": "array", "items": { "type": "string" } },
+                    "time_range": {
+                        "type": "object",
+                        "properties": {
+                            "start": { "type": "string" },
+                            "end": { "type": "string" }
+                        },
+                        "required": ["start", "end"]
                     },
-                    "required": ["start", "end"]
+                    "mode": { "type": "string", "enum": ["keyword", "hybrid"] },
+                    "expand": { "type": "boolean" },
+                    "rerank": { "type": "boolean" }
                 },
-                "mode": { "type": "string", "enum": ["keyword", "hybrid"] },
-                "expand": { "type": "boolean" },
-                "rerank": { "type": "boolean" }
-            },
-            "required": ["query"]
-        }),
-    )]
+                "required": ["query"]
+            }),
+        ),
+        ToolDef::new(
+            "strata_search_eval",
+            "Evaluation tool for tuning search: run a query through the agent-facing strata_search \
+             and check where known-relevant documents land. Pass 'query', the 'expected' keys, and \
+             optionally 'k' (default 10). Returns { query, k, recall, found, expected: [{ key, rank \
+             }] } where 'recall' is recall@k (the fraction of expected keys in the top k) and \
+             'rank' is the 1-based position of each key in the results, or null if it wasn't found.",
+            schema!(object {
+                required: { "query": string, "expected": array_string },
+                optional: { "k": integer }
+            }),
+        ),
+    ]
 }
 
 /// Dispatch a search tool call.
@@ -77,10 +95,54 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_search_eval" => eval(session, args),
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
 
+/// Run a query through the agent search and score it against expected keys.
+fn eval(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let query = get_string_arg(&args, "query")?;
+    let k = get_optional_u64(&args, "k").unwrap_or(DEFAULT_EVAL_K);
+    let expected = get_optional_string_array(&args, "expected")
+        .ok_or_else(|| McpError::MissingArg("expected".to_string()))?;
+    if expected.is_empty() {
+        return Err(McpError::InvalidArg {
+            name: "expected".to_string(),
+            reason: "Give at least one expected key".to_string(),
+        });
+    }
+
+    let mut search_args = Map::new();
+    search_args.insert("query".to_string(), JsonValue::String(query.clone()));
+    search_args.insert("k".to_string(), JsonValue::from(k));
+    let hits = agent::dispatch(session, "strata_search", search_args)?;
+    let ranked: Vec<&str> = hits
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|hit| hit["key"].as_str())
+        .collect();
+
+    let entries: Vec<JsonValue> = expected
+        .iter()
+        .map(|key| {
+            let rank = ranked.iter().position(|hit| hit == key).map(|i| i + 1);
+            serde_json::json!({ "key": key, "rank": rank })
+        })
+        .collect();
+    let found = entries.iter().filter(|e| !e["rank"].is_null()).count();
+
+    Ok(serde_json::json!({
+        "query": query,
+        "k": k,
+        "recall": found as f64 / expected.len() as f64,
+        "found": found,
+        "expected": entries,
+    }))
+}
+
 /// Helper to get an optional array of strings.
 fn get_optional_string_array(args: &Map<String, JsonValue>, name: &str) -> Option<Vec<String>> {
     args.get(name).and_then(|v| v.as_array()).map(|arr| {
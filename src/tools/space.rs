@@ -1,15 +1,16 @@
 //! Space management tools.
 //!
-//! Tools: strata_space_list, strata_space_create, strata_space_delete, strata_space_switch
+//! Tools: strata_space_list, strata_space_create, strata_space_delete, strata_space_switch,
+//!        strata_space_migrate
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::Command;
+use stratadb::{Command, Output, TxnOptions};
 
-use crate::convert::{get_optional_bool, get_string_arg, output_to_json};
+use crate::convert::{get_optional_bool, get_optional_string, get_string_arg, output_to_json};
 use crate::error::{McpError, Result};
 use crate::schema;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::{meta, scan, ToolDef};
 
 /// Get all space tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -17,8 +18,14 @@ pub fn tools() -> Vec<ToolDef> {
         ToolDef::new(
             "strata_space_list",
             "List all spaces in the current branch. Spaces are logical partitions \
-             within a branch that isolate data by namespace.",
-            schema!(object {}),
+             within a branch that isolate data by namespace. Pass with_counts: true to get \
+             [{ space, keys }] with the number of documents in each space instead of bare \
+             names; add with_time_range: true to also get 'oldest' and 'latest', the first and \
+             last write timestamps in the space (null when empty). Spot empty or stale spaces \
+             this way; counting reads every key, so it is slower on large spaces.",
+            schema!(object {
+                optional: { "with_counts": boolean, "with_time_range": boolean }
+            }),
         ),
         ToolDef::new(
             "strata_space_create",
@@ -52,6 +59,26 @@ pub fn tools() -> Vec<ToolDef> {
                 required: { "space": string }
             }),
         ),
+        ToolDef::new(
+            "strata_space_migrate",
+            "Move or copy documents from one space to another on the current branch, in a \
+             single transaction. Pass 'from', 'to' (both must exist), and optionally 'prefix' to \
+             limit which keys are migrated. 'mode' is 'move' (default; the source documents are \
+             deleted) or 'copy'. A key that already exists in 'to' is skipped unless \
+             overwrite: true. Pass with_history: true to replay every version of each document \
+             oldest first instead of only the latest. Metadata set with strata_meta_set goes \
+             with each key. Returns { migrated, skipped, keys: [{ key, \
+             status }] } where status is 'moved', 'copied', or 'skipped'.",
+            schema!(object {
+                required: { "from": string, "to": string },
+                optional: {
+                    "prefix": string,
+                    "mode": string,
+                    "overwrite": boolean,
+                    "with_history": boolean
+                }
+            }),
+        ),
     ]
 }
 
@@ -63,11 +90,41 @@ pub fn dispatch(
 ) -> Result<JsonValue> {
     match name {
         "strata_space_list" => {
+            let with_time_range = get_optional_bool(&args, "with_time_range").unwrap_or(false);
+            let with_counts =
+                get_optional_bool(&args, "with_counts").unwrap_or(false) || with_time_range;
+
             let cmd = Command::SpaceList {
                 branch: session.branch_id(),
             };
-            let output = session.execute(cmd)?;
-            Ok(output_to_json(output))
+            let spaces = output_to_json(session.execute(cmd)?);
+            if !with_counts {
+                return Ok(spaces);
+            }
+
+            let mut entries = Vec::new();
+            for space in spaces.as_array().into_iter().flatten().filter_map(|s| s.as_str()) {
+                let keys = scan::list_all_in(session, space, None)?;
+                let mut entry = serde_json::json!({
+                    "space": space,
+                    "keys": keys.len(),
+                });
+                if with_time_range {
+                    let mut range: Option<(u64, u64)> = None;
+                    for key in &keys {
+                        if let Some((oldest, latest)) = scan::time_range_in(session, space, key)? {
+                            range = Some(match range {
+                                Some((o, l)) => (o.min(oldest), l.max(latest)),
+                                None => (oldest, latest),
+                            });
+                        }
+                    }
+                    entry["oldest"] = serde_json::json!(range.map(|(oldest, _)| oldest));
+                    entry["latest"] = serde_json::json!(range.map(|(_, latest)| latest));
+                }
+                entries.push(entry);
+            }
+            Ok(JsonValue::Array(entries))
         }
 
         "strata_space_create" => {
@@ -114,6 +171,188 @@ pub fn dispatch(
             }))
         }
 
+        "strata_space_migrate" => migrate(session, args),
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+/// Move or copy documents between two spaces inside one transaction.
+fn migrate(session: &mut McpSession, args: Map<String, JsonValue>) -> Result<JsonValue> {
+    let from = get_string_arg(&args, "from")?;
+    let to = get_string_arg(&args, "to")?;
+    let prefix = get_optional_string(&args, "prefix");
+    let mode = get_optional_string(&args, "mode").unwrap_or_else(|| "move".to_string());
+    let overwrite = get_optional_bool(&args, "overwrite").unwrap_or(false);
+    let with_history = get_optional_bool(&args, "with_history").unwrap_or(false);
+
+    let moving = match mode.as_str() {
+        "move" => true,
+        "copy" => false,
+        other => {
+            return Err(McpError::InvalidArg {
+                name: "mode".to_string(),
+                reason: format!("Unknown mode '{}'. Use 'move' or 'copy'.", other),
+            })
+        }
+    };
+    if from == to {
+        return Err(McpError::InvalidArg {
+            name: "to".to_string(),
+            reason: "Source and destination spaces are the same".to_string(),
+        });
+    }
+    for (arg, space) in [("from", &from), ("to", &to)] {
+        let cmd = Command::SpaceExists {
+            branch: session.branch_id(),
+            space: space.clone(),
+        };
+        if !matches!(session.execute(cmd)?, Output::Bool(true)) {
+            return Err(McpError::InvalidArg {
+                name: arg.to_string(),
+                reason: format!(
+                    "space '{}' does not exist; create it with strata_space_create",
+                    space
+                ),
+            });
+        }
+    }
+    if session.in_transaction() {
+        return Err(McpError::TransactionAlreadyActive);
+    }
+
+    let keys = scan::list_all_in(session, &from, prefix)?;
+
+    session.execute(Command::TxnBegin {
+        branch: session.branch_id(),
+        options: Some(TxnOptions { read_only: false }),
+    })?;
+    match migrate_keys(session, &keys, &from, &to, moving, overwrite, with_history) {
+        Ok(results) => {
+            session.execute(Command::TxnCommit)?;
+            let skipped = results.iter().filter(|r| r["status"] == "skipped").count();
+            Ok(serde_json::json!({
+                "migrated": results.len() - skipped,
+                "skipped": skipped,
+                "keys": results,
+            }))
+        }
+        Err(e) => {
+            // The migration's error is the one to report, not the rollback's
+            let _ = session.execute(Command::TxnRollback);
+            Err(e)
+        }
+    }
+}
+
+/// Carry the metadata document of a migrated `key` along with it, so the
+/// key in `to` has the source's metadata and none left over from before.
+fn migrate_meta(
+    session: &mut McpSession,
+    key: &str,
+    from: &str,
+    to: &str,
+    moving: bool,
+) -> Result<()> {
+    let meta_key = meta::meta_key(key);
+    let read = |session: &mut McpSession, space: &str| {
+        session.execute(Command::JsonGet {
+            branch: session.branch_id(),
+            space: Some(space.to_string()),
+            key: meta_key.clone(),
+            path: "$".to_string(),
+            as_of: None,
+        })
+    };
+    let delete = |session: &mut McpSession, space: &str| {
+        session.execute(Command::JsonDelete {
+            branch: session.branch_id(),
+            space: Some(space.to_string()),
+            key: meta_key.clone(),
+            path: "$".to_string(),
+        })
+    };
+
+    match read(session, from)? {
+        Output::MaybeVersioned(Some(vv)) => {
+            session.execute(Command::JsonSet {
+                branch: session.branch_id(),
+                space: Some(to.to_string()),
+                key: meta_key.clone(),
+                path: "$".to_string(),
+                value: vv.value,
+            })?;
+            if moving {
+                delete(session, from)?;
+            }
+        }
+        _ => {
+            if matches!(read(session, to)?, Output::MaybeVersioned(Some(_))) {
+                delete(session, to)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Migrate each key, returning a `{ key, status }` entry per key.
+fn migrate_keys(
+    session: &mut McpSession,
+    keys: &[String],
+    from: &str,
+    to: &str,
+    moving: bool,
+    overwrite: bool,
+    with_history: bool,
+) -> Result<Vec<JsonValue>> {
+    let mut results = Vec::with_capacity(keys.len());
+    for key in keys {
+        let exists = session.execute(Command::JsonGet {
+            branch: session.branch_id(),
+            space: Some(to.to_string()),
+            key: key.clone(),
+            path: "$".to_string(),
+            as_of: None,
+        })?;
+        if !overwrite && matches!(exists, Output::MaybeVersioned(Some(_))) {
+            results.push(serde_json::json!({ "key": key, "status": "skipped" }));
+            continue;
+        }
+
+        let mut versions = match session.execute(Command::JsonGetv {
+            branch: session.branch_id(),
+            space: Some(from.to_string()),
+            key: key.clone(),
+            as_of: None,
+        })? {
+            Output::VersionHistory(Some(versions)) => versions,
+            _ => Vec::new(),
+        };
+        versions.sort_by_key(|vv| vv.version);
+        if !with_history {
+            versions = versions.pop().into_iter().collect();
+        }
+        for vv in versions {
+            session.execute(Command::JsonSet {
+                branch: session.branch_id(),
+                space: Some(to.to_string()),
+                key: key.clone(),
+                path: "$".to_string(),
+                value: vv.value,
+            })?;
+        }
+
+        if moving {
+            session.execute(Command::JsonDelete {
+                branch: session.branch_id(),
+                space: Some(from.to_string()),
+                key: key.clone(),
+                path: "$".to_string(),
+            })?;
+        }
+        migrate_meta(session, key, from, to, moving)?;
+        let status = if moving { "moved" } else { "copied" };
+        results.push(serde_json::json!({ "key": key, "status": status }));
+    }
+    Ok(results)
+}
@@ -16,19 +16,20 @@ pub fn tools() -> Vec<ToolDef> {
     vec![
         ToolDef::new(
             "strata_txn_begin",
-            "Begin a new transaction on the current branch. Operations within the transaction are atomic.",
+            "Begin a new transaction on the current branch. Operations within the transaction are atomic. \
+             Transactions do not nest: fails if one is already active.",
             schema!(object {
                 optional: { "read_only": boolean }
             }),
         ),
         ToolDef::new(
             "strata_txn_commit",
-            "Commit the current transaction, making all changes permanent.",
+            "Commit the current transaction, making all changes permanent. Fails if no transaction is active.",
             schema!(object {}),
         ),
         ToolDef::new(
             "strata_txn_rollback",
-            "Rollback the current transaction, discarding all changes.",
+            "Rollback the current transaction, discarding all changes. Fails if no transaction is active.",
             schema!(object {}),
         ),
         ToolDef::new(
@@ -52,6 +53,9 @@ pub fn dispatch(
 ) -> Result<JsonValue> {
     match name {
         "strata_txn_begin" => {
+            if session.in_transaction() {
+                return Err(McpError::TransactionAlreadyActive);
+            }
             let read_only = get_optional_bool(&args, "read_only").unwrap_or(false);
 
             let cmd = Command::TxnBegin {
@@ -63,11 +67,13 @@ pub fn dispatch(
         }
 
         "strata_txn_commit" => {
+            require_transaction(session, "commit")?;
             let output = session.execute(Command::TxnCommit)?;
             Ok(output_to_json(output))
         }
 
         "strata_txn_rollback" => {
+            require_transaction(session, "rollback")?;
             let output = session.execute(Command::TxnRollback)?;
             Ok(output_to_json(output))
         }
@@ -85,3 +91,12 @@ pub fn dispatch(
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
+
+/// Fail with a specific error when `operation` is attempted outside a transaction.
+fn require_transaction(session: &McpSession, operation: &str) -> Result<()> {
+    if session.in_transaction() {
+        Ok(())
+    } else {
+        Err(McpError::NoActiveTransaction(operation.to_string()))
+    }
+}
@@ -2,19 +2,21 @@
 //!
 //! Tools: strata_vector_upsert, strata_vector_get, strata_vector_delete, strata_vector_search,
 //!        strata_vector_create_collection, strata_vector_delete_collection,
-//!        strata_vector_list_collections, strata_vector_stats, strata_vector_batch_upsert
+//!        strata_vector_list_collections, strata_vector_stats, strata_vector_batch_upsert,
+//!        strata_vector_orphans
 
 use serde_json::{Map, Value as JsonValue};
-use stratadb::{BatchVectorEntry, Command, DistanceMetric, FilterOp, MetadataFilter};
+use stratadb::{BatchVectorEntry, Command, DistanceMetric, FilterOp, MetadataFilter, Output};
 
 use crate::convert::{
-    get_optional_string, get_optional_u64, get_string_arg, get_u64_arg, get_value_arg,
-    get_vector_arg, json_to_value, output_to_json,
+    get_optional_bool, get_optional_string, get_optional_u64, get_string_arg, get_u64_arg,
+    get_value_arg, get_vector_arg, json_to_value, output_to_json,
 };
 use crate::error::{McpError, Result};
 use crate::schema;
 use crate::session::McpSession;
-use crate::tools::ToolDef;
+use crate::tools::embed::SEARCH_INDEX_COLLECTION;
+use crate::tools::{scan, ToolAnnotations, ToolDef};
 
 /// Get all vector tool definitions.
 pub fn tools() -> Vec<ToolDef> {
@@ -111,9 +113,62 @@ pub fn tools() -> Vec<ToolDef> {
                 required: { "collection": string, "entries": array_object }
             }),
         ),
+        ToolDef::new(
+            "strata_vector_orphans",
+            "Find vectors in a document index whose key has no document in the current \
+             branch/space, e.g. left behind by deletes or failed writes. Checks the search index \
+             auto-embed and strata_embed_key write to, or the named collection; collections filled \
+             directly with strata_vector_upsert have no documents, so don't name one of those. \
+             Only reports by default; pass cleanup: true with a collection to delete its \
+             orphaned vectors. Unlike reindexing, this never rewrites documents. Returns \
+             { collections: [{ collection, vectors, orphans }], orphaned, removed }.",
+            schema!(object {
+                optional: { "collection": string, "cleanup": boolean }
+            }),
+        )
+        .with_example(
+            serde_json::json!({ "collection": "_system_embed_json" }),
+            serde_json::json!({
+                "collections": [
+                    { "collection": "_system_embed_json", "vectors": 3, "orphans": ["old-note"] }
+                ],
+                "orphaned": 1,
+                "removed": 0
+            }),
+        )
+        .with_annotations(ToolAnnotations::write(true, true)),
     ]
 }
 
+/// Every key in a vector collection.
+///
+/// There is no listing command for vectors, so this runs an unfiltered
+/// search with k equal to the collection's size: every vector matches.
+fn collection_keys(
+    session: &mut McpSession,
+    collection: &str,
+    dimension: usize,
+    count: u64,
+) -> Result<Vec<String>> {
+    if count == 0 || dimension == 0 {
+        return Ok(Vec::new());
+    }
+    let cmd = Command::VectorSearch {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        collection: collection.to_string(),
+        query: vec![1.0; dimension],
+        k: count,
+        filter: None,
+        metric: None,
+        as_of: None,
+    };
+    match session.execute(cmd)? {
+        Output::VectorMatches(matches) => Ok(matches.into_iter().map(|m| m.key).collect()),
+        _ => Ok(Vec::new()),
+    }
+}
+
 /// Parse a distance metric from a string.
 fn parse_metric(s: Option<&str>) -> Result<DistanceMetric> {
     match s {
@@ -402,6 +457,74 @@ pub fn dispatch(
             Ok(output_to_json(output))
         }
 
+        "strata_vector_orphans" => {
+            let only = get_optional_string(&args, "collection");
+            let cleanup = get_optional_bool(&args, "cleanup").unwrap_or(false);
+            if cleanup && only.is_none() {
+                return Err(McpError::InvalidArg {
+                    name: "collection".to_string(),
+                    reason: "cleanup deletes vectors, so name the collection to clean".to_string(),
+                });
+            }
+            let checked = only.as_deref().unwrap_or(SEARCH_INDEX_COLLECTION);
+
+            let cmd = Command::VectorListCollections {
+                branch: session.branch_id(),
+                space: session.space_id(),
+            };
+            let collections: Vec<(String, usize, u64)> = match session.execute(cmd)? {
+                Output::VectorCollectionList(list) => list
+                    .into_iter()
+                    .filter(|c| c.name == checked)
+                    .map(|c| (c.name, c.dimension as usize, c.count as u64))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if let Some(name) = &only {
+                if collections.is_empty() {
+                    return Err(McpError::InvalidArg {
+                        name: "collection".to_string(),
+                        reason: format!("no vector collection named '{}'", name),
+                    });
+                }
+            }
+
+            let mut report = Vec::new();
+            let (mut orphaned, mut removed) = (0u64, 0u64);
+            for (collection, dimension, count) in collections {
+                let keys = collection_keys(session, &collection, dimension, count)?;
+                let vectors = keys.len();
+                let mut orphans = Vec::new();
+                for key in keys {
+                    if scan::latest(session, &key)?.is_none() {
+                        orphans.push(key);
+                    }
+                }
+                orphaned += orphans.len() as u64;
+                if cleanup {
+                    for key in &orphans {
+                        session.execute(Command::VectorDelete {
+                            branch: session.branch_id(),
+                            space: session.space_id(),
+                            collection: collection.clone(),
+                            key: key.clone(),
+                        })?;
+                        removed += 1;
+                    }
+                }
+                report.push(serde_json::json!({
+                    "collection": collection,
+                    "vectors": vectors,
+                    "orphans": orphans,
+                }));
+            }
+            Ok(serde_json::json!({
+                "collections": report,
+                "orphaned": orphaned,
+                "removed": removed,
+            }))
+        }
+
         _ => Err(McpError::UnknownTool(name.to_string())),
     }
 }
@@ -0,0 +1,82 @@
+//! ULID generation for automatically assigned keys.
+//!
+//! A ULID is 26 characters of Crockford base32: 10 encoding the creation
+//! time in milliseconds since the Unix epoch, then 16 of randomness. Because
+//! the timestamp comes first, ULIDs sort lexicographically by creation time.
+//! Within one process, ULIDs generated in the same millisecond increment the
+//! random part instead of drawing a new one, so they stay strictly ordered.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Crockford base32 alphabet (no I, L, O, or U).
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Mask for the 80 random bits.
+const RANDOM_MASK: u128 = (1 << 80) - 1;
+
+/// Timestamp and random part of the last ULID generated by this process.
+static LAST: Mutex<(u64, u128)> = Mutex::new((0, 0));
+
+/// Generate a new ULID.
+pub fn generate() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+    let (millis, random) = if now <= last.0 {
+        // Same (or an earlier, if the clock stepped back) millisecond
+        (last.0, (last.1 + 1) & RANDOM_MASK)
+    } else {
+        (now, random_bits())
+    };
+    *last = (millis, random);
+
+    encode(((millis as u128) << 80) | random)
+}
+
+/// 80 bits of randomness from the standard library's per-process hash keys.
+fn random_bits() -> u128 {
+    let mut bits = 0u128;
+    for _ in 0..2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(bits);
+        bits = (bits << 64) | hasher.finish() as u128;
+    }
+    bits & RANDOM_MASK
+}
+
+/// Encode 128 bits as 26 base32 characters, most significant first.
+fn encode(mut value: u128) -> String {
+    let mut out = [0u8; 26];
+    for slot in out.iter_mut().rev() {
+        *slot = ALPHABET[(value & 0x1f) as usize];
+        value >>= 5;
+    }
+    out.iter().map(|&b| b as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_format() {
+        let id = generate();
+        assert_eq!(id.len(), 26);
+        assert!(id.bytes().all(|b| ALPHABET.contains(&b)));
+    }
+
+    #[test]
+    fn test_generate_is_ordered() {
+        let ids: Vec<String> = (0..100).map(|_| generate()).collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(ids, sorted);
+    }
+}
@@ -0,0 +1,180 @@
+//! Unix domain socket transport.
+//!
+//! Lets many short-lived local processes share one long-running server.
+//! Each connection speaks the same newline-delimited JSON-RPC as stdio, on
+//! its own thread with its own `McpServer`, so branch and space context,
+//! transactions, and session branches stay per client. The socket is only
+//! reachable by its owner, and as over HTTP at most [`MAX_CONNECTIONS`]
+//! connections are served at once.
+
+use std::io::{BufReader, ErrorKind, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::error::{rpc_codes, McpError, Result};
+use crate::http::{ConnectionSlot, MAX_CONNECTIONS};
+use crate::server::{JsonRpcResponse, McpServer};
+
+/// Bind a listener at `path`, replacing a stale socket left by a server
+/// that exited without cleaning up. Fails if another server is listening,
+/// or if `path` is something other than a socket. The socket is made
+/// readable and writable by its owner only.
+pub fn bind_unix_socket(path: &Path) -> Result<UnixListener> {
+    let listener = match UnixListener::bind(path) {
+        Err(e) if e.kind() == ErrorKind::AddrInUse && UnixStream::connect(path).is_err() => {
+            if !std::fs::symlink_metadata(path)?.file_type().is_socket() {
+                return Err(McpError::Io(format!(
+                    "{} exists and is not a socket; not replacing it",
+                    path.display()
+                )));
+            }
+            std::fs::remove_file(path)?;
+            UnixListener::bind(path)?
+        }
+        result => result?,
+    };
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Serve MCP on `listener` until accepting a connection fails.
+///
+/// `new_server` builds the server for each connection; when it fails, the
+/// error is written to the client as a JSON-RPC error and the connection is
+/// closed. When a connection ends, its buffered write is flushed and its
+/// session branch closed.
+pub fn serve_unix_socket<F>(listener: UnixListener, new_server: F) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String> + Sync,
+{
+    serve_with_limit(listener, new_server, MAX_CONNECTIONS)
+}
+
+fn serve_with_limit<F>(listener: UnixListener, new_server: F, max_connections: usize) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String> + Sync,
+{
+    let connections = AtomicUsize::new(0);
+    std::thread::scope(|scope| -> Result<()> {
+        for stream in listener.incoming() {
+            let mut stream = stream?;
+            if connections.fetch_add(1, Ordering::SeqCst) >= max_connections {
+                connections.fetch_sub(1, Ordering::SeqCst);
+                // Answer without a thread, and without waiting on a slow client
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let reason = format!("Too many connections (at most {})", max_connections);
+                let error = JsonRpcResponse::error(None, rpc_codes::INTERNAL_ERROR, reason);
+                if let Ok(line) = serde_json::to_string(&error) {
+                    let _ = writeln!(stream, "{}", line);
+                }
+                continue;
+            }
+            let slot = ConnectionSlot(&connections);
+            let new_server = &new_server;
+            scope.spawn(move || {
+                let _slot = slot;
+                match handle_connection(stream, new_server) {
+                    Ok(()) => tracing::debug!("Unix socket client disconnected"),
+                    Err(e) => tracing::debug!("Unix socket client failed: {}", e),
+                }
+            });
+        }
+        Ok(())
+    })
+}
+
+fn handle_connection<F>(stream: UnixStream, new_server: &F) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String>,
+{
+    let mut writer = stream.try_clone()?;
+    let mut server = match new_server() {
+        Ok(server) => server,
+        Err(reason) => {
+            let error = JsonRpcResponse::error(None, rpc_codes::INTERNAL_ERROR, reason);
+            writeln!(writer, "{}", serde_json::to_string(&error)?)?;
+            return Ok(());
+        }
+    };
+
+    let result = server.serve(BufReader::new(stream), &mut writer);
+    server.end_client();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::McpSession;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_unix_socket_request_response() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("strata.sock");
+        let listener = bind_unix_socket(&path).expect("Failed to bind");
+        std::thread::spawn(move || {
+            let _ = serve_unix_socket(listener, || {
+                let db = stratadb::Strata::cache().map_err(|e| e.to_string())?;
+                Ok(McpServer::new(McpSession::new(db)))
+            });
+        });
+
+        let mut client = UnixStream::connect(&path).expect("Failed to connect");
+        writeln!(client, r#"{{"jsonrpc":"2.0","id":1,"method":"ping"}}"#).unwrap();
+        let mut line = String::new();
+        BufReader::new(&client).read_line(&mut line).expect("Failed to read");
+        let response: serde_json::Value = serde_json::from_str(&line).expect("Not JSON");
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], serde_json::json!({}));
+
+        // A second server can't take over a live socket
+        assert!(bind_unix_socket(&path).is_err());
+    }
+
+    #[test]
+    fn test_unix_socket_is_private_and_capped() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("strata.sock");
+        let listener = bind_unix_socket(&path).expect("Failed to bind");
+        let mode = std::fs::metadata(&path).expect("No socket").permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        std::thread::spawn(move || {
+            let new_server = || {
+                let db = stratadb::Strata::cache().map_err(|e| e.to_string())?;
+                Ok(McpServer::new(McpSession::new(db)))
+            };
+            let _ = serve_with_limit(listener, new_server, 1);
+        });
+        let _held = UnixStream::connect(&path).expect("Failed to connect");
+        // Let the server take the only slot before the second client arrives
+        std::thread::sleep(Duration::from_millis(50));
+        let refused = UnixStream::connect(&path).expect("Failed to connect");
+        let mut line = String::new();
+        BufReader::new(&refused).read_line(&mut line).expect("Failed to read");
+        let response: serde_json::Value = serde_json::from_str(&line).expect("Not JSON");
+        assert!(response["error"]["message"].as_str().unwrap().contains("Too many"));
+    }
+
+    #[test]
+    fn test_bind_replaces_stale_socket() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("strata.sock");
+        drop(UnixListener::bind(&path).expect("Failed to bind"));
+        assert!(path.exists());
+        bind_unix_socket(&path).expect("Stale socket should be replaced");
+    }
+
+    #[test]
+    fn test_bind_keeps_files_that_are_not_sockets() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "keep me").expect("Failed to write file");
+        assert!(bind_unix_socket(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+    }
+}
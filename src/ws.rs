@@ -0,0 +1,319 @@
+//! WebSocket transport.
+//!
+//! Runs the JSON-RPC loop over WebSockets for clients that can't spawn a
+//! subprocess, such as browser-based agent frontends. Every text message is
+//! one request or batch and gets one response message back, in order. Each
+//! connection gets its own thread and its own `McpServer`, so branch and
+//! space switches, transactions, and session branches stay per client.
+//! Browsers don't apply CORS to WebSockets, so handshakes from web pages on
+//! other origins are refused, as the HTTP transport refuses their requests,
+//! unless the origin is on the allow-list given to [`serve_websocket`].
+//!
+//! As over HTTP, at most [`MAX_CONNECTIONS`] connections are served at once,
+//! and a client that sends nothing for [`IDLE_TIMEOUT`] is disconnected.
+
+use std::io::{ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Error as WsError, Message};
+
+use crate::error::{McpError, Result};
+use crate::http::{is_local_origin, ConnectionSlot, MAX_CONNECTIONS};
+use crate::server::McpServer;
+
+/// How long a client has to complete the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a connection may go without a message (pings count) before it is
+/// closed, matching the HTTP transport's session expiry.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Bounds on what clients may hold open.
+#[derive(Clone, Copy)]
+struct Limits {
+    max_connections: usize,
+    idle_timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_connections: MAX_CONNECTIONS,
+            idle_timeout: IDLE_TIMEOUT,
+        }
+    }
+}
+
+/// Serve MCP over WebSockets on `listener` until accepting a connection fails.
+///
+/// `new_server` builds the server for each connection. When it fails, the
+/// connection is closed with its message as the reason. When a connection
+/// ends, its buffered write is flushed and its session branch closed.
+/// Handshakes from web pages are accepted from localhost and from the
+/// origins in `allowed_origins`, such as `https://app.example.com`.
+pub fn serve_websocket<F>(
+    listener: TcpListener,
+    new_server: F,
+    allowed_origins: &[String],
+) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String> + Sync,
+{
+    serve_with_limits(listener, new_server, allowed_origins, Limits::default())
+}
+
+fn serve_with_limits<F>(
+    listener: TcpListener,
+    new_server: F,
+    allowed_origins: &[String],
+    limits: Limits,
+) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String> + Sync,
+{
+    let connections = AtomicUsize::new(0);
+    std::thread::scope(|scope| -> Result<()> {
+        for stream in listener.incoming() {
+            let mut stream = stream?;
+            if connections.fetch_add(1, Ordering::SeqCst) >= limits.max_connections {
+                connections.fetch_sub(1, Ordering::SeqCst);
+                // Refuse the handshake without a thread, and without waiting on a slow client
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let _ = stream.write_all(
+                    b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n",
+                );
+                continue;
+            }
+            let slot = ConnectionSlot(&connections);
+            let new_server = &new_server;
+            scope.spawn(move || {
+                let _slot = slot;
+                let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+                match handle_connection(stream, new_server, allowed_origins, limits) {
+                    Ok(()) => tracing::debug!("WebSocket client {} disconnected", peer),
+                    Err(e) => tracing::debug!("WebSocket client {} failed: {}", peer, e),
+                }
+            });
+        }
+        Ok(())
+    })
+}
+
+fn handle_connection<F>(
+    stream: TcpStream,
+    new_server: &F,
+    allowed_origins: &[String],
+    limits: Limits,
+) -> Result<()>
+where
+    F: Fn() -> std::result::Result<McpServer, String>,
+{
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let check = |request: &Request, response: Response| {
+        check_origin(request, response, allowed_origins)
+    };
+    let mut socket =
+        tungstenite::accept_hdr(stream, check).map_err(|e| McpError::Io(e.to_string()))?;
+    socket.get_ref().set_read_timeout(Some(limits.idle_timeout))?;
+    let mut server = match new_server() {
+        Ok(server) => server,
+        Err(reason) => {
+            let frame = tungstenite::protocol::CloseFrame {
+                code: tungstenite::protocol::frame::coding::CloseCode::Error,
+                reason: reason.into(),
+            };
+            let _ = socket.close(Some(frame));
+            let _ = socket.flush();
+            return Ok(());
+        }
+    };
+
+    let result = serve_socket(&mut server, &mut socket);
+    server.end_client();
+    result
+}
+
+/// Refuse handshakes whose `Origin` names another machine and isn't allowed.
+fn check_origin(
+    request: &Request,
+    response: Response,
+    allowed_origins: &[String],
+) -> std::result::Result<Response, ErrorResponse> {
+    let origin = request.headers().get("origin").map(|v| v.to_str().unwrap_or(""));
+    match origin {
+        Some(origin) if !is_local_origin(origin) && !is_allowed(origin, allowed_origins) => {
+            let mut refusal = ErrorResponse::new(Some("Origin not allowed".to_string()));
+            *refusal.status_mut() = StatusCode::FORBIDDEN;
+            Err(refusal)
+        }
+        _ => Ok(response),
+    }
+}
+
+fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Whether `origin` is on the allow-list. Origins compare without case and
+/// without a trailing slash, so `https://App.example.com/` matches.
+fn is_allowed(origin: &str, allowed_origins: &[String]) -> bool {
+    let origin = origin.trim_end_matches('/');
+    allowed_origins
+        .iter()
+        .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+/// Answer each text message until the client closes the connection or goes
+/// quiet for the idle timeout.
+fn serve_socket(
+    server: &mut McpServer,
+    socket: &mut tungstenite::WebSocket<TcpStream>,
+) -> Result<()> {
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(_) => continue,
+            },
+            // Pings are answered by tungstenite itself
+            Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_)) => continue,
+            Ok(Message::Close(_))
+            | Err(WsError::ConnectionClosed | WsError::AlreadyClosed) => return Ok(()),
+            // The read timeout: the client has been idle too long
+            Err(WsError::Io(e)) if is_timeout(&e) => {
+                let _ = socket.close(None);
+                let _ = socket.flush();
+                return Ok(());
+            }
+            Err(e) => return Err(McpError::Io(e.to_string())),
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_text(text.trim())? {
+            socket
+                .send(Message::Text(response))
+                .map_err(|e| McpError::Io(e.to_string()))?;
+        }
+        for notification in server.take_notifications() {
+            socket
+                .send(Message::Text(notification))
+                .map_err(|e| McpError::Io(e.to_string()))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::McpSession;
+    use serde_json::Value as JsonValue;
+
+    fn call<S: std::io::Read + std::io::Write>(
+        socket: &mut tungstenite::WebSocket<S>,
+        request: JsonValue,
+    ) -> JsonValue {
+        socket.send(Message::Text(request.to_string())).expect("send failed");
+        match socket.read().expect("read failed") {
+            Message::Text(text) => serde_json::from_str(&text).expect("Response was not JSON"),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    /// Start a server on a free local port and return its address.
+    fn start_server() -> std::net::SocketAddr {
+        start_server_with(vec!["https://app.example.com".to_string()], Limits::default())
+    }
+
+    fn start_server_with(allowed_origins: Vec<String>, limits: Limits) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let addr = listener.local_addr().expect("No local address");
+        std::thread::spawn(move || {
+            let new_server = || {
+                let db = stratadb::Strata::cache().map_err(|e| e.to_string())?;
+                Ok(McpServer::new(McpSession::new(db)))
+            };
+            let _ = serve_with_limits(listener, new_server, &allowed_origins, limits);
+        });
+        addr
+    }
+
+    #[test]
+    fn test_websocket_request_response() {
+        let addr = start_server();
+
+        let (mut socket, _) =
+            tungstenite::connect(format!("ws://{}", addr)).expect("Failed to connect");
+        let init = call(
+            &mut socket,
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        );
+        assert_eq!(init["result"]["serverInfo"]["name"], "strata-mcp");
+
+        call(
+            &mut socket,
+            serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": {"name": "strata_store", "arguments": {"key": "k", "value": "v"}}}),
+        );
+        let recall = call(
+            &mut socket,
+            serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": {"name": "strata_recall", "arguments": {"key": "k"}}}),
+        );
+        assert_eq!(recall["id"], 3);
+        let text = recall["result"]["content"][0]["text"].as_str().expect("Expected text");
+        let payload: JsonValue = serde_json::from_str(text).expect("Tool text was not JSON");
+        assert_eq!(payload["value"], "v");
+    }
+
+    #[test]
+    fn test_websocket_rejects_foreign_origins() {
+        use tungstenite::client::IntoClientRequest;
+
+        let addr = start_server();
+        let with_origin = |origin: &str| {
+            let mut request = format!("ws://{}", addr).into_client_request().unwrap();
+            request.headers_mut().insert("Origin", origin.parse().unwrap());
+            tungstenite::connect(request)
+        };
+        match with_origin("https://example.com") {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::FORBIDDEN),
+            other => panic!("Expected the handshake to be refused, got {:?}", other.map(|_| ())),
+        }
+        assert!(with_origin("http://localhost:3000").is_ok());
+        assert!(with_origin("https://app.example.com").is_ok());
+    }
+
+    #[test]
+    fn test_websocket_caps_connections_and_closes_idle_ones() {
+        let limits = Limits {
+            max_connections: 1,
+            idle_timeout: Duration::from_millis(200),
+        };
+        let addr = start_server_with(Vec::new(), limits);
+        let (mut socket, _) =
+            tungstenite::connect(format!("ws://{}", addr)).expect("Failed to connect");
+        match tungstenite::connect(format!("ws://{}", addr)) {
+            Err(WsError::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE)
+            }
+            other => panic!("Expected the handshake to be refused, got {:?}", other.map(|_| ())),
+        }
+
+        // Quiet past the idle timeout: the server closes, freeing the slot
+        loop {
+            match socket.read() {
+                Ok(Message::Close(_)) => continue,
+                Ok(other) => panic!("Unexpected message: {:?}", other),
+                Err(_) => break,
+            }
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(tungstenite::connect(format!("ws://{}", addr)).is_ok());
+    }
+}
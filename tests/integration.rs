@@ -1,7 +1,7 @@
 //! Integration tests for the MCP server.
 
 use serde_json::{json, Map, Value as JsonValue};
-use strata_mcp::{McpSession, ToolRegistry};
+use strata_mcp::{McpSession, ToolRegistry, WarningCode};
 use stratadb::Strata;
 
 /// Create a test session with an in-memory database.
@@ -73,6 +73,26 @@ fn extract_value(result: &JsonValue) -> &JsonValue {
     }
 }
 
+/// Whether `value` has one of the JSON types `schema` lists, or any type
+/// when it lists none.
+fn fits_type(schema: &JsonValue, value: &JsonValue) -> bool {
+    let types = match &schema["type"] {
+        JsonValue::String(t) => vec![t.as_str()],
+        JsonValue::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => return true,
+    };
+    types.iter().any(|t| match *t {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    })
+}
+
 // =============================================================================
 // Database Tools
 // =============================================================================
@@ -114,6 +134,33 @@ fn test_db_compact() {
     assert_eq!(result, json!(null));
 }
 
+#[test]
+fn test_db_benchmark() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_db_benchmark",
+        json!({"tool": "strata_search", "args": {"query": "anything", "k": 3}, "iterations": 5}),
+    );
+    assert_eq!(result["iterations"], 5);
+    let min = result["min_us"].as_u64().unwrap();
+    let p95 = result["p95_us"].as_u64().unwrap();
+    let max = result["max_us"].as_u64().unwrap();
+    assert!(min <= p95 && p95 <= max);
+
+    // Writes and oversized runs are rejected
+    call_tool_err(&mut session, &registry, "strata_db_benchmark", json!({"tool": "strata_store"}));
+    call_tool_err(
+        &mut session,
+        &registry,
+        "strata_db_benchmark",
+        json!({"tool": "strata_recall", "args": {"key": "k"}, "iterations": 1000}),
+    );
+}
+
 // =============================================================================
 // KV Tools
 // =============================================================================
@@ -425,6 +472,59 @@ fn test_event_list_paginated() {
     assert_eq!(events.len(), 2);
 }
 
+#[test]
+fn test_event_tail_across_types() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_event_append", json!({"event_type": "a", "payload": {"n": 1}}));
+    call_tool(&mut session, &registry, "strata_event_append", json!({"event_type": "b", "payload": {"n": 2}}));
+    call_tool(&mut session, &registry, "strata_event_append", json!({"event_type": "a", "payload": {"n": 3}}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_event_tail",
+        json!({"types": ["a", "b"], "limit": 2}),
+    );
+    let events = result["events"].as_array().expect("Expected events array");
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["payload"]["n"], 3);
+    assert_eq!(events[0]["type"], "a");
+    assert_eq!(events[1]["type"], "b");
+
+    // Incremental tail picks up only newer events
+    call_tool(&mut session, &registry, "strata_event_append", json!({"event_type": "b", "payload": {"n": 4}}));
+    let cursor = result["cursor"].clone();
+    let newer = call_tool(&mut session, &registry, "strata_event_tail", json!({"since": cursor}));
+    let events = newer["events"].as_array().expect("Expected events array");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["payload"]["n"], 4);
+
+    // More events than fit in a page come oldest first, one page at a time
+    for n in 5..=7 {
+        call_tool(&mut session, &registry, "strata_event_append", json!({"event_type": "a", "payload": {"n": n}}));
+    }
+    for types in [json!(null), json!(["a"])] {
+        let mut cursor = newer["cursor"].clone();
+        let mut seen = Vec::new();
+        loop {
+            let mut args = json!({"since": cursor, "limit": 2});
+            if !types.is_null() {
+                args["types"] = types.clone();
+            }
+            let page = call_tool(&mut session, &registry, "strata_event_tail", args);
+            let events = page["events"].as_array().expect("Expected events array");
+            if events.is_empty() {
+                break;
+            }
+            seen.extend(events.iter().map(|e| e["payload"]["n"].as_u64().unwrap()));
+            cursor = page["cursor"].clone();
+        }
+        assert_eq!(seen, vec![5, 6, 7], "paging with types {}", types);
+    }
+}
+
 // =============================================================================
 // JSON Tools
 // =============================================================================
@@ -495,6 +595,375 @@ fn test_json_history() {
     assert!(history.len() >= 2);
 }
 
+#[test]
+fn test_json_check_path() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_json_set",
+        json!({"key": "doc", "path": "$", "value": {"name": "app", "tags": ["a"]}}),
+    );
+
+    let result = call_tool(&mut session, &registry, "strata_json_check_path", json!({"key": "doc", "path": "$.tags"}));
+    assert_eq!(result["resolves"], true);
+    assert_eq!(result["type"], "array");
+    assert_eq!(result["write"], "overwrite");
+
+    let result = call_tool(&mut session, &registry, "strata_json_check_path", json!({"key": "doc", "path": "$.settings.theme"}));
+    assert_eq!(result["resolves"], false);
+    assert_eq!(result["write"], "create");
+    assert_eq!(result["missing"], "$.settings");
+
+    let result = call_tool(&mut session, &registry, "strata_json_check_path", json!({"key": "doc", "path": "$.name.first"}));
+    assert_eq!(result["write"], "fail");
+    assert!(result["reason"].as_str().unwrap().contains("string"));
+}
+
+#[test]
+fn test_json_exists_many() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "present", "path": "$", "value": {"a": 1}}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_json_exists_many",
+        json!({"keys": ["present", "absent"]}),
+    );
+    assert_eq!(result["present"], json!({"exists": true}));
+    assert_eq!(result["absent"], json!({"exists": false}));
+
+    // A key that only prefixes a stored key doesn't exist
+    let result = call_tool(&mut session, &registry, "strata_json_exists_many", json!({"keys": ["pre"]}));
+    assert_eq!(result["pre"]["exists"], json!(false));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_json_exists_many",
+        json!({"keys": ["present", "absent"], "with_versions": true}),
+    );
+    assert_eq!(result["present"]["exists"], json!(true));
+    assert!(result["present"]["version"].is_number());
+    assert_eq!(result["absent"]["exists"], json!(false));
+    assert_eq!(result["absent"]["version"], json!(null));
+}
+
+#[test]
+fn test_json_fingerprint() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "a", "path": "$", "value": {"x": 1, "y": [true, "s"]}}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "b", "path": "$", "value": {"y": [true, "s"], "x": 1}}));
+
+    let a = call_tool(&mut session, &registry, "strata_json_fingerprint", json!({"key": "a"}));
+    let b = call_tool(&mut session, &registry, "strata_json_fingerprint", json!({"key": "b", "store": true}));
+    assert_eq!(a["sha256"], b["sha256"]);
+    assert_eq!(a["sha256"].as_str().map(|d| d.len()), Some(64));
+    assert!(a["version"].is_number());
+
+    let meta = call_tool(&mut session, &registry, "strata_meta_get", json!({"key": "b"}));
+    assert_eq!(meta["metadata"]["sha256"], b["sha256"]);
+
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "a", "path": "$.x", "value": 2}));
+    let changed = call_tool(&mut session, &registry, "strata_json_fingerprint", json!({"key": "a"}));
+    assert_ne!(changed["sha256"], a["sha256"]);
+
+    let missing = call_tool(&mut session, &registry, "strata_json_fingerprint", json!({"key": "nope"}));
+    assert_eq!(missing, json!(null));
+}
+
+#[test]
+fn test_json_find() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_json_set",
+        json!({"key": "config", "path": "$", "value": {
+            "ui": {"Theme": "dark", "font": "mono"},
+            "plugins": [{"name": "theme-switcher"}, {"name": "git"}]
+        }}),
+    );
+
+    let result = call_tool(&mut session, &registry, "strata_json_find", json!({"key": "config", "term": "theme"}));
+    let paths: Vec<(&str, &str)> = result["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| (m["path"].as_str().unwrap(), m["matched"].as_str().unwrap()))
+        .collect();
+    assert!(paths.contains(&("$.ui.Theme", "key")));
+    assert!(paths.contains(&("$.plugins[0].name", "value")));
+    assert_eq!(paths.len(), 2);
+    assert_eq!(result["truncated"], false);
+
+    let strict = call_tool(
+        &mut session,
+        &registry,
+        "strata_json_find",
+        json!({"key": "config", "term": "theme", "case_sensitive": true, "limit": 0}),
+    );
+    assert_eq!(strict["matches"], json!([]));
+    assert_eq!(strict["truncated"], true);
+
+    let missing = call_tool(&mut session, &registry, "strata_json_find", json!({"key": "nope", "term": "x"}));
+    assert_eq!(missing, json!(null));
+}
+
+#[test]
+fn test_json_rotate() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    for reading in 1..=4 {
+        call_tool(
+            &mut session,
+            &registry,
+            "strata_json_rotate",
+            json!({"key": "recent", "value": reading, "keep": 3}),
+        );
+    }
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "recent", "path": "$"}));
+    assert_eq!(extract_value(&doc), &json!([2, 3, 4]));
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_json_set",
+        json!({"key": "sensor", "path": "$", "value": {"name": "t1", "log": [1, 2]}}),
+    );
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_json_rotate",
+        json!({"key": "sensor", "path": "$.log", "value": 3, "keep": 2}),
+    );
+    assert_eq!(result["length"], 2);
+    assert_eq!(result["dropped"], 1);
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "sensor", "path": "$"}));
+    assert_eq!(extract_value(&doc), &json!({"name": "t1", "log": [2, 3]}));
+
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_json_rotate",
+        json!({"key": "sensor", "path": "$.name", "value": 1, "keep": 2}),
+    );
+    assert!(matches!(err, strata_mcp::McpError::InvalidArg { .. }));
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_json_rotate",
+        json!({"key": "sensor", "path": "$.log", "value": 1, "keep": 0}),
+    );
+    assert!(matches!(err, strata_mcp::McpError::InvalidArg { .. }));
+}
+
+#[test]
+fn test_json_canonicalize() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_json_set",
+        json!({"key": "doc", "path": "$", "value": {"b": 2.0, "a": [1.5, {"d": 1, "c": 3.0}]}}),
+    );
+    let first = call_tool(&mut session, &registry, "strata_json_canonicalize", json!({"key": "doc"}));
+    assert_eq!(first["changed"], true);
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "doc", "path": "$"}));
+    assert_eq!(extract_value(&doc).to_string(), r#"{"a":[1.5,{"c":3,"d":1}],"b":2}"#);
+
+    let second = call_tool(&mut session, &registry, "strata_json_canonicalize", json!({"key": "doc"}));
+    assert_eq!(second["changed"], false);
+    assert_eq!(second["version"], first["version"]);
+
+    let missing = call_tool(&mut session, &registry, "strata_json_canonicalize", json!({"key": "nope"}));
+    assert_eq!(missing, json!(null));
+}
+
+#[test]
+fn test_json_dangling_refs() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    for (key, value) in [
+        ("task:1", json!({"title": "root"})),
+        ("task:2", json!({"parent_key": "task:1"})),
+        ("task:3", json!({"parent_key": "task:9"})),
+        ("task:4", json!({"parent_key": ["task:1", "task:8"]})),
+        ("note", json!({"parent_key": "gone"})),
+    ] {
+        call_tool(&mut session, &registry, "strata_json_set", json!({"key": key, "path": "$", "value": value}));
+    }
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_json_dangling_refs",
+        json!({"path": "$.parent_key", "prefix": "task:"}),
+    );
+    assert_eq!(result["scanned"], 4);
+    assert_eq!(result["references"], 4);
+    assert_eq!(
+        result["dangling"],
+        json!([{"key": "task:3", "target": "task:9"}, {"key": "task:4", "target": "task:8"}])
+    );
+    assert_eq!(result["truncated"], false);
+
+    let limited = call_tool(
+        &mut session,
+        &registry,
+        "strata_json_dangling_refs",
+        json!({"path": "$.parent_key", "limit": 1}),
+    );
+    assert_eq!(limited["dangling"].as_array().unwrap().len(), 1);
+    assert_eq!(limited["truncated"], true);
+}
+
+#[test]
+fn test_import_file_csv_and_jsonl() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    session.set_export_dir(Some(dir.path().to_path_buf()));
+
+    let csv = dir.path().join("people.csv");
+    std::fs::write(&csv, "id,name,note\nu1,Alice,\"likes \"\"tea\"\", a lot\"\nu2,Bob\nu3,Carol,\n").unwrap();
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_import_file",
+        json!({"path": "people.csv", "key_column": "id", "batch_size": 2}),
+    );
+    assert_eq!(result["imported"], 2);
+    assert_eq!(result["failed"], 1);
+    assert_eq!(result["rows"][1]["line"], 3);
+    assert!(result["rows"][1]["error"].as_str().unwrap().contains("columns"));
+
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "u1", "path": "$"}));
+    assert_eq!(extract_value(&doc), &json!({"name": "Alice", "note": "likes \"tea\", a lot"}));
+
+    let jsonl = dir.path().join("docs.jsonl");
+    std::fs::write(&jsonl, "{\"key\": \"d1\", \"value\": {\"n\": 1}}\n\nnot json\n{\"value\": 2}\n").unwrap();
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_import_file",
+        json!({"path": "docs.jsonl", "validate_only": true}),
+    );
+    assert_eq!(result["imported"], 1);
+    assert_eq!(result["failed"], 2);
+    assert_eq!(result["rows"][1]["line"], 3);
+    assert_eq!(result["rows"][2]["line"], 4);
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "d1", "path": "$"}));
+    assert_eq!(doc, json!(null));
+
+    call_tool_err(&mut session, &registry, "strata_import_file", json!({"path": "people.csv", "key_column": "missing"}));
+}
+
+#[test]
+fn test_import_and_export_stay_in_the_export_dir() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let inside = dir.path().join("exports");
+    std::fs::create_dir(&inside).unwrap();
+    std::fs::write(dir.path().join("outside.jsonl"), "{\"key\": \"k\", \"value\": 1}\n").unwrap();
+
+    // Without an export directory every path is refused
+    let err = call_tool_err(&mut session, &registry, "strata_export_ndjson", json!({"path": "dump.ndjson"}));
+    assert!(err.to_string().contains("--export-dir"));
+
+    session.set_export_dir(Some(inside.clone()));
+    let outside = dir.path().join("outside.jsonl");
+    for path in [outside.to_str().unwrap(), "../outside.jsonl", ""] {
+        call_tool_err(&mut session, &registry, "strata_import_file", json!({"path": path}));
+        call_tool_err(&mut session, &registry, "strata_export_ndjson", json!({"path": path}));
+    }
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&outside, inside.join("link.jsonl")).unwrap();
+        call_tool_err(&mut session, &registry, "strata_import_file", json!({"path": "link.jsonl"}));
+        call_tool_err(&mut session, &registry, "strata_export_ndjson", json!({"path": "link.jsonl"}));
+    }
+    assert_eq!(std::fs::read_to_string(&outside).unwrap(), "{\"key\": \"k\", \"value\": 1}\n");
+
+    let result = call_tool(&mut session, &registry, "strata_export_ndjson", json!({"path": "dump.ndjson"}));
+    assert_eq!(result["lines"], 0);
+    assert!(inside.join("dump.ndjson").exists());
+}
+
+#[test]
+fn test_failed_batched_import_rolls_back() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    session.set_export_dir(Some(dir.path().to_path_buf()));
+
+    // The second line isn't UTF-8, so reading it fails mid-batch
+    let mut bytes = b"{\"key\": \"first\", \"value\": 1}\n".to_vec();
+    bytes.extend_from_slice(b"\xff\xfe\n");
+    std::fs::write(dir.path().join("bad.ndjson"), bytes).unwrap();
+    call_tool_err(&mut session, &registry, "strata_import_ndjson", json!({"path": "bad.ndjson", "batch_size": 10}));
+
+    assert!(!session.in_transaction());
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "first", "path": "$"}));
+    assert_eq!(doc, json!(null));
+}
+
+#[test]
+fn test_ndjson_export_import_round_trip() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+    let agent = ToolRegistry::new();
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    session.set_export_dir(Some(dir.path().to_path_buf()));
+    let path = "dump.ndjson";
+
+    for i in 0..3 {
+        call_tool(&mut session, &agent, "strata_store", json!({"key": format!("doc:{}", i), "value": {"n": i}}));
+    }
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "other", "value": "x"}));
+    call_tool(&mut session, &agent, "strata_store", json!({"snapshot": true, "value": "skip me"}));
+    call_tool(&mut session, &registry, "strata_meta_set", json!({"key": "doc:1", "metadata": {"source": "crm"}}));
+
+    let result = call_tool(&mut session, &registry, "strata_export_ndjson", json!({"path": path, "prefix": "doc:"}));
+    assert_eq!(result["lines"], 3);
+    let result = call_tool(&mut session, &registry, "strata_export_ndjson", json!({"path": path}));
+    assert_eq!(result["lines"], 4);
+    let text = std::fs::read_to_string(dir.path().join(path)).unwrap();
+    assert_eq!(text.lines().count(), 4);
+    assert!(!text.contains("__snapshot"));
+
+    call_tool(&mut session, &registry, "strata_branch_create", json!({"branch_id": "restored"}));
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "restored"}));
+    std::fs::write(dir.path().join(path), format!("{}not json\n", text)).unwrap();
+    let result = call_tool(&mut session, &registry, "strata_import_ndjson", json!({"path": path, "batch_size": 2}));
+    assert_eq!(result["imported"], 4);
+    assert_eq!(result["failed"], 1);
+    assert_eq!(result["errors"][0]["line"], 5);
+    assert_eq!(result["errors_truncated"], false);
+
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "doc:2", "path": "$"}));
+    assert_eq!(extract_value(&doc), &json!({"n": 2}));
+
+    // Metadata travels inline with its key rather than as a reserved document
+    assert!(!text.contains("__meta:"));
+    let meta = call_tool(&mut session, &registry, "strata_meta_get", json!({"key": "doc:1"}));
+    assert_eq!(meta["metadata"], json!({"source": "crm"}));
+}
+
 // =============================================================================
 // Branch Tools
 // =============================================================================
@@ -632,6 +1101,177 @@ fn test_branch_merge() {
     assert!(result.get("keys_applied").is_some());
 }
 
+#[test]
+fn test_branch_compare() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "cfg:a", "path": "$", "value": 1}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "cfg:b", "path": "$", "value": "same"}));
+    call_tool(&mut session, &registry, "strata_branch_fork", json!({"destination": "cmp-1"}));
+    call_tool(&mut session, &registry, "strata_branch_fork", json!({"destination": "cmp-2"}));
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "cmp-2"}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "cfg:a", "path": "$", "value": 2}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_branch_compare",
+        json!({"branches": ["default", "cmp-1", "cmp-2"], "prefix": "cfg:"}),
+    );
+    assert_eq!(result["compared"], 2);
+    assert_eq!(result["divergent"], 1);
+    assert_eq!(result["keys"][0]["key"], "cfg:a");
+    assert_eq!(result["keys"][0]["diverges"], true);
+    assert_eq!(result["keys"][0]["values"]["cmp-2"], 2);
+    assert_eq!(result["keys"][1]["diverges"], false);
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_branch_compare",
+        json!({"branches": ["default", "cmp-2"], "prefix": "cfg:", "only_divergent": true}),
+    );
+    assert_eq!(result["keys"].as_array().map(|k| k.len()), Some(1));
+
+    call_tool_err(&mut session, &registry, "strata_branch_compare", json!({"branches": ["default"], "key": "cfg:a"}));
+    call_tool_err(
+        &mut session,
+        &registry,
+        "strata_branch_compare",
+        json!({"branches": ["default", "no-such-branch"], "key": "cfg:a"}),
+    );
+}
+
+#[test]
+fn test_branch_divergence() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "d:a", "path": "$", "value": 1}));
+    call_tool(&mut session, &registry, "strata_branch_fork", json!({"destination": "div-small"}));
+    call_tool(&mut session, &registry, "strata_branch_fork", json!({"destination": "div-wide"}));
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "div-wide"}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "d:b", "path": "$", "value": 2}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "d:c", "path": "$", "value": 3}));
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "div-small"}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "d:b", "path": "$", "value": 2}));
+
+    let result = call_tool(&mut session, &registry, "strata_branch_divergence", json!({}));
+    assert_eq!(result["base"], "default");
+    assert_eq!(result["branches"][0]["branch"], "div-wide");
+    assert_eq!(result["branches"][1]["branch"], "div-small");
+    assert!(result["branches"][0]["total"].as_u64() > result["branches"][1]["total"].as_u64());
+
+    let result = call_tool(&mut session, &registry, "strata_branch_divergence", json!({"limit": 1}));
+    assert_eq!(result["branches"].as_array().map(|b| b.len()), Some(1));
+    // The most changed branch is kept, though another sorts before it by name
+    assert_eq!(result["branches"][0]["branch"], "div-wide");
+    assert_eq!(result["skipped"], 1);
+
+    call_tool_err(&mut session, &registry, "strata_branch_divergence", json!({"base": "no-such-branch"}));
+}
+
+#[test]
+fn test_branch_changes_since() {
+    let mut session = test_session();
+    let agent = ToolRegistry::new();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "kept", "value": 1}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "edited", "value": 1}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "dropped", "value": 1}));
+    let range = call_tool(&mut session, &agent, "strata_history", json!({}));
+    let checkpoint = range["latest"].as_u64().expect("Expected latest timestamp");
+
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "edited", "value": 2}));
+    call_tool(&mut session, &agent, "strata_forget", json!({"key": "dropped"}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "fresh", "value": 3}));
+
+    let result = call_tool(&mut session, &registry, "strata_branch_changes", json!({"since": checkpoint}));
+    assert_eq!(result["summary"], json!({"added": 1, "removed": 1, "modified": 1}));
+    assert_eq!(result["added"], json!(["fresh"]));
+    assert_eq!(result["removed"], json!(["dropped"]));
+    assert_eq!(result["modified"], json!(["edited"]));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_branch_changes",
+        json!({"since": checkpoint, "detail": true}),
+    );
+    assert_eq!(result["modified"][0], json!({"key": "edited", "before": 1, "after": 2}));
+    assert_eq!(result["removed"][0]["after"], JsonValue::Null);
+}
+
+#[test]
+fn test_branch_key_sets() {
+    let mut session = test_session();
+    let agent = ToolRegistry::new();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "shared", "value": 1}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "dropped", "value": 1}));
+    call_tool(&mut session, &registry, "strata_branch_fork", json!({"destination": "other"}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "main-only", "value": 1}));
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "other"}));
+    call_tool(&mut session, &agent, "strata_forget", json!({"key": "dropped"}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "other-only", "value": 1}));
+
+    let args = json!({"branch_a": "default", "branch_b": "other"});
+    let result = call_tool(&mut session, &registry, "strata_branch_keys", args);
+    assert_eq!(result["a_only"], json!(["dropped", "main-only"]));
+    assert_eq!(result["b_only"], json!(["other-only"]));
+    assert_eq!(result["common"], json!(["shared"]));
+    assert_eq!(result["counts"], json!({"a_only": 2, "b_only": 1, "common": 1}));
+    assert_eq!(result["cursor"], json!(null));
+
+    let args = json!({"branch_a": "default", "branch_b": "other", "limit": 3});
+    let page = call_tool(&mut session, &registry, "strata_branch_keys", args);
+    assert_eq!(page["cursor"], json!("other-only"));
+    assert_eq!(page["common"], json!([]));
+    let args = json!({"branch_a": "default", "branch_b": "other", "limit": 3, "cursor": "other-only"});
+    let page = call_tool(&mut session, &registry, "strata_branch_keys", args);
+    assert_eq!(page["common"], json!(["shared"]));
+    assert_eq!(page["cursor"], json!(null));
+    assert_eq!(page["counts"]["a_only"], json!(2));
+
+    call_tool_err(&mut session, &registry, "strata_branch_keys", json!({"branch_a": "default", "branch_b": "nope"}));
+}
+
+#[test]
+fn test_branch_tree() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "t:a", "path": "$", "value": 1}));
+    call_tool(&mut session, &registry, "strata_branch_fork", json!({"destination": "tree-a"}));
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "tree-a"}));
+    call_tool(&mut session, &registry, "strata_branch_fork", json!({"destination": "tree-b"}));
+
+    let result = call_tool(&mut session, &registry, "strata_branch_tree", json!({}));
+    fn names(node: &JsonValue, out: &mut Vec<String>) {
+        out.push(node["name"].as_str().unwrap_or_default().to_string());
+        for child in node["children"].as_array().expect("Expected children array") {
+            names(child, out);
+        }
+    }
+    let mut seen = Vec::new();
+    for root in result["roots"].as_array().expect("Expected roots array") {
+        names(root, &mut seen);
+    }
+    seen.sort();
+    assert_eq!(seen, vec!["default", "tree-a", "tree-b"]);
+
+    let default = result["roots"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["name"] == "default")
+        .expect("default is a root");
+    assert_eq!(default["keys"], 1);
+}
+
 // =============================================================================
 // Space Tools
 // =============================================================================
@@ -679,8 +1319,127 @@ fn test_space_delete() {
     assert_eq!(result, json!(false));
 }
 
-// =============================================================================
-// Vector Tools
+#[test]
+fn test_branch_name_validation_and_normalize() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_branch_create",
+        json!({"branch_id": "my experiment!"}),
+    );
+    assert!(format!("{}", err).contains("invalid branch name"));
+    call_tool_err(&mut session, &registry, "strata_branch_fork", json!({"destination": "-bad"}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_branch_normalize",
+        json!({"name": "  my experiment!! v2 "}),
+    );
+    assert_eq!(result["normalized"], "my-experiment-v2");
+    assert_eq!(result["valid"], false);
+    assert_eq!(result["available"], true);
+
+    call_tool(&mut session, &registry, "strata_branch_create", json!({"branch_id": "my-experiment-v2"}));
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_branch_normalize",
+        json!({"name": "my-experiment-v2"}),
+    );
+    assert_eq!(result["valid"], true);
+    assert_eq!(result["available"], false);
+
+    // Only new names are checked: a branch the rules would refuse stays usable
+    session
+        .execute(stratadb::Command::BranchCreate { branch_id: Some("legacy name".to_string()), metadata: None })
+        .expect("Failed to create branch");
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "legacy name"}));
+    assert_eq!(session.branch(), "legacy name");
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "default"}));
+    call_tool(&mut session, &registry, "strata_branch_delete", json!({"branch": "legacy name"}));
+}
+
+#[test]
+fn test_space_list_with_counts() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_space_create", json!({"space": "empty-space"}));
+    call_tool(&mut session, &registry, "strata_space_switch", json!({"space": "busy"}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "a", "path": "$", "value": 1}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "b", "path": "$", "value": 2}));
+    call_tool(&mut session, &registry, "strata_space_switch", json!({"space": "default"}));
+
+    let result = call_tool(&mut session, &registry, "strata_space_list", json!({"with_time_range": true}));
+    let spaces = result.as_array().expect("Expected array");
+    let find = |name: &str| spaces.iter().find(|s| s["space"] == name).expect("space listed");
+    assert_eq!(find("busy")["keys"], 2);
+    assert!(find("busy")["oldest"].as_u64() <= find("busy")["latest"].as_u64());
+    assert_eq!(find("empty-space")["keys"], 0);
+    assert!(find("empty-space")["latest"].is_null());
+
+    // Bare names by default
+    let result = call_tool(&mut session, &registry, "strata_space_list", json!({}));
+    assert!(result.as_array().expect("Expected array").iter().all(|s| s.is_string()));
+}
+
+#[test]
+fn test_space_migrate() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_space_create", json!({"space": "src"}));
+    call_tool(&mut session, &registry, "strata_space_create", json!({"space": "dst"}));
+    call_tool(&mut session, &registry, "strata_space_switch", json!({"space": "src"}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "m:a", "path": "$", "value": 1}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "m:b", "path": "$", "value": 2}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "other", "path": "$", "value": 3}));
+    call_tool(&mut session, &registry, "strata_meta_set", json!({"key": "m:b", "metadata": {"tag": "src"}}));
+    call_tool(&mut session, &registry, "strata_space_switch", json!({"space": "dst"}));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "m:b", "path": "$", "value": "taken"}));
+    call_tool(&mut session, &registry, "strata_meta_set", json!({"key": "m:a", "metadata": {"tag": "stale"}}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_space_migrate",
+        json!({"from": "src", "to": "dst", "prefix": "m:", "mode": "copy"}),
+    );
+    assert_eq!(result["migrated"], 1);
+    assert_eq!(result["skipped"], 1);
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "m:b", "path": "$"}));
+    assert_eq!(extract_value(&doc), &json!("taken"));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_space_migrate",
+        json!({"from": "src", "to": "dst", "prefix": "m:", "overwrite": true}),
+    );
+    assert_eq!(result["keys"][0]["status"], "moved");
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "m:b", "path": "$"}));
+    assert_eq!(extract_value(&doc), &json!(2));
+    // Metadata goes with each key; m:a had none, so dst's old metadata is gone
+    let meta = call_tool(&mut session, &registry, "strata_meta_get", json!({"key": "m:b"}));
+    assert_eq!(meta["metadata"], json!({"tag": "src"}));
+    let meta = call_tool(&mut session, &registry, "strata_meta_get", json!({"key": "m:a"}));
+    assert_eq!(meta["metadata"], JsonValue::Null);
+
+    call_tool(&mut session, &registry, "strata_space_switch", json!({"space": "src"}));
+    let result = call_tool(&mut session, &registry, "strata_json_list", json!({}));
+    assert_eq!(result["keys"], json!(["other"]));
+    let meta = call_tool(&mut session, &registry, "strata_meta_get", json!({"key": "m:b"}));
+    assert_eq!(meta["metadata"], JsonValue::Null);
+
+    call_tool_err(&mut session, &registry, "strata_space_migrate", json!({"from": "src", "to": "nowhere"}));
+}
+
+// =============================================================================
+// Vector Tools
 // =============================================================================
 
 #[test]
@@ -802,6 +1561,44 @@ fn test_vector_batch_upsert() {
     assert_eq!(versions.len(), 2);
 }
 
+#[test]
+fn test_vector_orphans() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_vector_create_collection", json!({"collection": "docs", "dimension": 2}));
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_vector_batch_upsert",
+        json!({"collection": "docs", "entries": [
+            {"key": "kept", "vector": [1.0, 0.0]},
+            {"key": "gone", "vector": [0.0, 1.0]}
+        ]}),
+    );
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "kept", "path": "$", "value": {"a": 1}}));
+
+    // Only the document index is checked unless a collection is named
+    let report = call_tool(&mut session, &registry, "strata_vector_orphans", json!({}));
+    assert_eq!(report["collections"], json!([]));
+    call_tool_err(&mut session, &registry, "strata_vector_orphans", json!({"cleanup": true}));
+
+    let report = call_tool(&mut session, &registry, "strata_vector_orphans", json!({"collection": "docs"}));
+    assert_eq!(report["orphaned"], json!(1));
+    assert_eq!(report["removed"], json!(0));
+    assert_eq!(report["collections"][0]["vectors"], json!(2));
+    assert_eq!(report["collections"][0]["orphans"], json!(["gone"]));
+
+    let report = call_tool(&mut session, &registry, "strata_vector_orphans", json!({"collection": "docs", "cleanup": true}));
+    assert_eq!(report["removed"], json!(1));
+    let gone = call_tool(&mut session, &registry, "strata_vector_get", json!({"collection": "docs", "key": "gone"}));
+    assert_eq!(gone, json!(null));
+    let kept = call_tool(&mut session, &registry, "strata_vector_get", json!({"collection": "docs", "key": "kept"}));
+    assert!(!kept.is_null());
+
+    call_tool_err(&mut session, &registry, "strata_vector_orphans", json!({"collection": "nope"}));
+}
+
 #[test]
 fn test_vector_search_filtered() {
     let mut session = test_session();
@@ -827,6 +1624,75 @@ fn test_vector_search_filtered() {
     assert_eq!(matches[0].get("key").and_then(|v| v.as_str()), Some("f1"));
 }
 
+#[test]
+fn test_embed_key_needs_stored_text() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool_err(&mut session, &registry, "strata_embed_key", json!({"key": "missing"}));
+
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "numbers", "path": "$", "value": {"n": 1}}));
+    call_tool_err(&mut session, &registry, "strata_embed_key", json!({"key": "numbers"}));
+}
+
+#[cfg(feature = "embed")]
+#[test]
+fn test_embed_key_makes_a_document_searchable() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let note = "The feline dozed all afternoon on the windowsill";
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "note:cat", "value": note}));
+    let embedded = call_tool(&mut session, &registry, "strata_embed_key", json!({"key": "note:cat"}));
+    assert_eq!(embedded["key"], "note:cat");
+    assert!(embedded["dimension"].as_u64() > Some(0));
+    assert_ne!(embedded.get("model"), Some(&json!("built-in")));
+
+    // No word in common with the note, so only the vector can find it
+    let found = call_tool(&mut session, &registry, "strata_search", json!({"query": "sleeping cat"}));
+    let results = found["results"].as_array().expect("Expected results");
+    assert!(results.iter().any(|r| r["key"] == "note:cat"), "{}", found);
+}
+
+#[test]
+fn test_embed_status_reports_pending_age() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let status = call_tool(&mut session, &registry, "strata_embed_status", json!({}));
+    let pending = status["pending"].as_u64().unwrap();
+    assert_eq!(status["oldest_pending_age_ms"].is_null(), pending == 0);
+}
+
+#[test]
+fn test_embed_benchmark_bounds_samples() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    for samples in [0, 257] {
+        let err = call_tool_err(&mut session, &registry, "strata_embed_benchmark", json!({"samples": samples}));
+        assert!(matches!(err, strata_mcp::McpError::InvalidArg { .. }));
+    }
+}
+
+#[cfg(feature = "embed")]
+#[test]
+fn test_embed_benchmark_reports_throughput() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let report = call_tool(&mut session, &registry, "strata_embed_benchmark", json!({"samples": 4}));
+    assert_eq!(report["samples"], 4);
+    let min = report["min_us"].as_u64().unwrap();
+    let p95 = report["p95_us"].as_u64().unwrap();
+    let max = report["max_us"].as_u64().unwrap();
+    assert!(min <= p95 && p95 <= max, "{}", report);
+    assert!(report["mean_us"].as_u64().unwrap() <= max);
+    assert!(report["embeddings_per_second"].as_f64().unwrap() > 0.0);
+    assert!(report["batch_us"].is_u64());
+    assert!(report["batch_embeddings_per_second"].as_f64().unwrap() > 0.0);
+}
+
 // =============================================================================
 // Transaction Tools
 // =============================================================================
@@ -898,6 +1764,23 @@ fn test_transaction_read_only() {
     call_tool(&mut session, &registry, "strata_txn_rollback", json!({}));
 }
 
+#[test]
+fn test_transaction_out_of_order_calls() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let err = call_tool_err(&mut session, &registry, "strata_txn_commit", json!({}));
+    assert!(matches!(err, strata_mcp::McpError::NoActiveTransaction(_)));
+    let err = call_tool_err(&mut session, &registry, "strata_txn_rollback", json!({}));
+    assert!(matches!(err, strata_mcp::McpError::NoActiveTransaction(_)));
+
+    call_tool(&mut session, &registry, "strata_txn_begin", json!({}));
+    let err = call_tool_err(&mut session, &registry, "strata_txn_begin", json!({}));
+    assert!(matches!(err, strata_mcp::McpError::TransactionAlreadyActive));
+
+    call_tool(&mut session, &registry, "strata_txn_rollback", json!({}));
+}
+
 // =============================================================================
 // Bundle Tools
 // =============================================================================
@@ -948,6 +1831,96 @@ fn test_bundle_export_import() {
     assert!(result.get("keys_written").is_some());
 }
 
+#[test]
+fn test_bundle_inspect_preflight() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_branch_create", json!({"branch_id": "inspect-src"}));
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("inspect.bundle");
+    let path = path.to_str().unwrap();
+    call_tool(&mut session, &registry, "strata_bundle_export", json!({"branch": "inspect-src", "path": path}));
+
+    let report = call_tool(&mut session, &registry, "strata_bundle_inspect", json!({"path": path}));
+    assert_eq!(report["branch_id"], "inspect-src");
+    assert_eq!(report["branch_exists"], true);
+    assert_eq!(report["compatible"], false);
+    assert!(report["problems"][0].as_str().unwrap().contains("already exists"));
+    assert!(report["format_version"].is_u64());
+    assert_eq!(report["engine_format"], report["format_version"]);
+    assert!(report["server_version"].is_string());
+
+    call_tool(&mut session, &registry, "strata_branch_delete", json!({"branch": "inspect-src"}));
+    let report = call_tool(&mut session, &registry, "strata_bundle_inspect", json!({"path": path}));
+    assert_eq!(report["compatible"], true, "problems: {}", report["problems"]);
+    assert_eq!(report["problems"], json!([]));
+}
+
+#[test]
+fn test_bundle_incremental_export_applies_over_full() {
+    let mut session = test_session();
+    let agent = ToolRegistry::new();
+    let registry = ToolRegistry::developer();
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let full = dir.path().join("full.bundle");
+    let full = full.to_str().unwrap();
+    let incr = dir.path().join("incr.bundle");
+    let incr = incr.to_str().unwrap();
+
+    call_tool(&mut session, &registry, "strata_branch_create", json!({"branch_id": "inc-src"}));
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "inc-src"}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "a", "value": 1}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "b", "value": 1}));
+    call_tool(&mut session, &registry, "strata_bundle_export", json!({"branch": "inc-src", "path": full}));
+    let range = call_tool(&mut session, &agent, "strata_history", json!({}));
+    let checkpoint = range["latest"].as_u64().expect("Expected latest timestamp");
+
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "b", "value": 2}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "c", "value": 3}));
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_bundle_export",
+        json!({"branch": "inc-src", "path": incr, "after": checkpoint + 1}),
+    );
+    assert_eq!(result["incremental"]["keys"], json!(2));
+    assert_eq!(result["source_branch"], json!("inc-src"));
+
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "default"}));
+    call_tool(&mut session, &registry, "strata_branch_delete", json!({"branch": "inc-src"}));
+    call_tool(&mut session, &registry, "strata_bundle_import", json!({"path": full}));
+    let result = call_tool(&mut session, &registry, "strata_bundle_import", json!({"path": incr, "into": "inc-src"}));
+    assert_eq!(result["branch_id"], json!("inc-src"));
+    assert_eq!(session.branch(), "default");
+
+    let branches = call_tool(&mut session, &registry, "strata_branch_list", json!({}));
+    assert!(!branches.to_string().contains("incr-"));
+
+    call_tool(&mut session, &registry, "strata_branch_switch", json!({"branch": "inc-src"}));
+    for (key, value) in [("a", 1), ("b", 2), ("c", 3)] {
+        let recalled = call_tool(&mut session, &agent, "strata_recall", json!({"key": key}));
+        assert_eq!(recalled["value"], json!(value), "key {}", key);
+    }
+}
+
+#[test]
+fn test_bundle_incremental_export_refuses_read_only_database() {
+    let mut session = read_only_session();
+    let registry = ToolRegistry::developer();
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let incr = dir.path().join("incr.bundle");
+
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_bundle_export",
+        json!({"branch": "default", "path": incr.to_str().unwrap(), "after": 1}),
+    );
+    assert!(matches!(err, strata_mcp::McpError::Strata { ref code, .. } if code == "ACCESS_DENIED"), "{}", err);
+    assert!(!incr.exists());
+}
+
 // =============================================================================
 // Retention Tool
 // =============================================================================
@@ -961,6 +1934,52 @@ fn test_retention_apply() {
     assert_eq!(result, json!(null));
 }
 
+#[test]
+fn test_retention_version_stats() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+    let agent = ToolRegistry::new();
+
+    for i in 0..6 {
+        call_tool(&mut session, &agent, "strata_store", json!({"key": "hot", "value": i}));
+    }
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "warm", "value": 1}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "warm", "value": 2}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "cold", "value": 1}));
+
+    let stats = call_tool(&mut session, &registry, "strata_retention_version_stats", json!({"top": 2}));
+    assert_eq!(stats["keys"], 3);
+    assert_eq!(stats["versions"], 9);
+    assert_eq!(stats["min"], 1);
+    assert_eq!(stats["max"], 6);
+    assert_eq!(stats["mean"], 3.0);
+    assert_eq!(stats["top"], json!([{"key": "hot", "versions": 6}, {"key": "warm", "versions": 2}]));
+    assert_eq!(stats["histogram"][0], json!({"versions": "1", "keys": 1}));
+    assert_eq!(stats["histogram"][1], json!({"versions": "2-4", "keys": 1}));
+    assert_eq!(stats["histogram"][2], json!({"versions": "5-9", "keys": 1}));
+}
+
+// =============================================================================
+// Durability Tools
+// =============================================================================
+
+#[test]
+fn test_durability_mode_get_and_set() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let current = call_tool(&mut session, &registry, "strata_durability_mode", json!({}));
+    assert!(current["durability"].is_string());
+    assert_eq!(current["previous"], json!(null));
+
+    let result = call_tool(&mut session, &registry, "strata_durability_mode", json!({"mode": "always"}));
+    assert_eq!(result["durability"], json!("always"));
+    assert_eq!(result["previous"], current["durability"]);
+
+    let err = call_tool_err(&mut session, &registry, "strata_durability_mode", json!({"mode": "fast"}));
+    assert!(err.to_string().contains("always, standard, cache"));
+}
+
 // =============================================================================
 // Search Tool
 // =============================================================================
@@ -1078,58 +2097,1299 @@ fn test_search_with_all_options() {
     assert!(result.is_array());
 }
 
+#[test]
+fn test_generate_logprobs_not_implemented() {
+    let mut session = test_session();
+    let registry = ToolRegistry::developer();
+
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_generate",
+        json!({"model": "any", "prompt": "hi", "logprobs": true}),
+    );
+    match err {
+        strata_mcp::McpError::Strata { code, .. } => assert_eq!(code, "NOT_IMPLEMENTED"),
+        other => panic!("Expected NOT_IMPLEMENTED, got {}", other),
+    }
+}
+
+#[test]
+fn test_search_eval_reports_recall_and_ranks() {
+    let mut session = test_session();
+    let agent = ToolRegistry::new();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "recipe:apple", "value": "apple pie with cinnamon"}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "note:car", "value": "change the oil"}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_search_eval",
+        json!({"query": "apple pie", "expected": ["recipe:apple", "recipe:missing"]}),
+    );
+    assert_eq!(result["k"], 10);
+    assert_eq!(result["found"], 1);
+    assert_eq!(result["recall"], 0.5);
+    assert_eq!(result["expected"][0]["rank"], 1);
+    assert!(result["expected"][1]["rank"].is_null());
+
+    call_tool_err(&mut session, &registry, "strata_search_eval", json!({"query": "apple", "expected": []}));
+}
+
 // =============================================================================
-// Read-Only Mode
+// Metadata Tools
 // =============================================================================
 
 #[test]
-fn test_read_only_rejects_writes() {
-    let mut session = read_only_session();
+fn test_meta_set_get_merge() {
+    let mut session = test_session();
     let registry = ToolRegistry::developer();
 
-    // Read should work
-    let result = call_tool(&mut session, &registry, "strata_kv_get", json!({"key": "test"}));
-    assert_eq!(extract_value(&result), &json!("hello"));
+    call_tool(&mut session, &registry, "strata_json_set", json!({"key": "doc", "path": "$", "value": {"x": 1}}));
 
-    // Write should fail
-    let err = call_tool_err(
+    let result = call_tool(&mut session, &registry, "strata_meta_get", json!({"key": "doc"}));
+    assert!(result["metadata"].is_null());
+
+    call_tool(
         &mut session,
         &registry,
-        "strata_kv_put",
-        json!({"key": "new", "value": "fail"}),
+        "strata_meta_set",
+        json!({"key": "doc", "metadata": {"source": "web", "confidence": 0.5}}),
     );
-    let err_str = format!("{}", err);
-    assert!(
-        err_str.contains("read-only") || err_str.contains("ACCESS_DENIED"),
-        "Expected read-only error, got: {}",
-        err_str
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_meta_set",
+        json!({"key": "doc", "metadata": {"confidence": 0.9}, "merge": true}),
     );
+
+    let result = call_tool(&mut session, &registry, "strata_meta_get", json!({"key": "doc"}));
+    assert_eq!(result["metadata"], json!({"source": "web", "confidence": 0.9}));
+
+    // The value itself is untouched
+    let doc = call_tool(&mut session, &registry, "strata_json_get", json!({"key": "doc", "path": "$"}));
+    assert_eq!(doc["value"], json!({"x": 1}));
 }
 
+// =============================================================================
+// Model Tools
+// =============================================================================
+
 #[test]
-fn test_read_only_allows_reads() {
-    let mut session = read_only_session();
+fn test_models_loaded_empty() {
+    let mut session = test_session();
     let registry = ToolRegistry::developer();
 
-    // All read operations should work
-    call_tool(&mut session, &registry, "strata_db_ping", json!({}));
-    call_tool(&mut session, &registry, "strata_db_info", json!({}));
-    call_tool(&mut session, &registry, "strata_kv_list", json!({}));
-    call_tool(&mut session, &registry, "strata_branch_list", json!({}));
-    call_tool(&mut session, &registry, "strata_space_list", json!({}));
+    let result = call_tool(&mut session, &registry, "strata_models_loaded", json!({}));
+    assert_eq!(result, json!([]));
 }
 
+// =============================================================================
+// Agent Tools
+// =============================================================================
+
 #[test]
-fn test_read_only_rejects_state_write() {
-    let mut session = read_only_session();
-    let registry = ToolRegistry::developer();
+fn test_agent_search_ties_are_deterministic() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    for key in ["tie-c", "tie-a", "tie-b"] {
+        call_tool(
+            &mut session,
+            &registry,
+            "strata_store",
+            json!({"key": key, "value": {"text": "identical quarterly planning notes"}}),
+        );
+    }
 
-    let err = call_tool_err(
+    let first = call_tool(&mut session, &registry, "strata_search", json!({"query": "quarterly planning"}));
+    let hits = first.as_array().expect("Expected array of results");
+
+    // Equal scores must be ordered by key
+    for pair in hits.windows(2) {
+        if pair[0]["score"] == pair[1]["score"] {
+            assert!(pair[0]["key"].as_str() <= pair[1]["key"].as_str());
+        }
+    }
+
+    for _ in 0..5 {
+        let again = call_tool(&mut session, &registry, "strata_search", json!({"query": "quarterly planning"}));
+        assert_eq!(again, first);
+    }
+}
+
+#[test]
+fn test_agent_search_group_by_prefix() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    for key in ["project-a:tasks", "project-a:notes", "project-b:tasks", "loose"] {
+        call_tool(
+            &mut session,
+            &registry,
+            "strata_store",
+            json!({"key": key, "value": {"text": "migration checklist"}}),
+        );
+    }
+
+    let result = call_tool(
         &mut session,
         &registry,
-        "strata_state_set",
-        json!({"cell": "c", "value": 1}),
+        "strata_search",
+        json!({"query": "migration checklist", "group_by_prefix": ":"}),
     );
+    let groups = result.as_object().expect("Expected grouped object");
+    for (prefix, hits) in groups {
+        for hit in hits.as_array().expect("Expected ranked array") {
+            let key = hit["key"].as_str().unwrap();
+            if prefix.is_empty() {
+                assert!(!key.contains(':'));
+            } else {
+                assert!(key.starts_with(&format!("{}:", prefix)));
+            }
+        }
+    }
+
+    // Flat array remains the default
+    let flat = call_tool(&mut session, &registry, "strata_search", json!({"query": "migration checklist"}));
+    assert!(flat.is_array());
+}
+
+#[test]
+fn test_agent_store_oversized_integer_as_string() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "ids", "value": {"small": 42, "big": u64::MAX}}),
+    );
+
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "ids"}));
+    assert_eq!(result["value"]["small"], 42);
+    assert_eq!(result["value"]["big"], u64::MAX.to_string());
+}
+
+#[test]
+fn test_agent_recall_chunked() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let items: Vec<i64> = (0..25).collect();
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "big", "value": {"items": items, "text": "abcdefghij"}}),
+    );
+
+    // Arrays chunk by element
+    let first = call_tool(
+        &mut session,
+        &registry,
+        "strata_recall",
+        json!({"key": "big", "path": "$.items", "length": 10}),
+    );
+    assert_eq!(first["chunked"], "array");
+    assert_eq!(first["value"].as_array().unwrap().len(), 10);
+    assert_eq!(first["total"], 25);
+    assert_eq!(first["has_more"], true);
+    assert_eq!(first["next_offset"], 10);
+
+    let last = call_tool(
+        &mut session,
+        &registry,
+        "strata_recall",
+        json!({"key": "big", "path": "$.items", "offset": 20, "length": 10}),
+    );
+    assert_eq!(last["value"], json!([20, 21, 22, 23, 24]));
+    assert_eq!(last["has_more"], false);
+    assert!(last["next_offset"].is_null());
+
+    // Strings chunk by character
+    let text = call_tool(
+        &mut session,
+        &registry,
+        "strata_recall",
+        json!({"key": "big", "path": "$.text", "offset": 2, "length": 3}),
+    );
+    assert_eq!(text["chunked"], "string");
+    assert_eq!(text["value"], "cde");
+
+    // Objects chunk their serialized form
+    let doc = call_tool(&mut session, &registry, "strata_recall", json!({"key": "big", "length": 5}));
+    assert_eq!(doc["chunked"], "serialized");
+    assert_eq!(doc["value"].as_str().unwrap().chars().count(), 5);
+}
+
+#[test]
+fn test_agent_recall_multiple_paths() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "cfg", "value": {"db": {"host": "h", "port": 5432}, "auth": {"token": null}}}),
+    );
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_recall",
+        json!({"key": "cfg", "paths": ["$.db.host", "$.db.port", "$.auth.token", "$.auth.user"]}),
+    );
+    assert_eq!(result["found"], true);
+    assert_eq!(
+        result["values"],
+        json!({"$.db.host": "h", "$.db.port": 5432, "$.auth.token": null, "$.auth.user": null})
+    );
+    assert_eq!(result["missing"], json!(["$.auth.user"]));
+    assert!(result["version"].is_u64());
+
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "nope", "paths": ["$.a"]}));
+    assert_eq!(result, json!({"found": false, "values": null}));
+
+    call_tool_err(&mut session, &registry, "strata_recall", json!({"key": "cfg", "paths": ["$.a"], "path": "$.b"}));
+}
+
+#[test]
+fn test_agent_recall_limit_on_subtree() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let items: Vec<i64> = (0..50).collect();
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "big", "value": {"items": items, "meta": {"a": 1, "b": 2, "c": 3}}}),
+    );
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_recall",
+        json!({"key": "big", "path": "$.items", "limit": 10}),
+    );
+    assert_eq!(result["value"].as_array().unwrap().len(), 10);
+    assert_eq!(result["truncated"], true);
+    assert_eq!(result["total"], 50);
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_recall",
+        json!({"key": "big", "path": "$.meta", "limit": 2}),
+    );
+    assert_eq!(result["value"].as_object().unwrap().len(), 2);
+    assert_eq!(result["total"], 3);
+
+    // Nothing cut: no truncation markers
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_recall",
+        json!({"key": "big", "path": "$.meta", "limit": 5}),
+    );
+    assert!(result.get("truncated").is_none());
+}
+
+#[test]
+fn test_agent_reads_distinguish_missing_from_null() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "empty", "value": null}));
+
+    let stored = call_tool(&mut session, &registry, "strata_recall", json!({"key": "empty"}));
+    assert_eq!(stored["found"], true);
+    assert!(stored["value"].is_null());
+
+    let missing = call_tool(&mut session, &registry, "strata_recall", json!({"key": "nope"}));
+    assert_eq!(missing, json!({"found": false, "value": null}));
+
+    let history = call_tool(&mut session, &registry, "strata_history", json!({"key": "nope"}));
+    assert_eq!(history, json!({"found": false, "versions": []}));
+    let history = call_tool(&mut session, &registry, "strata_history", json!({"key": "empty"}));
+    assert_eq!(history["found"], true);
+    assert_eq!(history["versions"].as_array().unwrap().len(), 1);
+
+    // Compatibility mode restores bare nulls
+    session.set_legacy_reads(true);
+    let missing = call_tool(&mut session, &registry, "strata_recall", json!({"key": "nope"}));
+    assert!(missing.is_null());
+}
+
+#[test]
+fn test_agent_search_filters_on_metadata() {
+    let mut session = test_session();
+    let dev = ToolRegistry::developer();
+    let registry = ToolRegistry::new();
+
+    for (key, source) in [("note-a", "web"), ("note-b", "email")] {
+        call_tool(&mut session, &registry, "strata_store", json!({"key": key, "value": {"text": "budget review"}}));
+        call_tool(&mut session, &dev, "strata_meta_set", json!({"key": key, "metadata": {"source": source}}));
+    }
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_search",
+        json!({"query": "budget review", "metadata": {"source": "web"}}),
+    );
+    for hit in result.as_array().expect("Expected array") {
+        assert_eq!(hit["key"], "note-a");
+    }
+
+    // Metadata documents never show up as hits
+    let result = call_tool(&mut session, &registry, "strata_search", json!({"query": "web email source"}));
+    for hit in result.as_array().expect("Expected array") {
+        assert!(!hit["key"].as_str().unwrap().starts_with("__"));
+    }
+}
+
+#[test]
+fn test_agent_history_changes_since() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "old", "value": 1}));
+    let range = call_tool(&mut session, &registry, "strata_history", json!({}));
+    let checkpoint = range["latest"].as_u64().expect("Expected latest timestamp");
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "new", "value": 2}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_history",
+        json!({"since": checkpoint, "with_values": true}),
+    );
+    let changes = result["changes"].as_array().expect("Expected changes array");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["key"], "new");
+    assert_eq!(changes[0]["value"], 2);
+    assert!(result["cursor"].is_null());
+}
+
+#[test]
+fn test_agent_search_explain_empty() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_search",
+        json!({"query": "anything", "explain": true}),
+    );
+    assert_eq!(result["results"], json!([]));
+    assert_eq!(result["explain"]["returned"], 0);
+    assert!(result["explain"]["reason"].is_string());
+    // A cache database has auto-embed off and nothing indexed: keyword only
+    assert_eq!(result["explain"]["backend"], "keyword");
+    assert_eq!(result["explain"]["backends"], json!(["keyword"]));
+    assert_eq!(result["explain"]["semantic_search"], false);
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "doc", "value": {"text": "release notes"}}));
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_search",
+        json!({"query": "release notes", "explain": true}),
+    );
+    if result["explain"]["returned"].as_u64().unwrap() > 0 {
+        assert!(result["explain"]["reason"].is_null());
+    }
+}
+
+#[test]
+fn test_agent_search_recency() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "old", "value": {"text": "quarterly report"}}));
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "new", "value": {"text": "quarterly report"}}));
+
+    let plain = call_tool(&mut session, &registry, "strata_search", json!({"query": "quarterly report"}));
+    let boosted = call_tool(
+        &mut session,
+        &registry,
+        "strata_search",
+        json!({"query": "quarterly report", "recency_boost": 1.0}),
+    );
+    let boosted = boosted.as_array().unwrap();
+    assert_eq!(plain.as_array().unwrap().len(), 2);
+    assert_eq!(boosted.len(), 2);
+    assert_eq!(boosted[0]["key"], "new");
+    assert_eq!(boosted[1]["key"], "old");
+
+    let recent = call_tool(
+        &mut session,
+        &registry,
+        "strata_search",
+        json!({"query": "quarterly report", "max_age": 3600, "explain": true}),
+    );
+    assert_eq!(recent["explain"]["filtered_out"]["age"], 0);
+
+    // Hits in other primitives are dated from their own write times
+    let developer = ToolRegistry::developer();
+    call_tool(&mut session, &developer, "strata_kv_put", json!({"key": "note", "value": "quarterly report"}));
+    let recent = call_tool(
+        &mut session,
+        &registry,
+        "strata_search",
+        json!({"query": "quarterly report", "max_age": 3600, "explain": true}),
+    );
+    assert_eq!(recent["explain"]["filtered_out"]["age"], 0);
+
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_search",
+        json!({"query": "quarterly report", "recency_boost": 2}),
+    );
+    assert!(matches!(err, strata_mcp::McpError::InvalidArg { .. }));
+}
+
+#[test]
+fn test_agent_merge_with_compact_reports_history() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "doc", "value": 1}));
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "fork", "name": "work"}));
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "work"}));
+    for i in 2..5 {
+        call_tool(&mut session, &registry, "strata_store", json!({"key": "doc", "value": i}));
+    }
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "default"}));
+
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_branch",
+        json!({"action": "merge", "source": "work", "compact": true}),
+    );
+    assert_eq!(result["merged"], true);
+    let compacted = &result["compacted"];
+    assert_eq!(compacted["counted"], true);
+    let before = compacted["versions_before"].as_u64().unwrap();
+    let after = compacted["versions_after"].as_u64().unwrap();
+    assert!(after <= before);
+    assert_eq!(compacted["versions_removed"].as_u64().unwrap(), before - after);
+
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "doc"}));
+    assert_eq!(recalled["value"], 4);
+}
+
+#[test]
+fn test_agent_run_saved_query() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "notes:1", "value": {"text": "quarterly roadmap"}}));
+
+    let saved = call_tool(
+        &mut session,
+        &registry,
+        "strata_run_query",
+        json!({"action": "save", "name": "roadmap", "params": {"query": "quarterly roadmap", "k": 5}}),
+    );
+    assert_eq!(saved["saved"], true);
+
+    let direct = call_tool(&mut session, &registry, "strata_search", json!({"query": "quarterly roadmap", "k": 5}));
+    let run = call_tool(&mut session, &registry, "strata_run_query", json!({"name": "roadmap"}));
+    assert_eq!(run, direct);
+    let keys: Vec<&str> = run.as_array().unwrap().iter().filter_map(|h| h["key"].as_str()).collect();
+    assert!(!keys.iter().any(|k| k.starts_with("__")));
+
+    let list = call_tool(&mut session, &registry, "strata_run_query", json!({"action": "list"}));
+    assert_eq!(list, json!([{"name": "roadmap", "params": {"query": "quarterly roadmap", "k": 5}}]));
+
+    call_tool_err(
+        &mut session,
+        &registry,
+        "strata_run_query",
+        json!({"action": "save", "name": "bad", "params": {"query": "x", "path": "$"}}),
+    );
+
+    let deleted = call_tool(&mut session, &registry, "strata_run_query", json!({"action": "delete", "name": "roadmap"}));
+    assert_eq!(deleted["deleted"], true);
+    call_tool_err(&mut session, &registry, "strata_run_query", json!({"name": "roadmap"}));
+}
+
+#[test]
+fn test_agent_branch_archive_blocks_writes() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "fork", "name": "done"}));
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "archive", "name": "done"}));
+
+    let branches = call_tool(&mut session, &registry, "strata_branch", json!({"action": "list"}));
+    let done = branches
+        .as_array()
+        .expect("Expected array")
+        .iter()
+        .find(|b| b["id"] == "done")
+        .expect("archived branch listed");
+    assert_eq!(done["status"], "archived");
+
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "done"}));
+    call_tool(&mut session, &registry, "strata_recall", json!({"key": "anything"}));
+    let err = call_tool_err(&mut session, &registry, "strata_store", json!({"key": "k", "value": 1}));
+    assert!(err.to_string().contains("archived"));
+
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "default"}));
+    call_tool_err(&mut session, &registry, "strata_branch", json!({"action": "delete", "name": "done"}));
+
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "unarchive", "name": "done"}));
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "done"}));
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "k", "value": 1}));
+}
+
+#[test]
+fn test_archive_applies_to_every_session() {
+    let db = Strata::cache().expect("Failed to create cache database");
+    let mut first = McpSession::new(db.clone());
+    let mut second = McpSession::new(db);
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut first, &registry, "strata_branch", json!({"action": "fork", "name": "done"}));
+    call_tool(&mut second, &registry, "strata_branch", json!({"action": "switch", "name": "done"}));
+    call_tool(&mut second, &registry, "strata_store", json!({"key": "k", "value": 1}));
+
+    // The second session sees the archive on its next write
+    call_tool(&mut first, &registry, "strata_branch", json!({"action": "archive", "name": "done"}));
+    let err = call_tool_err(&mut second, &registry, "strata_store", json!({"key": "k", "value": 2}));
+    assert!(err.to_string().contains("archived"));
+
+    // The archive list isn't user data: no branch carries it or lists its branch
+    let keys = call_tool(&mut first, &ToolRegistry::developer(), "strata_json_list", json!({}));
+    assert!(!keys.to_string().contains("__archived_branches"));
+    let branches = call_tool(&mut first, &registry, "strata_branch", json!({"action": "list"}));
+    assert!(branches.as_array().unwrap().iter().all(|b| b["id"] != "_strata_mcp"));
+    call_tool_err(&mut first, &registry, "strata_branch", json!({"action": "switch", "name": "_strata_mcp"}));
+}
+
+#[test]
+fn test_agent_store_creates_missing_parents() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "cfg", "value": {"name": "app"}}));
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "cfg", "path": "$.db.pool.size", "value": 8}),
+    );
+
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "cfg"}));
+    assert_eq!(extract_value(&result), &json!({"name": "app", "db": {"pool": {"size": 8}}}));
+}
+
+#[test]
+fn test_agent_store_without_create_parents_names_missing_segment() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "cfg", "value": {"db": {}}}));
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "cfg", "path": "$.db.pool.size", "value": 8, "create_parents": false}),
+    );
+    let err_str = format!("{}", err);
+    assert!(err_str.contains("$.db.pool"), "Expected missing segment in error, got: {}", err_str);
+}
+
+#[test]
+fn test_agent_nested_store_joins_open_transaction() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+    let developer = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "cfg", "value": {"name": "app"}}));
+    call_tool(&mut session, &developer, "strata_txn_begin", json!({}));
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "cfg", "path": "$.db.pool.size", "value": 8}),
+    );
+
+    // The write stays in the caller's transaction rather than committing it
+    let result = call_tool(&mut session, &developer, "strata_txn_active", json!({}));
+    assert_eq!(result, json!(true));
+    call_tool(&mut session, &developer, "strata_txn_rollback", json!({}));
+
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "cfg"}));
+    assert_eq!(extract_value(&result), &json!({"name": "app"}));
+}
+
+#[test]
+fn test_agent_recall_with_types() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "typed", "value": {"count": 3, "ratio": 0.5, "tags": ["a"]}}),
+    );
+
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "typed", "with_types": true}));
+    assert_eq!(result["types"], json!({"count": "Int", "ratio": "Float", "tags": ["String"]}));
+
+    // Opt-in only
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "typed"}));
+    assert!(result.get("types").is_none());
+}
+
+#[test]
+fn test_agent_store_with_value_type() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"key": "vec", "value": {"weights": [1, 2, 0.5]}, "value_type": "float"}),
+    );
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "vec", "with_types": true}));
+    assert_eq!(result["types"], json!({"weights": ["Float", "Float", "Float"]}));
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "id", "value": 42, "value_type": "string"}));
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": "id"}));
+    assert_eq!(result["value"], "42");
+
+    call_tool_err(&mut session, &registry, "strata_store", json!({"key": "n", "value": 1.5, "value_type": "int"}));
+    call_tool_err(&mut session, &registry, "strata_store", json!({"key": "n", "value": 1, "value_type": "decimal"}));
+}
+
+#[test]
+fn test_agent_store_auto_key() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let first = call_tool(&mut session, &registry, "strata_store", json!({"value": "note one", "auto_key": true}));
+    let second = call_tool(&mut session, &registry, "strata_store", json!({"value": "note two", "auto_key": true}));
+    let first_key = first["key"].as_str().expect("Expected generated key");
+    let second_key = second["key"].as_str().expect("Expected generated key");
+    assert_eq!(first_key.len(), 26);
+    assert!(first_key < second_key);
+
+    let result = call_tool(&mut session, &registry, "strata_recall", json!({"key": second_key}));
+    assert_eq!(result["value"], "note two");
+
+    call_tool_err(&mut session, &registry, "strata_store", json!({"key": "k", "value": 1, "auto_key": true}));
+    call_tool_err(&mut session, &registry, "strata_store", json!({"value": 1}));
+}
+
+#[test]
+fn test_agent_truncated_recall_warns() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "list", "value": [1, 2, 3, 4]}));
+    session.take_warnings();
+
+    call_tool(&mut session, &registry, "strata_recall", json!({"key": "list", "limit": 2}));
+    let warnings = session.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, WarningCode::Truncated);
+
+    call_tool(&mut session, &registry, "strata_recall", json!({"key": "list"}));
+    assert!(session.take_warnings().is_empty());
+}
+
+#[test]
+fn test_agent_log_event_type_case() {
+    use strata_mcp::EventTypeCase;
+
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let result = call_tool(&mut session, &registry, "strata_log", json!({"event": "UserAction", "data": 1}));
+    assert_eq!(result["event"], "UserAction");
+
+    session.set_event_type_case(EventTypeCase::Snake);
+    for (given, logged) in [
+        ("UserAction", "user_action"),
+        ("user-action", "user_action"),
+        ("userAction", "user_action"),
+        ("HTTPError", "http_error"),
+        ("Tool Call 2", "tool_call_2"),
+    ] {
+        let result = call_tool(&mut session, &registry, "strata_log", json!({"event": given, "data": 1}));
+        assert_eq!(result["event"], logged, "normalizing {}", given);
+    }
+
+    session.set_event_type_case(EventTypeCase::Lower);
+    let result = call_tool(&mut session, &registry, "strata_log", json!({"event": "UserAction", "data": 1}));
+    assert_eq!(result["event"], "useraction");
+
+    session.set_event_type_case(EventTypeCase::Snake);
+    let err = call_tool_err(&mut session, &registry, "strata_log", json!({"event": "--", "data": 1}));
+    assert!(matches!(err, strata_mcp::McpError::InvalidArg { .. }));
+}
+
+#[test]
+fn test_agent_orient_summary() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let empty = call_tool(&mut session, &registry, "strata_orient", json!({}));
+    let summary = empty["summary"].as_str().expect("Expected summary string");
+    assert!(summary.contains("branch 'default'"), "summary: {}", summary);
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "note", "value": "hello"}));
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "fork", "name": "experiment"}));
+
+    let result = call_tool(&mut session, &registry, "strata_orient", json!({}));
+    let summary = result["summary"].as_str().expect("Expected summary string");
+    assert!(summary.contains("1 other branch exists"), "summary: {}", summary);
+    assert!(summary.contains("last change"), "summary: {}", summary);
+}
+
+#[test]
+fn test_agent_store_snapshot() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "task", "value": "draft"}));
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_store",
+        json!({"snapshot": true, "value": "halfway through the draft"}),
+    );
+    assert_eq!(result["key"], "__snapshot");
+    assert!(result["version"].is_u64());
+
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "__snapshot"}));
+    let snapshot = &recalled["value"];
+    assert_eq!(snapshot["note"], "halfway through the draft");
+    assert_eq!(snapshot["status"]["branch"], "default");
+    assert!(snapshot["summary"].as_str().unwrap().contains("branch 'default'"));
+    assert_eq!(snapshot["recent_writes"][0]["key"], "task");
+
+    let orient = call_tool(&mut session, &registry, "strata_orient", json!({}));
+    assert!(orient["summary"].as_str().unwrap().contains("recall '__snapshot'"));
+
+    call_tool_err(&mut session, &registry, "strata_store", json!({"snapshot": true, "key": "k", "value": 1}));
+}
+
+#[test]
+fn test_session_branch_isolation_merges_on_end() {
+    use strata_mcp::SessionBranchEnd;
+
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let name = session
+        .start_session_branch("default", SessionBranchEnd::Merge)
+        .expect("Failed to start session branch")
+        .name
+        .clone();
+    assert_eq!(session.branch(), name);
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "work", "value": "done"}));
+    let status = call_tool(&mut session, &registry, "strata_status", json!({}));
+    assert_eq!(status["session_branch"]["name"], name.as_str());
+    assert_eq!(status["session_branch"]["base"], "default");
+
+    // A concurrent reader reports the same session branch
+    let mut reader = session.reader().expect("Expected a reader");
+    let status = call_tool(&mut reader, &registry, "strata_status", json!({}));
+    assert_eq!(status["session_branch"]["name"], name.as_str());
+
+    session.end_session_branch().expect("Failed to end session branch");
+    assert_eq!(session.branch(), "default");
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "work"}));
+    assert_eq!(recalled["value"], "done");
+    assert!(session.switch_branch(&name).is_err());
+}
+
+#[test]
+fn test_per_call_space_override() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "k", "value": "v", "space": "shared"}));
+    assert_eq!(session.space(), "default");
+
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "k"}));
+    assert_eq!(recalled["found"], false);
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "k", "space": "shared"}));
+    assert_eq!(recalled["value"], "v");
+
+    let history = call_tool(&mut session, &registry, "strata_history", json!({"key": "k", "space": "shared"}));
+    assert_eq!(history["found"], true);
+    let deleted = call_tool(&mut session, &registry, "strata_forget", json!({"key": "k", "space": "shared"}));
+    assert_eq!(deleted["deleted"], true);
+    call_tool_err(&mut session, &registry, "strata_recall", json!({"key": "k", "space": ""}));
+    assert_eq!(session.space(), "default");
+
+    let store = registry.tools().iter().find(|t| t.name == "strata_store").unwrap();
+    assert!(store.input_schema["properties"]["space"]["description"].is_string());
+}
+
+#[test]
+fn test_resume_position_across_sessions() {
+    let db = Strata::cache().expect("Failed to create cache database");
+    let registry = ToolRegistry::new();
+
+    let mut session = McpSession::new(db.clone());
+    assert_eq!(session.resume_position().expect("Failed to resume"), None);
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "create", "name": "work"}));
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "work"}));
+    session.switch_space("notes");
+    session.save_position().expect("Failed to save position");
+
+    let mut restarted = McpSession::new(db.clone());
+    let resumed = restarted.resume_position().expect("Failed to resume");
+    assert_eq!(resumed, Some(("work".to_string(), "notes".to_string())));
+    assert_eq!(restarted.branch(), "work");
+    assert_eq!(restarted.space(), "notes");
+
+    // A deleted branch isn't resumed
+    restarted.switch_branch("default").expect("Failed to switch");
+    call_tool(&mut restarted, &registry, "strata_branch", json!({"action": "delete", "name": "work"}));
+    let mut fresh = McpSession::new(db);
+    assert_eq!(fresh.resume_position().expect("Failed to resume"), None);
+    assert_eq!(fresh.branch(), "default");
+}
+
+#[test]
+fn test_attach_reads_alias_qualified_keys() {
+    use stratadb::OpenOptions;
+
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    {
+        let db = Strata::open_with(dir.path(), OpenOptions::new()).expect("Failed to create db");
+        let mut reference = McpSession::new(db);
+        let registry = ToolRegistry::new();
+        call_tool(&mut reference, &registry, "strata_store", json!({"key": "guide", "value": "be brief"}));
+    }
+
+    let mut session = test_session();
+    call_tool_err(&mut session, &ToolRegistry::new(), "strata_attach", json!({"alias": "ref"}));
+
+    let registry = ToolRegistry::new().with_attach();
+    let path = dir.path().to_str().unwrap();
+    let attached = call_tool(&mut session, &registry, "strata_attach", json!({"alias": "ref", "path": path}));
+    assert_eq!(attached["attached"][0]["alias"], "ref");
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "guide", "value": "local"}));
+
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "ref:guide"}));
+    assert_eq!(recalled["value"], "be brief");
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "guide"}));
+    assert_eq!(recalled["value"], "local");
+    call_tool_err(&mut session, &registry, "strata_store", json!({"key": "ref:guide", "value": "x"}));
+
+    // Unknown aliases are part of an ordinary key
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "other:guide"}));
+    assert_eq!(recalled["found"], false);
+
+    call_tool_err(&mut session, &registry, "strata_attach", json!({"alias": "bad:alias", "path": path}));
+
+    // An alias can't shadow local keys, before or after attaching
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "notes:a", "value": 1}));
+    call_tool_err(&mut session, &registry, "strata_attach", json!({"alias": "notes", "path": path}));
+    let developer = ToolRegistry::developer();
+    call_tool(&mut session, &developer, "strata_json_set", json!({"key": "ref:guide", "path": "$", "value": "mine"}));
+    call_tool_err(&mut session, &registry, "strata_recall", json!({"key": "ref:guide"}));
+    call_tool(&mut session, &developer, "strata_json_delete", json!({"key": "ref:guide", "path": "$"}));
+
+    let detached = call_tool(&mut session, &registry, "strata_attach", json!({"alias": "ref", "detach": true}));
+    assert_eq!(detached["attached"], json!([]));
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "ref:guide"}));
+    assert_eq!(recalled["found"], false);
+}
+
+#[test]
+fn test_developer_access_escape_hatch() {
+    let mut session = test_session();
+
+    // Not available by default
+    let registry = ToolRegistry::new();
+    assert!(!registry.tools().iter().any(|t| t.name == "strata_developer"));
+    call_tool_err(&mut session, &registry, "strata_developer", json!({"action": "list"}));
+
+    let registry = ToolRegistry::new().with_developer_access();
+    assert_eq!(registry.tools().len(), 11);
+
+    let list = call_tool(&mut session, &registry, "strata_developer", json!({"action": "list"}));
+    let names: Vec<&str> = list
+        .as_array()
+        .expect("Expected array")
+        .iter()
+        .filter_map(|t| t["name"].as_str())
+        .collect();
+    assert!(names.contains(&"strata_kv_put"));
+
+    let def = call_tool(
+        &mut session,
+        &registry,
+        "strata_developer",
+        json!({"action": "describe", "tool": "strata_kv_put"}),
+    );
+    assert!(def.get("inputSchema").is_some());
+
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_developer",
+        json!({"action": "call", "tool": "strata_kv_put", "args": {"key": "k", "value": 1}}),
+    );
+    let result = call_tool(
+        &mut session,
+        &registry,
+        "strata_developer",
+        json!({"action": "call", "tool": "strata_kv_get", "args": {"key": "k"}}),
+    );
+    assert_eq!(extract_value(&result), &json!(1));
+}
+
+#[test]
+fn test_debug_echo() {
+    let mut session = test_session();
+
+    let registry = ToolRegistry::new();
+    assert!(!registry.tools().iter().any(|t| t.name == "strata_debug_echo"));
+    call_tool_err(&mut session, &registry, "strata_debug_echo", json!({}));
+
+    let registry = ToolRegistry::new().with_debug_tools();
+    let result = call_tool(&mut session, &registry, "strata_debug_echo", json!({"key": "k", "k": 3, "x": 1.5}));
+    assert_eq!(result["args"], json!({"key": "k", "k": 3, "x": 1.5}));
+    assert_eq!(result["arg_types"], json!({"key": "string", "k": "integer", "x": "number"}));
+    assert_eq!(result["context"]["branch"], "default");
+    assert_eq!(result["context"]["in_transaction"], false);
+}
+
+#[test]
+fn test_debug_recent_writes() {
+    let mut session = test_session();
+    let agent = ToolRegistry::new();
+    assert!(!agent.tools().iter().any(|t| t.name == "strata_debug_recent_writes"));
+
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "first", "value": 1}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "second", "value": 1}));
+    call_tool(&mut session, &agent, "strata_store", json!({"key": "first", "value": 2}));
+
+    let registry = ToolRegistry::new().with_debug_tools();
+    let result = call_tool(&mut session, &registry, "strata_debug_recent_writes", json!({"limit": 2}));
+    let writes = result["writes"].as_array().expect("Expected writes array");
+    assert_eq!(writes.len(), 2);
+    assert_eq!(writes[0]["key"], "first");
+    assert_eq!(writes[0]["op"], "update");
+    assert_eq!(writes[1]["key"], "second");
+    assert_eq!(writes[1]["op"], "create");
+    assert!(writes[0].get("value").is_none());
+    assert!(result["counters"].is_object());
+}
+
+#[test]
+fn test_agent_output_schemas_match_examples() {
+    let registry = ToolRegistry::new();
+    let with_schema: Vec<_> = registry.tools().iter().filter(|t| t.output_schema.is_some()).collect();
+    assert_eq!(with_schema.len(), 7);
+
+    for tool in with_schema {
+        let schema = tool.output_schema.as_ref().unwrap();
+        assert_eq!(schema["type"], "object", "{}", tool.name);
+        let properties = schema["properties"].as_object().expect("Expected properties");
+        for example in &tool.examples {
+            let output = example.output.as_object().expect("Example output is not an object");
+            for field in output.keys() {
+                assert!(properties.contains_key(field), "{} example has undeclared '{}'", tool.name, field);
+            }
+            for field in schema["required"].as_array().into_iter().flatten() {
+                let field = field.as_str().unwrap();
+                assert!(output.contains_key(field), "{} example lacks '{}'", tool.name, field);
+            }
+        }
+    }
+
+    // Real results fit the schemas too, and a tool whose results can be
+    // arrays declares none
+    let mut session = test_session();
+    let calls = [
+        ("strata_store", json!({"key": "k", "value": 1})),
+        ("strata_recall", json!({"key": "k"})),
+        ("strata_recall", json!({"key": "k", "offset": 0})),
+        ("strata_forget", json!({"key": "k"})),
+    ];
+    for (name, args) in calls {
+        let result = call_tool(&mut session, &registry, name, args);
+        let tool = registry.tools().iter().find(|t| t.name == name).unwrap();
+        let schema = tool.output_schema.as_ref().expect("Expected an output schema");
+        for (field, value) in result.as_object().expect("Result is not an object") {
+            let declared = &schema["properties"][field];
+            assert!(declared.is_object(), "{} result has undeclared '{}'", name, field);
+            assert!(fits_type(declared, value), "{} result's '{}' is {}", name, field, value);
+        }
+    }
+    let branches = call_tool(&mut session, &registry, "strata_branch", json!({"action": "list"}));
+    assert!(branches.is_array());
+    let branch = registry.tools().iter().find(|t| t.name == "strata_branch").unwrap();
+    assert!(branch.output_schema.is_none());
+}
+
+#[test]
+fn test_debug_schema() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new().with_debug_tools();
+
+    let def = call_tool(&mut session, &registry, "strata_debug_schema", json!({"tool": "strata_store"}));
+    assert_eq!(def["name"], "strata_store");
+    assert_eq!(def["surface"], "listed");
+    assert!(def["inputSchema"]["properties"]["value"].is_object());
+    assert_eq!(def["outputSchema"]["required"], json!(["key", "stored"]));
+
+    let def = call_tool(&mut session, &registry, "strata_debug_schema", json!({"tool": "strata_kv_put"}));
+    assert_eq!(def["surface"], "developer");
+    assert!(def.get("outputSchema").is_none());
+    assert_eq!(def["inputSchema"]["required"], json!(["key", "value"]));
+
+    call_tool_err(&mut session, &registry, "strata_debug_schema", json!({"tool": "strata_nope"}));
+}
+
+#[test]
+fn test_debug_tool_calls() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new().with_debug_tools().with_developer_access();
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "k", "value": 1}));
+    for _ in 0..3 {
+        call_tool(&mut session, &registry, "strata_recall", json!({"key": "k"}));
+    }
+    call_tool_err(&mut session, &registry, "strata_store", json!({}));
+    // Unknown names aren't counted, and a developer call counts once
+    call_tool_err(&mut session, &registry, "strata_no_such_tool", json!({}));
+    call_tool(
+        &mut session,
+        &registry,
+        "strata_developer",
+        json!({"action": "call", "tool": "strata_kv_put", "args": {"key": "n", "value": 1}}),
+    );
+
+    let result = call_tool(&mut session, &registry, "strata_debug_tool_calls", json!({}));
+    assert_eq!(result["calls"], 6);
+    assert_eq!(result["errors"], 1);
+    assert_eq!(
+        result["tools"],
+        json!([
+            {"tool": "strata_recall", "calls": 3, "ok": 3, "errors": 0},
+            {"tool": "strata_store", "calls": 2, "ok": 1, "errors": 1},
+            {"tool": "strata_developer", "calls": 1, "ok": 1, "errors": 0},
+        ])
+    );
+
+    // The profile call itself is counted once it finishes
+    let result = call_tool(&mut session, &registry, "strata_debug_tool_calls", json!({}));
+    assert_eq!(result["calls"], 7);
+}
+
+#[test]
+fn test_store_coalescing_keeps_final_value() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+    session
+        .set_coalesce_window(Some(std::time::Duration::from_secs(60)))
+        .expect("Failed to enable coalescing");
+
+    for i in 0..5 {
+        let result = call_tool(&mut session, &registry, "strata_store", json!({"key": "counter", "value": i}));
+        assert_eq!(result["buffered"], true);
+        assert_eq!(result["stored"], false);
+    }
+
+    // Reads flush the buffered write
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "counter"}));
+    assert_eq!(recalled["value"], 4);
+
+    let history = call_tool(&mut session, &registry, "strata_history", json!({"key": "counter"}));
+    assert_eq!(history["versions"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_command_keeps_branch_across_switch() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "create", "name": "other"}));
+
+    // A command built on "default" and executed after another request switched branches
+    let cmd = stratadb::Command::JsonSet {
+        branch: session.branch_id(),
+        space: session.space_id(),
+        key: "inflight".to_string(),
+        path: "$".to_string(),
+        value: stratadb::Value::String("intended".to_string()),
+    };
+    session.switch_branch("other").expect("Failed to switch");
+    session.execute(cmd).expect("Failed to execute");
+
+    let on_other = call_tool(&mut session, &registry, "strata_recall", json!({"key": "inflight"}));
+    assert_eq!(on_other["found"], false);
+    session.switch_branch("default").expect("Failed to switch");
+    let on_default = call_tool(&mut session, &registry, "strata_recall", json!({"key": "inflight"}));
+    assert_eq!(on_default["value"], "intended");
+}
+
+#[test]
+fn test_coalesced_write_keeps_branch_across_switch() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "create", "name": "other"}));
+    session
+        .set_coalesce_window(Some(std::time::Duration::from_secs(60)))
+        .expect("Failed to enable coalescing");
+
+    let result = call_tool(&mut session, &registry, "strata_store", json!({"key": "buffered", "value": 1}));
+    assert_eq!(result["buffered"], true);
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "other"}));
+
+    let on_other = call_tool(&mut session, &registry, "strata_recall", json!({"key": "buffered"}));
+    assert_eq!(on_other["found"], false);
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "default"}));
+    let on_default = call_tool(&mut session, &registry, "strata_recall", json!({"key": "buffered"}));
+    assert_eq!(on_default["value"], 1);
+}
+
+#[test]
+fn test_status_reports_embed_model_pin() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let status = call_tool(&mut session, &registry, "strata_status", json!({}));
+    assert!(status.get("embed_model").is_none());
+
+    session.set_embed_model(Some("minilm-v2".to_string()));
+    session.record_embed_model("minilm-v2").expect("Failed to record model");
+    let status = call_tool(&mut session, &registry, "strata_status", json!({}));
+    assert_eq!(status["embed_model"], json!({"pinned": "minilm-v2", "index": "minilm-v2", "consistent": true}));
+
+    session.set_embed_model(Some("minilm-v3".to_string()));
+    let status = call_tool(&mut session, &registry, "strata_status", json!({}));
+    assert_eq!(status["embed_model"]["consistent"], false);
+
+    // One record covers the whole database, not just the branch it was made on
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "create", "name": "elsewhere"}));
+    call_tool(&mut session, &registry, "strata_branch", json!({"action": "switch", "name": "elsewhere"}));
+    let status = call_tool(&mut session, &registry, "strata_status", json!({}));
+    assert_eq!(status["embed_model"]["index"], "minilm-v2");
+
+    // The record is bookkeeping, not data
+    let results = call_tool(&mut session, &registry, "strata_search", json!({"query": "minilm"}));
+    assert_eq!(results, json!([]));
+}
+
+#[test]
+fn test_store_rejects_reserved_keys() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+
+    let err = call_tool_err(&mut session, &registry, "strata_store", json!({"key": "__meta:x", "value": 1}));
+    assert!(matches!(err, strata_mcp::McpError::InvalidArg { .. }), "{}", err);
+}
+
+#[test]
+fn test_store_requires_explicit_replace() {
+    let mut session = test_session();
+    let registry = ToolRegistry::new();
+    session.set_require_explicit_replace(true);
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "cfg", "value": {"theme": "dark", "lang": "en"}}));
+
+    let err = call_tool_err(&mut session, &registry, "strata_store", json!({"key": "cfg", "value": {"theme": "light"}}));
+    assert!(format!("{}", err).contains("replace: true"));
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "cfg", "path": "$.theme", "value": "light"}));
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "cfg"}));
+    assert_eq!(recalled["value"], json!({"theme": "light", "lang": "en"}));
+
+    call_tool(&mut session, &registry, "strata_store", json!({"key": "cfg", "value": {"theme": "x"}, "replace": true}));
+    let recalled = call_tool(&mut session, &registry, "strata_recall", json!({"key": "cfg"}));
+    assert_eq!(recalled["value"], json!({"theme": "x"}));
+}
+
+// =============================================================================
+// Read-Only Mode
+// =============================================================================
+
+#[test]
+fn test_read_only_rejects_writes() {
+    let mut session = read_only_session();
+    let registry = ToolRegistry::developer();
+
+    // Read should work
+    let result = call_tool(&mut session, &registry, "strata_kv_get", json!({"key": "test"}));
+    assert_eq!(extract_value(&result), &json!("hello"));
+
+    // Write should fail
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_kv_put",
+        json!({"key": "new", "value": "fail"}),
+    );
+    let err_str = format!("{}", err);
+    assert!(
+        err_str.contains("read-only") || err_str.contains("ACCESS_DENIED"),
+        "Expected read-only error, got: {}",
+        err_str
+    );
+}
+
+#[test]
+fn test_read_only_allows_reads() {
+    let mut session = read_only_session();
+    let registry = ToolRegistry::developer();
+
+    // All read operations should work
+    call_tool(&mut session, &registry, "strata_db_ping", json!({}));
+    call_tool(&mut session, &registry, "strata_db_info", json!({}));
+    call_tool(&mut session, &registry, "strata_kv_list", json!({}));
+    call_tool(&mut session, &registry, "strata_branch_list", json!({}));
+    call_tool(&mut session, &registry, "strata_space_list", json!({}));
+}
+
+#[test]
+fn test_read_only_rejects_state_write() {
+    let mut session = read_only_session();
+    let registry = ToolRegistry::developer();
+
+    let err = call_tool_err(
+        &mut session,
+        &registry,
+        "strata_state_set",
+        json!({"cell": "c", "value": 1}),
+    );
+    let err_str = format!("{}", err);
+    assert!(err_str.contains("read-only") || err_str.contains("ACCESS_DENIED"));
+}
+
+#[test]
+fn test_read_only_rejects_durability_change() {
+    let mut session = read_only_session();
+    let registry = ToolRegistry::developer();
+
+    call_tool(&mut session, &registry, "strata_durability_mode", json!({}));
+    let err = call_tool_err(&mut session, &registry, "strata_durability_mode", json!({"mode": "cache"}));
     let err_str = format!("{}", err);
     assert!(err_str.contains("read-only") || err_str.contains("ACCESS_DENIED"));
 }
@@ -1194,24 +3454,24 @@ fn test_branch_not_found() {
 
 #[test]
 fn test_tool_count() {
-    // Developer registry: 74 tools across 17 categories (used by integration tests)
+    // Developer registry: 104 tools across 20 categories (used by integration tests)
     let dev_registry = ToolRegistry::developer();
     let dev_tools = dev_registry.tools();
     assert_eq!(
         dev_tools.len(),
-        74,
-        "Expected 74 developer tools, got {}. Tools: {:?}",
+        104,
+        "Expected 104 developer tools, got {}. Tools: {:?}",
         dev_tools.len(),
         dev_tools.iter().map(|t| &t.name).collect::<Vec<_>>()
     );
 
-    // Default registry: 8 agent-friendly tools (exposed via MCP)
+    // Default registry: 10 agent-friendly tools (exposed via MCP)
     let agent_registry = ToolRegistry::new();
     let agent_tools = agent_registry.tools();
     assert_eq!(
         agent_tools.len(),
-        8,
-        "Expected 8 agent tools, got {}. Tools: {:?}",
+        10,
+        "Expected 10 agent tools, got {}. Tools: {:?}",
         agent_tools.len(),
         agent_tools.iter().map(|t| &t.name).collect::<Vec<_>>()
     );